@@ -0,0 +1,12 @@
+//! Stamps the engine's git commit into `receipt::ENGINE_COMMIT`.
+
+use std::process::Command;
+
+fn main() {
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs/heads");
+    let commit = Command::new("git").args(["rev-parse", "HEAD"]).output();
+    if let Some(out) = commit.ok().filter(|out| out.status.success()) {
+        println!("cargo:rustc-env=PROP_AMM_ENGINE_COMMIT={}", String::from_utf8_lossy(&out.stdout).trim());
+    }
+}
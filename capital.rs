@@ -1,4 +1,15 @@
-use crate::types::{AmmState, EpochSummary, SimConfig, SCALE};
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use libloading::Library;
+
+use rand::Rng;
+use rand_distr::{Distribution, Normal};
+use serde::{Deserialize, Serialize};
+
+use crate::sim::RunningStat;
+use crate::types::{AmmState, EpochSummary, SimConfig, REBALANCE_REQUEST_SLOT, SCALE};
 
 /// Compute risk-adjusted score for a strategy's epoch performance.
 ///
@@ -34,7 +45,12 @@ pub fn softmax_weights(scores: &[f64], temperature: f64, min_weight: f64) -> Vec
     let sum_exp: f64 = exps.iter().sum();
 
     let raw_weights: Vec<f64> = exps.iter().map(|&e| e / sum_exp).collect();
+    apply_weight_floor(raw_weights, min_weight)
+}
 
+/// Lift every weight to at least `min_weight` (when the floors fit) and renormalize.
+fn apply_weight_floor(raw_weights: Vec<f64>, min_weight: f64) -> Vec<f64> {
+    let n = raw_weights.len();
     let floor_total = min_weight * n as f64;
     let mut weights = if min_weight > 0.0 && floor_total < 1.0 {
         let remaining = 1.0 - floor_total;
@@ -52,45 +68,643 @@ pub fn softmax_weights(scores: &[f64], temperature: f64, min_weight: f64) -> Vec
     weights
 }
 
+/// Per-strategy override of the capital-weight floor and/or ceiling
+/// (`SimConfig::capital_bounds`), e.g. capping a house strategy at 20%. `strategy` matches
+/// the strategy name, and every self-play copy of it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CapitalBound {
+    pub strategy: String,
+    pub floor: Option<f64>,
+    pub ceiling: Option<f64>,
+}
+
+impl CapitalBound {
+    fn matches(&self, name: &str) -> bool {
+        names_strategy(&self.strategy, name)
+    }
+}
+
+/// Starting capital weight for one strategy (`SimConfig::initial_weights`), e.g. a
+/// seeded incumbent that begins with 40%. Matches like `CapitalBound`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct InitialWeight {
+    pub strategy: String,
+    pub weight: f64,
+}
+
+/// `pattern` is `name`, or the strategy `name` is a self-play copy of.
+fn names_strategy(pattern: &str, name: &str) -> bool {
+    name == pattern || name.strip_prefix(pattern).is_some_and(|rest| rest.starts_with(" #"))
+}
+
+/// Capital weights at the start of a run: strategies named in `SimConfig::initial_weights`
+/// get theirs, the rest split what is left equally, and the result is normalized to sum
+/// to 1. Equal weights when none are set.
+pub fn initial_weights(names: &[&str], config: &SimConfig) -> Vec<f64> {
+    let n = names.len();
+    if config.initial_weights.is_empty() {
+        return vec![1.0 / n as f64; n];
+    }
+    let set: Vec<Option<f64>> = names.iter()
+        .map(|name| config.initial_weights.iter().find(|w| names_strategy(&w.strategy, name)).map(|w| w.weight))
+        .collect();
+    let claimed: f64 = set.iter().flatten().sum();
+    let unset = set.iter().filter(|w| w.is_none()).count();
+    let share = if unset > 0 { (1.0 - claimed).max(0.0) / unset as f64 } else { 0.0 };
+    let mut weights: Vec<f64> = set.iter().map(|w| w.unwrap_or(share)).collect();
+    let total: f64 = weights.iter().sum();
+    if total > 0.0 {
+        weights.iter_mut().for_each(|w| *w /= total);
+    } else {
+        weights = vec![1.0 / n as f64; n];
+    }
+    weights
+}
+
+/// Clamp weights to per-strategy `(floor, ceiling)` bounds and renormalize, handing the
+/// excess or shortfall to the unclamped strategies in proportion to their weight.
+/// Infeasible bounds (floors summing above 1, ceilings below 1) are scaled to fit.
+pub fn enforce_weight_bounds(weights: Vec<f64>, bounds: &[(f64, f64)]) -> Vec<f64> {
+    let n = weights.len();
+    let mut fixed: Vec<Option<f64>> = vec![None; n];
+    loop {
+        let fixed_mass: f64 = fixed.iter().flatten().sum();
+        let free: Vec<usize> = (0..n).filter(|&i| fixed[i].is_none()).collect();
+        if free.is_empty() {
+            break;
+        }
+        let free_raw: f64 = free.iter().map(|&i| weights[i]).sum();
+        let share = |i: usize| {
+            let remaining = (1.0 - fixed_mass).max(0.0);
+            if free_raw > 0.0 { weights[i] / free_raw * remaining } else { remaining / free.len() as f64 }
+        };
+        // Pin only the worst violator per pass: pinning all at once can overshoot
+        let worst = free
+            .iter()
+            .map(|&i| {
+                let (lo, hi) = bounds[i];
+                let w = share(i);
+                (i, (lo - w).max(w - hi), w.clamp(lo, hi))
+            })
+            .filter(|&(_, violation, _)| violation > 0.0)
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        match worst {
+            Some((i, _, clamped)) => fixed[i] = Some(clamped),
+            None => {
+                for &i in &free {
+                    fixed[i] = Some(share(i));
+                }
+                break;
+            }
+        }
+    }
+    let mut out: Vec<f64> = fixed.into_iter().map(|w| w.unwrap_or(0.0)).collect();
+    let total: f64 = out.iter().sum();
+    if total > 0.0 {
+        out.iter_mut().for_each(|w| *w /= total);
+    }
+    out
+}
+
+/// Resolve `config.capital_bounds` against the AMMs' names; `None` when there are none.
+/// Strategies without an override are bounded by `[0, 1]` (the global floor is already
+/// in the allocator's output).
+fn resolve_weight_bounds(amms: &[AmmState], config: &SimConfig) -> Option<Vec<(f64, f64)>> {
+    if config.capital_bounds.is_empty() {
+        return None;
+    }
+    Some(amms.iter().map(|a| {
+        let bound = config.capital_bounds.iter().find(|b| b.matches(&a.name));
+        let floor = bound.and_then(|b| b.floor).unwrap_or(0.0);
+        let ceiling = bound.and_then(|b| b.ceiling).unwrap_or(1.0);
+        (floor, ceiling.max(floor))
+    }).collect())
+}
+
+// ─── Allocators ───────────────────────────────────────────────────────────────
+
+/// Turns one epoch's summaries into new capital weights (non-negative, summing to 1).
+///
+/// Allocators live for a whole simulation, so they may carry state across epochs.
+pub trait CapitalAllocator {
+    fn weights(&mut self, summaries: &[EpochSummary], config: &SimConfig) -> Vec<f64>;
+
+    /// What each strategy was scored on in the `weights` call just made, for
+    /// `EpochSummary::score_components`. `None` means the risk-adjusted score of this
+    /// epoch alone (see `ScoreComponents::single_epoch`).
+    fn components(&self, _summaries: &[EpochSummary], _config: &SimConfig) -> Option<Vec<ScoreComponents>> {
+        None
+    }
+}
+
+/// The inputs behind one strategy's allocation at a rebalance, so authors can see why
+/// their weight moved. For penalty-based rules `score = window_mean − penalty`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+pub struct ScoreComponents {
+    /// Value the allocator ranked the strategy on
+    pub score: f64,
+    /// Downside penalty subtracted from `window_mean` (0 for rules without one)
+    pub penalty: f64,
+    /// Mean and (population) std of the statistic over the allocator's window, and the
+    /// number of epochs in it
+    pub window_mean: f64,
+    pub window_std: f64,
+    pub window_epochs: usize,
+}
+
+impl ScoreComponents {
+    /// Components of the default rule: this epoch's edge less `λ·max(0, −edge)`
+    pub fn single_epoch(summary: &EpochSummary) -> Self {
+        Self {
+            score: summary.risk_adjusted_score,
+            penalty: summary.edge - summary.risk_adjusted_score,
+            window_mean: summary.edge,
+            window_std: 0.0,
+            window_epochs: 1,
+        }
+    }
+}
+
+/// Allocation rule selected by `SimConfig::allocator`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AllocatorKind {
+    /// Temperature-scaled softmax of risk-adjusted scores (original behavior)
+    #[default]
+    Softmax,
+    /// Fractional Kelly sizing from per-trade edge mean / variance
+    Kelly { fraction: f64 },
+    /// Softmax of mean − λ·CVaR over each strategy's last `window` epoch edges, with the
+    /// tail taken as the worst `alpha` fraction
+    Cvar { window: usize, alpha: f64 },
+    /// Full-information EXP3 (Hedge) over epochs, with exploration rate `gamma` in (0, 1]
+    Exp3 { gamma: f64 },
+    /// Fixed payout ladder by epoch-edge rank: `ladder[r]` is the share for rank r
+    /// (0 = best); ranks past the end of the ladder get only the weight floor
+    Rank { ladder: Vec<f64> },
+    /// Softmax of the Sharpe ratio of each strategy's epoch edges so far
+    SharpeOfEpochs,
+    /// Host-supplied rule loaded from a shared library; see `DylibAllocator`
+    Plugin { path: PathBuf },
+}
+
+impl AllocatorKind {
+    /// A fresh allocator for one simulation. Only a plug-in can fail to build, if its
+    /// library does not load; `SimConfig::validate` checks that up front.
+    pub fn build(&self) -> Result<Box<dyn CapitalAllocator>, String> {
+        Ok(match self {
+            AllocatorKind::Softmax => Box::new(SoftmaxAllocator),
+            AllocatorKind::Kelly { fraction } => Box::new(KellyAllocator { fraction: *fraction }),
+            AllocatorKind::Cvar { window, alpha } => Box::new(CvarAllocator::new(*window, *alpha)),
+            AllocatorKind::Exp3 { gamma } => Box::new(Exp3Allocator::new(*gamma)),
+            AllocatorKind::Rank { ladder } => Box::new(RankAllocator { ladder: ladder.clone() }),
+            AllocatorKind::SharpeOfEpochs => Box::new(SharpeAllocator::default()),
+            AllocatorKind::Plugin { path } => Box::new(
+                DylibAllocator::load(path).map_err(|e| format!("allocator plugin {}: {e}", path.display()))?,
+            ),
+        })
+    }
+}
+
+/// Softmax of risk-adjusted scores; see `softmax_weights`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SoftmaxAllocator;
+
+impl CapitalAllocator for SoftmaxAllocator {
+    fn weights(&mut self, summaries: &[EpochSummary], config: &SimConfig) -> Vec<f64> {
+        let scores: Vec<f64> = summaries.iter().map(|s| s.risk_adjusted_score).collect();
+        softmax_weights(&scores, config.softmax_temperature, config.min_capital_weight)
+    }
+}
+
+/// Kelly-style sizing: each strategy's bankroll fraction is `fraction · μ / σ²` of its
+/// per-trade edge (0 when μ ≤ 0). Fractions summing past 1 are normalized; capital
+/// they leave unclaimed is spread evenly.
+#[derive(Clone, Copy, Debug)]
+pub struct KellyAllocator {
+    pub fraction: f64,
+}
+
+impl KellyAllocator {
+    fn kelly_fraction(&self, s: &EpochSummary) -> f64 {
+        if s.trade_edge_mean <= 0.0 { return 0.0; }
+        (self.fraction * s.trade_edge_mean / s.trade_edge_var.max(1e-12)).min(1.0)
+    }
+}
+
+impl CapitalAllocator for KellyAllocator {
+    fn weights(&mut self, summaries: &[EpochSummary], config: &SimConfig) -> Vec<f64> {
+        let n = summaries.len();
+        if n == 0 { return vec![]; }
+        let kelly: Vec<f64> = summaries.iter().map(|s| self.kelly_fraction(s)).collect();
+        let claimed: f64 = kelly.iter().sum();
+        let raw = if claimed >= 1.0 {
+            kelly.iter().map(|f| f / claimed).collect()
+        } else {
+            let spread = (1.0 - claimed) / n as f64;
+            kelly.iter().map(|f| f + spread).collect()
+        };
+        apply_weight_floor(raw, config.min_capital_weight)
+    }
+
+    /// Score is the Kelly fraction; the window is this epoch's per-trade edge
+    fn components(&self, summaries: &[EpochSummary], _config: &SimConfig) -> Option<Vec<ScoreComponents>> {
+        Some(summaries.iter().map(|s| ScoreComponents {
+            score: self.kelly_fraction(s),
+            penalty: 0.0,
+            window_mean: s.trade_edge_mean,
+            window_std: s.trade_edge_var.sqrt(),
+            window_epochs: 1,
+        }).collect())
+    }
+}
+
+/// Rolling-window CVaR: scores each strategy on `mean − λ·max(0, CVaR_α)` of its last
+/// `window` epoch edges, so one lucky epoch cannot dominate capital. Weights follow
+/// from the scores by the same softmax as `SoftmaxAllocator`.
+#[derive(Clone, Debug)]
+pub struct CvarAllocator {
+    pub window: usize,
+    pub alpha: f64,
+    history: Vec<VecDeque<f64>>,
+}
+
+impl CvarAllocator {
+    pub fn new(window: usize, alpha: f64) -> Self {
+        Self { window: window.max(1), alpha, history: Vec::new() }
+    }
+}
+
+/// Expected loss in the worst `alpha` fraction of `edges` (−mean of the ⌈α·n⌉ lowest).
+pub fn cvar(edges: &[f64], alpha: f64) -> f64 {
+    if edges.is_empty() { return 0.0; }
+    let mut sorted = edges.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let k = ((alpha * sorted.len() as f64).ceil() as usize).clamp(1, sorted.len());
+    -sorted[..k].iter().sum::<f64>() / k as f64
+}
+
+impl CapitalAllocator for CvarAllocator {
+    fn weights(&mut self, summaries: &[EpochSummary], config: &SimConfig) -> Vec<f64> {
+        self.history.resize_with(summaries.len(), VecDeque::new);
+        for (s, window) in summaries.iter().zip(&mut self.history) {
+            window.push_back(s.edge);
+            if window.len() > self.window { window.pop_front(); }
+        }
+        let scores: Vec<f64> = self.components(summaries, config).unwrap().iter().map(|c| c.score).collect();
+        softmax_weights(&scores, config.softmax_temperature, config.min_capital_weight)
+    }
+
+    fn components(&self, _summaries: &[EpochSummary], config: &SimConfig) -> Option<Vec<ScoreComponents>> {
+        Some(self.history.iter().map(|window| {
+            let mut stat = RunningStat::default();
+            window.iter().for_each(|&e| stat.push(e));
+            let edges: Vec<f64> = window.iter().copied().collect();
+            let penalty = config.lambda * cvar(&edges, self.alpha).max(0.0);
+            ScoreComponents {
+                score: stat.mean() - penalty,
+                penalty,
+                window_mean: stat.mean(),
+                window_std: stat.variance().sqrt(),
+                window_epochs: window.len(),
+            }
+        }).collect())
+    }
+}
+
+/// EXP3 in its full-information form (Hedge): epochs are rounds and capital weights the
+/// arm probabilities. Every strategy's edge is observed each epoch, whatever capital it
+/// held, so rewards need no importance weighting.
+///
+/// Each epoch's edges are min–max scaled to rewards in [0, 1] and added to each
+/// strategy's cumulative reward Ŝ_i, and
+///
+///   p_i = (1 − γ) · exp(γ·Ŝ_i) / Σ exp(γ·Ŝ_j) + γ / K
+///
+/// so a strategy keeps at least γ/K of capital and a chance to prove itself. The usual
+/// `min_capital_weight` floor applies on top.
+#[derive(Clone, Debug)]
+pub struct Exp3Allocator {
+    pub gamma: f64,
+    estimates: Vec<f64>,
+}
+
+impl Exp3Allocator {
+    pub fn new(gamma: f64) -> Self {
+        Self { gamma: gamma.clamp(1e-6, 1.0), estimates: Vec::new() }
+    }
+}
+
+impl CapitalAllocator for Exp3Allocator {
+    fn weights(&mut self, summaries: &[EpochSummary], config: &SimConfig) -> Vec<f64> {
+        let k = summaries.len();
+        if k == 0 { return vec![]; }
+        if self.estimates.len() != k {
+            self.estimates = vec![0.0; k];
+        }
+
+        let lo = summaries.iter().map(|s| s.edge).fold(f64::INFINITY, f64::min);
+        let hi = summaries.iter().map(|s| s.edge).fold(f64::NEG_INFINITY, f64::max);
+        for (estimate, s) in self.estimates.iter_mut().zip(summaries) {
+            *estimate += if hi > lo { (s.edge - lo) / (hi - lo) } else { 0.5 };
+        }
+
+        let max_est = self.estimates.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        let exps: Vec<f64> = self.estimates.iter().map(|s| (self.gamma * (s - max_est)).exp()).collect();
+        let sum: f64 = exps.iter().sum();
+        let probs = exps.iter()
+            .map(|e| (1.0 - self.gamma) * e / sum + self.gamma / k as f64)
+            .collect();
+        apply_weight_floor(probs, config.min_capital_weight)
+    }
+
+    /// Score is the cumulative scaled reward Ŝ_i
+    fn components(&self, summaries: &[EpochSummary], _config: &SimConfig) -> Option<Vec<ScoreComponents>> {
+        Some(summaries.iter().zip(&self.estimates).map(|(s, &estimate)| ScoreComponents {
+            score: estimate,
+            window_mean: s.edge,
+            window_epochs: 1,
+            ..ScoreComponents::default()
+        }).collect())
+    }
+}
+
+/// Rank-based payout ladder: weights depend only on the ordering of epoch edges, not
+/// their scale, so a single monster epoch buys no more than first place. Tied strategies
+/// split the payouts of the ranks they span; the ladder is normalized over the ranks in
+/// play, then the usual weight floor applies.
+#[derive(Clone, Debug)]
+pub struct RankAllocator {
+    pub ladder: Vec<f64>,
+}
+
+impl RankAllocator {
+    /// 50% / 30% / 20% to the top three.
+    pub fn default_ladder() -> Vec<f64> {
+        vec![0.5, 0.3, 0.2]
+    }
+}
+
+impl CapitalAllocator for RankAllocator {
+    fn weights(&mut self, summaries: &[EpochSummary], config: &SimConfig) -> Vec<f64> {
+        let n = summaries.len();
+        if n == 0 { return vec![]; }
+        let payout = |rank: usize| self.ladder.get(rank).copied().unwrap_or(0.0).max(0.0);
+        let total: f64 = (0..n).map(payout).sum();
+        if total <= 0.0 {
+            return vec![1.0 / n as f64; n];
+        }
+
+        let mut order: Vec<usize> = (0..n).collect();
+        order.sort_by(|&a, &b| summaries[b].edge.total_cmp(&summaries[a].edge));
+        let mut raw = vec![0.0; n];
+        let mut start = 0;
+        while start < n {
+            let edge = summaries[order[start]].edge;
+            let end = (start..n).find(|&r| summaries[order[r]].edge != edge).unwrap_or(n);
+            let share = (start..end).map(payout).sum::<f64>() / (end - start) as f64;
+            for &i in &order[start..end] {
+                raw[i] = share / total;
+            }
+            start = end;
+        }
+        apply_weight_floor(raw, config.min_capital_weight)
+    }
+    /// Ranked on raw epoch edge, with no penalty
+    fn components(&self, summaries: &[EpochSummary], _config: &SimConfig) -> Option<Vec<ScoreComponents>> {
+        Some(summaries.iter().map(|s| ScoreComponents {
+            score: s.edge,
+            window_mean: s.edge,
+            window_epochs: 1,
+            ..ScoreComponents::default()
+        }).collect())
+    }
+}
+
+/// Scores each strategy by mean / std of all its epoch edges so far, so consistently
+/// decent strategies out-accumulate boom/bust ones. Until a strategy has two epochs with
+/// some dispersion its score is 0. Weights follow by the usual softmax.
+#[derive(Clone, Debug, Default)]
+pub struct SharpeAllocator {
+    history: Vec<RunningStat>,
+}
+
+impl CapitalAllocator for SharpeAllocator {
+    fn weights(&mut self, summaries: &[EpochSummary], config: &SimConfig) -> Vec<f64> {
+        self.history.resize(summaries.len(), RunningStat::default());
+        for (s, stat) in summaries.iter().zip(&mut self.history) {
+            stat.push(s.edge);
+        }
+        let scores: Vec<f64> = self.components(summaries, config).unwrap().iter().map(|c| c.score).collect();
+        softmax_weights(&scores, config.softmax_temperature, config.min_capital_weight)
+    }
+
+    fn components(&self, _summaries: &[EpochSummary], _config: &SimConfig) -> Option<Vec<ScoreComponents>> {
+        Some(self.history.iter().map(|stat| {
+            let std = stat.variance().sqrt();
+            ScoreComponents {
+                score: if std > 0.0 { stat.mean() / std } else { 0.0 },
+                penalty: 0.0,
+                window_mean: stat.mean(),
+                window_std: std,
+                window_epochs: stat.count() as usize,
+            }
+        }).collect())
+    }
+}
+
+// ─── Allocator plug-ins ───────────────────────────────────────────────────────
+
+/// Symbol a plug-in allocator exports: reads `n` risk-adjusted scores and writes `n`
+/// raw weights. Weights need not be normalized; the engine sanitizes them.
+type AllocateFn = unsafe extern "C" fn(scores: *const f64, n: usize, weights_out: *mut f64);
+
+/// A capital allocator loaded from a shared library exposing `__prop_amm_allocate`,
+/// for hosts with proprietary allocation rules.
+pub struct DylibAllocator {
+    /// Keep the library alive for the duration of the simulation
+    _lib: Arc<Library>,
+    allocate: AllocateFn,
+}
+
+/// Plug-in libraries loaded so far, by path. Each is loaded once per process and shared
+/// by every simulation that uses it.
+static PLUGINS: OnceLock<Mutex<HashMap<PathBuf, Arc<Library>>>> = OnceLock::new();
+
+impl DylibAllocator {
+    /// Load a compiled allocator shared library from disk, or reuse it if this process
+    /// already has.
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut plugins = PLUGINS.get_or_init(Default::default).lock().unwrap_or_else(|e| e.into_inner());
+        let lib = match plugins.get(path) {
+            Some(lib) => Arc::clone(lib),
+            None => Arc::new(unsafe { Library::new(path)? }),
+        };
+        let allocate: AllocateFn = unsafe { *lib.get::<AllocateFn>(b"__prop_amm_allocate\0")? };
+        plugins.entry(path.to_path_buf()).or_insert_with(|| Arc::clone(&lib));
+        Ok(Self { _lib: lib, allocate })
+    }
+}
+
+impl CapitalAllocator for DylibAllocator {
+    fn weights(&mut self, summaries: &[EpochSummary], config: &SimConfig) -> Vec<f64> {
+        let scores: Vec<f64> = summaries.iter().map(|s| s.risk_adjusted_score).collect();
+        let mut raw = vec![0.0; scores.len()];
+        unsafe { (self.allocate)(scores.as_ptr(), scores.len(), raw.as_mut_ptr()) };
+        sanitize_weights(raw, config.min_capital_weight)
+    }
+}
+
+/// Normalize untrusted raw weights: non-finite or negative entries count as zero, and
+/// an all-zero result falls back to uniform before the weight floor is applied.
+fn sanitize_weights(raw: Vec<f64>, min_weight: f64) -> Vec<f64> {
+    let mut raw: Vec<f64> = raw.into_iter().map(|w| if w.is_finite() { w.max(0.0) } else { 0.0 }).collect();
+    let total: f64 = raw.iter().sum();
+    if total > 0.0 {
+        raw.iter_mut().for_each(|w| *w /= total);
+    } else {
+        let n = raw.len() as f64;
+        raw.iter_mut().for_each(|w| *w = 1.0 / n);
+    }
+    apply_weight_floor(raw, min_weight)
+}
+
+// ─── Parameter schedules ──────────────────────────────────────────────────────
+
+/// How an allocation parameter evolves across epochs from its `SimConfig` base value
+/// (`SimConfig::temperature_schedule`, `SimConfig::lambda_schedule`).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Schedule {
+    /// The base value throughout (original behavior)
+    #[default]
+    Constant,
+    /// Straight line from the base value to `end` over `epochs` epochs, then held
+    Linear { end: f64, epochs: u32 },
+    /// base · decay^epoch
+    Exponential { decay: f64 },
+}
+
+impl Schedule {
+    /// Value at the rebalance closing `epoch` (0-based).
+    pub fn value(self, base: f64, epoch: u32) -> f64 {
+        match self {
+            Schedule::Constant => base,
+            Schedule::Linear { end, epochs } => {
+                let t = if epochs == 0 { 1.0 } else { (epoch as f64 / epochs as f64).min(1.0) };
+                base + (end - base) * t
+            }
+            Schedule::Exponential { decay } => base * decay.powi(epoch as i32),
+        }
+    }
+}
+
+// ─── Rebalancing ──────────────────────────────────────────────────────────────
+
+/// Rebalance AMM reserves at an epoch boundary with the allocator `config.allocator`
+/// selects; fails only if a plug-in allocator does not load. Stateful allocators should
+/// use `rebalance_capital_with` and one instance per simulation.
+pub fn rebalance_capital(
+    amms: &mut [AmmState],
+    config: &SimConfig,
+    epoch_number: u32,
+) -> Result<Vec<EpochSummary>, String> {
+    Ok(rebalance_capital_with(amms, config, epoch_number, config.allocator.build()?.as_mut()))
+}
+
 /// Rebalance AMM reserves at an epoch boundary.
 ///
 /// 1. Compute risk-adjusted scores for each strategy.
-/// 2. Derive new capital weights via softmax.
+/// 2. Derive new capital weights via the allocator.
 /// 3. Scale each AMM's reserves to reflect its new weight (total capital is conserved).
 ///
 /// Returns the list of epoch summaries (one per AMM), updated in-place.
-pub fn rebalance_capital(
+pub fn rebalance_capital_with(
     amms: &mut [AmmState],
     config: &SimConfig,
     epoch_number: u32,
+    allocator: &mut dyn CapitalAllocator,
 ) -> Vec<EpochSummary> {
+    let config = &*config.scheduled_for(epoch_number);
+
     // ── 1. Gather epoch stats ──────────────────────────────────────────────────
-    let summaries: Vec<EpochSummary> = amms.iter().map(|amm| {
+    let mut summaries: Vec<EpochSummary> = amms.iter().map(|amm| {
         let score = risk_adjusted_score(amm.epoch_edge, config.lambda);
+        let trades = amm.epoch_trade_count.max(1) as f64;
+        let trade_edge_mean = amm.epoch_edge / trades;
         EpochSummary {
             epoch_number,
             edge: amm.epoch_edge,
             trade_count: amm.epoch_trade_count,
-            arb_losses: f64::min(0.0, amm.epoch_edge),  // crude; engine can track separately
-            retail_gains: f64::max(0.0, amm.epoch_edge),
+            buy_volume: amm.epoch_buy_volume,
+            sell_volume: amm.epoch_sell_volume,
+            retail_trade_count: amm.epoch_retail_trades,
+            arb_trade_count: amm.epoch_arb_trades,
+            mean_flow_share: amm.epoch_flow_share_sum / amm.epoch_retail_trades.max(1) as f64,
+            largest_trade: amm.epoch_largest_trade,
+            arb_losses: amm.epoch_arb_edge,
+            retail_gains: amm.epoch_retail_edge,
+            fee_revenue: amm.epoch_fee_revenue,
             risk_adjusted_score: score,
+            sandwich_count: amm.epoch_sandwiches,
+            rfq_requests: 0, // pool-level; filled in by the engine
+            rfq_wins: amm.epoch_rfq_wins,
+            rfq_edge: amm.epoch_rfq_edge,
+            inventory_pnl: amm.epoch_inventory_pnl,
+            impermanent_loss: amm.epoch_hold_pnl - amm.epoch_inventory_pnl,
+            killed_at_step: amm.frozen_at.map(|s| s as u64),
+            protocol_fee: config.protocol_fee_share * amm.epoch_fee_revenue.max(0.0),
+            rebalance_cost: 0.0, // filled in after the epoch hook
+            trade_edge_mean,
+            trade_edge_var: (amm.epoch_trade_edge_sq / trades - trade_edge_mean.powi(2)).max(0.0),
+            turnover: 0.0,
+            turnover_cost: 0.0,
+            capital_flow: 0.0, // filled in by the engine
+            inventory_transfer: 0.0,
+            recenter_cost: 0.0,
+            score_components: ScoreComponents::default(),
+            prior_weight: 0.0,
+            target_weight: 0.0,
+            new_weight: 0.0,
         }
     }).collect();
 
     // ── 2. Compute new weights ─────────────────────────────────────────────────
-    let scores: Vec<f64> = summaries.iter().map(|s| s.risk_adjusted_score).collect();
-    let new_weights = softmax_weights(&scores, config.softmax_temperature, config.min_capital_weight);
+    let old_weights: Vec<f64> = amms.iter().map(|a| a.capital_weight).collect();
+    let mut target = allocator.weights(&summaries, config);
+    if let Some(bounds) = resolve_weight_bounds(amms, config) {
+        target = enforce_weight_bounds(target, &bounds);
+    }
+    let components = allocator.components(&summaries, config)
+        .unwrap_or_else(|| summaries.iter().map(ScoreComponents::single_epoch).collect());
+    for ((s, c), (&old, &t)) in summaries.iter_mut().zip(components).zip(old_weights.iter().zip(&target)) {
+        s.score_components = c;
+        s.prior_weight = old;
+        s.target_weight = t;
+    }
+    let new_weights = limit_turnover(&old_weights, target, config.max_rebalance_delta);
 
     // ── 3. Compute total capital currently in the system (sum of each AMM's USD value)
     //    Capital of AMM i = 2 * reserve_y_i (assuming spot ≈ fair, so X value ≈ Y value)
     //    We conserve total Y-denominated capital.
-    let total_capital_y: u128 = amms.iter()
-        .map(|a| a.reserve_y as u128 * 2) // 2× because X+Y reserves are balanced at fair
+    //    Fees held outside reserves are swept in, valued at spot; the protocol take leaves.
+    let total_capital_y: u128 = amms.iter().zip(&summaries)
+        .map(|(a, s)| {
+            let fees_y = a.fee_account_y as f64 + a.fee_account_x as f64 * a.spot_price();
+            let capital = a.reserve_y as u128 * 2 + fees_y as u128; // 2× because X+Y reserves are balanced at fair
+            capital.saturating_sub((s.protocol_fee * SCALE as f64) as u128)
+        })
         .sum();
 
     // ── 4. Rebalance: scale each AMM's reserves to match its new weight ─────────
+    //    Moved capital pays the proportional turnover cost out of the mover's allocation.
     for (i, amm) in amms.iter_mut().enumerate() {
-        let target_capital_y = (total_capital_y as f64 * new_weights[i]) as u128;
+        let moved = (new_weights[i] - old_weights[i]).abs();
+        let turnover_cost_y = total_capital_y as f64 * moved * config.turnover_cost_bps as f64 / 10_000.0;
+        summaries[i].turnover = moved;
+        summaries[i].new_weight = new_weights[i];
+        summaries[i].turnover_cost = turnover_cost_y / SCALE as f64;
+        let target_capital_y = (total_capital_y as f64 * new_weights[i] - turnover_cost_y) as u128;
         // Each pool gets target_capital_y / 2 in Y reserves, and the same value in X
         let new_reserve_y = (target_capital_y / 2).max(SCALE as u128) as u64;
         // Actually: preserve the spot price. If spot = ry/rx, and we want new_ry:
@@ -100,19 +714,202 @@ pub fn rebalance_capital(
 
         amm.reserve_x = new_rx;
         amm.reserve_y = new_reserve_y;
+        amm.fee_account_x = 0;
+        amm.fee_account_y = 0;
         amm.capital_weight = new_weights[i];
 
         // Reset epoch accumulators
         amm.epoch_edge = 0.0;
         amm.epoch_trade_count = 0;
+        amm.epoch_sandwiches = 0;
+        amm.epoch_rfq_wins = 0;
+        amm.epoch_rfq_edge = 0.0;
+        amm.epoch_retail_edge = 0.0;
+        amm.epoch_arb_edge = 0.0;
+        amm.epoch_fee_revenue = 0.0;
+        amm.epoch_trade_edge_sq = 0.0;
+        amm.epoch_buy_volume = 0.0;
+        amm.epoch_sell_volume = 0.0;
+        amm.epoch_retail_trades = 0;
+        amm.epoch_arb_trades = 0;
+        amm.epoch_flow_share_sum = 0.0;
+        amm.epoch_largest_trade = 0.0;
+        amm.epoch_inventory_pnl = 0.0;
+        amm.epoch_hold_pnl = 0.0;
+        amm.epoch_hold_x = amm.reserve_x as f64 / SCALE as f64;
+        amm.frozen_at = None;
     }
 
     summaries
 }
 
+/// Fair-value capital audit of one rebalance (`SimConfig::assert_invariants`).
+///
+/// `rebalance_capital` conserves Σ 2·reserve_y, which equals pool value only when every
+/// spot sits at fair; this measures what actually happened at the fair price.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConservationAudit {
+    pub epoch: u32,
+    /// Total value (Y at fair) of reserves plus fee accounts before and after
+    pub before: f64,
+    pub after: f64,
+    /// Capital that left on purpose: protocol take plus turnover costs (Y)
+    pub expected_outflow: f64,
+}
+
+impl ConservationAudit {
+    /// Unexplained change in value (Y): positive means capital was created
+    pub fn drift(&self) -> f64 {
+        self.after - (self.before - self.expected_outflow)
+    }
+
+    /// Err with a description when |drift| exceeds `tolerance` relative to `before`
+    pub fn check(&self, tolerance: f64) -> Result<(), String> {
+        let rel = self.drift().abs() / self.before.max(f64::MIN_POSITIVE);
+        if rel <= tolerance {
+            return Ok(());
+        }
+        Err(format!(
+            "epoch {}: capital drifted by {:.6} Y ({:.3e} relative, tolerance {:.1e}): {:.6} -> {:.6}, expected outflow {:.6}",
+            self.epoch, self.drift(), rel, tolerance, self.before, self.after, self.expected_outflow
+        ))
+    }
+}
+
+/// `rebalance_capital_with`, valuing total capital at `fair_price` before and after
+pub fn rebalance_capital_audited(
+    amms: &mut [AmmState],
+    config: &SimConfig,
+    epoch_number: u32,
+    allocator: &mut dyn CapitalAllocator,
+    fair_price: f64,
+) -> (Vec<EpochSummary>, ConservationAudit) {
+    let before: f64 = amms.iter().map(|a| a.value_at(fair_price)).sum();
+    let summaries = rebalance_capital_with(amms, config, epoch_number, allocator);
+    let after: f64 = amms.iter().map(|a| a.value_at(fair_price)).sum();
+    let expected_outflow = summaries.iter().map(|s| s.protocol_fee + s.turnover_cost).sum();
+    (summaries, ConservationAudit { epoch: epoch_number, before, after, expected_outflow })
+}
+
+// ─── External capital flows ──────────────────────────────────────────────────
+
+/// One scheduled deposit (> 0) or withdrawal (< 0), as a fraction of total capital.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CapitalFlow {
+    /// Applied at the boundary closing this epoch
+    pub epoch: u32,
+    pub fraction: f64,
+}
+
+/// External deposits/withdrawals applied at epoch boundaries (`SimConfig::capital_flows`).
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CapitalFlows {
+    /// Total capital is fixed (original behavior)
+    #[default]
+    None,
+    /// Fixed schedule of flows
+    Schedule(Vec<CapitalFlow>),
+    /// Each boundary draws a flow fraction ~ N(drift, vol²)
+    Random { drift: f64, vol: f64 },
+}
+
+impl CapitalFlows {
+    /// Flow fraction for the boundary closing `epoch`, floored at −95%. The rng is only
+    /// drawn for `Random`.
+    pub fn fraction(&self, epoch: u32, rng: &mut impl Rng) -> f64 {
+        let f = match self {
+            CapitalFlows::None => 0.0,
+            CapitalFlows::Schedule(flows) => {
+                flows.iter().filter(|f| f.epoch == epoch).map(|f| f.fraction).sum()
+            }
+            CapitalFlows::Random { drift, vol } => {
+                Normal::new(*drift, vol.max(0.0)).map(|d| d.sample(rng)).unwrap_or(*drift)
+            }
+        };
+        f.max(-0.95)
+    }
+}
+
+/// Grow or shrink every pool by `fraction`, which distributes the flow by current
+/// capital weight and preserves each spot price. Returns each pool's flow (Y at spot).
+pub fn apply_capital_flow(amms: &mut [AmmState], fraction: f64) -> Vec<f64> {
+    amms.iter_mut().map(|amm| {
+        let before = amm.reserve_y as f64 * 2.0;
+        amm.reserve_x = ((amm.reserve_x as f64 * (1.0 + fraction)) as u64).max(1);
+        amm.reserve_y = ((amm.reserve_y as f64 * (1.0 + fraction)) as u64).max(SCALE);
+        amm.epoch_hold_x = amm.reserve_x as f64 / SCALE as f64;
+        (amm.reserve_y as f64 * 2.0 - before) / SCALE as f64
+    }).collect()
+}
+
+/// Move from `old` toward the allocator's `target` weights by at most `max_delta` per
+/// strategy. The move is scaled uniformly, so weights still sum to 1 and floors hold.
+pub fn limit_turnover(old: &[f64], target: Vec<f64>, max_delta: f64) -> Vec<f64> {
+    let largest = old.iter().zip(&target).map(|(o, t)| (t - o).abs()).fold(0.0, f64::max);
+    if max_delta >= 1.0 || largest <= max_delta {
+        return target;
+    }
+    let step = max_delta / largest;
+    old.iter().zip(&target).map(|(o, t)| o + step * (t - o)).collect()
+}
+
+/// How reserves are treated when capital is reallocated (`SimConfig::rebalance_mode`).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RebalanceMode {
+    /// Rescale both reserves by the weight ratio, keeping each pool's own (possibly
+    /// skewed) spot, so inventory risk carries over to the new capital unpriced
+    #[default]
+    PreserveSpot,
+    /// Re-center every pool at fair price before rescaling (half its value in each
+    /// token), trading the skew away at fair for `rebalance_cost_bps` on the notional
+    InventoryNeutral,
+}
+
+/// Apply a strategy-requested inventory rebalance, if one is pending in its storage.
+///
+/// The request is the target share of pool value held in X, written as an f64 to
+/// `REBALANCE_REQUEST_SLOT`; anything outside (0, 1) means no request. Reserves are
+/// swapped to the target at `fair_price`, paying `cost_bps` on the notional moved, and
+/// the slot is zeroed. Returns the cost paid (Y).
+pub fn apply_rebalance_request(amm: &mut AmmState, fair_price: f64, cost_bps: u32) -> f64 {
+    let off = REBALANCE_REQUEST_SLOT * 8;
+    let target = f64::from_le_bytes(amm.storage[off..off + 8].try_into().unwrap());
+    amm.storage[off..off + 8].fill(0);
+    if !(target > 0.0 && target < 1.0) {
+        return 0.0;
+    }
+    swap_to_share(amm, target, fair_price, cost_bps).1
+}
+
+/// Re-center `amm` at `fair_price` with equal value in X and Y (`RebalanceMode::
+/// InventoryNeutral`). Returns `(inventory_transfer, cost)` in Y: the signed value of X
+/// the pool sold at fair (negative = bought) and the cost paid on it.
+pub fn recenter_at_fair(amm: &mut AmmState, fair_price: f64, cost_bps: u32) -> (f64, f64) {
+    swap_to_share(amm, 0.5, fair_price, cost_bps)
+}
+
+/// Swap reserves at `fair_price` so a `target` share of pool value sits in X, paying
+/// `cost_bps` on the notional moved. Returns `(x_value_sold, cost)` in Y.
+fn swap_to_share(amm: &mut AmmState, target: f64, fair_price: f64, cost_bps: u32) -> (f64, f64) {
+    let x = amm.reserve_x as f64 / SCALE as f64;
+    let y = amm.reserve_y as f64 / SCALE as f64;
+    let value = x * fair_price + y;
+    let sold = x * fair_price - target * value;
+    let cost = sold.abs() * cost_bps as f64 / 10_000.0;
+    let value = value - cost;
+
+    amm.reserve_x = ((target * value / fair_price) * SCALE as f64).max(1.0) as u64;
+    amm.reserve_y = (((1.0 - target) * value) * SCALE as f64).max(1.0) as u64;
+    amm.epoch_hold_x = amm.reserve_x as f64 / SCALE as f64;
+    (sold, cost)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rand::SeedableRng;
 
     #[test]
     fn softmax_weights_sum_to_one() {
@@ -139,4 +936,320 @@ mod tests {
             assert!((w - 0.2).abs() < 1e-8);
         }
     }
+
+    #[test]
+    fn kelly_favors_consistent_positive_edge() {
+        let summary = |mean: f64, var: f64| EpochSummary {
+            trade_edge_mean: mean, trade_edge_var: var, ..EpochSummary::default()
+        };
+        let config = SimConfig { min_capital_weight: 0.0, ..SimConfig::default() };
+        let mut kelly = KellyAllocator { fraction: 0.5 };
+
+        // f = 0.5·μ/σ²: 0.5 and 0.25 claim 75%; the rest is spread evenly
+        let w = kelly.weights(&[summary(1.0, 1.0), summary(1.0, 2.0), summary(-1.0, 1.0)], &config);
+        let expected = [0.5 + 0.25 / 3.0, 0.25 + 0.25 / 3.0, 0.25 / 3.0];
+        for (w, e) in w.iter().zip(expected) {
+            assert!((w - e).abs() < 1e-12, "{w} vs {e}");
+        }
+
+        // Over-claimed fractions are normalized
+        let w = kelly.weights(&[summary(4.0, 1.0), summary(2.0, 1.0)], &config);
+        assert!((w[0] - 0.5).abs() < 1e-12 && (w[1] - 0.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn cvar_allocator_remembers_bad_epochs_within_window() {
+        assert!((cvar(&[5.0, -10.0, 3.0, -2.0], 0.5) - 6.0).abs() < 1e-12);
+
+        let summary = |edge: f64| EpochSummary { edge, ..EpochSummary::default() };
+        let config = SimConfig { min_capital_weight: 0.0, ..SimConfig::default() };
+        let mut alloc = CvarAllocator::new(3, 0.34);
+        // Strategy 0 blows up once, then out-earns strategy 1 while the loss is in the window
+        alloc.weights(&[summary(-100.0), summary(10.0)], &config);
+        let w = alloc.weights(&[summary(30.0), summary(10.0)], &config);
+        assert!(w[0] < w[1], "loss still in window: {w:?}");
+        alloc.weights(&[summary(30.0), summary(10.0)], &config);
+        let w = alloc.weights(&[summary(30.0), summary(10.0)], &config);
+        assert!(w[0] > w[1], "loss aged out: {w:?}");
+    }
+
+    #[test]
+    fn exp3_shifts_capital_to_the_consistent_winner_but_keeps_exploring() {
+        let summary = |edge: f64| EpochSummary { edge, ..EpochSummary::default() };
+        let mut alloc = Exp3Allocator::new(0.2);
+        let mut w = vec![];
+        let config = SimConfig { min_capital_weight: 0.0, ..SimConfig::default() };
+        for _ in 0..50 {
+            w = alloc.weights(&[summary(10.0), summary(0.0), summary(5.0)], &config);
+        }
+        assert!((w.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+        assert!(w[0] > w[2] && w[2] > w[1], "{w:?}");
+        assert!(w.iter().all(|&p| p >= 0.2 / 3.0 - 1e-12), "exploration floor: {w:?}");
+
+        // Every strategy's reward counts in full, however little capital it held
+        let mut alloc = Exp3Allocator::new(0.2);
+        alloc.weights(&[summary(10.0), summary(0.0)], &config);
+        alloc.weights(&[summary(0.0), summary(10.0)], &config);
+        w = alloc.weights(&[summary(5.0), summary(5.0)], &config);
+        assert!((w[0] - w[1]).abs() < 1e-12, "{w:?}");
+
+        // The configured weight floor lifts the loser above the exploration share
+        let mut alloc = Exp3Allocator::new(0.01);
+        let floored = SimConfig { min_capital_weight: 0.2, ..SimConfig::default() };
+        for _ in 0..500 {
+            w = alloc.weights(&[summary(10.0), summary(0.0)], &floored);
+        }
+        assert!(w[1] >= 0.2 && w[1] < 0.21, "{w:?}");
+    }
+
+    #[test]
+    fn rank_ladder_ignores_edge_scale_and_splits_ties() {
+        let summary = |edge: f64| EpochSummary { edge, ..EpochSummary::default() };
+        let config = SimConfig { min_capital_weight: 0.0, ..SimConfig::default() };
+        let mut alloc = RankAllocator { ladder: RankAllocator::default_ladder() };
+
+        let w = alloc.weights(&[summary(1e6), summary(2.0), summary(1.0), summary(-5.0)], &config);
+        assert_eq!(w, vec![0.5, 0.3, 0.2, 0.0]);
+
+        let w = alloc.weights(&[summary(3.0), summary(3.0), summary(1.0)], &config);
+        assert!((w[0] - 0.4).abs() < 1e-12 && (w[1] - 0.4).abs() < 1e-12 && (w[2] - 0.2).abs() < 1e-12);
+    }
+
+    #[test]
+    fn turnover_limit_caps_weight_moves_and_charges_cost() {
+        let w = limit_turnover(&[0.5, 0.5], vec![0.9, 0.1], 0.1);
+        assert!((w[0] - 0.6).abs() < 1e-12 && (w[1] - 0.4).abs() < 1e-12);
+        assert_eq!(limit_turnover(&[0.5, 0.5], vec![0.9, 0.1], 1.0), vec![0.9, 0.1]);
+
+        let mut amms: Vec<AmmState> = (0..2)
+            .map(|i| AmmState::new(100 * SCALE, 10_000 * SCALE, i, "S"))
+            .collect();
+        amms.iter_mut().for_each(|a| a.capital_weight = 0.5);
+        amms[0].epoch_edge = 1_000.0;
+        let config = SimConfig { max_rebalance_delta: 0.1, turnover_cost_bps: 100, ..SimConfig::default() };
+        let summaries = rebalance_capital(&mut amms, &config, 0).unwrap();
+        assert!((amms[0].capital_weight - 0.6).abs() < 1e-9);
+        // 10% of 40_000 Y moved each way at 1%
+        assert!((summaries[0].turnover_cost - 40.0).abs() < 1e-6, "{}", summaries[0].turnover_cost);
+        let capital: u64 = amms.iter().map(|a| a.reserve_y * 2).sum();
+        assert!(capital.abs_diff(39_920 * SCALE) <= 4, "capital = {capital}");
+    }
+
+    #[test]
+    fn capital_flows_scale_pools_by_weight() {
+        let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(0);
+        let schedule = CapitalFlows::Schedule(vec![CapitalFlow { epoch: 2, fraction: 0.5 }]);
+        assert_eq!(schedule.fraction(1, &mut rng), 0.0);
+        assert_eq!(schedule.fraction(2, &mut rng), 0.5);
+        assert_eq!(CapitalFlows::Schedule(vec![CapitalFlow { epoch: 0, fraction: -3.0 }]).fraction(0, &mut rng), -0.95);
+
+        let mut amms = vec![
+            AmmState::new(300 * SCALE, 30_000 * SCALE, 0, "A"),
+            AmmState::new(100 * SCALE, 10_000 * SCALE, 1, "B"),
+        ];
+        let flows = apply_capital_flow(&mut amms, 0.5);
+        assert!((flows[0] - 30_000.0).abs() < 1e-6 && (flows[1] - 10_000.0).abs() < 1e-6);
+        assert_eq!(amms[1].reserve_x, 150 * SCALE);
+        assert!((amms[0].spot_price() - 100.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn schedules_move_temperature_and_lambda_across_epochs() {
+        assert_eq!(Schedule::Constant.value(2.0, 7), 2.0);
+        let linear = Schedule::Linear { end: 0.5, epochs: 3 };
+        assert_eq!((linear.value(2.0, 0), linear.value(2.0, 2), linear.value(2.0, 9)), (2.0, 1.0, 0.5));
+        assert!((Schedule::Exponential { decay: 0.5 }.value(8.0, 3) - 1.0).abs() < 1e-12);
+
+        let config = SimConfig { lambda_schedule: linear, ..SimConfig::default() };
+        assert!(matches!(SimConfig::default().scheduled_for(5), std::borrow::Cow::Borrowed(_)));
+        assert_eq!(config.scheduled_for(3).lambda, 0.5);
+        assert_eq!(config.scheduled_for(3).softmax_temperature, config.softmax_temperature);
+    }
+
+    #[test]
+    fn sharpe_allocator_prefers_steady_over_boom_bust() {
+        let summary = |edge: f64| EpochSummary { edge, ..EpochSummary::default() };
+        let mut alloc = SharpeAllocator::default();
+        let mut w = vec![];
+        // Steady earns 10, 12, 10, 12; boom/bust earns more on average but swings wildly
+        for (steady, wild) in [(10.0, 100.0), (12.0, -60.0), (10.0, 100.0), (12.0, -60.0)] {
+            w = alloc.weights(&[summary(steady), summary(wild)], &SimConfig::default());
+        }
+        assert!(w[0] > w[1], "{w:?}");
+    }
+
+    #[test]
+    fn rebalance_records_and_clears_kill_switch() {
+        let mut amms: Vec<AmmState> = (0..2)
+            .map(|i| AmmState::new(100 * SCALE, 10_000 * SCALE, i, "S"))
+            .collect();
+        amms[1].frozen_at = Some(42);
+
+        let summaries = rebalance_capital(&mut amms, &SimConfig::default(), 0).unwrap();
+        assert_eq!(summaries[0].killed_at_step, None);
+        assert_eq!(summaries[1].killed_at_step, Some(42));
+        assert!(amms.iter().all(|a| a.frozen_at.is_none()));
+    }
+
+    #[test]
+    fn fees_held_outside_reserves_are_swept_into_capital() {
+        let mut amms: Vec<AmmState> = (0..2)
+            .map(|i| AmmState::new(100 * SCALE, 10_000 * SCALE, i, "S"))
+            .collect();
+        // 100 Y in at a 1% implied fee: 1 Y goes to the fee account, 99 Y to reserves
+        amms[0].settle_fill(true, 100 * SCALE, SCALE, 0.01, true);
+        assert_eq!(amms[0].fee_account_y, SCALE);
+        assert_eq!(amms[0].reserve_y, 10_099 * SCALE);
+
+        let before: u128 = amms.iter().map(|a| a.reserve_y as u128 * 2).sum::<u128>() + SCALE as u128;
+        rebalance_capital(&mut amms, &SimConfig::default(), 0).unwrap();
+        let after: u128 = amms.iter().map(|a| a.reserve_y as u128 * 2).sum();
+        assert!(before.abs_diff(after) <= 4, "capital not conserved: {before} vs {after}");
+        assert!(amms.iter().all(|a| a.fee_account_x == 0 && a.fee_account_y == 0));
+    }
+
+    #[test]
+    fn protocol_take_leaves_the_capital_pool() {
+        let mut amms: Vec<AmmState> = (0..2)
+            .map(|i| AmmState::new(100 * SCALE, 10_000 * SCALE, i, "S"))
+            .collect();
+        amms[0].epoch_fee_revenue = 50.0;
+        let config = SimConfig { protocol_fee_share: 0.2, ..SimConfig::default() };
+
+        let summaries = rebalance_capital(&mut amms, &config, 0).unwrap();
+        assert!((summaries[0].protocol_fee - 10.0).abs() < 1e-12);
+        assert_eq!(summaries[1].protocol_fee, 0.0);
+        let capital: u64 = amms.iter().map(|a| a.reserve_y * 2).sum();
+        assert!(capital.abs_diff(39_990 * SCALE) <= 4, "capital = {capital}");
+    }
+
+    #[test]
+    fn rebalance_request_moves_inventory_to_target_at_fair() {
+        let mut amm = AmmState::new(100 * SCALE, 10_000 * SCALE, 0, "S");
+        let off = REBALANCE_REQUEST_SLOT * 8;
+        amm.storage[off..off + 8].copy_from_slice(&0.25f64.to_le_bytes());
+
+        // Value 20_000 Y at fair 100; moving to 25% X swaps 5_000 Y of notional at 10 bps
+        let cost = apply_rebalance_request(&mut amm, 100.0, 10);
+        assert!((cost - 5.0).abs() < 1e-9, "cost = {cost}");
+        assert!((amm.inventory_share_x(100.0) - 0.25).abs() < 1e-9);
+        assert!(amm.storage[off..off + 8].iter().all(|&b| b == 0), "request consumed");
+        assert_eq!(apply_rebalance_request(&mut amm, 100.0, 10), 0.0);
+    }
+
+    #[test]
+    fn recenter_trades_skew_away_at_fair() {
+        // Spot 100 but fair 80: the pool is long X worth 8_000 Y against 10_000 Y
+        let mut amm = AmmState::new(100 * SCALE, 10_000 * SCALE, 0, "S");
+        let (transfer, cost) = recenter_at_fair(&mut amm, 80.0, 10);
+
+        // Balanced at 9_000 Y a side, the pool bought 1_000 Y of X
+        assert!((transfer + 1_000.0).abs() < 1e-9, "transfer = {transfer}");
+        assert!((cost - 1.0).abs() < 1e-9, "cost = {cost}");
+        assert!((amm.inventory_share_x(80.0) - 0.5).abs() < 1e-9);
+        assert!((amm.spot_price() - 80.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn audit_passes_at_fair_and_flags_skewed_spots() {
+        let mut amms: Vec<AmmState> = (0..2)
+            .map(|i| AmmState::new(100 * SCALE, 10_000 * SCALE, i, "S"))
+            .collect();
+        amms[0].epoch_edge = 5.0;
+        let config = SimConfig::default();
+        let mut alloc = SoftmaxAllocator;
+
+        let (_, audit) = rebalance_capital_audited(&mut amms, &config, 0, &mut alloc, 100.0);
+        assert!(audit.check(1e-9).is_ok(), "{:?}", audit.check(1e-9));
+
+        // Pool 1 quotes 121 against fair 100: Σ 2·reserve_y overstates its value
+        amms[1] = AmmState::new(100 * SCALE, 11_000 * SCALE, 1, "S");
+        amms[1].reserve_x = (amms[1].reserve_x as f64 / 1.1) as u64;
+        amms[0].epoch_edge = 5.0;
+        let (_, audit) = rebalance_capital_audited(&mut amms, &config, 1, &mut alloc, 100.0);
+        assert!(audit.drift() > 1.0, "drift = {}", audit.drift());
+        assert!(audit.check(1e-6).is_err());
+    }
+
+    #[test]
+    fn plugin_weights_are_sanitized() {
+        let w = sanitize_weights(vec![3.0, f64::NAN, -1.0, 1.0], 0.0);
+        assert_eq!(w, vec![0.75, 0.0, 0.0, 0.25]);
+
+        let w = sanitize_weights(vec![0.0, f64::INFINITY], 0.1);
+        assert!(w.iter().all(|&x| (x - 0.5).abs() < 1e-12), "{w:?}");
+    }
+
+    #[test]
+    fn missing_plugin_is_a_config_error_not_a_panic() {
+        let path = std::env::temp_dir().join("no-such-allocator.so");
+        let kind = AllocatorKind::Plugin { path: path.clone() };
+        let err = kind.build().err().expect("nothing to load");
+        assert!(err.starts_with("allocator plugin") && err.contains("no-such-allocator.so"), "{err}");
+
+        let config = SimConfig { allocator: kind, ..SimConfig::default() };
+        assert!(config.validate().unwrap_err().contains("allocator plugin"));
+        let mut amms = vec![AmmState::new(100 * SCALE, 10_000 * SCALE, 0, "a")];
+        assert!(rebalance_capital(&mut amms, &config, 0).is_err());
+    }
+
+    #[test]
+    fn per_strategy_bounds_cap_and_floor() {
+        // House strategy capped at 20%; its excess goes to the others pro rata
+        let w = enforce_weight_bounds(vec![0.6, 0.3, 0.1], &[(0.0, 0.2), (0.0, 1.0), (0.0, 1.0)]);
+        assert!((w[0] - 0.2).abs() < 1e-12);
+        assert!((w[1] - 0.6).abs() < 1e-12 && (w[2] - 0.2).abs() < 1e-12, "{w:?}");
+
+        // Cascading: lifting a floor pushes another strategy past its ceiling
+        let w = enforce_weight_bounds(vec![0.5, 0.45, 0.05], &[(0.0, 0.4), (0.0, 0.4), (0.25, 1.0)]);
+        assert!((w.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+        assert!(w[0] <= 0.4 + 1e-12 && w[1] <= 0.4 + 1e-12 && w[2] >= 0.25 - 1e-12, "{w:?}");
+
+        let bound = CapitalBound { strategy: "House".into(), floor: None, ceiling: Some(0.2) };
+        assert!(bound.matches("House") && bound.matches("House #2"));
+        assert!(!bound.matches("Housekeeper"));
+    }
+
+    #[test]
+    fn rebalance_enforces_configured_ceiling() {
+        let mut amms: Vec<AmmState> = ["House", "Other"]
+            .iter()
+            .enumerate()
+            .map(|(i, n)| AmmState::new(100 * SCALE, 10_000 * SCALE, i as u8, n))
+            .collect();
+        amms[0].epoch_edge = 100.0;
+        let config = SimConfig {
+            capital_bounds: vec![CapitalBound { strategy: "House".into(), floor: None, ceiling: Some(0.2) }],
+            ..SimConfig::default()
+        };
+        rebalance_capital(&mut amms, &config, 0).unwrap();
+        assert!((amms[0].capital_weight - 0.2).abs() < 1e-12, "w = {}", amms[0].capital_weight);
+    }
+
+    #[test]
+    fn summaries_explain_the_allocation() {
+        let mut amms: Vec<AmmState> = (0..2)
+            .map(|i| AmmState::new(100 * SCALE, 10_000 * SCALE, i, "S"))
+            .collect();
+        amms[0].epoch_edge = 4.0;
+        amms[1].epoch_edge = -1.0;
+        let prior = amms[1].capital_weight;
+        let summaries = rebalance_capital(&mut amms, &SimConfig::default(), 0).unwrap();
+        let c = summaries[1].score_components;
+        assert_eq!((c.score, c.penalty, c.window_mean), (-3.0, 2.0, -1.0));
+        assert_eq!(summaries[1].prior_weight, prior);
+        assert_eq!(summaries[1].new_weight, amms[1].capital_weight);
+
+        // CVaR scores on its window: mean of [-1, 3] less λ·CVaR = 1 − 2·1
+        let config = SimConfig { allocator: AllocatorKind::Cvar { window: 2, alpha: 0.5 }, ..SimConfig::default() };
+        let mut alloc = config.allocator.build().unwrap();
+        for edge in [-1.0, 3.0] {
+            amms.iter_mut().for_each(|a| a.epoch_edge = edge);
+            let summaries = rebalance_capital_with(&mut amms, &config, 0, alloc.as_mut());
+            let c = summaries[0].score_components;
+            assert_eq!(c.window_epochs, if edge < 0.0 { 1 } else { 2 });
+        }
+        let c = alloc.components(&[], &config).unwrap()[0];
+        assert_eq!((c.window_mean, c.penalty, c.score, c.window_std), (1.0, 2.0, -1.0, 2.0));
+    }
 }
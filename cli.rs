@@ -1,14 +1,32 @@
 use std::fs;
+use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::OnceLock;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use anyhow::{bail, Context, Result};
-use clap::{Parser, Subcommand};
-use prop_amm_engine::runner::StrategyRunner;
-use prop_amm_engine::sim::run_parallel;
-use prop_amm_engine::types::{SimConfig, STORAGE_SIZE};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use indicatif::{ProgressBar, ProgressStyle};
+use prop_amm_engine::compare;
+use prop_amm_engine::compile::{self, Compiled};
+use prop_amm_engine::events::{parse_slots, write_step_log};
+use prop_amm_engine::fingerprint::{find_duplicates, DuplicatePair, Fingerprint};
+use prop_amm_engine::leaderboard::Leaderboard;
+use prop_amm_engine::manifest;
+use prop_amm_engine::profile::{SimProfile, Timing};
+use prop_amm_engine::optimize::{self, SearchMethod, Tunable};
+use prop_amm_engine::receipt;
+use prop_amm_engine::reference;
+use prop_amm_engine::scaffold::{self, Template};
+use prop_amm_engine::runner::{check_determinism, serve_worker, Runtime, StrategyRunner, WORKER_ARG};
+use prop_amm_engine::sim::{aggregate_by_regime, holdout_commitment, holdout_seeds, parse_seeds, run_batch, BatchOptions, BatchResult, CancelToken, SimProgress};
+use prop_amm_engine::types::SimConfig;
+use prop_amm_engine::validation::{self, Severity, ValidationReport};
+use prop_amm_engine::watch::{file_stamp, SaveDetector, WatchScore};
 use serde_json::json;
+use sha2::{Digest, Sha256};
 
 #[derive(Parser)]
 #[command(name = "prop-amm-multi", about = "CLI for Prop AMM Multi strategies")]
@@ -19,135 +37,1762 @@ struct Cli {
 
 #[derive(Subcommand)]
 enum Commands {
+	/// Check each strategy's quotes on both sides over a grid of sizes, reserve levels and
+	/// skews (zero input, monotonicity, concavity, staying inside the reserve), feed it
+	/// truncated and random payloads, and check that it quotes deterministically
 	Validate {
+		/// Strategy sources; prefix one with `wasm:` to run it in the WASM sandbox, or pass a
+		/// prebuilt Solana program as `sbf:<program.so>`
 		files: Vec<PathBuf>,
+		#[command(flatten)]
+		dir: StrategyDirOpts,
+		/// Also write every check's outcome as JSON
+		#[arg(long)]
+		report: Option<PathBuf>,
+	},
+	/// Write a ready-to-validate starter strategy: the SDK entry points, a storage layout
+	/// table and local tests
+	New {
+		/// Strategy name, also the file (or project directory) name
+		name: String,
+		#[arg(long, value_enum, default_value_t = StarterTemplate::VolAdaptive)]
+		template: StarterTemplate,
+		/// Directory to create the strategy in
+		#[arg(long, default_value = ".")]
+		dir: PathBuf,
+		/// Create a cargo project (`<name>/Cargo.toml`, `<name>/src/lib.rs`) instead of a
+		/// single source file
+		#[arg(long)]
+		cargo: bool,
 	},
 	Run {
+		/// Strategy sources, prebuilt artifacts (`.so`, `.dylib`, `.dll`, `.wasm`) and
+		/// built-in reference names (`fixed_30`, `field`, …), in any mix; prefix a source
+		/// with `wasm:` to run it in the WASM sandbox, or pass a prebuilt Solana program as
+		/// `sbf:<program.so>`
 		files: Vec<PathBuf>,
+		#[command(flatten)]
+		dir: StrategyDirOpts,
 		#[arg(long, default_value_t = 100)]
 		simulations: usize,
-		#[arg(long, default_value_t = 10_000)]
-		steps: usize,
-		#[arg(long, default_value_t = 1_000)]
-		epoch_len: usize,
-		#[arg(long, default_value_t = 0)]
-		seed_start: u64,
+		#[command(flatten)]
+		output: OutputOpts,
+		#[command(flatten)]
+		opts: RunOpts,
 	},
 	Submit {
+		/// Strategy sources; prefix one with `wasm:` to run it in the WASM sandbox, or pass a
+		/// prebuilt Solana program as `sbf:<program.so>`
 		files: Vec<PathBuf>,
+		#[command(flatten)]
+		dir: StrategyDirOpts,
 		#[arg(long, default_value_t = 250)]
 		simulations: usize,
-		#[arg(long, default_value_t = 10_000)]
-		steps: usize,
-		#[arg(long, default_value_t = 1_000)]
-		epoch_len: usize,
+		#[command(flatten)]
+		submit: SubmitOpts,
+		#[command(flatten)]
+		opts: RunOpts,
+	},
+	/// Score one strategy against the standard reference field on fixed seeds, overall and
+	/// per volatility regime (`--opponents` is ignored)
+	Bench {
+		/// Strategy source, prebuilt artifact or built-in reference name; prefix a source
+		/// with `wasm:` to run it in the WASM sandbox, or pass a prebuilt Solana program as
+		/// `sbf:<program.so>`
+		file: PathBuf,
+		#[arg(long, default_value_t = 100)]
+		simulations: usize,
+		#[command(flatten)]
+		opts: RunOpts,
+	},
+	/// Recompile a strategy and rerun a quick benchmark every time the file is saved,
+	/// printing how its key metrics moved since the previous run (`--steps` defaults to
+	/// 2000; the field is the standard reference field unless `--opponents` is given)
+	Watch {
+		/// Strategy source; prefix it with `wasm:` to run it in the WASM sandbox
+		file: PathBuf,
+		#[arg(long, default_value_t = 20)]
+		simulations: usize,
+		#[command(flatten)]
+		opts: RunOpts,
+	},
+	/// Rerun one seed with trade and storage tracing and write a per-step JSONL log
+	Replay {
+		/// Strategy sources, artifacts and built-in reference names, as passed to the run
+		/// that produced the seed
+		files: Vec<PathBuf>,
+		#[command(flatten)]
+		dir: StrategyDirOpts,
+		#[arg(long)]
+		seed: u64,
+		/// JSONL log to write
+		#[arg(long)]
+		trace: PathBuf,
+		#[command(flatten)]
+		opts: SimOpts,
+	},
+	/// Run one seed and follow storage slots of a strategy across its trades, decoded as
+	/// u64 and f64
+	Inspect {
+		/// Strategy source; prefix it with `wasm:` to run it in the WASM sandbox, or pass a
+		/// prebuilt Solana program as `sbf:<program.so>`
+		file: PathBuf,
+		#[arg(long, default_value_t = 0)]
+		seed: u64,
+		/// Slots to follow: slots and half-open ranges, e.g. `0..16` or `2,3,7`
+		#[arg(long, default_value = "0..16")]
+		slots: String,
+		/// Trades to print, evenly spaced over the run
+		#[arg(long, default_value_t = 40)]
+		rows: usize,
+		/// Also write every trade's slot values as CSV
+		#[arg(long)]
+		out: Option<PathBuf>,
+		#[command(flatten)]
+		opts: SimOpts,
+	},
+	/// Evaluate strategies on every cell of a grid of pinned market parameters and write
+	/// edge per cell and strategy as CSV
+	Sweep {
+		/// Strategy sources, prebuilt artifacts and built-in reference names, in any mix;
+		/// prefix a source with `wasm:` to run it in the WASM sandbox, or pass a prebuilt
+		/// Solana program as `sbf:<program.so>`
+		files: Vec<PathBuf>,
+		#[command(flatten)]
+		dir: StrategyDirOpts,
+		#[command(flatten)]
+		grid: SweepGrid,
+		/// Simulations per cell, on the same seeds in every cell
+		#[arg(long, default_value_t = 20)]
+		simulations: usize,
+		/// CSV to write
+		#[arg(long, default_value = "sweep.csv")]
+		out: PathBuf,
+		#[command(flatten)]
+		opts: RunOpts,
+	},
+	/// Tune a strategy's annotated constants (`// tune: MIN..MAX`) by recompiling it with
+	/// candidate values and scoring each on the same seeds
+	Optimize {
+		/// Strategy source; prefix it with `wasm:` to run it in the WASM sandbox
+		file: PathBuf,
+		/// JSON sidecar of `{"NAME": [min, max]}` ranges, used alongside the annotations
+		/// [default: <file stem>.tune.json next to the source, if present]
+		#[arg(long)]
+		tunables: Option<PathBuf>,
+		#[arg(long, value_enum, default_value_t = Method::Cmaes)]
+		method: Method,
+		/// Grid points per constant (`grid`)
+		#[arg(long, default_value_t = 3)]
+		points: usize,
+		/// Candidates to draw (`random`)
+		#[arg(long, default_value_t = 30)]
+		trials: usize,
+		/// Generations to run (`cmaes`)
+		#[arg(long, default_value_t = 8)]
+		generations: usize,
+		/// Seed for the search's own draws (`random`, `cmaes`)
 		#[arg(long, default_value_t = 0)]
-		seed_start: u64,
+		search_seed: u64,
+		/// Simulations per candidate, on the same seeds for every candidate
+		#[arg(long, default_value_t = 20)]
+		simulations: usize,
+		#[command(flatten)]
+		opts: RunOpts,
+	},
+	/// Run every strategy in a directory in tournament mode and update a persistent
+	/// leaderboard, printing rank changes since the previous run
+	Tournament {
+		/// Directory of strategies: the enabled entries of its `strategies.toml`, or else
+		/// every source, artifact and remote manifest in it
+		#[arg(long)]
+		dir: PathBuf,
+		#[arg(long, default_value_t = 250)]
+		simulations: usize,
+		/// Leaderboard file, created on first use [default: <dir>/leaderboard.json]
+		#[arg(long)]
+		leaderboard: Option<PathBuf>,
+		#[command(flatten)]
+		opts: RunOpts,
+	},
+	/// Diff two submission receipts: metric deltas per strategy, with significance flags,
+	/// and any differences in how the runs were configured
+	Compare {
+		/// Baseline receipt
+		a: PathBuf,
+		/// Receipt compared against the baseline
+		b: PathBuf,
+	},
+	/// Check a submission receipt: its signature, the content hashes of the files stored
+	/// beside it and its config hash; `--rerun` also replays its seeds
+	Verify {
+		receipt: PathBuf,
+		/// Key the receipt was signed with [default: $PROP_AMM_RECEIPT_KEY; the signature is
+		/// not checked without either]
+		#[arg(long)]
+		receipt_key: Option<String>,
+		/// Recompile the stored sources, rerun the recorded seeds under the recorded config
+		/// and require every strategy's mean edge to match
+		#[arg(long)]
+		rerun: bool,
+	},
+	/// Host a competition backend: an HTTP API that takes strategy uploads, scores them in
+	/// turn against the standard reference field and serves a leaderboard kept in SQLite
+	/// (build with `--features serve`; `--opponents` is ignored, `--tournament` and
+	/// `--isolate` implied)
+	Serve {
+		#[arg(long, default_value = "127.0.0.1:8080")]
+		addr: String,
+		/// SQLite database, created on first use
+		#[arg(long, default_value = "submissions.db")]
+		db: PathBuf,
+		/// Directory uploaded sources are written to before they are compiled
+		#[arg(long, default_value = "target/serve")]
+		work_dir: PathBuf,
+		#[arg(long, default_value_t = 100)]
+		simulations: usize,
+		#[command(flatten)]
+		opts: RunOpts,
 	},
+	/// Serve one strategy to a host process over stdin/stdout (used by `--isolate`)
+	#[command(name = WORKER_ARG, hide = true)]
+	Worker { artifact: PathBuf },
+}
+
+/// Simulation options shared by `run` and `submit`.
+#[derive(Args)]
+struct RunOpts {
+	#[command(flatten)]
+	sim: SimOpts,
+	#[arg(long, default_value_t = 0)]
+	seed_start: u64,
+	/// File listing the exact seeds to run, in place of `--seed-start` and the simulation
+	/// count: integers or half-open `A..B` ranges separated by whitespace or commas, `#`
+	/// comments
+	#[arg(long, conflicts_with = "seed_start")]
+	seeds: Option<PathBuf>,
+	/// Tournament mode: refuse native strategies that import network, filesystem,
+	/// thread or clock functions, or carry more than 1 MiB of static data, and flag
+	/// submissions that duplicate each other
+	#[arg(long)]
+	tournament: bool,
+}
+
+/// Settings for the simulations themselves, shared by every command that runs one; the
+/// single-seed `replay` and `inspect` take only these.
+#[derive(Args)]
+struct SimOpts {
+	/// Simulation settings as TOML (any `SimConfig` field; omitted ones keep their
+	/// defaults). Flags given on the command line override the file.
+	#[arg(long)]
+	config: Option<PathBuf>,
+	/// Steps per simulation [default: 10000]
+	#[arg(long)]
+	steps: Option<usize>,
+	/// Steps per epoch; must divide the step count [default: 1000]
+	#[arg(long)]
+	epoch_len: Option<usize>,
+	/// Run each strategy as N independent competing copies; with the opponents, at most
+	/// 16 strategies in all [default: 1]
+	#[arg(long)]
+	self_play: Option<usize>,
+	/// Run each native strategy in its own worker process, so one that crashes is
+	/// disabled instead of aborting the run
+	#[arg(long)]
+	isolate: bool,
+	/// Fence each native strategy's payload and storage with canaries and disqualify
+	/// any strategy that writes outside its storage
+	#[arg(long)]
+	harden: bool,
+	/// Disqualify a strategy after this many quotes that exceed the pool's output
+	/// reserve (such quotes are always refused)
+	#[arg(long)]
+	over_quote_strikes: Option<u32>,
+	/// Built-in reference opponents to add to the field, comma-separated (`fixed_10` …
+	/// `fixed_100`, `oracle_anchored`, `inventory_skew`, `chaos_monkey`, or `field` for a
+	/// standard mix)
+	#[arg(long, value_delimiter = ',')]
+	opponents: Vec<String>,
+	/// Print each native strategy's `log!` output from the first simulation (strategies
+	/// must export `__prop_amm_log`; not collected under `--isolate`, WASM or SBF)
+	#[arg(long)]
+	debug_strategy: bool,
+}
+
+/// Strategies from a directory, taken after those listed on the command line.
+#[derive(Args)]
+struct StrategyDirOpts {
+	/// Directory of strategies: the enabled entries of its `strategies.toml` manifest, or
+	/// else every source, artifact and remote manifest in it
+	#[arg(long)]
+	strategies_dir: Option<PathBuf>,
+}
+
+impl StrategyDirOpts {
+	/// `files` followed by the directory's strategies.
+	fn with(&self, mut files: Vec<PathBuf>) -> Result<Vec<PathBuf>> {
+		if let Some(dir) = &self.strategies_dir {
+			let found = manifest::inputs(dir).map_err(anyhow::Error::msg)?;
+			if found.is_empty() {
+				bail!("no strategies in {}", dir.display());
+			}
+			files.extend(found);
+		}
+		Ok(files)
+	}
+}
+
+/// Hold-out evaluation and receipt signing for `submit`.
+#[derive(Args)]
+struct SubmitOpts {
+	#[command(flatten)]
+	holdout: HoldoutOpts,
+	/// Host key the receipt is signed with (HMAC-SHA256) [default: $PROP_AMM_RECEIPT_KEY;
+	/// the receipt is left unsigned without either]
+	#[arg(long)]
+	receipt_key: Option<String>,
+}
+
+/// Receipt signing key: `flag`, else `$PROP_AMM_RECEIPT_KEY` if set and non-empty.
+fn receipt_key(flag: &Option<String>) -> Option<String> {
+	flag.clone().or_else(|| std::env::var("PROP_AMM_RECEIPT_KEY").ok().filter(|k| !k.is_empty()))
+}
+
+/// Sealed hold-out evaluation for `submit`, opt-in because it reruns the whole field.
+#[derive(Args)]
+struct HoldoutOpts {
+	/// Host key the sealed hold-out seeds are derived from [default: $PROP_AMM_HOLDOUT_KEY].
+	/// With neither there is no hold-out run. With a key, the field is rerun on the
+	/// hold-out seeds, which at the default count doubles the time `submit` takes
+	#[arg(long)]
+	holdout_key: Option<String>,
+	/// Hold-out simulations, on seeds disjoint from the development set; 0 skips the
+	/// hold-out [default: as many as the development set]
+	#[arg(long)]
+	holdout_simulations: Option<usize>,
+}
+
+/// A hold-out evaluation, for the receipt.
+struct HoldoutRun {
+	commitment: String,
+	/// Where the key came from: `flag` or `env`
+	key_source: &'static str,
+	simulations: usize,
+	batch: BatchResult,
+}
+
+/// Machine-readable results and diagnostics for `run`.
+#[derive(Args, Default)]
+struct OutputOpts {
+	/// Results format. `json` and `csv` carry every aggregated field; written to stdout
+	/// they replace the tables, and notices go to stderr
+	#[arg(long, value_enum, default_value_t)]
+	output: OutputFormat,
+	/// Write `--output` results to this file instead of stdout (the tables still print)
+	#[arg(long)]
+	output_file: Option<PathBuf>,
+	/// Include per-simulation strategy results: a `per_simulation` array in JSON, or one
+	/// CSV row per seed and strategy instead of one per strategy
+	#[arg(long)]
+	per_sim: bool,
+	/// Report where the wall time went: strategy calls per strategy and entry point, then
+	/// the engine's routing, arbitrage and rebalancing (net of the strategy calls inside)
+	#[arg(long)]
+	profile: bool,
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+	#[default]
+	Table,
+	Json,
+	Csv,
+}
+
+/// Starter for `new`.
+#[derive(Clone, Copy, ValueEnum)]
+enum StarterTemplate {
+	VolAdaptive,
+	FixedFee,
+	InventorySkew,
+}
+
+/// Search method for `optimize`.
+#[derive(Clone, Copy, ValueEnum)]
+enum Method {
+	Grid,
+	Random,
+	Cmaes,
+}
+
+/// Grid axes for `sweep`, each comma-separated. An axis left out is sampled per seed as
+/// in a normal run.
+#[derive(Args)]
+struct SweepGrid {
+	/// Per-step volatilities σ
+	#[arg(long, value_delimiter = ',')]
+	sigma: Vec<f64>,
+	/// Retail arrival rates λ (orders per step)
+	#[arg(long, value_delimiter = ',')]
+	lambda: Vec<f64>,
+	/// Normalizer fees (bps)
+	#[arg(long, value_delimiter = ',')]
+	norm_fee: Vec<u32>,
+	/// Normalizer liquidity multipliers
+	#[arg(long, value_delimiter = ',')]
+	norm_liquidity: Vec<f64>,
+}
+
+impl SweepGrid {
+	/// `config` pinned to every combination of the axes, σ varying slowest.
+	fn cells(&self, config: &SimConfig) -> Vec<SimConfig> {
+		fn axis<T: Copy>(values: &[T]) -> Vec<Option<T>> {
+			if values.is_empty() { vec![None] } else { values.iter().copied().map(Some).collect() }
+		}
+		let mut cells = Vec::new();
+		for &sigma in &axis(&self.sigma) {
+			for &lambda in &axis(&self.lambda) {
+				for &fee in &axis(&self.norm_fee) {
+					for &liquidity in &axis(&self.norm_liquidity) {
+						cells.push(SimConfig {
+							sigma_per_step: sigma.or(config.sigma_per_step),
+							orders_per_step: lambda.or(config.orders_per_step),
+							norm_fee_bps: fee.or(config.norm_fee_bps),
+							norm_liquidity_mult: liquidity.or(config.norm_liquidity_mult),
+							..config.clone()
+						});
+					}
+				}
+			}
+		}
+		cells
+	}
 }
 
 fn main() -> Result<()> {
 	let cli = Cli::parse();
 	match cli.command {
-		Commands::Validate { files } => validate_cmd(&files),
-		Commands::Run {
-			files,
-			simulations,
-			steps,
-			epoch_len,
-			seed_start,
-		} => run_cmd(&files, simulations, steps, epoch_len, seed_start, false),
-		Commands::Submit {
-			files,
-			simulations,
-			steps,
-			epoch_len,
-			seed_start,
-		} => run_cmd(&files, simulations, steps, epoch_len, seed_start, true),
+		Commands::Validate { files, dir, report } => {
+			validate_reporting(&dir.with(files)?, false, false, report.as_deref(), |line| println!("{line}")).map(|_| ())
+		}
+		Commands::New { name, template, dir, cargo } => {
+			let template = match template {
+				StarterTemplate::VolAdaptive => Template::VolAdaptive,
+				StarterTemplate::FixedFee => Template::FixedFee,
+				StarterTemplate::InventorySkew => Template::InventorySkew,
+			};
+			new_cmd(&name, template, &dir, cargo)
+		}
+		Commands::Run { files, dir, simulations, output, opts } => {
+			run_cmd(&dir.with(files)?, simulations, &opts, &output, None).map(|_| ())
+		}
+		Commands::Submit { files, dir, simulations, submit, opts } => {
+			run_cmd(&dir.with(files)?, simulations, &opts, &OutputOpts::default(), Some(&submit)).map(|_| ())
+		}
+		Commands::Bench { file, simulations, opts } => bench_cmd(&file, simulations, &opts),
+		Commands::Watch { file, simulations, opts } => watch_cmd(&file, simulations, &opts),
+		Commands::Replay { files, dir, seed, trace, opts } => replay_cmd(&dir.with(files)?, seed, &trace, &opts),
+		Commands::Inspect { file, seed, slots, rows, out, opts } => inspect_cmd(&file, seed, &slots, rows, out.as_deref(), &opts),
+		Commands::Sweep { files, dir, grid, simulations, out, opts } => {
+			sweep_cmd(&dir.with(files)?, &grid, simulations, &out, &opts)
+		}
+		Commands::Optimize { file, tunables, method, points, trials, generations, search_seed, simulations, opts } => {
+			let method = match method {
+				Method::Grid => SearchMethod::Grid { points },
+				Method::Random => SearchMethod::Random { trials },
+				Method::Cmaes => SearchMethod::Cmaes { generations },
+			};
+			optimize_cmd(&file, tunables.as_deref(), method, search_seed, simulations, &opts)
+		}
+		Commands::Tournament { dir, simulations, leaderboard, mut opts } => {
+			opts.tournament = true;
+			tournament_cmd(&dir, simulations, leaderboard.as_deref(), &opts)
+		}
+		Commands::Compare { a, b } => compare_cmd(&a, &b),
+		Commands::Verify { receipt, receipt_key: key, rerun } => verify_cmd(&receipt, receipt_key(&key).as_deref(), rerun),
+		Commands::Serve { addr, db, work_dir, simulations, opts } => serve_cmd(addr, db, work_dir, simulations, &opts),
+		Commands::Worker { artifact } => serve_worker(&artifact).map_err(|e| anyhow::anyhow!("{e}")),
 	}
 }
 
-fn validate_cmd(files: &[PathBuf]) -> Result<()> {
+/// Compile and check every strategy, returning the artifacts in `files` order.
+fn validate_cmd(files: &[PathBuf], isolate: bool, tournament: bool) -> Result<Vec<PathBuf>> {
+	validate_reporting(files, isolate, tournament, None, |line| println!("{line}"))
+}
+
+/// `validate_cmd`, handing each status line to `report` and writing the checks' outcomes
+/// to `json` if given. Every strategy is checked before a failure is returned.
+fn validate_reporting(
+	files: &[PathBuf],
+	isolate: bool,
+	tournament: bool,
+	json: Option<&Path>,
+	report: impl Fn(String) + Sync,
+) -> Result<Vec<PathBuf>> {
 	if files.is_empty() {
 		bail!("Provide at least one strategy source file.");
 	}
 
-	for file in files {
-		let artifact = compile_strategy(file)?;
-		let runner = StrategyRunner::load(&artifact).map_err(|e| {
+	let artifacts = compile_all(files, &report)?;
+	let mut reports = Vec::new();
+	let mut failed = Vec::new();
+	for (file, artifact) in files.iter().zip(&artifacts) {
+		let artifact = artifact.clone();
+		// Prebuilt libraries are checked like compiled ones: the load fails unless their
+		// ABI handshake (`__prop_amm_abi_version`) matches this engine
+		let runtime = match Runtime::for_artifact(&artifact) {
+			Runtime::Native if isolate => Runtime::Subprocess,
+			runtime => runtime,
+		};
+		let load = if tournament { StrategyRunner::load_audited } else { StrategyRunner::load_with };
+		let runner = load(&artifact, runtime).map_err(|e| {
 			anyhow::anyhow!("failed to load compiled strategy for {}: {e}", file.display())
 		})?;
 
-		let storage = [0u8; STORAGE_SIZE];
-		let rx = 100 * 1_000_000_000u64;
-		let ry = 10_000 * 1_000_000_000u64;
+		let mut checks = ValidationReport { strategy: runner.name.clone(), checks: validation::check_quotes(&runner) };
 
-		let out_small = runner.compute_swap(true, 1_000_000_000u64, rx, ry, &storage);
-		let out_large = runner.compute_swap(true, 5_000_000_000u64, rx, ry, &storage);
-		if out_small == 0 || out_large == 0 {
-			bail!("{} produced zero output on validation quotes", file.display());
+		// A second instance in its own process (or VM) must quote exactly the same, and
+		// takes the malformed payloads, which may crash it
+		let fresh_runtime = match runtime {
+			Runtime::Native => Runtime::Subprocess,
+			runtime => runtime,
+		};
+		let fresh = StrategyRunner::load_with(&artifact, fresh_runtime).map_err(|e| {
+			anyhow::anyhow!("failed to load a second instance of {}: {e}", file.display())
+		})?;
+		checks.push("determinism", Severity::Error, check_determinism(&runner, &fresh).err().map(|e| e.to_string()));
+		checks.checks.push(validation::check_payloads(&fresh, 0));
+
+		for c in checks.checks.iter().filter(|c| !c.passed) {
+			let label = if c.severity == Severity::Error { "FAIL" } else { "WARN" };
+			report(format!("[{label}] {} {}: {}", file.display(), c.name, c.detail));
 		}
-		if out_large <= out_small {
-			bail!("{} failed monotonicity check", file.display());
+		if !checks.passed() {
+			failed.push(file.display().to_string());
+		} else {
+			match &runner.metadata.model {
+				Some(model) => report(format!("[PASS] {} (model: {model})", file.display())),
+				None => report(format!("[PASS] {}", file.display())),
+			}
 		}
+		reports.push(checks);
+	}
 
-		println!("[PASS] {}", file.display());
+	if let Some(path) = json {
+		fs::write(path, serde_json::to_vec_pretty(&reports)?).with_context(|| format!("failed to write {}", path.display()))?;
 	}
+	if !failed.is_empty() {
+		bail!("validation failed for {}", failed.join(", "));
+	}
+	Ok(artifacts)
+}
+
+/// Compile `files` in parallel (see `compile::build_all`). Cargo builds share the SDK
+/// package, so it is written once up front rather than by racing builds.
+fn compile_all(files: &[PathBuf], report: &(impl Fn(String) + Sync)) -> Result<Vec<PathBuf>> {
+	if files.iter().any(|f| needs_cargo(f)) {
+		sdk_dir()?;
+	}
+	compile::build_all(files, build_strategy, report)
+}
+
+/// Whether `arg` is a source `build_strategy` builds through a cargo project.
+fn needs_cargo(arg: &Path) -> bool {
+	let (file, _) = strip_runtime(arg);
+	fs::read_to_string(file).is_ok_and(|source| matches!(compile::cargo_deps(&source), Ok(Some(_))))
+}
+
+/// Write a starter strategy under `dir`, refusing to overwrite anything.
+fn new_cmd(name: &str, template: Template, dir: &Path, cargo: bool) -> Result<()> {
+	scaffold::check_name(name).map_err(anyhow::Error::msg)?;
+	let (source_path, next) = if cargo {
+		let root = dir.join(name);
+		if root.exists() {
+			bail!("{} already exists", root.display());
+		}
+		fs::create_dir_all(root.join("src")).with_context(|| format!("failed to create {}", root.display()))?;
+		fs::write(root.join("Cargo.toml"), scaffold::cargo_manifest(name))?;
+		let lib = root.join("src").join("lib.rs");
+		fs::write(&lib, scaffold::render_crate(template, name))?;
+		(lib, format!("cd {} && cargo test", root.display()))
+	} else {
+		let file = dir.join(format!("{name}.rs"));
+		if file.exists() {
+			bail!("{} already exists", file.display());
+		}
+		fs::create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))?;
+		fs::write(&file, scaffold::render_file(template, name)).with_context(|| format!("failed to write {}", file.display()))?;
+		(file.clone(), format!("rustc --edition 2021 --test {0} -o {name}_tests && ./{name}_tests", file.display()))
+	};
 
+	println!("Created {}", source_path.display());
+	println!("\nNext steps:");
+	println!("  {next}");
+	println!("  prop-amm-multi validate {}", source_path.display());
+	println!("  prop-amm-multi bench {}", source_path.display());
 	Ok(())
 }
 
 fn run_cmd(
 	files: &[PathBuf],
 	simulations: usize,
-	steps: usize,
-	epoch_len: usize,
-	seed_start: u64,
-	submit_mode: bool,
-) -> Result<()> {
+	opts: &RunOpts,
+	output: &OutputOpts,
+	submit: Option<&SubmitOpts>,
+) -> Result<BatchResult> {
 	if files.is_empty() {
 		bail!("Provide at least one strategy source file.");
 	}
 
-	validate_cmd(files)?;
+	// JSON / CSV on stdout must stay parseable, so tables are skipped and notices go to stderr
+	let to_stdout = output.output != OutputFormat::Table && output.output_file.is_none();
+	let say = |line: String| if to_stdout { eprintln!("{line}") } else { println!("{line}") };
+	let mut config = sim_config(opts)?;
+	config.profile = output.profile;
+	let seeds = seed_set(opts, simulations)?;
+	let simulations = seeds.len();
+	let files = resolve_inputs(files, &mut config);
+	let artifacts = if files.is_empty() {
+		Vec::new()
+	} else {
+		validate_reporting(&files, config.isolate_strategies, config.audit_artifacts, None, say)?
+	};
+	let files: Vec<PathBuf> = files.iter().map(|p| strip_runtime(p).0).collect();
+	let files = files.as_slice();
 
-	let artifacts: Vec<PathBuf> = files
+	let fingerprints = files
 		.iter()
-		.map(|p| compile_strategy(p.as_path()))
+		.zip(&artifacts)
+		.map(|(file, artifact)| {
+			let source = fs::read(file).with_context(|| format!("failed to read {}", file.display()))?;
+			Fingerprint::of(&String::from_utf8_lossy(&source), artifact)
+				.with_context(|| format!("failed to fingerprint {}", file.display()))
+		})
 		.collect::<Result<Vec<_>>>()?;
+	let duplicates = if config.audit_artifacts { find_duplicates(&fingerprints) } else { Vec::new() };
+	for d in &duplicates {
+		say(format!(
+			"DUPLICATE: {} and {} ({}source similarity {:.2})",
+			files[d.a].display(),
+			files[d.b].display(),
+			if d.identical_binary { "identical binary, " } else { "" },
+			d.similarity
+		));
+	}
+
+	let keep_results = config.strategy_logs || output.per_sim;
+	let batch = run_with_progress(&artifacts, &config, &seeds, keep_results)?;
+	let results = &batch.aggregated;
+	if batch.partial {
+		say(format!("\nInterrupted: aggregating {} of {} simulations.", batch.completed, simulations));
+	}
+	if output.output != OutputFormat::Table {
+		write_results(output, &batch, simulations, &config)?;
+	}
+	if !to_stdout {
+		print_results(results);
+	}
+	if let Some(profile) = &batch.profile {
+		print_profile(profile, &say);
+	}
+	for r in results.iter().filter(|r| r.over_quotes > 0) {
+		say(format!("OVER-QUOTED: {} quoted more than the output reserve {} times", r.name, r.over_quotes));
+	}
+	for r in results.iter().filter(|r| r.degenerate_orders > 0) {
+		say(format!(
+			"DEGENERATE QUOTES: {} was left out of {} orders for non-monotone or non-concave quotes",
+			r.name, r.degenerate_orders
+		));
+	}
+	for r in results.iter().filter(|r| r.disqualified_sims > 0) {
+		say(format!(
+			"DISQUALIFIED: {} tampered with memory or over-quoted in {} of {} simulations",
+			r.name, r.disqualified_sims, batch.completed
+		));
+	}
 
-	let mut config = SimConfig::default();
-	config.total_steps = steps;
-	config.epoch_len = epoch_len;
+	if let Some(first) = batch.results.first().filter(|_| config.strategy_logs) {
+		say(format!("\nStrategy log (seed {}): {} lines", first.seed, first.strategy_logs.len()));
+		for e in &first.strategy_logs {
+			say(format!("[step {}] {} {:?}: {}", e.step, first.strategies[e.amm].name, e.call, e.line));
+		}
+	}
 
-	let results = run_parallel(&artifacts, &config, simulations, seed_start);
+	if let Some(submit) = submit {
+		let holdout = run_holdout(&submit.holdout, &artifacts, &config, &seeds, &batch)?;
+		let key = receipt_key(&submit.receipt_key);
+		let receipt = write_submission_receipt(
+			files,
+			&fingerprints,
+			&duplicates,
+			results,
+			simulations,
+			&batch.seeds,
+			&config,
+			holdout.as_ref(),
+			key.as_deref(),
+		)?;
+		match &key {
+			Some(key) => println!("\nSubmission receipt: {} (signed, key {})", receipt.display(), receipt::key_id(key)),
+			None => println!("\nSubmission receipt: {} (unsigned: no --receipt-key or $PROP_AMM_RECEIPT_KEY)", receipt.display()),
+		}
+	}
+
+	Ok(batch)
+}
+
+/// Rerun the field on sealed hold-out seeds disjoint from the development `seeds` and
+/// print how each strategy's edge held up. Skipped without a hold-out key, and after an
+/// interrupted development run.
+fn run_holdout(
+	opts: &HoldoutOpts,
+	artifacts: &[PathBuf],
+	config: &SimConfig,
+	seeds: &[u64],
+	dev: &BatchResult,
+) -> Result<Option<HoldoutRun>> {
+	let simulations = opts.holdout_simulations.unwrap_or(seeds.len());
+	if simulations == 0 || dev.partial {
+		return Ok(None);
+	}
+	// The key is the host's secret: it is never generated here, where it would be lost
+	let (key, key_source) = match (&opts.holdout_key, std::env::var("PROP_AMM_HOLDOUT_KEY")) {
+		(Some(key), _) => (key.clone(), "flag"),
+		(None, Ok(key)) if !key.is_empty() => (key, "env"),
+		_ => {
+			println!("\nHold-out: skipped (no --holdout-key or $PROP_AMM_HOLDOUT_KEY)");
+			return Ok(None);
+		}
+	};
 
-	println!("\nStrategy                           Mean Edge    Std Edge   vs Norm    Sharpe   Final Cap%");
-	println!("---------------------------------------------------------------------------------------------");
-	for r in &results {
+	println!("\nHold-out: {simulations} sealed seeds");
+	let batch = run_with_progress(artifacts, config, &holdout_seeds(&key, simulations, seeds), false)?;
+	if batch.partial {
+		println!("\nInterrupted: aggregating {} of {} hold-out simulations.", batch.completed, simulations);
+	}
+	print_results(&batch.aggregated);
+	println!("\n{:<34} {:>12} {:>12} {:>10}", "Strategy", "Dev Edge", "Hold-out", "Gap");
+	println!("{}", "-".repeat(71));
+	for (d, h) in dev.aggregated.iter().zip(&batch.aggregated) {
+		println!("{:<34} {:>12.2} {:>12.2} {:>+10.2}", d.name, d.mean_edge, h.mean_edge, h.mean_edge - d.mean_edge);
+	}
+	Ok(Some(HoldoutRun { commitment: holdout_commitment(&key), key_source, simulations, batch }))
+}
+
+/// Wall time per strategy and entry point, then per engine phase, as totals over the
+/// batch (summed across worker threads) and per-call means.
+fn print_profile(profile: &SimProfile, say: &dyn Fn(String)) {
+	let wall = profile.wall.as_secs_f64().max(f64::MIN_POSITIVE);
+	let row = |label: &str, t: Timing| {
+		format!(
+			"{:<40} {:>10.3} {:>7.1}% {:>12} {:>10.2}",
+			label,
+			t.total.as_secs_f64(),
+			t.total.as_secs_f64() / wall * 100.0,
+			t.count,
+			t.mean().as_secs_f64() * 1e6
+		)
+	};
+	say(format!("\nProfile: {:.3}s over {} simulations (summed across threads)", profile.wall.as_secs_f64(), profile.simulations));
+	say(format!("{:<40} {:>10} {:>8} {:>12} {:>10}", "", "Total (s)", "Share", "Calls", "Mean (µs)"));
+	say("-".repeat(84));
+	let mut strategies: Vec<_> = profile.strategies.iter().collect();
+	strategies.sort_by_key(|s| std::cmp::Reverse(s.total().total));
+	for s in strategies {
+		say(row(&s.name, s.total()));
+		for &(call, t) in &s.calls {
+			say(row(&format!("  {call:?}"), t));
+		}
+	}
+	say(row("Routing (per order)", profile.routing));
+	say(row("Arbitrage (per step)", profile.arb));
+	say(row("Rebalancing (per epoch)", profile.rebalancing));
+	let other = profile.other();
+	say(format!("{:<40} {:>10.3} {:>7.1}%", "Other", other.as_secs_f64(), other.as_secs_f64() / wall * 100.0));
+}
+
+fn print_results(results: &[prop_amm_engine::sim::AggregatedResult]) {
+	println!("\nStrategy                           Mean Edge    Std Edge   vs Norm    Sharpe   Sortino     Max DD   Final Cap%    Flow%   Flow-Wtd    Ann. Ret%");
+	println!("-------------------------------------------------------------------------------------------------------------------------------------------------");
+	for r in results {
 		println!(
-			"{:<34} {:>10.2} {:>10.2} {:>9.2} {:>9.3} {:>10.2}",
+			"{:<34} {:>10.2} {:>10.2} {:>9.2} {:>9.3} {:>9.3} {:>10.2} {:>10.2} {:>8.2} {:>10.2} {:>12}",
 			r.name,
 			r.mean_edge,
 			r.std_edge,
 			r.edge_vs_normalizer,
 			r.sharpe,
-			r.mean_final_capital_weight * 100.0
+			r.sortino,
+			r.mean_max_drawdown,
+			r.mean_final_capital_weight * 100.0,
+			r.mean_flow_share * 100.0,
+			r.flow_weighted_edge,
+			r.mean_annualized_return.map_or("-".to_string(), |ret| format!("{:.2}", ret * 100.0))
+		);
+	}
+	println!("\nPer epoch                           Buy Vol    Sell Vol    Retail       Arb   Fill Share%    Largest");
+	println!("----------------------------------------------------------------------------------------------------");
+	for r in results {
+		println!(
+			"{:<34} {:>9.1} {:>11.1} {:>9.1} {:>9.1} {:>13.2} {:>10.2}",
+			r.name,
+			r.mean_epoch_buy_volume,
+			r.mean_epoch_sell_volume,
+			r.mean_epoch_retail_trades,
+			r.mean_epoch_arb_trades,
+			r.mean_epoch_fill_share * 100.0,
+			r.largest_trade
+		);
+	}
+}
+
+/// An optional metric as a CSV cell: empty when absent.
+fn csv_opt(value: Option<f64>) -> String {
+	value.map_or(String::new(), |v| v.to_string())
+}
+
+/// Write the batch as JSON or CSV to `--output-file`, or stdout.
+fn write_results(output: &OutputOpts, batch: &BatchResult, simulations: usize, config: &SimConfig) -> Result<()> {
+	let text = match output.output {
+		OutputFormat::Table => return Ok(()),
+		OutputFormat::Json => {
+			let mut payload = json!({
+				"simulations": simulations,
+				"completed_simulations": batch.completed,
+				"partial": batch.partial,
+				"seeds": batch.seeds,
+				"config": config,
+				"results": batch.aggregated,
+			});
+			if output.per_sim {
+				payload["per_simulation"] = batch.results.iter().map(|r| json!({
+					"seed": r.seed,
+					"normalizer_edge": r.normalizer_edge,
+					"retail_volume": r.retail_volume,
+					"strategies": r.strategies,
+				})).collect();
+			}
+			serde_json::to_string_pretty(&payload)? + "\n"
+		}
+		OutputFormat::Csv if output.per_sim => {
+			let mut csv = String::from(
+				"seed,strategy,final_edge,final_marked_edge,normalizer_edge,final_capital_weight,final_inventory_pnl,\
+				 max_drawdown,downside_deviation,retail_volume,annualized_return,fee_yield,mean_effective_spread_bps,\
+				 mean_price_improvement_bps,median_price_improvement_bps,budget_overruns,faults,over_quotes,\
+				 degenerate_orders,disqualified\n",
+			);
+			for r in &batch.results {
+				for s in &r.strategies {
+					csv.push_str(&format!(
+						"{},\"{}\",{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}\n",
+						r.seed,
+						s.name.replace('"', "\"\""),
+						s.final_edge,
+						s.final_marked_edge,
+						r.normalizer_edge,
+						s.final_capital_weight,
+						s.final_inventory_pnl,
+						s.max_drawdown,
+						s.downside_deviation,
+						s.retail_volume,
+						csv_opt(s.annualized_return),
+						s.fee_yield,
+						s.mean_effective_spread_bps,
+						s.mean_price_improvement_bps,
+						s.median_price_improvement_bps,
+						s.budget_overruns,
+						s.faults,
+						s.over_quotes,
+						s.degenerate_orders,
+						s.disqualified
+					));
+				}
+			}
+			csv
+		}
+		OutputFormat::Csv => {
+			let mut csv = String::from(
+				"strategy,simulations,mean_edge,std_edge,edge_vs_normalizer,sharpe,sortino,mean_max_drawdown,\
+				 mean_final_capital_weight,mean_fee_yield,mean_price_improvement_bps,mean_flow_share,flow_weighted_edge,\
+				 mean_annualized_return,mean_epoch_buy_volume,mean_epoch_sell_volume,mean_epoch_retail_trades,\
+				 mean_epoch_arb_trades,mean_epoch_fill_share,largest_trade,over_quotes,degenerate_orders,disqualified_sims\n",
+			);
+			for r in &batch.aggregated {
+				csv.push_str(&format!(
+					"\"{}\",{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}\n",
+					r.name.replace('"', "\"\""),
+					batch.completed,
+					r.mean_edge,
+					r.std_edge,
+					r.edge_vs_normalizer,
+					r.sharpe,
+					r.sortino,
+					r.mean_max_drawdown,
+					r.mean_final_capital_weight,
+					r.mean_fee_yield,
+					r.mean_price_improvement_bps,
+					r.mean_flow_share,
+					r.flow_weighted_edge,
+					csv_opt(r.mean_annualized_return),
+					r.mean_epoch_buy_volume,
+					r.mean_epoch_sell_volume,
+					r.mean_epoch_retail_trades,
+					r.mean_epoch_arb_trades,
+					r.mean_epoch_fill_share,
+					r.largest_trade,
+					r.over_quotes,
+					r.degenerate_orders,
+					r.disqualified_sims
+				));
+			}
+			csv
+		}
+	};
+	match &output.output_file {
+		Some(path) => fs::write(path, text).with_context(|| format!("failed to write {}", path.display())),
+		None => {
+			print!("{text}");
+			Ok(())
+		}
+	}
+}
+
+/// The seeds to run: the `--seeds` file, or `simulations` seeds from `--seed-start`.
+fn seed_set(opts: &RunOpts, simulations: usize) -> Result<Vec<u64>> {
+	match &opts.seeds {
+		Some(path) => {
+			let text = fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+			parse_seeds(&text).map_err(|e| anyhow::anyhow!("{}: {e}", path.display()))
+		}
+		None => Ok((opts.seed_start..opts.seed_start + simulations as u64).collect()),
+	}
+}
+
+/// `seeds A..B` for a contiguous run of seeds, otherwise their count.
+fn describe_seeds(seeds: &[u64]) -> String {
+	match (seeds.first(), seeds.last()) {
+		(Some(&first), Some(&last)) if seeds.windows(2).all(|w| w[1] == w[0] + 1) => format!("seeds {first}..{}", last + 1),
+		_ => format!("{} listed seeds", seeds.len()),
+	}
+}
+
+/// Run `seeds` behind a progress bar. Ctrl-C stops the batch early and keeps the
+/// completed simulations.
+fn run_with_progress(artifacts: &[PathBuf], config: &SimConfig, seeds: &[u64], keep_results: bool) -> Result<BatchResult> {
+	run_batch_with_progress(artifacts, config, seeds, BatchOptions { keep_results, ..BatchOptions::default() })
+}
+
+/// Ctrl-C stops in-flight simulations after their current step and keeps what completed.
+/// The handler is installed once; every batch in the same command shares its token.
+fn ctrl_c_token() -> Result<CancelToken> {
+	static CANCEL: OnceLock<CancelToken> = OnceLock::new();
+	Ok(match CANCEL.get() {
+		Some(token) => token.clone(),
+		None => {
+			let token = CancelToken::new();
+			let handler_token = token.clone();
+			ctrlc::set_handler(move || handler_token.cancel()).context("failed to install Ctrl-C handler")?;
+			CANCEL.get_or_init(|| token).clone()
+		}
+	})
+}
+
+/// `run_with_progress` with `opts` for everything but progress, cancellation and seeds.
+fn run_batch_with_progress(artifacts: &[PathBuf], config: &SimConfig, seeds: &[u64], opts: BatchOptions) -> Result<BatchResult> {
+	let bar = ProgressBar::new(seeds.len() as u64);
+	bar.set_style(
+		ProgressStyle::with_template("{bar:40} {pos}/{len} sims [{elapsed_precise}, ETA {eta}] {msg}")
+			.expect("valid progress template"),
+	);
+	let on_progress = |p: &SimProgress| {
+		bar.set_position(p.completed as u64);
+		if let Some((name, edge)) = p.mean_edge.iter().max_by(|a, b| a.1.total_cmp(&b.1)) {
+			bar.set_message(format!("leader: {name} ({edge:.2})"));
+		}
+	};
+	let cancel = ctrl_c_token()?;
+	let batch_opts = BatchOptions { on_progress: Some(&on_progress), cancel: Some(&cancel), seeds: Some(seeds), ..opts };
+	let batch = run_batch(artifacts, config, seeds.len(), 0, &batch_opts);
+	bar.finish_and_clear();
+	batch.map_err(anyhow::Error::msg)
+}
+
+/// Score `file` against `reference::STANDARD_FIELD` and print its scorecard.
+fn bench_cmd(file: &Path, simulations: usize, opts: &RunOpts) -> Result<()> {
+	let mut config = sim_config(opts)?;
+	// A built-in name is benched as the first opponent, against the rest of the field
+	config.opponents.clear();
+	let files = resolve_inputs(std::slice::from_ref(&file.to_path_buf()), &mut config);
+	let benched = config.opponents.len();
+	for name in reference::STANDARD_FIELD {
+		if !config.opponents.iter().any(|o| o == name) {
+			config.opponents.push(name.to_string());
+		}
+	}
+	let seeds = seed_set(opts, simulations)?;
+	let artifacts = validate_files(&files, &config)?;
+
+	let batch = run_with_progress(&artifacts, &config, &seeds, true)?;
+	if batch.partial {
+		println!("\nInterrupted: scoring {} of {} simulations.", batch.completed, seeds.len());
+	}
+	if batch.completed == 0 {
+		bail!("no simulation completed");
+	}
+
+	println!(
+		"\nBench: {} vs {} ({})",
+		batch.aggregated[0].name,
+		config.opponents[benched..].join(", "),
+		describe_seeds(&batch.seeds)
+	);
+	println!(
+		"\n{:<10} {:>6} {:>11} {:>10} {:>9} {:>8} {:>10} {:>8} {:>6}",
+		"Regime", "Sims", "Mean Edge", "Std Edge", "vs Norm", "Flow%", "Max DD", "Sharpe", "Rank"
+	);
+	println!("{}", "-".repeat(86));
+	let row = |label: &str, sims: usize, results: &[prop_amm_engine::sim::AggregatedResult]| {
+		let r = &results[0];
+		let rank = 1 + results.iter().filter(|o| o.mean_edge > r.mean_edge).count();
+		println!(
+			"{:<10} {:>6} {:>11.2} {:>10.2} {:>9.2} {:>8.2} {:>10.2} {:>8.3} {:>6}",
+			label,
+			sims,
+			r.mean_edge,
+			r.std_edge,
+			r.edge_vs_normalizer,
+			r.mean_flow_share * 100.0,
+			r.mean_max_drawdown,
+			r.sharpe,
+			format!("{rank}/{}", results.len())
+		);
+	};
+	row("All", batch.completed, &batch.aggregated);
+	for (regime, sims, results) in aggregate_by_regime(&batch.results) {
+		row(&format!("{regime:?}"), sims, &results);
+	}
+	Ok(())
+}
+
+/// Steps per simulation in `watch` unless `--steps` or `--config` says otherwise
+const WATCH_STEPS: usize = 2_000;
+
+/// Benchmark `file` on the same seeds after every save until Ctrl-C. A source that fails
+/// to compile or validate is reported and waited out; the last good run stays the
+/// baseline for the next diff.
+fn watch_cmd(file: &Path, simulations: usize, opts: &RunOpts) -> Result<()> {
+	let mut config = sim_config(opts)?;
+	if opts.sim.steps.is_none() && opts.sim.config.is_none() {
+		config.total_steps = WATCH_STEPS;
+		config.validate().map_err(|e| anyhow::anyhow!("invalid simulation settings: {e}"))?;
+	}
+	if opts.sim.opponents.is_empty() {
+		config.opponents = reference::STANDARD_FIELD.map(String::from).to_vec();
+	}
+	let seeds = seed_set(opts, simulations)?;
+	let source = strip_runtime(file).0;
+	let cancel = ctrl_c_token()?;
+	println!(
+		"Watching {} ({} sims x {} steps vs {}, {}); Ctrl-C to stop",
+		source.display(),
+		seeds.len(),
+		config.total_steps,
+		config.opponents.join(", "),
+		describe_seeds(&seeds)
+	);
+
+	let mut previous: Option<WatchScore> = None;
+	let mut saves = SaveDetector::default();
+	while !cancel.is_cancelled() {
+		// A save counts once the file has held still for a poll, so the editor is done writing
+		if !saves.observe(file_stamp(&source)) {
+			std::thread::sleep(std::time::Duration::from_millis(200));
+			continue;
+		}
+
+		// `run_batch` releases the runners it loaded, so the next rebuild is loaded afresh
+		let run = validate_cmd(&[file.to_path_buf()], config.isolate_strategies, config.audit_artifacts)
+			.and_then(|artifacts| run_batch_with_progress(&artifacts, &config, &seeds, BatchOptions::default()));
+		let batch = match run {
+			Ok(batch) if batch.partial => break,
+			Ok(batch) => batch,
+			Err(e) => {
+				println!("{e:#}\nWaiting for the next change...");
+				continue;
+			}
+		};
+		let Some(score) = WatchScore::of(&batch.aggregated) else { continue };
+		print!("\n{}", score.render(previous.as_ref()));
+		previous = Some(score);
+		println!("Waiting for changes...");
+	}
+	println!("\nStopped.");
+	Ok(())
+}
+
+/// Run `seed` alone with every trade, snapshot and storage change recorded, and write
+/// the step log to `trace`.
+fn replay_cmd(files: &[PathBuf], seed: u64, trace: &Path, opts: &SimOpts) -> Result<()> {
+	if files.is_empty() {
+		bail!("Provide at least one strategy source file.");
+	}
+	let mut config = sim_settings(opts)?;
+	config.record_trades = true;
+	config.snapshot_stride = 1;
+	config.snapshot_storage = true;
+	config.strategy_logs = true;
+	let files = resolve_inputs(files, &mut config);
+	let artifacts = validate_files(&files, &config)?;
+
+	let batch = run_with_progress(&artifacts, &config, &[seed], true)?;
+	let Some(result) = batch.results.first() else {
+		bail!("seed {seed} was interrupted before it completed");
+	};
+	let mut out = std::io::BufWriter::new(
+		fs::File::create(trace).with_context(|| format!("failed to create {}", trace.display()))?,
+	);
+	write_step_log(result, seed, &mut out).and_then(|_| out.flush())
+		.with_context(|| format!("failed to write {}", trace.display()))?;
+
+	println!("\nSeed {seed}: {} steps, {} trades", result.snapshots.len(), result.trades.len());
+	for s in &result.strategies {
+		println!("{:<34} edge {:>10.2}  max DD {:>8.2}", s.name, s.final_edge, s.max_drawdown);
+	}
+	println!("Normalizer                         edge {:>10.2}", result.normalizer_edge);
+	println!("\nStep log: {}", trace.display());
+	Ok(())
+}
+
+/// Run `seed` alone, recording the chosen slots of `file`'s storage after each of its
+/// trades, and print how they evolve: a summary per slot that changed, then `rows`
+/// trades evenly spaced over the run.
+fn inspect_cmd(file: &Path, seed: u64, slots: &str, rows: usize, out: Option<&Path>, opts: &SimOpts) -> Result<()> {
+	let slots = parse_slots(slots).map_err(|e| anyhow::anyhow!("--slots: {e}"))?;
+	let mut config = sim_settings(opts)?;
+	config.record_trades = true;
+	config.trade_slots = slots.clone();
+	let artifacts = validate_cmd(std::slice::from_ref(&file.to_path_buf()), config.isolate_strategies, config.audit_artifacts)?;
+
+	let batch = run_with_progress(&artifacts, &config, &[seed], true)?;
+	let Some(result) = batch.results.first() else {
+		bail!("seed {seed} was interrupted before it completed");
+	};
+	let name = &result.strategies[0].name;
+	let trades: Vec<_> = result.trades.iter().filter(|t| t.amm == 0).collect();
+	println!("\nSeed {seed}: {} trades on {name} ({} steps)", trades.len(), config.total_steps);
+
+	if let Some(path) = out {
+		let mut csv = String::from("step,kind,side,input,output,fair_price");
+		for s in &slots {
+			csv.push_str(&format!(",slot{s}_u64,slot{s}_f64"));
+		}
+		csv.push('\n');
+		for t in &trades {
+			csv.push_str(&format!("{},{:?},{},{},{},{}", t.step, t.kind, side(t.is_buy), t.input, t.output, t.fair_price));
+			for v in &t.slots {
+				csv.push_str(&format!(",{v},{:?}", f64::from_bits(*v)));
+			}
+			csv.push('\n');
+		}
+		fs::write(path, csv).with_context(|| format!("failed to write {}", path.display()))?;
+	}
+	let Some(first) = trades.first() else {
+		println!("No trades: the storage was never written after a fill.");
+		return Ok(());
+	};
+
+	// Slots the strategy wrote during the run, by position in `slots`
+	let changed: Vec<usize> = (0..slots.len()).filter(|&k| trades.iter().any(|t| t.slots[k] != first.slots[k])).collect();
+	let unchanged: Vec<String> = (0..slots.len())
+		.filter(|k| !changed.contains(k))
+		.map(|k| format!("{}={}", slots[k], slot_value(first.slots[k])))
+		.collect();
+	if !changed.is_empty() {
+		println!("\n{:<6} {:>8} {:>16} {:>16} {:>16}  Note", "Slot", "Changes", "First", "Last", "Max |f64|");
+		println!("{}", "-".repeat(80));
+	}
+	for &k in &changed {
+		let values: Vec<u64> = trades.iter().map(|t| t.slots[k]).collect();
+		let changes = values.windows(2).filter(|w| w[0] != w[1]).count();
+		let floats = || values.iter().filter(|&&v| v >= FLOAT_BITS).map(|&v| f64::from_bits(v));
+		let max = floats().filter(|f| f.is_finite()).map(f64::abs).fold(None, |m: Option<f64>, f| Some(m.map_or(f, |m| m.max(f))));
+		let note = match trades.iter().find(|t| t.slots[k] >= FLOAT_BITS && !f64::from_bits(t.slots[k]).is_finite()) {
+			Some(t) => format!("non-finite from step {}", t.step),
+			None => String::new(),
+		};
+		println!(
+			"{:<6} {:>8} {:>16} {:>16} {:>16}  {note}",
+			slots[k],
+			changes,
+			slot_value(values[0]),
+			slot_value(values[values.len() - 1]),
+			max.map_or("-".to_string(), |m| format!("{m:.6e}"))
 		);
 	}
+	if !unchanged.is_empty() {
+		println!("Unchanged: {}", unchanged.join(" "));
+	}
+
+	if rows > 0 && !changed.is_empty() {
+		let picks: Vec<usize> = if trades.len() <= rows {
+			(0..trades.len()).collect()
+		} else {
+			(0..rows).map(|i| i * (trades.len() - 1) / (rows - 1).max(1)).collect()
+		};
+		print!("\n{:>7} {:<12} {:<4}", "Step", "Kind", "Side");
+		for &k in &changed {
+			print!(" {:>14}", format!("slot {}", slots[k]));
+		}
+		println!();
+		for i in picks {
+			let t = trades[i];
+			print!("{:>7} {:<12} {:<4}", t.step, format!("{:?}", t.kind), side(t.is_buy));
+			for &k in &changed {
+				print!(" {:>14}", slot_value(t.slots[k]));
+			}
+			println!();
+		}
+	}
+	if let Some(path) = out {
+		println!("\nSlot values: {}", path.display());
+	}
+	Ok(())
+}
+
+/// Words at or above this are shown as f64: below it an f64 is subnormal, which no
+/// strategy state is, while counters and amounts stay far under it
+const FLOAT_BITS: u64 = 1 << 52;
+
+/// A storage word as the u64 or the f64 it most likely holds.
+fn slot_value(v: u64) -> String {
+	if v < FLOAT_BITS {
+		return v.to_string();
+	}
+	let f = f64::from_bits(v);
+	if f.is_finite() && (f == 0.0 || (1e-3..1e9).contains(&f.abs())) { format!("{f:.6}") } else { format!("{f:.4e}") }
+}
+
+fn side(is_buy: bool) -> &'static str {
+	if is_buy { "buy" } else { "sell" }
+}
+
+/// Search the strategy's tunable constants, scoring each candidate by its mean edge over
+/// the same seeds (against the standard reference field unless `--opponents` is given).
+/// Candidate sources are written to `target/optimize/`, the best one as `<stem>_tuned.rs`.
+fn optimize_cmd(
+	file: &Path,
+	sidecar: Option<&Path>,
+	method: SearchMethod,
+	search_seed: u64,
+	simulations: usize,
+	opts: &RunOpts,
+) -> Result<()> {
+	if let Some(name) = reference::resolve_inputs(std::slice::from_ref(&file.to_path_buf())).builtins.first() {
+		bail!("optimize needs a strategy source, not the built-in `{name}`");
+	}
+	let (path, target) = strip_runtime(file);
+	if target == Target::Sbf || path.extension().is_some_and(|e| e == "remote") {
+		bail!("optimize needs a strategy source, not {}", file.display());
+	}
+	let source = fs::read_to_string(&path).with_context(|| format!("failed to read {}", path.display()))?;
+	let default_sidecar = path.with_extension("tune.json");
+	let sidecar = match sidecar {
+		Some(p) => Some(fs::read_to_string(p).with_context(|| format!("failed to read {}", p.display()))?),
+		None if default_sidecar.exists() => Some(
+			fs::read_to_string(&default_sidecar).with_context(|| format!("failed to read {}", default_sidecar.display()))?,
+		),
+		None => None,
+	};
+	let tunables = optimize::tunables(&source, sidecar.as_deref()).map_err(anyhow::Error::msg)?;
+	if tunables.is_empty() {
+		bail!("{} has no tunable constants; annotate one like `const FEE_BPS: u32 = 30; // tune: 10..100`", path.display());
+	}
 
-	if submit_mode {
-		let receipt = write_submission_receipt(files, &results, simulations, steps, epoch_len, seed_start)?;
-		println!("\nSubmission receipt: {}", receipt.display());
+	let mut config = sim_config(opts)?;
+	let seeds = seed_set(opts, simulations)?;
+	if config.opponents.is_empty() {
+		config.opponents = reference::STANDARD_FIELD.map(String::from).to_vec();
+	}
+	let stem = path.file_stem().and_then(|s| s.to_str()).context("invalid strategy filename")?;
+	let out_dir = PathBuf::from("target/optimize");
+	fs::create_dir_all(&out_dir)?;
+	let candidate = out_dir.join(format!("{stem}.rs"));
+	let prefixed = |p: &Path| if target == Target::Wasm { PathBuf::from(format!("wasm:{}", p.display())) } else { p.to_path_buf() };
+	let describe = |values: &[f64]| {
+		tunables.iter().zip(values).map(|(t, v)| format!("{}={}", t.name, format_value(t, *v))).collect::<Vec<_>>().join(" ")
+	};
+
+	println!(
+		"Optimizing {} over {} against {} ({})",
+		tunables.iter().map(|t| format!("{} in {}..{}", t.name, t.min, t.max)).collect::<Vec<_>>().join(", "),
+		path.display(),
+		config.opponents.join(", "),
+		describe_seeds(&seeds)
+	);
+	let mut failure = None;
+	let mut evaluated = 0;
+	let trials = optimize::search(&tunables, method, search_seed, |values| {
+		let scored = (|| {
+			fs::write(&candidate, optimize::apply(&source, &tunables, values))?;
+			let artifact = compile_strategy(&prefixed(&candidate))?;
+			let batch = run_with_progress(&[artifact], &config, &seeds, false)?;
+			if batch.partial {
+				bail!("interrupted");
+			}
+			Ok(batch.aggregated[0].mean_edge)
+		})();
+		match scored {
+			Ok(edge) => {
+				evaluated += 1;
+				println!("[{evaluated:>3}] {:<50} edge {edge:>10.2}", describe(values));
+				Some(edge)
+			}
+			Err(e) => {
+				failure = Some(e);
+				None
+			}
+		}
+	});
+	let _ = fs::remove_file(&candidate);
+	if let Some(e) = &failure {
+		println!("\nSearch stopped: {e}");
 	}
+	let Some(best) = trials.first() else {
+		return Err(failure.unwrap_or_else(|| anyhow::anyhow!("no candidate was evaluated")));
+	};
 
+	println!("\n{:<6} {:>11}  Constants", "Rank", "Mean Edge");
+	println!("{}", "-".repeat(70));
+	for (i, t) in trials.iter().take(5).enumerate() {
+		println!("{:<6} {:>11.2}  {}", i + 1, t.score, describe(&t.values));
+	}
+	let tuned = out_dir.join(format!("{stem}_tuned.rs"));
+	fs::write(&tuned, optimize::apply(&source, &tunables, &best.values))?;
+	println!("\nBest of {} candidates (mean edge {:.2}):", trials.len(), best.score);
+	for (t, v) in tunables.iter().zip(&best.values) {
+		println!("  {} = {}", t.name, format_value(t, *v));
+	}
+	println!("Tuned source: {}", tuned.display());
+	Ok(())
+}
+
+fn format_value(tunable: &Tunable, value: f64) -> String {
+	if tunable.integer { format!("{}", value as i64) } else { format!("{value:.6}") }
+}
+
+/// Run the same seeds on every grid cell and write one CSV row per cell and strategy.
+fn sweep_cmd(files: &[PathBuf], grid: &SweepGrid, simulations: usize, out: &Path, opts: &RunOpts) -> Result<()> {
+	if files.is_empty() {
+		bail!("Provide at least one strategy source file.");
+	}
+	let mut config = sim_config(opts)?;
+	let files = resolve_inputs(files, &mut config);
+	let cells = grid.cells(&config);
+	for cell in &cells {
+		cell.validate().map_err(|e| anyhow::anyhow!("invalid sweep grid: {e}"))?;
+	}
+	let seeds = seed_set(opts, simulations)?;
+	let artifacts = validate_files(&files, &config)?;
+
+	let pinned = |v: Option<String>| v.unwrap_or_default();
+	let mut csv = String::from(
+		"sigma,lambda,norm_fee_bps,norm_liquidity_mult,strategy,simulations,mean_edge,std_edge,edge_vs_normalizer,mean_flow_share,sharpe\n",
+	);
+	println!("\n{:<40} {:<34} {:>10}", "Cell", "Leader", "Mean Edge");
+	println!("{}", "-".repeat(86));
+	for (k, cell) in cells.iter().enumerate() {
+		let columns = [
+			pinned(cell.sigma_per_step.map(|v| v.to_string())),
+			pinned(cell.orders_per_step.map(|v| v.to_string())),
+			pinned(cell.norm_fee_bps.map(|v| v.to_string())),
+			pinned(cell.norm_liquidity_mult.map(|v| v.to_string())),
+		];
+		let batch = run_with_progress(&artifacts, cell, &seeds, false)?;
+		for r in &batch.aggregated {
+			csv.push_str(&format!(
+				"{},\"{}\",{},{},{},{},{},{}\n",
+				columns.join(","),
+				r.name.replace('"', "\"\""),
+				batch.completed,
+				r.mean_edge,
+				r.std_edge,
+				r.edge_vs_normalizer,
+				r.mean_flow_share,
+				r.sharpe
+			));
+		}
+		let label = ["σ", "λ", "fee", "liq"]
+			.iter()
+			.zip(&columns)
+			.filter(|(_, v)| !v.is_empty())
+			.map(|(k, v)| format!("{k}={v}"))
+			.collect::<Vec<_>>()
+			.join(" ");
+		if let Some(leader) = batch.aggregated.iter().max_by(|a, b| a.mean_edge.total_cmp(&b.mean_edge)) {
+			println!("{:<40} {:<34} {:>10.2}", label, leader.name, leader.mean_edge);
+		}
+		if batch.partial {
+			println!("\nInterrupted: writing {} of {} cells, the last one partial.", k + 1, cells.len());
+			break;
+		}
+	}
+
+	fs::write(out, csv).with_context(|| format!("failed to write {}", out.display()))?;
+	println!("\nSweep: {}", out.display());
 	Ok(())
 }
 
-fn compile_strategy(file: &Path) -> Result<PathBuf> {
+/// Evaluate every strategy in `dir` and fold the results into the leaderboard file.
+fn tournament_cmd(dir: &Path, simulations: usize, leaderboard: Option<&Path>, opts: &RunOpts) -> Result<()> {
+	let files = manifest::inputs(dir).map_err(anyhow::Error::msg)?;
+	if files.is_empty() {
+		bail!("no strategies in {}", dir.display());
+	}
+	let path = leaderboard.map_or_else(|| dir.join("leaderboard.json"), Path::to_path_buf);
+	let mut board = Leaderboard::load(&path).map_err(anyhow::Error::msg)?;
+
+	let batch = run_cmd(&files, simulations, opts, &OutputOpts::default(), None)?;
+	if batch.partial {
+		println!("Leaderboard not updated: the run was interrupted.");
+		return Ok(());
+	}
+	let ts = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+	board.record(&batch.aggregated, ts);
+	board.save(&path).map_err(anyhow::Error::msg)?;
+
+	println!(
+		"\n{:<34} {:>6} {:>8} {:>12} {:>9} {:>6} {:>6}",
+		format!("Leaderboard (run {})", board.runs), "Rank", "Change", "Mean Edge", "Sharpe", "Best", "Runs"
+	);
+	println!("{}", "-".repeat(87));
+	for s in &board.standings {
+		let rank = s.rank.map_or("-".to_string(), |r| r.to_string());
+		let change = match (s.rank, s.rank_delta()) {
+			(None, _) => "absent".to_string(),
+			(Some(_), None) => "new".to_string(),
+			(Some(_), Some(0)) => "=".to_string(),
+			(Some(_), Some(d)) => format!("{d:+}"),
+		};
+		println!(
+			"{:<34} {:>6} {:>8} {:>12.2} {:>9.3} {:>6} {:>6}",
+			s.name, rank, change, s.mean_edge, s.sharpe, s.best_rank, s.appearances
+		);
+	}
+	println!("\nLeaderboard: {}", path.display());
+	Ok(())
+}
+
+/// Print how receipt `b` differs from receipt `a`.
+fn compare_cmd(a: &Path, b: &Path) -> Result<()> {
+	let read = |path: &Path| -> Result<serde_json::Value> {
+		let text = fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+		serde_json::from_str(&text).with_context(|| format!("{} is not a receipt", path.display()))
+	};
+	let diff = compare::compare(&read(a)?, &read(b)?).map_err(anyhow::Error::msg)?;
+
+	println!("A: {}\nB: {}", a.display(), b.display());
+	if diff.config.is_empty() {
+		println!("\nSame configuration.");
+	} else {
+		println!("\n{:<40} {:>24} {:>24}", "Config difference", "A", "B");
+		println!("{}", "-".repeat(90));
+		for c in &diff.config {
+			// Seed lists and the like are cut to the column
+			let show = |v: &Option<String>| match v.as_deref() {
+				None => "-".to_string(),
+				Some(v) if v.chars().count() > 24 => format!("{}…", v.chars().take(23).collect::<String>()),
+				Some(v) => v.to_string(),
+			};
+			println!("{:<40} {:>24} {:>24}", c.key, show(&c.a), show(&c.b));
+		}
+	}
+
+	for s in &diff.strategies {
+		println!("\n{:<34} {:>12} {:>12} {:>12} {:>8} {:>4}", s.name, "A", "B", "Delta", "z", "");
+		println!("{}", "-".repeat(87));
+		for m in &s.metrics {
+			let z = m.z.map_or("-".to_string(), |z| format!("{z:.2}"));
+			println!("{:<34} {:>12.3} {:>12.3} {:>+12.3} {:>8} {:>4}", m.metric, m.a, m.b, m.delta(), z, m.flag());
+		}
+	}
+	if !diff.strategies.is_empty() {
+		println!("\n* |z| >= {} (p < 0.05), ** |z| >= {} (p < 0.01); conservative when the runs share seeds", compare::Z_5PCT, compare::Z_1PCT);
+	}
+	for name in &diff.only_a {
+		println!("Only in A: {name}");
+	}
+	for name in &diff.only_b {
+		println!("Only in B: {name}");
+	}
+	Ok(())
+}
+
+/// Evaluate uploads like `bench` (against `reference::STANDARD_FIELD` on fixed seeds) behind
+/// the `serve` API. Uploads are untrusted, so they must pass the tournament-mode audit;
+/// `--isolate` additionally keeps a crashing one from taking the server down.
+#[cfg(feature = "serve")]
+fn serve_cmd(addr: String, db: PathBuf, work_dir: PathBuf, simulations: usize, opts: &RunOpts) -> Result<()> {
+	use prop_amm_engine::serve::{self, Evaluation, ServeOptions};
+
+	let mut config = sim_config(opts)?;
+	config.opponents = reference::STANDARD_FIELD.map(String::from).to_vec();
+	// Uploads are untrusted: audit them, and run them out of process (the audit is not a
+	// sandbox)
+	config.audit_artifacts = true;
+	config.isolate_strategies = true;
+	let seeds = seed_set(opts, simulations)?;
+	println!(
+		"Serving on http://{addr}: uploads scored against {} on {}, results in {}",
+		config.opponents.join(", "),
+		describe_seeds(&seeds),
+		db.display()
+	);
+
+	let evaluate = move |source: &Path| -> Result<Evaluation, String> {
+		let artifacts = validate_reporting(&[source.to_path_buf()], true, true, None, |line| println!("{line}"))
+			.map_err(|e| format!("{e:#}"))?;
+		let batch_opts = BatchOptions { seeds: Some(&seeds), ..BatchOptions::default() };
+		let batch = run_batch(&artifacts, &config, seeds.len(), 0, &batch_opts)?;
+		let mine = batch.aggregated.first().ok_or("no simulation completed")?;
+		let rank = 1 + batch.aggregated.iter().filter(|o| o.mean_edge > mine.mean_edge).count();
+		println!("{}: {} mean edge {:.2}, rank {rank}/{}", source.display(), mine.name, mine.mean_edge, batch.aggregated.len());
+		Ok(Evaluation {
+			strategy: mine.name.clone(),
+			mean_edge: mine.mean_edge,
+			details: json!({
+				"simulations": batch.completed,
+				"seeds": batch.seeds,
+				"rank_in_field": rank,
+				"field": batch.aggregated.iter().map(receipt_strategy).collect::<Vec<_>>(),
+			}),
+		})
+	};
+	let opts = ServeOptions { addr, db, work_dir };
+	serve::serve(&opts, Box::new(evaluate)).map_err(anyhow::Error::msg)
+}
+
+#[cfg(not(feature = "serve"))]
+fn serve_cmd(_addr: String, _db: PathBuf, _work_dir: PathBuf, _simulations: usize, _opts: &RunOpts) -> Result<()> {
+	bail!("serve needs the `serve` feature: cargo run --features serve --bin prop-amm-multi -- serve")
+}
+
+/// Check `path`'s signature (when a key is given), the files stored beside it against
+/// their recorded content hashes, and its config against the config hash; with `rerun`,
+/// replay the recorded seeds on the stored sources and compare mean edges. Any mismatch
+/// is an error; a different engine build is only reported.
+fn verify_cmd(path: &Path, key: Option<&str>, rerun: bool) -> Result<()> {
+	use serde_json::Value;
+
+	let text = fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+	let receipt: Value = serde_json::from_str(&text).with_context(|| format!("{} is not a receipt", path.display()))?;
+	let dir = path.parent().unwrap_or(Path::new("."));
+	let mut failures = Vec::new();
+	fn check(failures: &mut Vec<String>, what: String, outcome: Result<String, String>) {
+		match outcome {
+			Ok(note) => println!("[OK]   {what}{note}"),
+			Err(e) => {
+				println!("[FAIL] {what}: {e}");
+				failures.push(what);
+			}
+		}
+	}
+
+	let signed_by = receipt.pointer("/signature/key_id").and_then(Value::as_str);
+	match (key, signed_by) {
+		(Some(key), _) => check(&mut failures, "signature".into(), receipt::verify(&receipt, key).map(|()| format!(" (key {})", receipt::key_id(key)))),
+		(None, Some(id)) => println!("[SKIP] signature (key {id}): no --receipt-key or $PROP_AMM_RECEIPT_KEY"),
+		(None, None) => check(&mut failures, "signature".into(), Err("receipt is not signed".into())),
+	}
+
+	let config = receipt.get("config").context("receipt has no `config`")?;
+	let recorded = receipt.get("config_hash").and_then(Value::as_str);
+	let hash = receipt::value_hash(config);
+	check(&mut failures, 
+		"config hash".into(),
+		match recorded {
+			Some(recorded) if recorded == hash => Ok(String::new()),
+			Some(recorded) => Err(format!("recorded {recorded}, config hashes to {hash}")),
+			None => Err("receipt records no config hash".into()),
+		},
+	);
+
+	let entries = receipt.get("fingerprints").and_then(Value::as_array).context("receipt has no `fingerprints`")?;
+	let mut sources = Vec::new();
+	for entry in entries {
+		let file = entry.get("file").and_then(Value::as_str).context("fingerprint without a `file`")?;
+		let stored = dir.join(file);
+		let outcome = match (entry.get("sha256").and_then(Value::as_str), fs::read(&stored)) {
+			(None, _) => Err("receipt records no content hash".into()),
+			(_, Err(e)) => Err(format!("cannot read {}: {e}", stored.display())),
+			(Some(recorded), Ok(bytes)) if receipt::sha256_hex(&bytes) == recorded => Ok(String::new()),
+			(Some(_), Ok(_)) => Err("content differs from the recorded hash".into()),
+		};
+		check(&mut failures, file.to_string(), outcome);
+		sources.push(stored);
+	}
+
+	let engine = receipt.get("engine").cloned().unwrap_or(Value::Null);
+	let ours = receipt::engine_info();
+	for field in ["version", "commit", "abi_version"] {
+		if engine.get(field) != ours.get(field) {
+			println!(
+				"[NOTE] engine {field}: receipt {}, this build {}",
+				engine.get(field).unwrap_or(&Value::Null),
+				ours.get(field).unwrap_or(&Value::Null)
+			);
+		}
+	}
+
+	if rerun && failures.is_empty() {
+		let mut config: SimConfig = serde_json::from_value(config.clone()).context("receipt config does not parse")?;
+		let seeds: Vec<u64> = serde_json::from_value(receipt.get("seeds").cloned().unwrap_or(Value::Null))
+			.context("receipt has no `seeds` list")?;
+		// Built-in opponents are rerun from the recorded config, like the stored sources
+		let sources = resolve_inputs(&sources, &mut config);
+		let artifacts = validate_files(&sources, &config)?;
+		let batch = run_with_progress(&artifacts, &config, &seeds, false)?;
+		if batch.partial {
+			bail!("rerun interrupted after {} of {} seeds", batch.completed, seeds.len());
+		}
+		let recorded = receipt.get("strategies").and_then(Value::as_array).context("receipt has no `strategies`")?;
+		println!("\n{:<34} {:>14} {:>14}", "Strategy", "Receipt Edge", "Rerun Edge");
+		println!("{}", "-".repeat(64));
+		for r in &batch.aggregated {
+			let then = recorded.iter().find(|s| s.get("name").and_then(Value::as_str) == Some(&r.name)).and_then(|s| s.get("mean_edge")?.as_f64());
+			println!("{:<34} {:>14} {:>14.4}", r.name, then.map_or("-".to_string(), |e| format!("{e:.4}")), r.mean_edge);
+			// Aggregates are summed in completion order, so allow for rounding
+			let outcome = match then {
+				Some(e) if (e - r.mean_edge).abs() <= 1e-9 * e.abs().max(1.0) => Ok(String::new()),
+				Some(e) => Err(format!("mean edge {e} in the receipt, {} on rerun", r.mean_edge)),
+				None => Err("not in the receipt".into()),
+			};
+			check(&mut failures, format!("rerun {}", r.name), outcome);
+		}
+	}
+
+	if !failures.is_empty() {
+		bail!("verification failed: {}", failures.join(", "));
+	}
+	if key.is_some() {
+		println!("\nReceipt verified.");
+	} else {
+		println!("\nReceipt consistent; its signature was not checked.");
+	}
+	Ok(())
+}
+
+/// `sim_settings`, with tournament mode's artifact audit.
+fn sim_config(opts: &RunOpts) -> Result<SimConfig> {
+	let mut config = sim_settings(&opts.sim)?;
+	config.audit_artifacts |= opts.tournament;
+	Ok(config)
+}
+
+/// The `--config` file (or the defaults) with command-line flags applied on top, validated.
+fn sim_settings(opts: &SimOpts) -> Result<SimConfig> {
+	let mut config = match &opts.config {
+		Some(path) => {
+			let text = fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+			SimConfig::from_toml(&text).map_err(|e| anyhow::anyhow!("{}: {e}", path.display()))?
+		}
+		None => SimConfig::default(),
+	};
+	config.total_steps = opts.steps.unwrap_or(config.total_steps);
+	config.epoch_len = opts.epoch_len.unwrap_or(config.epoch_len);
+	config.self_play_copies = opts.self_play.unwrap_or(config.self_play_copies);
+	config.isolate_strategies |= opts.isolate;
+	config.harden_storage |= opts.harden;
+	config.strategy_logs |= opts.debug_strategy;
+	if opts.over_quote_strikes.is_some() {
+		config.quote_guard.disqualify_after = opts.over_quote_strikes;
+	}
+	if !opts.opponents.is_empty() {
+		config.opponents = reference::expand_names(&opts.opponents).map_err(anyhow::Error::msg)?;
+	}
+	config.validate().map_err(|e| anyhow::anyhow!("invalid simulation settings: {e}"))?;
+	Ok(config)
+}
+
+/// The files among `inputs` (see `reference::resolve_inputs`); the built-in names among
+/// them are appended to `config.opponents`.
+fn resolve_inputs(inputs: &[PathBuf], config: &mut SimConfig) -> Vec<PathBuf> {
+	let inputs = reference::resolve_inputs(inputs);
+	for name in inputs.builtins {
+		if !config.opponents.contains(&name) {
+			config.opponents.push(name);
+		}
+	}
+	inputs.files
+}
+
+/// `validate_cmd`, skipping the build when every input was a built-in name.
+fn validate_files(files: &[PathBuf], config: &SimConfig) -> Result<Vec<PathBuf>> {
+	if files.is_empty() {
+		return Ok(Vec::new());
+	}
+	validate_cmd(files, config.isolate_strategies, config.audit_artifacts)
+}
+
+/// Where a strategy argument should run, from its `wasm:` / `sbf:` prefix.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Target {
+	Native,
+	Wasm,
+	Sbf,
+}
+
+/// Split a runtime prefix off a strategy argument.
+fn strip_runtime(arg: &Path) -> (PathBuf, Target) {
+	let s = arg.to_string_lossy();
+	if let Some(rest) = s.strip_prefix("wasm:") {
+		(PathBuf::from(rest), Target::Wasm)
+	} else if let Some(rest) = s.strip_prefix("sbf:") {
+		(PathBuf::from(rest), Target::Sbf)
+	} else {
+		(arg.to_path_buf(), Target::Native)
+	}
+}
+
+/// `rustc -vV`, queried once per process: part of every artifact's cache key.
+fn rustc_version() -> Result<&'static str> {
+	static VERSION: OnceLock<String> = OnceLock::new();
+	if let Some(v) = VERSION.get() {
+		return Ok(v);
+	}
+	let out = Command::new("rustc").arg("-vV").output().context("failed to invoke rustc")?;
+	if !out.status.success() {
+		bail!("rustc -vV failed");
+	}
+	Ok(VERSION.get_or_init(|| String::from_utf8_lossy(&out.stdout).into_owned()))
+}
+
+/// Compile a strategy to a native library, or to a `.wasm` module for a `wasm:` argument.
+/// An `sbf:` argument is already a compiled program (build it with `cargo build-sbf`),
+/// and a `.remote` manifest names a strategy served elsewhere (`RemoteEndpoint`).
+///
+/// A source that names the SDK or an allowlisted crate is built with cargo instead of bare
+/// rustc (`compile::cargo_deps`, `cargo_build`).
+///
+/// Artifacts are cached in `target/strategies/` under the source's content hash, keyed
+/// with the rustc version and target, so an unchanged source is never rebuilt and
+/// same-named sources in different directories never share an artifact.
+fn compile_strategy(arg: &Path) -> Result<PathBuf> {
+	build_strategy(arg).map(|(artifact, _)| artifact)
+}
+
+/// Extensions of artifacts that are loaded as given rather than compiled
+const ARTIFACT_EXTENSIONS: [&str; 5] = ["so", "dylib", "dll", "wasm", "remote"];
+
+/// `compile_strategy`, also saying whether the artifact was cached.
+fn build_strategy(arg: &Path) -> Result<(PathBuf, Compiled)> {
+	let (file, target) = strip_runtime(arg);
+	let file = file.as_path();
 	if !file.exists() {
 		bail!("strategy file not found: {}", file.display());
 	}
+	if target == Target::Sbf || file.extension().is_some_and(|e| ARTIFACT_EXTENSIONS.iter().any(|a| e == *a)) {
+		return Ok((file.to_path_buf(), Compiled::Prebuilt));
+	}
 
 	let target_dir = PathBuf::from("target/strategies");
 	fs::create_dir_all(&target_dir)?;
@@ -157,68 +1802,234 @@ fn compile_strategy(file: &Path) -> Result<PathBuf> {
 		.and_then(|s| s.to_str())
 		.context("invalid strategy filename")?;
 
-	let output = target_dir.join(format!("lib{}_{}", stem, dylib_ext()));
+	let wasm = target == Target::Wasm;
+	let source = fs::read(file).with_context(|| format!("failed to read {}", file.display()))?;
+	let deps = compile::cargo_deps(&String::from_utf8_lossy(&source)).map_err(|e| anyhow::anyhow!("{}: {e}", file.display()))?;
+	let mut hasher = Sha256::new();
+	hasher.update(&source);
+	hasher.update(rustc_version()?);
+	hasher.update(if wasm { "wasm32-unknown-unknown" } else { "native" });
+	if let Some(deps) = &deps {
+		hasher.update(compile::SDK_LIB);
+		hasher.update(compile::SDK_WIRE);
+		for (name, version) in deps {
+			hasher.update(format!("{name}={version}"));
+		}
+	}
+	let key: String = hasher.finalize()[..8].iter().map(|b| format!("{b:02x}")).collect();
+
+	let output = if wasm {
+		target_dir.join(format!("lib{}_{}.wasm", stem, key))
+	} else {
+		target_dir.join(format!("lib{}_{}_{}", stem, key, dylib_ext()))
+	};
+	if output.exists() {
+		return Ok((output, Compiled::Cached));
+	}
 
-	let status = Command::new("rustc")
-		.arg(file)
+	// Build under a temporary name so an interrupted compile never leaves a cache entry,
+	// unique per call since identical sources may compile concurrently
+	static BUILDS: AtomicUsize = AtomicUsize::new(0);
+	let partial = target_dir.join(format!(
+		"lib{}_{}.partial{}_{}",
+		stem,
+		key,
+		std::process::id(),
+		BUILDS.fetch_add(1, Ordering::Relaxed)
+	));
+	if let Some(deps) = deps {
+		cargo_build(file, &format!("{stem}_{key}"), &deps, wasm, &partial)?;
+		fs::rename(&partial, &output)?;
+		return Ok((output, Compiled::Built));
+	}
+	let mut cmd = Command::new("rustc");
+	cmd.arg(file)
 		.arg("--edition")
 		.arg("2021")
 		.arg("--crate-type")
 		.arg("cdylib")
 		.arg("-O")
 		.arg("-o")
-		.arg(&output)
-		.status()
+		.arg(&partial);
+	if wasm {
+		cmd.arg("--target").arg("wasm32-unknown-unknown");
+	}
+	// Captured, so diagnostics from concurrent compiles do not interleave
+	let out = cmd
+		.output()
 		.with_context(|| format!("failed to invoke rustc for {}", file.display()))?;
 
-	if !status.success() {
-		bail!("rustc failed compiling {}", file.display());
+	if !out.status.success() {
+		let _ = fs::remove_file(&partial);
+		bail!("rustc failed compiling {}:\n{}", file.display(), String::from_utf8_lossy(&out.stderr));
 	}
+	fs::rename(&partial, &output)?;
 
-	Ok(output)
+	Ok((output, Compiled::Built))
 }
 
+fn write_if_changed(path: PathBuf, contents: &str) -> Result<()> {
+	if fs::read_to_string(&path).ok().as_deref() != Some(contents) {
+		fs::create_dir_all(path.parent().context("no parent directory")?)?;
+		fs::write(&path, contents).with_context(|| format!("failed to write {}", path.display()))?;
+	}
+	Ok(())
+}
+
+/// The embedded SDK package under `target/strategies/sdk`, written on first use only so
+/// concurrent cargo builds never see it half-written.
+fn sdk_dir() -> Result<&'static Path> {
+	static SDK: OnceLock<Result<PathBuf, String>> = OnceLock::new();
+	let sdk = SDK.get_or_init(|| {
+		let write = || -> Result<PathBuf> {
+			let sdk = PathBuf::from("target/strategies/sdk");
+			write_if_changed(sdk.join("Cargo.toml"), &compile::sdk_manifest())?;
+			write_if_changed(sdk.join("src").join("lib.rs"), compile::SDK_LIB)?;
+			write_if_changed(sdk.join("src").join("wire.rs"), compile::SDK_WIRE)?;
+			Ok(fs::canonicalize(&sdk)?)
+		};
+		write().map_err(|e| format!("{e:#}"))
+	});
+	sdk.as_deref().map_err(|e| anyhow::anyhow!("failed to write the strategy SDK: {e}"))
+}
+
+/// Build `file` as the cdylib of a generated cargo project `target/strategies/cargo/<id>`
+/// depending on the embedded SDK and `deps`, and copy the library to `out`. Projects
+/// share one cargo target directory, so dependencies are compiled once.
+fn cargo_build(file: &Path, id: &str, deps: &[(&str, &str)], wasm: bool, out: &Path) -> Result<()> {
+	let root = PathBuf::from("target/strategies");
+	let sdk = sdk_dir()?;
+
+	let package = compile::package_name(id);
+	let project = root.join("cargo").join(id);
+	let source = fs::read_to_string(file).with_context(|| format!("failed to read {}", file.display()))?;
+	write_if_changed(project.join("Cargo.toml"), &compile::strategy_manifest(&package, &sdk.to_string_lossy(), deps))?;
+	write_if_changed(project.join("src").join("lib.rs"), &source)?;
+
+	let target_dir = root.join("cargo-target");
+	let mut cmd = Command::new("cargo");
+	cmd.arg("build")
+		.arg("--release")
+		.arg("--quiet")
+		.arg("--manifest-path")
+		.arg(project.join("Cargo.toml"))
+		.arg("--target-dir")
+		.arg(&target_dir);
+	let built = if wasm {
+		cmd.arg("--target").arg("wasm32-unknown-unknown");
+		target_dir.join("wasm32-unknown-unknown/release").join(format!("{package}.wasm"))
+	} else {
+		target_dir.join("release").join(format!("{}{package}{}", std::env::consts::DLL_PREFIX, std::env::consts::DLL_SUFFIX))
+	};
+	let build = cmd.output().with_context(|| format!("failed to invoke cargo for {}", file.display()))?;
+	if !build.status.success() {
+		bail!("cargo build failed for {}:\n{}", file.display(), String::from_utf8_lossy(&build.stderr));
+	}
+	fs::copy(&built, out).with_context(|| format!("failed to copy {}", built.display()))?;
+	Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
 fn write_submission_receipt(
 	files: &[PathBuf],
+	fingerprints: &[Fingerprint],
+	duplicates: &[DuplicatePair],
 	results: &[prop_amm_engine::sim::AggregatedResult],
 	simulations: usize,
-	steps: usize,
-	epoch_len: usize,
-	seed_start: u64,
+	seeds: &[u64],
+	config: &SimConfig,
+	holdout: Option<&HoldoutRun>,
+	key: Option<&str>,
 ) -> Result<PathBuf> {
 	let ts = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
 	let out_dir = PathBuf::from("submissions").join(format!("submission_{}", ts));
 	fs::create_dir_all(&out_dir)?;
 
+	let mut contents = Vec::with_capacity(files.len());
 	for file in files {
 		let dest = out_dir.join(
 			file.file_name()
 				.context("invalid source filename")?,
 		);
 		fs::copy(file, dest)?;
+		contents.push(receipt::sha256_hex(fs::read(file)?));
 	}
 
-	let payload = json!({
+	let mut payload = json!({
 		"timestamp": ts,
 		"simulations": simulations,
-		"steps": steps,
-		"epoch_len": epoch_len,
-		"seed_start": seed_start,
-		"strategies": results.iter().map(|r| json!({
-			"name": r.name,
-			"mean_edge": r.mean_edge,
-			"std_edge": r.std_edge,
-			"edge_vs_normalizer": r.edge_vs_normalizer,
-			"sharpe": r.sharpe,
-			"mean_final_capital_weight": r.mean_final_capital_weight
-		})).collect::<Vec<_>>()
+		"completed_simulations": seeds.len(),
+		"partial": seeds.len() < simulations,
+		"steps": config.total_steps,
+		"epoch_len": config.epoch_len,
+		"seeds": seeds,
+		"config": config,
+		"config_hash": receipt::config_hash(config),
+		"engine": receipt::engine_info(),
+		"sdk": receipt::sdk_info(),
+		"fingerprints": files.iter().zip(fingerprints).zip(&contents).map(|((file, f), sha256)| json!({
+			"file": file.file_name().map(|n| n.to_string_lossy()),
+			"sha256": sha256,
+			"source_hash": f.source_hash,
+			"binary_hash": f.binary_hash
+		})).collect::<Vec<_>>(),
+		"duplicates": duplicates.iter().map(|d| json!({
+			"a": files[d.a].file_name().map(|n| n.to_string_lossy()),
+			"b": files[d.b].file_name().map(|n| n.to_string_lossy()),
+			"similarity": d.similarity,
+			"identical_binary": d.identical_binary
+		})).collect::<Vec<_>>(),
+		"strategies": results.iter().map(receipt_strategy).collect::<Vec<_>>(),
+		"holdout": holdout.map(|h| json!({
+			"key_commitment": h.commitment,
+			"key_source": h.key_source,
+			"simulations": h.simulations,
+			"completed_simulations": h.batch.completed,
+			"partial": h.batch.partial,
+			"strategies": h.batch.aggregated.iter().map(receipt_strategy).collect::<Vec<_>>(),
+		})),
+		"signature": null,
 	});
+	if let Some(key) = key {
+		receipt::sign(&mut payload, key);
+	}
 
 	let receipt = out_dir.join("receipt.json");
 	fs::write(&receipt, serde_json::to_vec_pretty(&payload)?)?;
 	Ok(receipt)
 }
 
+/// One strategy's aggregates as recorded in a receipt.
+fn receipt_strategy(r: &prop_amm_engine::sim::AggregatedResult) -> serde_json::Value {
+	json!({
+		"name": r.name,
+		"model": r.metadata.model,
+		"metadata": r.metadata.entries.iter().map(|(k, v)| (k.clone(), json!(v))).collect::<serde_json::Map<_, _>>(),
+		"mean_edge": r.mean_edge,
+		"std_edge": r.std_edge,
+		"edge_vs_normalizer": r.edge_vs_normalizer,
+		"sharpe": r.sharpe,
+		"sortino": r.sortino,
+		"mean_max_drawdown": r.mean_max_drawdown,
+		"mean_fee_yield": r.mean_fee_yield,
+		"mean_price_improvement_bps": r.mean_price_improvement_bps,
+		"mean_flow_share": r.mean_flow_share,
+		"flow_weighted_edge": r.flow_weighted_edge,
+		"mean_annualized_return": r.mean_annualized_return,
+		"mean_epoch_buy_volume": r.mean_epoch_buy_volume,
+		"mean_epoch_sell_volume": r.mean_epoch_sell_volume,
+		"mean_epoch_retail_trades": r.mean_epoch_retail_trades,
+		"mean_epoch_arb_trades": r.mean_epoch_arb_trades,
+		"mean_epoch_fill_share": r.mean_epoch_fill_share,
+		"largest_trade": r.largest_trade,
+		"mean_weight_trajectory": r.mean_weight_trajectory,
+		"mean_final_capital_weight": r.mean_final_capital_weight,
+		"over_quotes": r.over_quotes,
+		"degenerate_orders": r.degenerate_orders,
+		"disqualified_sims": r.disqualified_sims
+	})
+}
+
 fn dylib_ext() -> &'static str {
 	#[cfg(target_os = "macos")]
 	{
@@ -233,3 +2044,61 @@ fn dylib_ext() -> &'static str {
 		"dll"
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn single_seed_commands_take_only_simulation_settings() {
+		use clap::CommandFactory;
+		Cli::command().debug_assert();
+		assert!(Cli::try_parse_from(["prop-amm-multi", "replay", "--seed", "3", "--trace", "t.jsonl", "--steps", "200"]).is_ok());
+		for command in [&["replay", "--seed", "3", "--trace", "t.jsonl"][..], &["inspect", "s.rs"]] {
+			for batch_only in [&["--seed-start", "1"][..], &["--seeds", "seeds.txt"], &["--tournament"]] {
+				let args = ["prop-amm-multi"].iter().chain(command).chain(batch_only);
+				assert!(Cli::try_parse_from(args).is_err(), "{command:?} {batch_only:?}");
+			}
+		}
+	}
+
+	#[test]
+	fn results_are_written_as_json_and_csv() {
+		let config = SimConfig {
+			total_steps: 200,
+			epoch_len: 100,
+			opponents: vec!["fixed_30".into(), "fixed_60".into()],
+			..SimConfig::default()
+		};
+		let opts = BatchOptions { keep_results: true, ..BatchOptions::default() };
+		let batch = run_batch(&[], &config, 3, 0, &opts).unwrap();
+		let path = std::env::temp_dir().join(format!("prop-amm-results-{}", std::process::id()));
+		let write = |output: OutputFormat, per_sim: bool| {
+			let output = OutputOpts { output, output_file: Some(path.clone()), per_sim, profile: false };
+			write_results(&output, &batch, 3, &config).unwrap();
+			fs::read_to_string(&path).unwrap()
+		};
+
+		let json: serde_json::Value = serde_json::from_str(&write(OutputFormat::Json, true)).unwrap();
+		assert_eq!(json["completed_simulations"], 3);
+		assert_eq!(json["seeds"], json!([0, 1, 2]));
+		assert_eq!(json["results"].as_array().unwrap().len(), 2);
+		let per_sim = json["per_simulation"].as_array().unwrap();
+		assert_eq!(per_sim.len(), 3);
+		assert_eq!(per_sim[1]["seed"], 1);
+		assert_eq!(per_sim[1]["strategies"][0]["name"], "ref_fixed_30");
+		let json: serde_json::Value = serde_json::from_str(&write(OutputFormat::Json, false)).unwrap();
+		assert!(json.get("per_simulation").is_none());
+
+		// Every row has as many cells as the header: one per strategy, or per seed and strategy
+		for (per_sim, rows) in [(false, 2), (true, 6)] {
+			let csv = write(OutputFormat::Csv, per_sim);
+			let lines: Vec<&str> = csv.lines().collect();
+			assert_eq!(lines.len(), 1 + rows, "{csv}");
+			let columns = lines[0].split(',').count();
+			assert!(lines.iter().all(|l| l.split(',').count() == columns), "{csv}");
+			assert!(lines[1].contains("\"ref_fixed_30\""), "{csv}");
+		}
+		let _ = fs::remove_file(&path);
+	}
+}
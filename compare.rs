@@ -0,0 +1,161 @@
+//! Diffing two submission receipts.
+//!
+//! Strategies are matched by name. Each compared metric gets its delta (b − a) and, where
+//! the receipt carries enough to estimate a standard error, a z-score flag: mean edge uses
+//! `std_edge / √n`, Sharpe the asymptotic `√((1 + S²/2) / n)`. Runs on overlapping seeds
+//! are correlated, so the flags are conservative for them. Config differences are listed
+//! separately: a delta between runs on different markets says little about the strategy.
+
+use serde_json::Value;
+
+/// Receipt fields describing the run rather than its results
+const RUN_KEYS: &[&str] = &["simulations", "completed_simulations", "steps", "epoch_len", "seed_start", "seeds"];
+
+/// Metrics compared per strategy, in report order
+pub const METRICS: &[&str] = &[
+    "mean_edge",
+    "edge_vs_normalizer",
+    "sharpe",
+    "sortino",
+    "mean_max_drawdown",
+    "mean_flow_share",
+    "flow_weighted_edge",
+    "mean_final_capital_weight",
+    "mean_annualized_return",
+];
+
+/// |z| thresholds for the two-sided 5% and 1% levels
+pub const Z_5PCT: f64 = 1.96;
+pub const Z_1PCT: f64 = 2.576;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConfigChange {
+    /// Receipt key, dotted for fields inside `config`
+    pub key: String,
+    /// Value in each receipt, as JSON; `None` if absent
+    pub a: Option<String>,
+    pub b: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MetricDelta {
+    pub metric: &'static str,
+    pub a: f64,
+    pub b: f64,
+    /// Standardized delta; `None` when the receipts do not carry a standard error for it
+    pub z: Option<f64>,
+}
+
+impl MetricDelta {
+    pub fn delta(&self) -> f64 {
+        self.b - self.a
+    }
+
+    /// `"**"` at the 1% level, `"*"` at 5%, empty otherwise.
+    pub fn flag(&self) -> &'static str {
+        match self.z.map(f64::abs) {
+            Some(z) if z >= Z_1PCT => "**",
+            Some(z) if z >= Z_5PCT => "*",
+            _ => "",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct StrategyDiff {
+    pub name: String,
+    /// Metrics present in both receipts, in `METRICS` order
+    pub metrics: Vec<MetricDelta>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ReceiptDiff {
+    pub config: Vec<ConfigChange>,
+    /// Strategies in both receipts, in `a`'s order
+    pub strategies: Vec<StrategyDiff>,
+    pub only_a: Vec<String>,
+    pub only_b: Vec<String>,
+}
+
+/// Compare receipt `a` against receipt `b`.
+pub fn compare(a: &Value, b: &Value) -> Result<ReceiptDiff, String> {
+    let (list_a, list_b) = (strategies(a, "first")?, strategies(b, "second")?);
+    let (n_a, n_b) = (sample_size(a), sample_size(b));
+
+    let mut config = Vec::new();
+    for key in RUN_KEYS {
+        push_change(&mut config, key.to_string(), a.get(key), b.get(key));
+    }
+    let mut fields_a = Vec::new();
+    let mut fields_b = Vec::new();
+    flatten("config", a.get("config").unwrap_or(&Value::Null), &mut fields_a);
+    flatten("config", b.get("config").unwrap_or(&Value::Null), &mut fields_b);
+    let mut keys: Vec<&String> = fields_a.iter().chain(&fields_b).map(|(k, _)| k).collect();
+    keys.sort();
+    keys.dedup();
+    for key in keys {
+        let find = |fields: &[(String, Value)]| fields.iter().find(|(k, _)| k == key).map(|(_, v)| v.clone());
+        push_change(&mut config, key.clone(), find(&fields_a).as_ref(), find(&fields_b).as_ref());
+    }
+
+    let mut diff = ReceiptDiff { config, ..Default::default() };
+    for sa in list_a {
+        let name = name_of(sa);
+        let Some(sb) = list_b.iter().find(|s| name_of(s) == name) else {
+            diff.only_a.push(name.to_string());
+            continue;
+        };
+        let metrics = METRICS
+            .iter()
+            .filter_map(|&metric| {
+                let (va, vb) = (sa.get(metric)?.as_f64()?, sb.get(metric)?.as_f64()?);
+                let se = |s: &Value, v: f64, n: f64| match metric {
+                    "mean_edge" => Some(s.get("std_edge")?.as_f64()?.powi(2) / n),
+                    "sharpe" => Some((1.0 + v * v / 2.0) / n),
+                    _ => None,
+                };
+                let z = match (n_a.and_then(|n| se(sa, va, n)), n_b.and_then(|n| se(sb, vb, n))) {
+                    (Some(ea), Some(eb)) if ea + eb > 0.0 => Some((vb - va) / (ea + eb).sqrt()),
+                    _ => None,
+                };
+                Some(MetricDelta { metric, a: va, b: vb, z })
+            })
+            .collect();
+        diff.strategies.push(StrategyDiff { name: name.to_string(), metrics });
+    }
+    diff.only_b = list_b.iter().map(name_of).filter(|n| !list_a.iter().any(|s| name_of(s) == *n)).map(str::to_string).collect();
+    Ok(diff)
+}
+
+fn strategies<'a>(receipt: &'a Value, which: &str) -> Result<&'a Vec<Value>, String> {
+    receipt.get("strategies").and_then(Value::as_array).ok_or_else(|| format!("{which} receipt has no `strategies` list"))
+}
+
+fn name_of(strategy: &Value) -> &str {
+    strategy.get("name").and_then(Value::as_str).unwrap_or("")
+}
+
+/// Simulations behind the receipt's means, when it records them
+fn sample_size(receipt: &Value) -> Option<f64> {
+    let n = receipt.get("completed_simulations").or_else(|| receipt.get("simulations"))?.as_u64()?;
+    (n > 0).then_some(n as f64)
+}
+
+fn push_change(changes: &mut Vec<ConfigChange>, key: String, a: Option<&Value>, b: Option<&Value>) {
+    if a != b {
+        changes.push(ConfigChange { key, a: a.map(Value::to_string), b: b.map(Value::to_string) });
+    }
+}
+
+/// Leaf fields of `value` under dotted paths starting at `prefix`; arrays are leaves.
+fn flatten(prefix: &str, value: &Value, out: &mut Vec<(String, Value)>) {
+    match value {
+        Value::Object(map) => {
+            for (k, v) in map {
+                flatten(&format!("{prefix}.{k}"), v, out);
+            }
+        }
+        Value::Null => {}
+        leaf => out.push((prefix.to_string(), leaf.clone())),
+    }
+}
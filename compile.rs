@@ -0,0 +1,129 @@
+//! Cargo builds for strategies that depend on the SDK or other crates.
+//!
+//! A bare `rustc` compile cannot link any crate, so a source that names
+//! `prop_amm_submission_sdk` or an allowlisted crate is built inside a generated cargo
+//! project instead. The SDK dependency is a path dependency on the SDK sources embedded
+//! in this build of the engine, so a strategy always links the layout the engine speaks.
+//! Extra crates are pinned to exact versions.
+
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use rayon::prelude::*;
+
+/// Crate name strategies `use` the SDK under
+pub const SDK_CRATE: &str = "prop_amm_submission_sdk";
+
+/// Version the SDK package is built as
+pub const SDK_VERSION: &str = "0.1.0";
+
+/// SDK sources (`lib.rs` mounts `wire.rs` by path), pinned to this engine build
+pub const SDK_LIB: &str = include_str!("lib.rs");
+pub const SDK_WIRE: &str = include_str!("wire.rs");
+
+/// Crates a strategy may depend on besides the SDK, with their pinned versions.
+pub const ALLOWED_CRATES: &[(&str, &str)] = &[("libm", "0.2.16"), ("num_traits", "0.2.19"), ("smallvec", "1.16.3")];
+
+/// Crates the compiler provides without a dependency
+const BUILTIN_CRATES: &[&str] = &["std", "core", "alloc", "self", "super", "crate"];
+
+/// Dependencies `source` needs: `None` if plain `rustc` can build it, otherwise the
+/// allowlisted crates it names (possibly empty, when it only uses the SDK). An
+/// `extern crate` outside the SDK, the allowlist and the built-in crates is an error.
+pub fn cargo_deps(source: &str) -> Result<Option<Vec<(&'static str, &'static str)>>, String> {
+    for line in source.lines() {
+        let Some(rest) = line.trim_start().strip_prefix("extern crate ") else { continue };
+        let name = rest.split(|c: char| !(c.is_alphanumeric() || c == '_')).next().unwrap_or("");
+        if name != SDK_CRATE && !BUILTIN_CRATES.contains(&name) && !ALLOWED_CRATES.iter().any(|(c, _)| *c == name) {
+            return Err(format!(
+                "crate `{name}` is not allowed; strategies may depend on {SDK_CRATE} and {}",
+                ALLOWED_CRATES.iter().map(|(c, v)| format!("{c} {v}")).collect::<Vec<_>>().join(", ")
+            ));
+        }
+    }
+    let deps: Vec<_> = ALLOWED_CRATES.iter().copied().filter(|(c, _)| names_crate(source, c)).collect();
+    if deps.is_empty() && !names_crate(source, SDK_CRATE) {
+        return Ok(None);
+    }
+    Ok(Some(deps))
+}
+
+/// `source` refers to crate `name` as a path root (`name::…` or `extern crate name`).
+fn names_crate(source: &str, name: &str) -> bool {
+    source.match_indices(name).any(|(i, _)| {
+        let before = source[..i].chars().next_back();
+        let after = &source[i + name.len()..];
+        !before.is_some_and(|c| c.is_alphanumeric() || c == '_' || c == ':')
+            && (after.starts_with("::") || after.starts_with(';') || after.starts_with(" as "))
+    })
+}
+
+/// `Cargo.toml` of the SDK package.
+pub fn sdk_manifest() -> String {
+    format!(
+        "[package]\nname = \"{SDK_CRATE}\"\nversion = \"{SDK_VERSION}\"\nedition = \"2021\"\n\n\
+         [lib]\npath = \"src/lib.rs\"\n\n[workspace]\n\n\
+         [lints.rust]\nunexpected_cfgs = {{ level = \"warn\", check-cfg = ['cfg(target_os, values(\"solana\"))'] }}\n"
+    )
+}
+
+/// `Cargo.toml` of a generated strategy package `package`, depending on the SDK at
+/// `sdk_path` and the pinned `deps`.
+pub fn strategy_manifest(package: &str, sdk_path: &str, deps: &[(&str, &str)]) -> String {
+    let mut manifest = format!(
+        "[package]\nname = \"{package}\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n\
+         [lib]\ncrate-type = [\"cdylib\"]\npath = \"src/lib.rs\"\n\n\
+         [dependencies]\n{SDK_CRATE} = {{ path = {sdk_path:?} }}\n"
+    );
+    for (name, version) in deps {
+        manifest.push_str(&format!("{} = \"={version}\"\n", name.replace('_', "-")));
+    }
+    manifest.push_str("\n[workspace]\n\n[profile.release]\nopt-level = 3\n");
+    manifest
+}
+
+/// Cargo package name for a strategy file stem.
+pub fn package_name(stem: &str) -> String {
+    let name: String = stem.chars().map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '_' }).collect();
+    format!("strategy_{name}")
+}
+
+/// How a build produced a strategy artifact.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compiled {
+    /// A prebuilt library, SBF program or remote manifest, used as given
+    Prebuilt,
+    Cached,
+    Built,
+}
+
+/// Build `files` in parallel with `build`, reporting each one's status as it finishes.
+/// All files are attempted; the artifacts come back in `files` order, or else the first
+/// failure in `files` order.
+pub fn build_all<E: Send>(
+    files: &[PathBuf],
+    build: impl Fn(&Path) -> Result<(PathBuf, Compiled), E> + Sync,
+    report: &(impl Fn(String) + Sync),
+) -> Result<Vec<PathBuf>, E> {
+    let started = Instant::now();
+    let results: Vec<Result<PathBuf, E>> = files
+        .par_iter()
+        .map(|file| {
+            let t = Instant::now();
+            let result = build(file);
+            match &result {
+                Ok((_, Compiled::Built)) => {
+                    report(format!("[compiled] {} ({:.1}s)", file.display(), t.elapsed().as_secs_f64()))
+                }
+                Ok((_, Compiled::Cached)) => report(format!("[cached] {}", file.display())),
+                Ok((_, Compiled::Prebuilt)) => report(format!("[prebuilt] {}", file.display())),
+                Err(_) => report(format!("[FAILED] {}", file.display())),
+            }
+            result.map(|(artifact, _)| artifact)
+        })
+        .collect();
+    if files.len() > 1 {
+        report(format!("Compiled {} strategies in {:.1}s", files.len(), started.elapsed().as_secs_f64()));
+    }
+    results.into_iter().collect()
+}
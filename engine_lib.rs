@@ -1,10 +1,27 @@
 extern crate self as prop_amm_engine;
 
 pub mod capital;
+pub mod compare;
+pub mod compile;
+pub mod events;
+pub mod fingerprint;
+pub mod leaderboard;
+pub mod manifest;
 pub mod market;
+pub mod optimize;
+pub mod profile;
+pub mod receipt;
+pub mod reference;
 pub mod runner;
+pub mod scaffold;
+#[cfg(feature = "serve")]
+pub mod serve;
 pub mod sim;
+pub mod trace;
 pub mod types;
+pub mod validation;
+pub mod watch;
+pub mod wire;
 
 #[cfg(test)]
 #[path = "tests.rs"]
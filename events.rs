@@ -0,0 +1,304 @@
+//! Trade-level event log and periodic state snapshots.
+//!
+//! When `SimConfig::record_trades` is set, every executed trade (arb, retail fill, RFQ,
+//! sandwich leg) is captured as a `TradeEvent` and returned in `SimResult::trades`, so
+//! strategy authors can see exactly where edge was won or lost. The words of storage
+//! listed in `SimConfig::trade_slots` ride along on each strategy trade.
+//!
+//! Circuit-breaker halts (`SimConfig::circuit_breaker_band`) are returned as `HaltEvent`s
+//! in `SimResult::halts`, and strategy calls over `SimConfig::call_budget` as
+//! `BudgetEvent`s in `SimResult::budget_events`. Calls that panicked, trapped or crashed
+//! are `FaultEvent`s in `SimResult::fault_events`, and hardened-mode storage violations
+//! (`SimConfig::harden_storage`) `TamperEvent`s in `SimResult::tamper_events`.
+//!
+//! When `SimConfig::snapshot_stride` is K > 0, every AMM's state is sampled every K steps
+//! into `SimResult::snapshots` for time-series plots. `write_step_log` turns a run with
+//! trades and snapshots into a per-step JSON Lines log.
+
+use std::io::Write;
+
+use serde_json::json;
+
+use crate::market::OrderKind;
+use crate::runner::StrategyCall;
+use crate::sim::SimResult;
+use crate::types::{AmmState, STORAGE_SIZE};
+
+/// What caused a trade.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TradeKind {
+    /// Arbitrageur trading the AMM back toward fair
+    Arb,
+    /// Split-routed one-shot retail order
+    Retail,
+    /// Child slice of a TWAP/iceberg parent
+    TwapSlice,
+    /// Winner-take-all RFQ fill
+    Rfq,
+    /// Sandwich attacker's front-run leg
+    SandwichFront,
+    /// Sandwich attacker's back-run leg
+    SandwichBack,
+}
+
+impl TradeKind {
+    /// Informed flow trades against the AMM because its quote is off fair
+    /// (arbitrage and sandwich legs), as opposed to retail.
+    pub fn is_informed(self) -> bool {
+        matches!(self, TradeKind::Arb | TradeKind::SandwichFront | TradeKind::SandwichBack)
+    }
+}
+
+impl From<OrderKind> for TradeKind {
+    fn from(kind: OrderKind) -> Self {
+        match kind {
+            OrderKind::Market => TradeKind::Retail,
+            OrderKind::TwapSlice => TradeKind::TwapSlice,
+            OrderKind::Rfq => TradeKind::Rfq,
+        }
+    }
+}
+
+/// One executed trade.
+#[derive(Clone, Debug)]
+pub struct TradeEvent {
+    pub step: usize,
+    /// AMM index: strategies are 0..n_strat, normalizers follow
+    pub amm: usize,
+    pub kind: TradeKind,
+    /// true = buy X (Y in, X out)
+    pub is_buy: bool,
+    /// Input amount (1e9 scale)
+    pub input: u64,
+    /// Output amount (1e9 scale)
+    pub output: u64,
+    /// Fair price at execution
+    pub fair_price: f64,
+    /// Fraction of the retail order routed to this AMM (0.0 for arb and sandwich legs)
+    pub flow_share: f64,
+    /// `SimConfig::trade_slots` of the strategy's storage once its after_swap for this
+    /// trade returned (empty when none are chosen and for normalizers)
+    pub slots: Vec<u64>,
+}
+
+/// Append-only trade sink. Recording is a no-op unless enabled.
+#[derive(Clone, Debug, Default)]
+pub struct EventLog {
+    enabled: bool,
+    events: Vec<TradeEvent>,
+}
+
+impl EventLog {
+    pub fn new(enabled: bool) -> Self {
+        Self { enabled, events: Vec::new() }
+    }
+
+    #[inline]
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    #[inline]
+    pub fn record(&mut self, event: TradeEvent) {
+        if self.enabled {
+            self.events.push(event);
+        }
+    }
+
+    pub fn into_events(self) -> Vec<TradeEvent> {
+        self.events
+    }
+}
+
+/// Storage slots a strategy has (u64 words)
+pub const STORAGE_SLOTS: usize = STORAGE_SIZE / 8;
+
+/// Parse a slot selection for `SimConfig::trade_slots`: slots (`7`) and half-open ranges
+/// (`0..16`) separated by commas or whitespace, in the order given.
+pub fn parse_slots(text: &str) -> Result<Vec<usize>, String> {
+    let mut slots = Vec::new();
+    for token in text.split(|c: char| c.is_whitespace() || c == ',').filter(|t| !t.is_empty()) {
+        let parse = |s: &str| s.parse::<usize>().map_err(|e| format!("`{token}`: {e}"));
+        let range = match token.split_once("..") {
+            Some((a, b)) => parse(a)?..parse(b)?,
+            None => parse(token).map(|s| s..s.saturating_add(1))?,
+        };
+        if range.is_empty() {
+            return Err(format!("empty range `{token}`"));
+        }
+        if range.end > STORAGE_SLOTS {
+            return Err(format!("`{token}`: storage has slots 0..{STORAGE_SLOTS}"));
+        }
+        for slot in range {
+            if slots.contains(&slot) {
+                return Err(format!("slot {slot} is listed twice"));
+            }
+            slots.push(slot);
+        }
+    }
+    if slots.is_empty() {
+        return Err("no slots listed".to_string());
+    }
+    Ok(slots)
+}
+
+/// Read `slots` of `storage` as little-endian u64 words.
+pub fn read_slots(storage: &[u8], slots: &[usize]) -> Vec<u64> {
+    slots.iter().map(|&s| u64::from_le_bytes(storage[s * 8..s * 8 + 8].try_into().unwrap())).collect()
+}
+
+// ─── Circuit breaker ─────────────────────────────────────────────────────────
+
+/// A circuit-breaker halt on one strategy AMM.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HaltEvent {
+    pub amm: usize,
+    /// Step at which routing halted
+    pub step: usize,
+    /// Relative spot-vs-fair deviation that triggered the halt
+    pub deviation: f64,
+    /// Step at which the spot came back inside the band (None if still halted at the end)
+    pub resumed_at: Option<usize>,
+}
+
+/// A strategy call that exceeded its compute budget and was discarded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BudgetEvent {
+    pub amm: usize,
+    pub step: u64,
+    pub call: StrategyCall,
+}
+
+/// A strategy call that panicked, trapped, or crashed its worker process. A crashed
+/// strategy quotes 0 for the rest of the run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FaultEvent {
+    pub amm: usize,
+    pub step: u64,
+    pub call: StrategyCall,
+}
+
+/// A strategy call that wrote outside its storage (or into its read-only payload) under
+/// `SimConfig::harden_storage`. The strategy is disqualified: it quotes 0 from here on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TamperEvent {
+    pub amm: usize,
+    pub step: u64,
+    pub call: StrategyCall,
+}
+
+/// One diagnostics line a native strategy wrote with the SDK's `log!` during a call,
+/// when `SimConfig::strategy_logs` is set.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StrategyLogEvent {
+    pub amm: usize,
+    pub step: u64,
+    pub call: StrategyCall,
+    pub line: String,
+}
+
+// ─── State snapshots ──────────────────────────────────────────────────────────
+
+/// One AMM's state at a snapshot step.
+#[derive(Clone, Debug)]
+pub struct AmmSnapshot {
+    pub reserve_x: u64,
+    pub reserve_y: u64,
+    pub spot_price: f64,
+    pub cumulative_edge: f64,
+    pub capital_weight: f64,
+    /// The strategy's storage, when `SimConfig::snapshot_storage` is set (empty otherwise
+    /// and for normalizers)
+    pub storage: Vec<u8>,
+}
+
+impl From<&AmmState> for AmmSnapshot {
+    fn from(amm: &AmmState) -> Self {
+        Self {
+            reserve_x: amm.reserve_x,
+            reserve_y: amm.reserve_y,
+            spot_price: amm.spot_price(),
+            cumulative_edge: amm.cumulative_edge,
+            capital_weight: amm.capital_weight,
+            storage: Vec::new(),
+        }
+    }
+}
+
+/// State of every AMM at the end of one step.
+#[derive(Clone, Debug)]
+pub struct StepSnapshot {
+    pub step: usize,
+    pub fair_price: f64,
+    /// Strategies in index order, then normalizers
+    pub amms: Vec<AmmSnapshot>,
+}
+
+// ─── Step log ─────────────────────────────────────────────────────────────────
+
+/// Write `result` as JSON Lines: a header with the seed and venue names, then one line
+/// per snapshot with the trades and notable strategy calls since the previous one and
+/// every AMM's state. Storage is written as hex on the first snapshot and whenever it
+/// changed since. Run with `record_trades`, `snapshot_stride = 1` and `snapshot_storage`
+/// for a complete step-by-step log.
+pub fn write_step_log(result: &SimResult, seed: u64, out: &mut impl Write) -> std::io::Result<()> {
+    let names: Vec<&str> = result.strategies.iter().map(|s| s.name.as_str()).collect();
+    writeln!(out, "{}", json!({
+        "seed": seed,
+        "strategies": names,
+        "normalizers": result.normalizer_edges.len(),
+        "cancelled": result.cancelled,
+    }))?;
+
+    let mut calls: Vec<(u64, serde_json::Value)> = Vec::new();
+    let mut push = |amm: usize, step: u64, call: StrategyCall, event: &str| {
+        calls.push((step, json!({ "amm": amm, "call": format!("{call:?}"), "event": event })));
+    };
+    for e in &result.budget_events { push(e.amm, e.step, e.call, "over_budget"); }
+    for e in &result.fault_events { push(e.amm, e.step, e.call, "fault"); }
+    for e in &result.tamper_events { push(e.amm, e.step, e.call, "tamper"); }
+    for e in &result.strategy_logs {
+        calls.push((e.step, json!({ "amm": e.amm, "call": format!("{:?}", e.call), "event": "log", "line": e.line })));
+    }
+    calls.sort_by_key(|(step, _)| *step);
+
+    let mut last_storage: Vec<&[u8]> = vec![&[]; names.len()];
+    let (mut trades, mut calls) = (result.trades.iter().peekable(), calls.into_iter().peekable());
+    for snap in &result.snapshots {
+        let step_trades: Vec<_> = std::iter::from_fn(|| trades.next_if(|t| t.step <= snap.step))
+            .map(|t| json!({
+                "amm": t.amm,
+                "kind": format!("{:?}", t.kind),
+                "is_buy": t.is_buy,
+                "input": t.input,
+                "output": t.output,
+                "flow_share": t.flow_share,
+            }))
+            .collect();
+        let step_calls: Vec<_> = std::iter::from_fn(|| calls.next_if(|(step, _)| *step <= snap.step as u64))
+            .map(|(_, call)| call)
+            .collect();
+        let amms: Vec<_> = snap.amms.iter().enumerate().map(|(i, a)| {
+            let mut amm = json!({
+                "reserve_x": a.reserve_x,
+                "reserve_y": a.reserve_y,
+                "spot_price": a.spot_price,
+                "cumulative_edge": a.cumulative_edge,
+                "capital_weight": a.capital_weight,
+            });
+            if i < last_storage.len() && !a.storage.is_empty() && a.storage != last_storage[i] {
+                amm["storage"] = a.storage.iter().map(|b| format!("{b:02x}")).collect::<String>().into();
+                last_storage[i] = &a.storage;
+            }
+            amm
+        }).collect();
+        writeln!(out, "{}", json!({
+            "step": snap.step,
+            "fair_price": snap.fair_price,
+            "trades": step_trades,
+            "calls": step_calls,
+            "amms": amms,
+        }))?;
+    }
+    Ok(())
+}
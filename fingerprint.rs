@@ -0,0 +1,231 @@
+//! Submission fingerprints, for spotting the same strategy entered under different names.
+//!
+//! A `Fingerprint` carries two hashes. The source hash covers the strategy's token stream
+//! with comments, whitespace, string contents and non-keyword identifiers normalized
+//! away, so reformatting or renaming leaves it unchanged. The binary hash covers only the
+//! executable sections of the compiled artifact, so symbol tables, debug info and embedded
+//! names do not affect it. Near-duplicates (a reordered helper, an added dead branch) are
+//! caught by `similarity`, a Jaccard index over overlapping runs of normalized tokens,
+//! among sources that use the same numeric literals. Two copies of one template with
+//! different constants are different parameterizations, not duplicates.
+
+use std::path::Path;
+
+use sha2::{Digest, Sha256};
+
+/// Source similarity at or above which two submissions are reported as near-duplicates.
+pub const NEAR_DUPLICATE_SIMILARITY: f64 = 0.9;
+
+/// Normalized tokens per shingle in `similarity`.
+const SHINGLE_LEN: usize = 4;
+
+#[derive(Clone, Debug, Default)]
+pub struct Fingerprint {
+    /// SHA-256 of the normalized token stream (hex)
+    pub source_hash: String,
+    /// SHA-256 of the artifact's executable sections (hex); of the whole file for
+    /// artifacts `object` cannot parse
+    pub binary_hash: String,
+    /// Sorted, deduplicated hashes of every `SHINGLE_LEN`-token run
+    shingles: Vec<u64>,
+    /// Sorted, deduplicated numeric literals
+    literals: Vec<String>,
+}
+
+/// Two submissions whose fingerprints match or nearly match.
+#[derive(Clone, Debug)]
+pub struct DuplicatePair {
+    /// Indices into the fingerprints passed to `find_duplicates`
+    pub a: usize,
+    pub b: usize,
+    pub similarity: f64,
+    pub identical_binary: bool,
+}
+
+impl Fingerprint {
+    /// Fingerprint a strategy from its source text and compiled artifact.
+    pub fn of(source: &str, artifact: &Path) -> std::io::Result<Self> {
+        let tokens = normalized_tokens(source);
+        let mut shingles: Vec<u64> = tokens
+            .windows(SHINGLE_LEN.min(tokens.len()).max(1))
+            .map(|w| {
+                let digest = Sha256::digest(w.join(" "));
+                u64::from_le_bytes(digest[..8].try_into().unwrap())
+            })
+            .collect();
+        shingles.sort_unstable();
+        shingles.dedup();
+        let mut literals: Vec<String> = tokens.iter().filter(|t| t.starts_with(|c: char| c.is_ascii_digit())).cloned().collect();
+        literals.sort_unstable();
+        literals.dedup();
+        Ok(Self {
+            source_hash: hex(&Sha256::digest(tokens.join(" "))),
+            binary_hash: hex(&code_digest(&std::fs::read(artifact)?)),
+            shingles,
+            literals,
+        })
+    }
+
+    /// Whether both sources use the same set of numeric literals.
+    pub fn same_literals(&self, other: &Fingerprint) -> bool {
+        self.literals == other.literals
+    }
+
+    /// Jaccard index of the two sources' token shingles: 1.0 for identical normalized
+    /// sources, near 0 for unrelated ones.
+    pub fn similarity(&self, other: &Fingerprint) -> f64 {
+        let (mut i, mut j, mut shared) = (0, 0, 0usize);
+        while i < self.shingles.len() && j < other.shingles.len() {
+            match self.shingles[i].cmp(&other.shingles[j]) {
+                std::cmp::Ordering::Less => i += 1,
+                std::cmp::Ordering::Greater => j += 1,
+                std::cmp::Ordering::Equal => {
+                    shared += 1;
+                    i += 1;
+                    j += 1;
+                }
+            }
+        }
+        let union = self.shingles.len() + other.shingles.len() - shared;
+        if union == 0 { 1.0 } else { shared as f64 / union as f64 }
+    }
+}
+
+/// Every pair with an identical binary hash, or with the same numeric literals and a
+/// source similarity of at least `NEAR_DUPLICATE_SIMILARITY`.
+pub fn find_duplicates(fingerprints: &[Fingerprint]) -> Vec<DuplicatePair> {
+    let mut pairs = Vec::new();
+    for a in 0..fingerprints.len() {
+        for b in a + 1..fingerprints.len() {
+            let similarity = fingerprints[a].similarity(&fingerprints[b]);
+            let identical_binary = fingerprints[a].binary_hash == fingerprints[b].binary_hash;
+            let near = similarity >= NEAR_DUPLICATE_SIMILARITY && fingerprints[a].same_literals(&fingerprints[b]);
+            if identical_binary || near {
+                pairs.push(DuplicatePair { a, b, similarity, identical_binary });
+            }
+        }
+    }
+    pairs
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Digest of the executable sections, in file order.
+fn code_digest(data: &[u8]) -> Vec<u8> {
+    use object::{Object, ObjectSection, SectionKind};
+
+    let mut hasher = Sha256::new();
+    match object::File::parse(data) {
+        Ok(file) => {
+            for section in file.sections().filter(|s| s.kind() == SectionKind::Text) {
+                hasher.update(section.data().unwrap_or_default());
+            }
+        }
+        Err(_) => hasher.update(data),
+    }
+    hasher.finalize().to_vec()
+}
+
+const KEYWORDS: &[&str] = &[
+    "as", "break", "const", "continue", "crate", "else", "enum", "extern", "false", "fn", "for", "if", "impl",
+    "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return", "self", "Self", "static",
+    "struct", "super", "trait", "true", "type", "unsafe", "use", "where", "while",
+];
+
+/// Rust source as a token stream: comments and whitespace dropped, string and char
+/// literals collapsed to `"_"`, identifiers other than keywords collapsed to `_`, and
+/// digit separators removed from numbers.
+fn normalized_tokens(source: &str) -> Vec<String> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '/' && next == Some('/') {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if c == '/' && next == Some('*') {
+            let mut depth = 0;
+            while i < chars.len() {
+                if chars[i] == '/' && chars.get(i + 1) == Some(&'*') {
+                    depth += 1;
+                    i += 2;
+                } else if chars[i] == '*' && chars.get(i + 1) == Some(&'/') {
+                    depth -= 1;
+                    i += 2;
+                    if depth == 0 {
+                        break;
+                    }
+                } else {
+                    i += 1;
+                }
+            }
+        } else if c == 'r' && matches!(next, Some('"' | '#')) && raw_string_end(&chars, i).is_some() {
+            i = raw_string_end(&chars, i).unwrap();
+            tokens.push("\"_\"".to_string());
+        } else if c == '"' || (c == 'b' && next == Some('"')) {
+            i += if c == 'b' { 2 } else { 1 };
+            while i < chars.len() && chars[i] != '"' {
+                i += if chars[i] == '\\' { 2 } else { 1 };
+            }
+            i += 1;
+            tokens.push("\"_\"".to_string());
+        } else if c == '\'' && char_literal_end(&chars, i).is_some() {
+            i = char_literal_end(&chars, i).unwrap();
+            tokens.push("\"_\"".to_string());
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            let word: String = chars[start..i].iter().collect();
+            tokens.push(if KEYWORDS.contains(&word.as_str()) { word } else { "_".to_string() });
+        } else if c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '.') {
+                // `1..n` is a range, not a float
+                if chars[i] == '.' && chars.get(i + 1) == Some(&'.') {
+                    break;
+                }
+                i += 1;
+            }
+            tokens.push(chars[start..i].iter().filter(|&&d| d != '_').collect());
+        } else {
+            tokens.push(c.to_string());
+            i += 1;
+        }
+    }
+    tokens
+}
+
+/// End of an `r"…"` / `r#"…"#` literal starting at `i`, if that is what it is.
+fn raw_string_end(chars: &[char], i: usize) -> Option<usize> {
+    let hashes = chars[i + 1..].iter().take_while(|&&c| c == '#').count();
+    let open = i + 1 + hashes;
+    if chars.get(open) != Some(&'"') {
+        return None;
+    }
+    let mut j = open + 1;
+    while j < chars.len() {
+        if chars[j] == '"' && chars[j + 1..].iter().take(hashes).filter(|&&c| c == '#').count() == hashes {
+            return Some(j + 1 + hashes);
+        }
+        j += 1;
+    }
+    Some(chars.len())
+}
+
+/// End of a char literal starting at `i`, or `None` for a lifetime.
+fn char_literal_end(chars: &[char], i: usize) -> Option<usize> {
+    if chars.get(i + 1) == Some(&'\\') {
+        let close = chars[i + 2..].iter().position(|&c| c == '\'')?;
+        return Some(i + 2 + close + 1);
+    }
+    (chars.get(i + 2) == Some(&'\'')).then_some(i + 3)
+}
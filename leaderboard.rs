@@ -0,0 +1,107 @@
+//! Persistent tournament standings, carried across `tournament` invocations.
+//!
+//! The leaderboard is a JSON file. Each invocation ranks the field by mean edge and
+//! records every strategy's previous rank, so rank deltas survive between runs.
+//! Strategies are matched by name. One that sits out a run keeps its last standing,
+//! and its rank is cleared until it competes again.
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::sim::AggregatedResult;
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Leaderboard {
+    /// Tournament invocations recorded so far
+    pub runs: u64,
+    /// Unix time of the latest recorded run
+    pub updated: u64,
+    /// Strategies ever entered, current field first in rank order
+    pub standings: Vec<Standing>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Standing {
+    pub name: String,
+    /// 1-based rank in the latest run; `None` if the strategy sat it out
+    pub rank: Option<usize>,
+    /// Rank in the last run before the latest one that the strategy took part in
+    pub previous_rank: Option<usize>,
+    pub best_rank: usize,
+    /// Runs the strategy took part in
+    pub appearances: u64,
+    pub mean_edge: f64,
+    pub sharpe: f64,
+    pub mean_final_capital_weight: f64,
+}
+
+impl Standing {
+    /// Places gained (positive) or lost since the previous run; `None` for new entries
+    /// and strategies absent from the latest run.
+    pub fn rank_delta(&self) -> Option<i64> {
+        Some(self.previous_rank? as i64 - self.rank? as i64)
+    }
+}
+
+impl Leaderboard {
+    /// Read the leaderboard at `path`; a missing file is an empty leaderboard.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        match std::fs::read(path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).map_err(|e| format!("{}: {e}", path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(format!("{}: {e}", path.display())),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let json = serde_json::to_vec_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(path, json).map_err(|e| format!("{}: {e}", path.display()))
+    }
+
+    /// Rank `results` by mean edge and fold them into the standings as run `updated`.
+    pub fn record(&mut self, results: &[AggregatedResult], updated: u64) {
+        let mut ranked: Vec<&AggregatedResult> = results.iter().collect();
+        ranked.sort_by(|a, b| b.mean_edge.total_cmp(&a.mean_edge));
+
+        let mut absent: Vec<Standing> = std::mem::take(&mut self.standings);
+        let mut standings = Vec::with_capacity(ranked.len() + absent.len());
+        for (i, r) in ranked.into_iter().enumerate() {
+            let rank = i + 1;
+            let standing = match absent.iter().position(|s| s.name == r.name) {
+                Some(pos) => {
+                    let prev = absent.remove(pos);
+                    Standing {
+                        rank: Some(rank),
+                        previous_rank: prev.rank.or(prev.previous_rank),
+                        best_rank: prev.best_rank.min(rank),
+                        appearances: prev.appearances + 1,
+                        mean_edge: r.mean_edge,
+                        sharpe: r.sharpe,
+                        mean_final_capital_weight: r.mean_final_capital_weight,
+                        ..prev
+                    }
+                }
+                None => Standing {
+                    name: r.name.clone(),
+                    rank: Some(rank),
+                    previous_rank: None,
+                    best_rank: rank,
+                    appearances: 1,
+                    mean_edge: r.mean_edge,
+                    sharpe: r.sharpe,
+                    mean_final_capital_weight: r.mean_final_capital_weight,
+                },
+            };
+            standings.push(standing);
+        }
+        for s in &mut absent {
+            s.previous_rank = s.rank.take().or(s.previous_rank);
+        }
+        standings.extend(absent);
+
+        self.standings = standings;
+        self.runs += 1;
+        self.updated = updated;
+    }
+}
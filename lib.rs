@@ -1,16 +1,28 @@
 //! `prop_amm_submission_sdk` — Strategy author interface.
 //!
 //! This crate provides everything a strategy needs:
-//!  - Typed decoders for `ComputeSwap`, `AfterSwap`, and `EpochBoundary` payloads
+//!  - Typed decoders for `ComputeSwap`, `AfterSwap`, `EpochBoundary`, `ShockNews` and
+//!    `StepDigest` payloads
 //!  - `set_return_data_u64` / `set_storage` helpers
+//!  - A `log!` diagnostics channel for native debugging runs
 //!  - Fixed-point math utilities (wmul, wdiv, sqrt, bps_to_wad)
 //!
 //! Strategies only need to implement:
 //!   `fn compute_swap(ctx: &SwapContext) -> u64`
 //!   `fn after_swap(ctx: &AfterSwapContext, storage: &mut Storage)`   [optional]
 //!   `fn on_epoch_boundary(ctx: &EpochContext, storage: &mut Storage)` [optional]
+//!   `fn on_shock_news(ctx: &ShockNewsContext, storage: &mut Storage)` [optional]
+//!   `fn on_step_digest(ctx: &StepDigestContext, storage: &mut Storage)` [optional]
+//!
+//! The crate uses `std` for native and WASM builds and is `no_std` only on Solana
+//! (`target_os = "solana"`), where the thread-local return-data and storage helpers are
+//! left out (programs return data through syscalls) and `log!` does nothing.
+
+#![cfg_attr(target_os = "solana", no_std)]
 
-#![no_std]
+/// Payload layouts and codecs, shared with the engine (the engine's `wire` module).
+#[path = "wire.rs"]
+pub mod wire;
 
 // ─── Scale constants ──────────────────────────────────────────────────────────
 
@@ -23,9 +35,23 @@ pub const WAD: u64 = 1_000_000_000_000_000_000;
 pub const MAX_FEE_WAD: u64 = WAD / 10;  // 10% max fee
 pub const MIN_FEE_WAD: u64 = 0;
 
+// ─── ABI version ──────────────────────────────────────────────────────────────
+
+/// Payload layout this SDK decodes. Every strategy must export it; the engine refuses
+/// to load a strategy built against a layout it no longer speaks:
+///
+/// ```ignore
+/// #[no_mangle]
+/// pub extern "C" fn __prop_amm_abi_version() -> u32 { ABI_VERSION }
+/// ```
+pub const ABI_VERSION: u32 = wire::ABI_VERSION;
+
+/// Competitor slots in `AfterSwapContext` (the engine's `MAX_STRATEGIES`)
+pub const MAX_COMPETITORS: usize = wire::MAX_COMPETITORS;
+
 // ─── Storage ──────────────────────────────────────────────────────────────────
 
-pub const STORAGE_SIZE: usize = 1024;
+pub const STORAGE_SIZE: usize = wire::STORAGE_SIZE;
 
 /// Strategy persistent storage: 1024 bytes, zero-initialized, persists across
 /// all trades within a simulation AND across epoch boundaries.
@@ -34,7 +60,7 @@ pub type Storage = [u8; STORAGE_SIZE];
 // ─── Swap context ─────────────────────────────────────────────────────────────
 
 /// Context passed to `compute_swap`.
-/// Decoded from the wire payload at byte offsets [0..1049].
+/// Decoded from the `wire::swap` payload.
 pub struct SwapContext {
     /// true = buy X (Y is input), false = sell X (X is input)
     pub is_buy: bool,
@@ -51,13 +77,13 @@ pub struct SwapContext {
 impl SwapContext {
     /// Parse from raw instruction bytes.
     pub fn from_bytes(data: &[u8]) -> Option<Self> {
-        if data.len() < 25 + STORAGE_SIZE { return None; }
+        let (p, _) = wire::decode_compute_swap(data)?;
         Some(Self {
-            is_buy: data[0] == 0,
-            input_amount: u64::from_le_bytes(data[1..9].try_into().ok()?),
-            reserve_x:    u64::from_le_bytes(data[9..17].try_into().ok()?),
-            reserve_y:    u64::from_le_bytes(data[17..25].try_into().ok()?),
-            storage: data[25..25 + STORAGE_SIZE].try_into().ok()?,
+            is_buy:       p.tag == wire::TAG_SWAP_BUY,
+            input_amount: p.input_amount,
+            reserve_x:    p.reserve_x,
+            reserve_y:    p.reserve_y,
+            storage:      p.storage,
         })
     }
 
@@ -66,13 +92,36 @@ impl SwapContext {
     pub fn spot_price(&self) -> f64 {
         self.reserve_y as f64 / self.reserve_x as f64
     }
+
+    /// Quote each size in `inputs` into `outputs` against this context, decoded once:
+    /// the body of an optional batch export the router uses to build output curves.
+    ///
+    /// ```ignore
+    /// #[no_mangle]
+    /// pub extern "C" fn __prop_amm_compute_swap_batch(
+    ///     data: *const u8, len: usize, inputs: *const u64, outputs: *mut u64, n: usize,
+    /// ) {
+    ///     let bytes = unsafe { core::slice::from_raw_parts(data, len) };
+    ///     let inputs = unsafe { core::slice::from_raw_parts(inputs, n) };
+    ///     let outputs = unsafe { core::slice::from_raw_parts_mut(outputs, n) };
+    ///     if let Some(mut ctx) = SwapContext::from_bytes(bytes) {
+    ///         ctx.quote_batch(inputs, outputs, compute_swap);
+    ///     }
+    /// }
+    /// ```
+    pub fn quote_batch(&mut self, inputs: &[u64], outputs: &mut [u64], quote: impl Fn(&SwapContext) -> u64) {
+        for (&input, out) in inputs.iter().zip(outputs.iter_mut()) {
+            self.input_amount = input;
+            *out = quote(self);
+        }
+    }
 }
 
 // ─── AfterSwap context ────────────────────────────────────────────────────────
 
 /// Enriched context passed to `after_swap` after every real trade.
 ///
-/// Byte offsets mirror the `AfterSwapPayload` layout in the engine's `types.rs`.
+/// Decoded from the `wire::after_swap` payload.
 pub struct AfterSwapContext {
     pub is_buy:        bool,
     pub input_amount:  u64,
@@ -85,7 +134,7 @@ pub struct AfterSwapContext {
     pub epoch_step:    u32,
     /// Current epoch index (0-based)
     pub epoch_number:  u32,
-    /// Total number of competing AMMs (strategies + normalizer)
+    /// Total number of competing AMMs (strategies + normalizers)
     pub n_strategies:  u8,
     /// This strategy's index in the routing pool
     pub strategy_index: u8,
@@ -95,38 +144,49 @@ pub struct AfterSwapContext {
     /// This strategy's current fraction of total protocol capital
     pub capital_weight: f32,
 
+    /// Number of filled slots in `competing_spot_prices`
+    pub n_competing: u8,
     /// Spot prices of the other AMMs (NaN for unused slots).
-    /// Slots 0..n_strategies-2 are other strategies; last slot is the normalizer.
-    pub competing_spot_prices: [f32; 8],
+    /// Other strategies come first, then the normalizer venues.
+    pub competing_spot_prices: [f32; MAX_COMPETITORS],
+    /// Y reserves of the same AMMs (1e9 scale, like `reserve_y`; NaN for unused slots):
+    /// a venue with 10× your reserves at the same spot takes most of the flow.
+    pub competing_reserve_y: [f32; MAX_COMPETITORS],
 }
 
 impl AfterSwapContext {
     pub fn from_bytes(data: &[u8]) -> Option<Self> {
-        if data.len() < 92 { return None; }
+        let (p, _) = wire::decode_after_swap(data)?;
         Some(Self {
-            is_buy:         data[1] == 0,
-            input_amount:   u64::from_le_bytes(data[2..10].try_into().ok()?),
-            output_amount:  u64::from_le_bytes(data[10..18].try_into().ok()?),
-            reserve_x:      u64::from_le_bytes(data[18..26].try_into().ok()?),
-            reserve_y:      u64::from_le_bytes(data[26..34].try_into().ok()?),
-            sim_step:       u64::from_le_bytes(data[34..42].try_into().ok()?),
-            epoch_step:     u32::from_le_bytes(data[42..46].try_into().ok()?),
-            epoch_number:   u32::from_le_bytes(data[46..50].try_into().ok()?),
-            n_strategies:   data[50],
-            strategy_index: data[51],
-            flow_captured:  f32::from_le_bytes(data[52..56].try_into().ok()?),
-            capital_weight: f32::from_le_bytes(data[56..60].try_into().ok()?),
-            competing_spot_prices: {
-                let mut arr = [f32::NAN; 8];
-                for i in 0..8 {
-                    let off = 60 + i * 4;
-                    arr[i] = f32::from_le_bytes(data[off..off+4].try_into().ok()?);
-                }
-                arr
-            },
+            is_buy:         p.side == 0,
+            input_amount:   p.input_amount,
+            output_amount:  p.output_amount,
+            reserve_x:      p.reserve_x,
+            reserve_y:      p.reserve_y,
+            sim_step:       p.sim_step,
+            epoch_step:     p.epoch_step,
+            epoch_number:   p.epoch_number,
+            n_strategies:   p.n_strategies,
+            strategy_index: p.strategy_index,
+            flow_captured:  p.flow_captured,
+            capital_weight: p.capital_weight,
+            n_competing:    p.n_competing,
+            competing_spot_prices: p.competing_spot_prices,
+            competing_reserve_y:   p.competing_reserve_y,
         })
     }
 
+    /// This AMM's share of the Y liquidity across itself and its competitors (0.0-1.0).
+    pub fn depth_share(&self) -> f64 {
+        let others: f64 = self.competing_reserve_y[..self.n_competing as usize]
+            .iter()
+            .filter(|r| r.is_finite())
+            .map(|&r| r as f64)
+            .sum();
+        let own = self.reserve_y as f64;
+        if own + others > 0.0 { own / (own + others) } else { 0.0 }
+    }
+
     /// Spot price from post-trade reserves.
     #[inline]
     pub fn spot_price(&self) -> f64 {
@@ -170,14 +230,59 @@ pub struct EpochContext {
 
 impl EpochContext {
     pub fn from_bytes(data: &[u8]) -> Option<Self> {
-        if data.len() < 41 { return None; }
+        let (p, _) = wire::decode_epoch_boundary(data)?;
+        Some(Self {
+            epoch_number:    p.epoch_number,
+            new_reserve_x:   p.new_reserve_x,
+            new_reserve_y:   p.new_reserve_y,
+            epoch_edge:      p.epoch_edge,
+            cumulative_edge: p.cumulative_edge,
+            capital_weight:  p.capital_weight,
+        })
+    }
+}
+
+// ─── Shock news context ───────────────────────────────────────────────────────
+
+/// Context passed to `on_shock_news` (tag 6), one step before a fair-price jump.
+/// Only delivered when the host enables shock news.
+pub struct ShockNewsContext {
+    /// Step at which the jump lands
+    pub shock_step: u64,
+    /// Absolute fractional size of the jump (direction not revealed)
+    pub magnitude: f64,
+}
+
+impl ShockNewsContext {
+    pub fn from_bytes(data: &[u8]) -> Option<Self> {
+        let (p, _) = wire::decode_shock_news(data)?;
         Some(Self {
-            epoch_number:    u32::from_le_bytes(data[1..5].try_into().ok()?),
-            new_reserve_x:   u64::from_le_bytes(data[5..13].try_into().ok()?),
-            new_reserve_y:   u64::from_le_bytes(data[13..21].try_into().ok()?),
-            epoch_edge:      f64::from_le_bytes(data[21..29].try_into().ok()?),
-            cumulative_edge: f64::from_le_bytes(data[29..37].try_into().ok()?),
-            capital_weight:  f32::from_le_bytes(data[37..41].try_into().ok()?),
+            shock_step: p.shock_step,
+            magnitude:  p.magnitude,
+        })
+    }
+}
+
+/// Context passed to `on_step_digest` (tag 7) at the end of every step: what the whole
+/// pool set traded, not just this strategy. Only delivered when the host enables it.
+pub struct StepDigestContext {
+    pub sim_step: u64,
+    /// Retail volume filled across all AMMs this step (Y at fair)
+    pub retail_notional: f64,
+    /// Buy-X minus sell-X retail notional (Y at fair); positive = net buying pressure
+    pub net_retail_flow: f64,
+    /// Arbitrage trades across all AMMs this step
+    pub arb_count: u32,
+}
+
+impl StepDigestContext {
+    pub fn from_bytes(data: &[u8]) -> Option<Self> {
+        let (p, _) = wire::decode_step_digest(data)?;
+        Some(Self {
+            sim_step:        p.sim_step,
+            retail_notional: p.retail_notional,
+            net_retail_flow: p.net_retail_flow,
+            arb_count:       p.arb_count,
         })
     }
 }
@@ -211,6 +316,16 @@ pub fn write_f64(storage: &mut Storage, slot: usize, val: f64) {
     write_u64(storage, slot, val.to_bits());
 }
 
+/// Slot read by the host for strategy-requested rebalancing (when enabled).
+pub const REBALANCE_REQUEST_SLOT: usize = STORAGE_SIZE / 8 - 1;
+
+/// From `on_epoch_boundary`: ask the host to swap reserves so that `target_x_share` of
+/// pool value is held in X, at the fair price and a host-configured cost.
+#[inline]
+pub fn request_rebalance(storage: &mut Storage, target_x_share: f64) {
+    write_f64(storage, REBALANCE_REQUEST_SLOT, target_x_share);
+}
+
 // ─── Fixed-point math (WAD = 1e18) ───────────────────────────────────────────
 
 /// WAD-precision multiply: (a * b) / WAD
@@ -286,3 +401,86 @@ std::thread_local! {
     pub static RETURN_DATA_U64: RefCell<u64> = RefCell::new(0);
     pub static PENDING_STORAGE: RefCell<Storage> = RefCell::new([0u8; STORAGE_SIZE]);
 }
+
+// ─── Diagnostics log (native only) ────────────────────────────────────────────
+
+/// Bytes `log!` buffers between engine drains; output past it is dropped.
+pub const LOG_CAPACITY: usize = 4096;
+
+/// Bounded text buffer behind `log!`.
+pub struct LogBuffer {
+    buf: [u8; LOG_CAPACITY],
+    len: usize,
+}
+
+impl LogBuffer {
+    pub const fn new() -> Self {
+        Self { buf: [0u8; LOG_CAPACITY], len: 0 }
+    }
+
+    /// Copy the pending output into `out` (as much as fits), clear it, and return the
+    /// number of bytes copied.
+    pub fn drain(&mut self, out: &mut [u8]) -> usize {
+        let n = self.len.min(out.len());
+        out[..n].copy_from_slice(&self.buf[..n]);
+        self.len = 0;
+        n
+    }
+}
+
+impl core::fmt::Write for LogBuffer {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        let n = s.len().min(LOG_CAPACITY - self.len);
+        self.buf[self.len..self.len + n].copy_from_slice(&s.as_bytes()[..n]);
+        self.len += n;
+        Ok(())
+    }
+}
+
+#[cfg(not(target_os = "solana"))]
+std::thread_local! {
+    pub static LOG: RefCell<LogBuffer> = const { RefCell::new(LogBuffer::new()) };
+}
+
+/// Append one formatted line to the diagnostics buffer; use `log!`.
+#[cfg(not(target_os = "solana"))]
+pub fn log_line(args: core::fmt::Arguments) {
+    use core::fmt::Write;
+    LOG.with(|cell| {
+        let mut log = cell.borrow_mut();
+        let _ = log.write_fmt(args);
+        let _ = log.write_str("\n");
+    });
+}
+
+/// No log channel on-chain; `log!` compiles to nothing.
+#[cfg(target_os = "solana")]
+pub fn log_line(_args: core::fmt::Arguments) {}
+
+/// Trace a decision without spending storage slots on it. The engine collects the
+/// output after each call when run with `--debug-strategy`, and ignores it otherwise:
+///
+/// ```ignore
+/// log!("fee={} imbalance={:.3}", fee_bps, imbalance);
+/// ```
+///
+/// Lines reach the engine through an optional export:
+///
+/// ```ignore
+/// #[no_mangle]
+/// pub extern "C" fn __prop_amm_log(buf: *mut u8, max_len: usize) -> usize {
+///     drain_log(unsafe { core::slice::from_raw_parts_mut(buf, max_len) })
+/// }
+/// ```
+#[macro_export]
+macro_rules! log {
+    ($($arg:tt)*) => {
+        $crate::log_line(core::format_args!($($arg)*))
+    };
+}
+
+/// Body of the `__prop_amm_log` export: move the pending lines into `out`.
+#[cfg(not(target_os = "solana"))]
+pub fn drain_log(out: &mut [u8]) -> usize {
+    LOG.with(|cell| cell.borrow_mut().drain(out))
+}
@@ -0,0 +1,102 @@
+//! Strategy directories and their `strategies.toml` manifest.
+//!
+//! A directory without a manifest contributes every strategy source, prebuilt artifact
+//! and remote manifest in it, in file-name order. With a manifest it contributes exactly
+//! the enabled entries, in manifest order:
+//!
+//! ```toml
+//! [[strategy]]
+//! name = "vol_adaptive"
+//! path = "vol_adaptive.rs"   # relative to the directory
+//! runtime = "wasm"           # native (default), wasm or sbf
+//! enabled = false            # default true
+//! ```
+//!
+//! Entries resolve to the same inputs the command line takes, so `runtime = "wasm"` is
+//! the `wasm:` prefix and `runtime = "sbf"` the `sbf:` one.
+
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+pub const MANIFEST_FILE: &str = "strategies.toml";
+
+/// Extensions picked up from a directory without a manifest
+pub const STRATEGY_EXTENSIONS: [&str; 6] = ["rs", "so", "dylib", "dll", "wasm", "remote"];
+
+/// Runtime backend of a manifest entry.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    #[default]
+    Native,
+    Wasm,
+    Sbf,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Entry {
+    /// Label for the entry, unique within the manifest
+    pub name: String,
+    /// Source or artifact, relative to the manifest's directory
+    pub path: PathBuf,
+    #[serde(default)]
+    pub runtime: Backend,
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+impl Entry {
+    /// The command-line input for this entry, with `path` taken relative to `dir`.
+    pub fn input(&self, dir: &Path) -> PathBuf {
+        let path = dir.join(&self.path);
+        match self.runtime {
+            Backend::Native => path,
+            Backend::Wasm => PathBuf::from(format!("wasm:{}", path.display())),
+            Backend::Sbf => PathBuf::from(format!("sbf:{}", path.display())),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Manifest {
+    #[serde(default)]
+    strategy: Vec<Entry>,
+}
+
+/// Parse a `strategies.toml`, rejecting empty and repeated names.
+pub fn parse(text: &str) -> Result<Vec<Entry>, String> {
+    let manifest: Manifest = toml::from_str(text).map_err(|e| e.to_string())?;
+    for (i, entry) in manifest.strategy.iter().enumerate() {
+        if entry.name.trim().is_empty() {
+            return Err(format!("strategy {} has an empty name", i + 1));
+        }
+        if manifest.strategy[..i].iter().any(|e| e.name == entry.name) {
+            return Err(format!("strategy `{}` is listed twice", entry.name));
+        }
+    }
+    Ok(manifest.strategy)
+}
+
+/// Strategy inputs in `dir`: its manifest's enabled entries, or else every file with a
+/// `STRATEGY_EXTENSIONS` extension, sorted.
+pub fn inputs(dir: &Path) -> Result<Vec<PathBuf>, String> {
+    let manifest = dir.join(MANIFEST_FILE);
+    if manifest.exists() {
+        let text = std::fs::read_to_string(&manifest).map_err(|e| format!("{}: {e}", manifest.display()))?;
+        let entries = parse(&text).map_err(|e| format!("{}: {e}", manifest.display()))?;
+        return Ok(entries.iter().filter(|e| e.enabled).map(|e| e.input(dir)).collect());
+    }
+    let mut files = std::fs::read_dir(dir)
+        .and_then(|entries| entries.map(|entry| entry.map(|e| e.path())).collect::<std::io::Result<Vec<_>>>())
+        .map_err(|e| format!("failed to read {}: {e}", dir.display()))?;
+    files.retain(|p| p.is_file() && p.extension().is_some_and(|e| STRATEGY_EXTENSIONS.iter().any(|s| e == *s)));
+    files.sort();
+    Ok(files)
+}
@@ -1,8 +1,9 @@
 use rand::Rng;
 use rand_chacha::ChaCha8Rng;
-use rand_distr::{Distribution, LogNormal, Poisson};
+use rand_distr::{Distribution, Exp, LogNormal, Poisson};
+use serde::{Deserialize, Serialize};
 
-use crate::types::{AmmState, SCALE_F};
+use crate::types::{AmmState, NaiveRouting, ShockEvent, SCALE_F};
 
 // ─── GBM Price Process ────────────────────────────────────────────────────────
 
@@ -15,8 +16,121 @@ pub fn gbm_step(price: f64, sigma: f64, rng: &mut ChaCha8Rng) -> f64 {
     price * (-0.5 * sigma * sigma + sigma * z).exp()
 }
 
+/// Decide whether a shock lands at `step`: scheduled shocks take precedence, otherwise a
+/// random ±`size` jump with probability `prob`. The RNG is only drawn when `prob > 0`.
+pub fn sample_shock(
+    step: usize,
+    schedule: &[ShockEvent],
+    prob: f64,
+    size: f64,
+    rng: &mut ChaCha8Rng,
+) -> Option<f64> {
+    if let Some(ev) = schedule.iter().find(|ev| ev.step == step) {
+        return Some(ev.jump);
+    }
+    if prob > 0.0 && rng.gen_bool(prob.min(1.0)) {
+        return Some(if rng.gen_bool(0.5) { size } else { -size });
+    }
+    None
+}
+
 // ─── Market Parameters (sampled once per simulation) ─────────────────────────
 
+/// Intraday shape of the retail arrival rate over each epoch.
+///
+/// All curves average to 1.0 over the epoch, so the mean arrival rate stays `lambda`.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArrivalCurve {
+    /// Constant rate (original behavior)
+    Flat,
+    /// λ(φ) = λ · (1 + amplitude · sin 2πφ): busy first half, quiet second half
+    Sinusoidal { amplitude: f64 },
+    /// λ(φ) = λ · (1 + depth · cos 2πφ): busy open and close, quiet mid-epoch
+    UShaped { depth: f64 },
+}
+
+impl ArrivalCurve {
+    /// Rate multiplier at epoch phase φ ∈ [0, 1) (= epoch_step / epoch_len).
+    pub fn multiplier(&self, phase: f64) -> f64 {
+        use std::f64::consts::TAU;
+        match *self {
+            ArrivalCurve::Flat => 1.0,
+            ArrivalCurve::Sinusoidal { amplitude } => 1.0 + amplitude.clamp(0.0, 1.0) * (TAU * phase).sin(),
+            ArrivalCurve::UShaped { depth } => 1.0 + depth.clamp(0.0, 1.0) * (TAU * phase).cos(),
+        }
+    }
+}
+
+/// Which order-clustering model to sample for a simulation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClusteringKind {
+    /// IID order sizes and Poisson arrivals (original behavior)
+    Off,
+    /// AR(1) regime on log order size
+    Ar1,
+    /// Self-exciting (Hawkes-like) arrival bursts
+    Hawkes,
+}
+
+/// Per-simulation order clustering model with sampled parameters.
+///
+/// Both variants are mean-preserving: average order size and arrival rate are unchanged,
+/// only their autocorrelation within an epoch differs.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SizeClustering {
+    Iid,
+    /// Step size multiplier exp(s·z − s²/2), z_t = φ·z_{t−1} + √(1−φ²)·ε_t
+    Ar1 { phi: f64, regime_vol: f64 },
+    /// Intensity λ(1−n) + e_t, e_{t+1} = β·e_t + n(1−β)·count_t  (branching ratio n < 1)
+    Hawkes { branching: f64, decay: f64 },
+}
+
+impl SizeClustering {
+    /// Sample model parameters. Draws from the RNG only for non-`Off` kinds.
+    pub fn sample(kind: ClusteringKind, rng: &mut ChaCha8Rng) -> Self {
+        match kind {
+            ClusteringKind::Off => SizeClustering::Iid,
+            ClusteringKind::Ar1 => SizeClustering::Ar1 {
+                phi: rng.gen_range(0.80f64..=0.99),
+                regime_vol: rng.gen_range(0.3f64..=0.8),
+            },
+            ClusteringKind::Hawkes => SizeClustering::Hawkes {
+                branching: rng.gen_range(0.3f64..=0.7),
+                decay: rng.gen_range(0.80f64..=0.95),
+            },
+        }
+    }
+}
+
+/// Order-flow state carried across steps by clustering models.
+#[derive(Clone, Debug, Default)]
+pub struct FlowState {
+    /// AR(1) log-size regime z_t
+    pub log_regime: f64,
+    /// Hawkes excitation e_t (extra orders per step)
+    pub excitation: f64,
+}
+
+/// Per-simulation sampling ranges for the retail/volatility regime.
+const SIGMA_RANGE: std::ops::RangeInclusive<f64> = 0.0001..=0.0070;   // U[0.01%, 0.70%]
+const LAMBDA_RANGE: std::ops::RangeInclusive<f64> = 0.4..=1.2;
+const ORDER_SIZE_RANGE: std::ops::RangeInclusive<f64> = 12.0..=28.0;
+
+/// How σ, λ and the order-size mean evolve across epoch boundaries.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParamDynamics {
+    /// Sampled once per simulation (original behavior)
+    Fixed,
+    /// Independently re-sampled from the per-simulation ranges at each epoch boundary
+    Resample,
+    /// Each parameter takes a mean-one log-normal step of volatility `vol` per epoch,
+    /// clamped to the per-simulation ranges
+    RandomWalk { vol: f64 },
+}
+
 #[derive(Clone, Debug)]
 pub struct MarketParams {
     /// Per-step volatility
@@ -29,39 +143,188 @@ pub struct MarketParams {
     pub norm_fee_bps: u32,
     /// Normalizer liquidity multiplier (scales initial reserves)
     pub norm_liquidity_mult: f64,
+    /// Intraday shape applied to `lambda` (set from `SimConfig::arrival_curve`)
+    pub arrival_curve: ArrivalCurve,
+    /// Order clustering model (sampled per simulation from `SimConfig::size_clustering`)
+    pub clustering: SizeClustering,
+    /// Normalizers beyond the first (`SimConfig::n_normalizers` − 1), each sampled independently
+    pub extra_normalizers: Vec<NormalizerParams>,
+}
+
+/// Volatility regime of a simulation: the lower, middle or upper third of the σ range.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum VolRegime {
+    Calm,
+    Normal,
+    Volatile,
+}
+
+/// Fee and depth of one passive CPAMM normalizer venue.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NormalizerParams {
+    pub fee_bps: u32,
+    /// Scales the base initial reserves
+    pub liquidity_mult: f64,
+}
+
+impl NormalizerParams {
+    pub fn sample(rng: &mut ChaCha8Rng) -> Self {
+        Self {
+            fee_bps: rng.gen_range(30u32..=80),
+            liquidity_mult: rng.gen_range(0.4f64..=2.0),
+        }
+    }
 }
 
 impl MarketParams {
     /// Sample fresh parameters for a new simulation using the provided RNG.
     pub fn sample(rng: &mut ChaCha8Rng) -> Self {
-        let sigma = rng.gen_range(0.0001f64..=0.0070);   // U[0.01%, 0.70%]
-        let lambda = rng.gen_range(0.4f64..=1.2);
-        let order_size_mean = rng.gen_range(12.0f64..=28.0);
+        let sigma = rng.gen_range(SIGMA_RANGE);
+        let lambda = rng.gen_range(LAMBDA_RANGE);
+        let order_size_mean = rng.gen_range(ORDER_SIZE_RANGE);
         let norm_fee_bps = rng.gen_range(30u32..=80);
         let norm_liquidity_mult = rng.gen_range(0.4f64..=2.0);
 
-        Self { sigma, lambda, order_size_mean, norm_fee_bps, norm_liquidity_mult }
+        Self {
+            sigma,
+            lambda,
+            order_size_mean,
+            norm_fee_bps,
+            norm_liquidity_mult,
+            arrival_curve: ArrivalCurve::Flat,
+            clustering: SizeClustering::Iid,
+            extra_normalizers: Vec::new(),
+        }
+    }
+
+    /// Volatility tercile of `sigma` within the sampling range.
+    pub fn vol_regime(&self) -> VolRegime {
+        let (lo, hi) = (*SIGMA_RANGE.start(), *SIGMA_RANGE.end());
+        match (self.sigma - lo) / (hi - lo) {
+            f if f < 1.0 / 3.0 => VolRegime::Calm,
+            f if f < 2.0 / 3.0 => VolRegime::Normal,
+            _ => VolRegime::Volatile,
+        }
+    }
+
+    /// All normalizer venues, the primary (`norm_fee_bps`, `norm_liquidity_mult`) first.
+    pub fn normalizers(&self) -> Vec<NormalizerParams> {
+        std::iter::once(NormalizerParams {
+            fee_bps: self.norm_fee_bps,
+            liquidity_mult: self.norm_liquidity_mult,
+        })
+        .chain(self.extra_normalizers.iter().copied())
+        .collect()
+    }
+
+    /// Move σ, λ and the order-size mean to the next epoch's regime.
+    /// Draws from the RNG only for non-`Fixed` dynamics.
+    pub fn evolve(&mut self, dynamics: ParamDynamics, rng: &mut ChaCha8Rng) {
+        match dynamics {
+            ParamDynamics::Fixed => {}
+            ParamDynamics::Resample => {
+                self.sigma = rng.gen_range(SIGMA_RANGE);
+                self.lambda = rng.gen_range(LAMBDA_RANGE);
+                self.order_size_mean = rng.gen_range(ORDER_SIZE_RANGE);
+            }
+            ParamDynamics::RandomWalk { vol } => {
+                let mut walk = |x: f64, range: std::ops::RangeInclusive<f64>| {
+                    let z: f64 = rng.sample(rand_distr::StandardNormal);
+                    (x * (vol * z - 0.5 * vol * vol).exp()).clamp(*range.start(), *range.end())
+                };
+                self.sigma = walk(self.sigma, SIGMA_RANGE);
+                self.lambda = walk(self.lambda, LAMBDA_RANGE);
+                self.order_size_mean = walk(self.order_size_mean, ORDER_SIZE_RANGE);
+            }
+        }
+    }
+
+    /// Retail arrival rate at epoch phase φ ∈ [0, 1).
+    pub fn lambda_at(&self, phase: f64) -> f64 {
+        self.lambda * self.arrival_curve.multiplier(phase)
     }
 }
 
 // ─── Retail Order Generation ──────────────────────────────────────────────────
 
+/// Origin of a retail order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OrderKind {
+    /// Independent one-shot order
+    Market,
+    /// Child slice of a TWAP/iceberg parent order
+    TwapSlice,
+    /// Request-for-quote: every AMM quotes full size, best single quote wins it all
+    Rfq,
+}
+
 #[derive(Clone, Debug)]
 pub struct RetailOrder {
     /// true = buy X (Y is input), false = sell X (X is input)
     pub is_buy: bool,
     /// Order size in Y terms (unscaled, use directly as f64)
     pub size_y: f64,
+    pub kind: OrderKind,
+    /// Worst acceptable marginal execution price (Y per X): a ceiling for buys,
+    /// a floor for sells. `None` = market order.
+    pub limit_price: Option<f64>,
+}
+
+impl RetailOrder {
+    /// Minimum marginal output rate the router may fill at, derived from `limit_price`.
+    ///
+    /// Buy X (Y in): marginal dX/dY must be ≥ 1/limit. Sell X (X in): dY/dX must be ≥ limit.
+    pub fn min_marginal(&self) -> Option<f64> {
+        self.limit_price.map(|limit| if self.is_buy { 1.0 / limit } else { limit })
+    }
 }
 
 /// Generate retail orders for one step.
 /// Returns 0 or more orders (Poisson count), each with LogNormal size.
 pub fn generate_retail_orders(params: &MarketParams, rng: &mut ChaCha8Rng) -> Vec<RetailOrder> {
+    generate_retail_orders_at(params, 0.0, rng)
+}
+
+/// Generate retail orders for one step at epoch phase φ ∈ [0, 1).
+/// The Poisson rate follows `params.arrival_curve`.
+pub fn generate_retail_orders_at(
+    params: &MarketParams,
+    phase: f64,
+    rng: &mut ChaCha8Rng,
+) -> Vec<RetailOrder> {
+    generate_retail_orders_clustered(params, phase, &mut FlowState::default(), rng)
+}
+
+/// Generate retail orders for one step, advancing the clustering model in `state`.
+pub fn generate_retail_orders_clustered(
+    params: &MarketParams,
+    phase: f64,
+    state: &mut FlowState,
+    rng: &mut ChaCha8Rng,
+) -> Vec<RetailOrder> {
+    // Step-level size multiplier from the AR(1) regime (mean 1)
+    let size_mult = match params.clustering {
+        SizeClustering::Ar1 { phi, regime_vol } => {
+            let eps: f64 = rng.sample(rand_distr::StandardNormal);
+            state.log_regime = phi * state.log_regime + (1.0 - phi * phi).sqrt() * eps;
+            (regime_vol * state.log_regime - 0.5 * regime_vol * regime_vol).exp()
+        }
+        _ => 1.0,
+    };
+
     // Poisson arrival count
     let count = {
-        let pois = Poisson::new(params.lambda).unwrap();
-        pois.sample(rng) as usize
+        let rate = match params.clustering {
+            SizeClustering::Hawkes { branching, .. } => {
+                params.lambda_at(phase) * (1.0 - branching) + state.excitation
+            }
+            _ => params.lambda_at(phase),
+        };
+        if rate <= 0.0 { 0 } else { Poisson::new(rate).unwrap().sample(rng) as usize }
     };
+    if let SizeClustering::Hawkes { branching, decay } = params.clustering {
+        state.excitation = decay * state.excitation + branching * (1.0 - decay) * count as f64;
+    }
 
     // LogNormal parameters: want E[X] = order_size_mean, σ_ln = 1.2
     // E[X] = exp(μ + σ²/2) → μ = ln(E[X]) - σ²/2
@@ -72,50 +335,148 @@ pub fn generate_retail_orders(params: &MarketParams, rng: &mut ChaCha8Rng) -> Ve
     (0..count)
         .map(|_| RetailOrder {
             is_buy: rng.gen_bool(0.5),
-            size_y: ln_dist.sample(rng),
+            size_y: ln_dist.sample(rng) * size_mult,
+            kind: OrderKind::Market,
+            limit_price: None,
         })
         .collect()
 }
 
+/// Convert a fraction `prob` of one-shot market orders into RFQ orders.
+pub fn mark_rfq_orders(orders: &mut [RetailOrder], prob: f64, rng: &mut ChaCha8Rng) {
+    for order in orders.iter_mut().filter(|o| o.kind == OrderKind::Market) {
+        if rng.gen_bool(prob.min(1.0)) {
+            order.kind = OrderKind::Rfq;
+        }
+    }
+}
+
+/// Attach limit prices to a fraction `prob` of orders.
+///
+/// Limit = fair · (1 + tol) for buys and fair · (1 - tol) for sells, with the tolerance
+/// tol ~ Exp(mean = `tolerance_mean`) sampled per order.
+pub fn assign_limit_prices(
+    orders: &mut [RetailOrder],
+    fair_price: f64,
+    prob: f64,
+    tolerance_mean: f64,
+    rng: &mut ChaCha8Rng,
+) {
+    let tol_dist = Exp::new(1.0 / tolerance_mean.max(1e-12)).unwrap();
+    for order in orders.iter_mut() {
+        if !rng.gen_bool(prob.min(1.0)) { continue; }
+        let tol: f64 = tol_dist.sample(rng);
+        order.limit_price = Some(if order.is_buy {
+            fair_price * (1.0 + tol)
+        } else {
+            fair_price * (1.0 - tol).max(0.0)
+        });
+    }
+}
+
+/// Elastic demand multiplier for an order, given the pool's aggregate output vs. the
+/// output the normalizer alone would give for the same input.
+///
+/// mult = exp(elasticity · improvement), improvement = pool_out / norm_out - 1,
+/// clamped to [0, max_mult]. With elasticity = 0 the flow pie is fixed (mult = 1).
+pub fn demand_multiplier(pool_output: u64, norm_output: u64, elasticity: f64, max_mult: f64) -> f64 {
+    if elasticity == 0.0 || norm_output == 0 { return 1.0; }
+    let improvement = pool_output as f64 / norm_output as f64 - 1.0;
+    (elasticity * improvement).exp().clamp(0.0, max_mult)
+}
+
+// ─── TWAP / Iceberg Parent Orders ─────────────────────────────────────────────
+
+/// A large parent order executed in equal same-direction slices over consecutive steps.
+#[derive(Clone, Debug)]
+pub struct ParentOrder {
+    pub is_buy: bool,
+    /// Size of each child slice (in Y, unscaled)
+    pub slice_size_y: f64,
+    pub slices_remaining: usize,
+}
+
+/// Engine-side book of in-flight TWAP parents, carried across steps.
+#[derive(Clone, Debug, Default)]
+pub struct TwapBook {
+    pub parents: Vec<ParentOrder>,
+}
+
+impl TwapBook {
+    /// Process one step's fresh orders.
+    ///
+    /// Each fresh order is promoted to a TWAP parent with probability `prob`: its size is
+    /// scaled by `size_mult` and split into `slices` equal children. Every active parent
+    /// then emits one slice this step. Returns the remaining one-shot orders plus slices.
+    pub fn process(
+        &mut self,
+        orders: Vec<RetailOrder>,
+        prob: f64,
+        slices: usize,
+        size_mult: f64,
+        rng: &mut ChaCha8Rng,
+    ) -> Vec<RetailOrder> {
+        let slices = slices.max(1);
+        let mut out = Vec::with_capacity(orders.len() + self.parents.len());
+
+        for order in orders {
+            if prob > 0.0 && rng.gen_bool(prob.min(1.0)) {
+                self.parents.push(ParentOrder {
+                    is_buy: order.is_buy,
+                    slice_size_y: order.size_y * size_mult / slices as f64,
+                    slices_remaining: slices,
+                });
+            } else {
+                out.push(order);
+            }
+        }
+
+        for parent in &mut self.parents {
+            out.push(RetailOrder {
+                is_buy: parent.is_buy,
+                size_y: parent.slice_size_y,
+                kind: OrderKind::TwapSlice,
+                limit_price: None,
+            });
+            parent.slices_remaining -= 1;
+        }
+        self.parents.retain(|p| p.slices_remaining > 0);
+
+        out
+    }
+}
+
 // ─── Arbitrage ────────────────────────────────────────────────────────────────
 
 /// Compute the optimal arb trade size for a CPAMM-like AMM using golden-section search.
 ///
 /// The arb profit function for a given input trade size Δ is:
 ///   profit(Δ) = output(Δ) * fair_price_reciprocal - Δ   (buy X case)
-/// which is concave → golden-section search finds the max.
+/// which is concave → golden-section search finds the max. With `sanity_points` > 0 the
+/// quotes are first checked on a grid of that many sizes, and quotes that are not
+/// concave (see `degenerate_quotes`) narrow the search to the best grid point.
 ///
-/// Returns (optimal_input_in_Y, expected_profit_in_Y) for a buy-X arb,
-/// or (optimal_input_in_X, expected_profit_in_X) for a sell-X arb.
+/// Returns `(is_buy, input_scaled, output_scaled)` of the most profitable trade, if it
+/// clears `arb_profit_floor` (Y, unscaled).
 ///
 /// `compute_swap_fn`: takes (is_buy: bool, input_scaled: u64, rx: u64, ry: u64) → output_scaled: u64
 pub fn optimal_arb_trade<F>(
     amm: &AmmState,
     fair_price: f64,
     arb_profit_floor: f64,
+    sanity_points: usize,
     compute_swap: F,
 ) -> Option<(bool, u64, u64)>  // (is_buy, input_scaled, output_scaled)
 where
     F: Fn(bool, u64, u64, u64) -> u64,
 {
-    let rx = amm.reserve_x as f64;
-    let ry = amm.reserve_y as f64;
-    let spot = ry / rx;
-
-    // Determine arb direction
-    // Spot = Y/X price of X in Y terms.
-    // If spot > fair_price: AMM charges too much Y per X → arb sells X to AMM (buys X cheap externally)
-    //   Wait. Spot = ry/rx = "how many Y you get per X from AMM".
-    //   If spot > fair: AMM gives more Y per X than fair → arb BUYS X from AMM (is_buy=true, Y→X)
-    // If spot < fair: AMM gives less Y per X → arb SELLS X to AMM (is_buy=false, X→Y)
-    let is_buy_x = spot > fair_price;
-
-    // Golden-section search for max profit
-    let max_input = if is_buy_x {
-        ry * 0.9  // can't drain more than 90% of reserves
-    } else {
-        rx * 0.9
-    };
+    // Spot = ry/rx, the AMM's price of X in Y. Above fair the AMM overpays for X, so the
+    // arb sells X into it (is_buy=false, X→Y); below fair X is cheap there and the arb
+    // buys it (is_buy=true, Y→X).
+    let is_buy_x = amm.spot_price() < fair_price;
+
+    // Search range (unscaled, like every size below): at most 90% of the input reserve
+    let max_input = if is_buy_x { amm.reserve_y } else { amm.reserve_x } as f64 * 0.9 / SCALE_F;
 
     let profit_fn = |input_f: f64| -> f64 {
         let input_scaled = (input_f * SCALE_F) as u64;
@@ -131,9 +492,20 @@ where
         }
     };
 
-    let (best_input, best_profit) = golden_section_max(profit_fn, 0.0, max_input, 50);
+    // Degenerate quotes can hide the best arb from a unimodal search: if a coarse grid
+    // over the reserve fails `degenerate_quotes`, search only around its best point
+    let grid: Vec<f64> = (1..=sanity_points).map(|k| max_input * k as f64 / sanity_points as f64).collect();
+    let grid_inputs: Vec<u64> = grid.iter().map(|&x| (x * SCALE_F) as u64).collect();
+    let grid_outputs: Vec<u64> = grid_inputs.iter().map(|&x| compute_swap(is_buy_x, x, amm.reserve_x, amm.reserve_y)).collect();
+    let (lo, hi) = if degenerate_quotes(&grid_inputs, &grid_outputs) {
+        let best = (0..grid.len()).max_by(|&a, &b| profit_fn(grid[a]).total_cmp(&profit_fn(grid[b]))).unwrap_or(0);
+        (if best == 0 { 0.0 } else { grid[best - 1] }, grid[(best + 1).min(grid.len() - 1)])
+    } else {
+        (0.0, max_input)
+    };
+    let (best_input, best_profit) = golden_section_max(profit_fn, lo, hi, 50);
 
-    if best_profit < arb_profit_floor || best_input < 1.0 / SCALE_F {
+    if !best_profit.is_finite() || best_profit < arb_profit_floor || best_input < 1.0 / SCALE_F {
         return None;
     }
 
@@ -151,6 +523,70 @@ pub struct RoutingResult {
     pub allocations: Vec<(u64, u64)>,
     /// Total output across all AMMs (in output token, scaled)
     pub total_output: u64,
+    /// Input left unfilled because no AMM could fill within the order's limit (scaled)
+    pub unfilled_input: u64,
+    /// AMMs left out of this order because their quotes failed `degenerate_quotes`
+    pub excluded: Vec<usize>,
+}
+
+/// Minimum finite-difference step (unscaled) for the router's numerical marginals, on
+/// every order. A one-scaled-unit step of Y buys less than one scaled unit of X, so
+/// buy-side marginals round to nothing and the split misses equal marginal rates
+/// (pinned by `equimarginal_split_equalizes_unequal_pools`).
+const MARGINAL_PROBE: f64 = 1e-3;
+
+/// Reserve (scaled, either side) below which `route_order_n_amms` treats a pool as
+/// drained: 0.001 of a token.
+pub const DUST_RESERVE_FLOOR: u64 = 1_000_000;
+
+/// Whether either of `amm`'s reserves is below `floor`.
+#[inline]
+fn below_floor(amm: &AmmState, floor: u64) -> bool {
+    amm.reserve_x < floor || amm.reserve_y < floor
+}
+
+/// Quote sizes per AMM in the sanity check `SimConfig::degenerate_quote_guard` turns on
+/// for the equimarginal router and the arb search (see `degenerate_quotes`).
+pub const SANITY_GRID_POINTS: usize = 8;
+
+/// How far (relative) a grid segment's marginal rate may exceed every earlier segment's
+/// before the quotes count as non-concave.
+const CONCAVITY_SLACK: f64 = 0.05;
+
+/// Whether quotes `outputs` at increasing sizes `inputs` (both scaled) break the
+/// equimarginal router's assumptions: an output that shrinks as the size grows, or a
+/// segment whose marginal rate beats the best earlier one by more than
+/// `CONCAVITY_SLACK` (zero-then-huge quotes, convex kinks). A couple of scaled units of
+/// rounding are tolerated. An AMM that refuses every size is not degenerate.
+pub fn degenerate_quotes(inputs: &[u64], outputs: &[u64]) -> bool {
+    let (mut x0, mut y0) = (0u64, 0u64);
+    let mut best_slope: Option<f64> = None;
+    for (&x, &y) in inputs.iter().zip(outputs) {
+        if x <= x0 {
+            continue;
+        }
+        if y.saturating_add(2) < y0 {
+            return true;
+        }
+        let dx = (x - x0) as f64;
+        let dy = y as f64 - y0 as f64;
+        if let Some(best) = best_slope {
+            if dy > best * (1.0 + CONCAVITY_SLACK) * dx + 2.0 {
+                return true;
+            }
+        }
+        let slope = dy / dx;
+        best_slope = Some(best_slope.map_or(slope, |b| b.max(slope)));
+        (x0, y0) = (x, y);
+    }
+    false
+}
+
+/// Sizes the sanity check quotes an AMM at: `points` evenly spaced up to the smaller of
+/// the order and the router's input cap.
+fn sanity_grid(amm: &AmmState, is_buy: bool, total_input: f64, points: usize) -> Vec<u64> {
+    let span = max_router_input(amm, is_buy).min(total_input);
+    (1..=points).map(|k| (span * k as f64 / points as f64 * SCALE_F) as u64).collect()
 }
 
 /// Route a retail order of `total_input_y` (unscaled f64) optimally across N AMMs.
@@ -169,35 +605,211 @@ pub fn route_order_n_amms<F>(
     total_input: f64,  // unscaled Y (if is_buy) or X (if !is_buy)
     compute_swap: F,   // (amm_idx, is_buy, input_scaled, rx, ry) → output_scaled
 ) -> RoutingResult
+where
+    F: Fn(usize, bool, u64, u64, u64) -> u64,
+{
+    route_order_with_limit(amms, is_buy, total_input, None, DUST_RESERVE_FLOOR, 0, compute_swap)
+}
+
+/// Equimarginal routing with an optional floor on the shadow price.
+///
+/// `min_marginal` (see `RetailOrder::min_marginal`) encodes a limit price: AMMs whose
+/// marginal output rate is below it receive no flow, and if λ* would fall below the floor
+/// the order is filled only up to λ = `min_marginal`, leaving the rest unfilled.
+///
+/// AMMs with either reserve below `reserve_floor` (scaled) are never quoted and get no
+/// flow; if that leaves too little capacity, the rest of the order goes unfilled. With
+/// `sanity_points` > 0 each AMM is first quoted at that many sizes, and AMMs whose
+/// quotes fail `degenerate_quotes` sit the order out (`RoutingResult::excluded`).
+pub fn route_order_with_limit<F>(
+    amms: &[AmmState],
+    is_buy: bool,
+    total_input: f64,
+    min_marginal: Option<f64>,
+    reserve_floor: u64,
+    sanity_points: usize,
+    compute_swap: F,
+) -> RoutingResult
 where
     F: Fn(usize, bool, u64, u64, u64) -> u64,
 {
     let n = amms.len();
-    if n == 0 { return RoutingResult { allocations: vec![], total_output: 0 }; }
-    if n == 1 {
+    if n == 0 {
+        return RoutingResult {
+            allocations: vec![],
+            total_output: 0,
+            unfilled_input: (total_input * SCALE_F) as u64,
+            excluded: Vec::new(),
+        };
+    }
+    let dust: Vec<usize> = (0..n).filter(|&i| below_floor(&amms[i], reserve_floor)).collect();
+    if n == 1 && min_marginal.is_none() && dust.is_empty() {
         let input_scaled = (total_input * SCALE_F) as u64;
         let out = compute_swap(0, is_buy, input_scaled, amms[0].reserve_x, amms[0].reserve_y);
         return RoutingResult {
             allocations: vec![(input_scaled, out)],
             total_output: out,
+            unfilled_input: 0,
+            excluded: Vec::new(),
         };
     }
 
+    // AMMs whose quotes would mislead the bisection sit this order out
+    let excluded: Vec<usize> = (0..n)
+        .filter(|i| sanity_points > 0 && !dust.contains(i))
+        .filter(|&i| {
+            let inputs = sanity_grid(&amms[i], is_buy, total_input, sanity_points);
+            let outputs: Vec<u64> = inputs.iter().map(|&x| compute_swap(i, is_buy, x, amms[i].reserve_x, amms[i].reserve_y)).collect();
+            degenerate_quotes(&inputs, &outputs)
+        })
+        .collect();
+
     // Marginal output function for AMM i at input x (unscaled f64)
     // m_i(x) = (f_i(x+δ) - f_i(x)) / δ  — numerical derivative
+    // δ is floored well above 1 scaled unit: near x = 0 a one-unit step rounds the
+    // output difference to 0 whenever the output token is worth more than the input.
     let marginal = |i: usize, x: f64| -> f64 {
-        let delta = x * 0.001 + 1.0 / SCALE_F;
+        if dust.contains(&i) || excluded.contains(&i) {
+            return 0.0;
+        }
+        let delta = x * 0.001 + MARGINAL_PROBE;
         let o1 = compute_swap(i, is_buy, (x * SCALE_F) as u64, amms[i].reserve_x, amms[i].reserve_y) as f64 / SCALE_F;
         let o2 = compute_swap(i, is_buy, ((x + delta) * SCALE_F) as u64, amms[i].reserve_x, amms[i].reserve_y) as f64 / SCALE_F;
         (o2 - o1) / delta
     };
+    let max_in = |i: usize| {
+        if dust.contains(&i) || excluded.contains(&i) { 0.0 } else { max_router_input(&amms[i], is_buy) }
+    };
 
+    let (raw_allocs, limited) = equimarginal_split(n, total_input, min_marginal, max_in, marginal);
+    fill_allocations(amms, is_buy, total_input, raw_allocs, limited || !dust.is_empty(), excluded, compute_swap)
+}
+
+/// Equimarginal routing on batch-quoted output curves: `quote_batch` prices `points`
+/// evenly spaced sizes per AMM in one call (`(amm_idx, is_buy, inputs_scaled, rx, ry)
+/// → outputs_scaled`), the λ* bisection runs on the interpolated marginals, and only
+/// the final allocations go through `compute_swap`. That is `points` + 1 strategy
+/// calls per AMM per order instead of hundreds, at the cost of grid resolution: the
+/// curve spans at most the order size, so resolution is `total_input / points`. Pools
+/// below `reserve_floor` are skipped as in `route_order_with_limit`.
+#[allow(clippy::too_many_arguments)]
+pub fn route_order_on_curves<B, F>(
+    amms: &[AmmState],
+    is_buy: bool,
+    total_input: f64,
+    min_marginal: Option<f64>,
+    reserve_floor: u64,
+    points: usize,
+    quote_batch: B,
+    compute_swap: F,
+) -> RoutingResult
+where
+    B: Fn(usize, bool, &[u64], u64, u64) -> Vec<u64>,
+    F: Fn(usize, bool, u64, u64, u64) -> u64,
+{
+    let n = amms.len();
+    // Orders too small to span the grid are routed exactly
+    if n <= 1 || points == 0 || ((total_input * SCALE_F) as u64) < points as u64 {
+        return route_order_with_limit(amms, is_buy, total_input, min_marginal, reserve_floor, 0, compute_swap);
+    }
+    // The curves double as the sanity check: a degenerate AMM gets an empty curve
+    let mut excluded = Vec::new();
+    let mut drained = false;
+    let curves: Vec<OutputCurve> = (0..n).map(|i| {
+        if below_floor(&amms[i], reserve_floor) {
+            drained = true;
+            return OutputCurve::new(&[], &[]);
+        }
+        let inputs = sanity_grid(&amms[i], is_buy, total_input, points);
+        let outputs = quote_batch(i, is_buy, &inputs, amms[i].reserve_x, amms[i].reserve_y);
+        if degenerate_quotes(&inputs, &outputs) {
+            excluded.push(i);
+            return OutputCurve::new(&[], &[]);
+        }
+        OutputCurve::new(&inputs, &outputs)
+    }).collect();
+
+    let (allocs, limited) = equimarginal_split(
+        n,
+        total_input,
+        min_marginal,
+        |i| curves[i].span(),
+        |i, x| curves[i].marginal(x),
+    );
+    fill_allocations(amms, is_buy, total_input, allocs, limited || drained, excluded, compute_swap)
+}
+
+/// An AMM's quoted output at a grid of input sizes (unscaled), starting from (0, 0).
+#[derive(Clone, Debug)]
+pub struct OutputCurve {
+    /// Midpoint of each grid segment and the segment's slope (output per unit input)
+    mids: Vec<f64>,
+    slopes: Vec<f64>,
+    span: f64,
+}
+
+impl OutputCurve {
+    /// Curve through (0, 0) and each `(inputs[k], outputs[k])`, both 1e9-scaled with
+    /// `inputs` increasing. Missing outputs count as 0.
+    pub fn new(inputs: &[u64], outputs: &[u64]) -> Self {
+        let mut mids = Vec::with_capacity(inputs.len());
+        let mut slopes = Vec::with_capacity(inputs.len());
+        let (mut x0, mut y0) = (0.0, 0.0);
+        for (k, &x) in inputs.iter().enumerate() {
+            let x1 = x as f64 / SCALE_F;
+            let y1 = outputs.get(k).copied().unwrap_or(0) as f64 / SCALE_F;
+            if x1 > x0 {
+                mids.push(0.5 * (x0 + x1));
+                slopes.push((y1 - y0) / (x1 - x0));
+                (x0, y0) = (x1, y1);
+            }
+        }
+        Self { mids, slopes, span: x0 }
+    }
+
+    /// Largest input on the curve.
+    pub fn span(&self) -> f64 {
+        self.span
+    }
+
+    /// Marginal output rate at `x`: segment slopes interpolated between their midpoints,
+    /// held flat beyond the first and last.
+    pub fn marginal(&self, x: f64) -> f64 {
+        let Some(&last) = self.slopes.last() else { return 0.0 };
+        let k = self.mids.partition_point(|&m| m <= x);
+        if k == 0 {
+            return self.slopes[0];
+        }
+        if k == self.mids.len() {
+            return last;
+        }
+        let t = (x - self.mids[k - 1]) / (self.mids[k] - self.mids[k - 1]);
+        self.slopes[k - 1] + t * (self.slopes[k] - self.slopes[k - 1])
+    }
+}
+
+/// Most input the router will send one AMM: 90% of the input-side reserve (unscaled).
+fn max_router_input(amm: &AmmState, is_buy: bool) -> f64 {
+    if is_buy { amm.reserve_y as f64 * 0.9 / SCALE_F }
+    else      { amm.reserve_x as f64 * 0.9 / SCALE_F }
+}
+
+/// Core of equimarginal routing: bisect on the shadow price λ* until the AMMs'
+/// allocations x_i(λ*) sum to `total_input`, given each AMM's input cap and marginal
+/// output rate. Returns the allocations, normalized to the order size, and whether a
+/// `min_marginal` limit bound (in which case they may sum to less).
+fn equimarginal_split(
+    n: usize,
+    total_input: f64,
+    min_marginal: Option<f64>,
+    max_in: impl Fn(usize) -> f64,
+    marginal: impl Fn(usize, f64) -> f64,
+) -> (Vec<f64>, bool) {
     // For a given shadow price λ, find how much input AMM i would absorb
     // x_i(λ) = largest x such that marginal_i(x) >= λ
     // Uses bisection: marginal is decreasing (concavity requirement).
     let allocation_at_shadow = |i: usize, lambda: f64| -> f64 {
-        let max_in = if is_buy { amms[i].reserve_y as f64 * 0.9 / SCALE_F }
-                     else      { amms[i].reserve_x as f64 * 0.9 / SCALE_F };
+        let max_in = max_in(i);
 
         // If even marginal at 0 is below lambda, this AMM gets no flow
         if marginal(i, 1.0 / SCALE_F) < lambda { return 0.0; }
@@ -227,30 +839,307 @@ where
     for _ in 0..80 {
         let mid = 0.5 * (lo_lambda + hi_lambda);
         let total: f64 = (0..n).map(|i| allocation_at_shadow(i, mid)).sum();
-        if total > total_input { hi_lambda = mid; } else { lo_lambda = mid; }
+        // Allocations shrink as λ rises: oversubscribed means λ is still too low
+        if total > total_input { lo_lambda = mid; } else { hi_lambda = mid; }
         if (hi_lambda - lo_lambda) / (hi_lambda + lo_lambda + 1e-12) < 1e-6 { break; }
     }
 
     let lambda_star = 0.5 * (lo_lambda + hi_lambda);
-    let raw_allocs: Vec<f64> = (0..n).map(|i| allocation_at_shadow(i, lambda_star)).collect();
+
+    // Limit binds: fill only what every AMM can absorb at the limit's marginal rate
+    let limited = matches!(min_marginal, Some(m) if lambda_star < m);
+    let lambda_fill = if limited { min_marginal.unwrap_or(lambda_star) } else { lambda_star };
+    let raw_allocs: Vec<f64> = (0..n).map(|i| allocation_at_shadow(i, lambda_fill)).collect();
 
     // Normalize to ensure total_input constraint is satisfied exactly
+    // (a binding limit may only scale allocations down, never up)
     let raw_sum: f64 = raw_allocs.iter().sum();
-    let scale = if raw_sum > 1e-12 { total_input / raw_sum } else { 0.0 };
+    let scale = if raw_sum <= 1e-12 {
+        0.0
+    } else if limited {
+        (total_input / raw_sum).min(1.0)
+    } else {
+        total_input / raw_sum
+    };
+    (raw_allocs.into_iter().map(|a| a * scale).collect(), limited)
+}
 
+/// Quote each AMM's final allocation exactly and tally what was left unfilled: under a
+/// binding limit (`limited`, which callers also set when they skipped drained pools),
+/// when excluded AMMs left the rest without enough capacity, or when an allocation quoted
+/// zero output and was dropped.
+fn fill_allocations<F>(
+    amms: &[AmmState],
+    is_buy: bool,
+    total_input: f64,
+    allocs: Vec<f64>,
+    limited: bool,
+    excluded: Vec<usize>,
+    compute_swap: F,
+) -> RoutingResult
+where
+    F: Fn(usize, bool, u64, u64, u64) -> u64,
+{
     let mut total_output: u64 = 0;
-    let allocations: Vec<(u64, u64)> = (0..n).map(|i| {
-        let input_f = raw_allocs[i] * scale;
+    let mut dropped = false;
+    let allocations: Vec<(u64, u64)> = allocs.into_iter().enumerate().map(|(i, input_f)| {
         let input_scaled = (input_f * SCALE_F) as u64;
         if input_scaled == 0 {
             return (0, 0);
         }
         let out = compute_swap(i, is_buy, input_scaled, amms[i].reserve_x, amms[i].reserve_y);
+        // A sliver that quotes to nothing is not worth sending
+        if out == 0 {
+            dropped = true;
+            return (0, 0);
+        }
         total_output += out;
         (input_scaled, out)
     }).collect();
 
-    RoutingResult { allocations, total_output }
+    let filled: u64 = allocations.iter().map(|&(inp, _)| inp).sum();
+    let unfilled_input = ((total_input * SCALE_F) as u64).saturating_sub(filled);
+    let unfilled_input = if limited || dropped || !excluded.is_empty() { unfilled_input } else { 0 };
+
+    RoutingResult { allocations, total_output, unfilled_input, excluded }
+}
+
+/// Winner-take-all RFQ: each AMM quotes the full size and the single best output wins
+/// the entire order (no splitting). Ties go to the lowest index.
+///
+/// If `limit_price` is set and the winner's average execution price violates it, the
+/// order is left entirely unfilled.
+pub fn route_order_rfq<F>(
+    amms: &[AmmState],
+    is_buy: bool,
+    total_input: f64,
+    limit_price: Option<f64>,
+    compute_swap: F,   // (amm_idx, is_buy, input_scaled, rx, ry) → output_scaled
+) -> RoutingResult
+where
+    F: Fn(usize, bool, u64, u64, u64) -> u64,
+{
+    let n = amms.len();
+    let input_scaled = (total_input * SCALE_F) as u64;
+    let mut allocations = vec![(0u64, 0u64); n];
+    let unfilled = RoutingResult {
+        allocations: allocations.clone(),
+        total_output: 0,
+        unfilled_input: input_scaled,
+        excluded: Vec::new(),
+    };
+    if n == 0 || input_scaled == 0 { return unfilled; }
+
+    let mut best: Option<(usize, u64)> = None;
+    for (i, amm) in amms.iter().enumerate() {
+        let out = compute_swap(i, is_buy, input_scaled, amm.reserve_x, amm.reserve_y);
+        if best.is_none_or(|(_, b)| out > b) {
+            best = Some((i, out));
+        }
+    }
+    let Some((winner, out)) = best else { return unfilled; };
+    if out == 0 { return unfilled; }
+
+    if let Some(limit) = limit_price {
+        // Average price in Y per X
+        let avg_price = if is_buy {
+            input_scaled as f64 / out as f64
+        } else {
+            out as f64 / input_scaled as f64
+        };
+        let violates = if is_buy { avg_price > limit } else { avg_price < limit };
+        if violates { return unfilled; }
+    }
+
+    allocations[winner] = (input_scaled, out);
+    RoutingResult { allocations, total_output: out, unfilled_input: 0, excluded: Vec::new() }
+}
+
+/// Route a retail order naively, ignoring marginal prices.
+///
+/// Models unsophisticated flow that does not use an aggregator:
+///   - `RandomSingle`: the whole order goes to one AMM chosen uniformly at random
+///   - `ProportionalToTvl`: the order is split by each AMM's TVL (2 · reserve_y)
+///
+/// Same `compute_swap` signature and result shape as `route_order_n_amms`.
+pub fn route_order_naive<F>(
+    amms: &[AmmState],
+    is_buy: bool,
+    total_input: f64,
+    mode: NaiveRouting,
+    rng: &mut ChaCha8Rng,
+    compute_swap: F,   // (amm_idx, is_buy, input_scaled, rx, ry) → output_scaled
+) -> RoutingResult
+where
+    F: Fn(usize, bool, u64, u64, u64) -> u64,
+{
+    let n = amms.len();
+    if n == 0 {
+        return RoutingResult {
+            allocations: vec![],
+            total_output: 0,
+            unfilled_input: (total_input * SCALE_F) as u64,
+            excluded: Vec::new(),
+        };
+    }
+
+    let shares: Vec<f64> = match mode {
+        NaiveRouting::RandomSingle => {
+            let pick = rng.gen_range(0..n);
+            (0..n).map(|i| if i == pick { 1.0 } else { 0.0 }).collect()
+        }
+        NaiveRouting::ProportionalToTvl => {
+            let tvl: Vec<f64> = amms.iter().map(|a| 2.0 * a.reserve_y as f64).collect();
+            let total_tvl: f64 = tvl.iter().sum();
+            if total_tvl > 0.0 {
+                tvl.iter().map(|t| t / total_tvl).collect()
+            } else {
+                vec![1.0 / n as f64; n]
+            }
+        }
+    };
+
+    let mut total_output: u64 = 0;
+    let allocations: Vec<(u64, u64)> = (0..n).map(|i| {
+        let input_scaled = (total_input * shares[i] * SCALE_F) as u64;
+        if input_scaled == 0 {
+            return (0, 0);
+        }
+        let out = compute_swap(i, is_buy, input_scaled, amms[i].reserve_x, amms[i].reserve_y);
+        total_output += out;
+        (input_scaled, out)
+    }).collect();
+
+    RoutingResult { allocations, total_output, unfilled_input: 0, excluded: Vec::new() }
+}
+
+// ─── Tick / Lot Quantization ──────────────────────────────────────────────────
+
+/// Discrete price and size grid applied to every quote and fill, as an on-chain venue
+/// with integer math would impose. Both are off (continuous sizes) by default.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Quantization {
+    /// Price tick in Y per X; 0 = off. Execution prices are rounded to the tick against
+    /// the taker (buys up, sells down).
+    pub tick_size: f64,
+    /// Amount granularity in scaled units (1e9 = one token); 0 = off. Inputs are
+    /// floored to a whole number of lots before quoting, outputs after.
+    pub lot_size: u64,
+}
+
+impl Quantization {
+    pub fn is_off(&self) -> bool {
+        self.tick_size == 0.0 && self.lot_size == 0
+    }
+
+    /// `amount` floored to a whole number of lots.
+    pub fn lot(&self, amount: u64) -> u64 {
+        if self.lot_size == 0 { amount } else { amount - amount % self.lot_size }
+    }
+
+    /// Quantize the quote `output` for `input` (already lot-floored): cap it at the
+    /// tick-rounded execution price, then floor it to a lot.
+    pub fn output(&self, is_buy: bool, input: u64, output: u64) -> u64 {
+        if input == 0 || output == 0 {
+            return output;
+        }
+        let mut output = output;
+        if self.tick_size > 0.0 {
+            // A relative nudge keeps prices already on the tick from rounding a tick away
+            const EPS: f64 = 1e-9;
+            let ticks = if is_buy { input as f64 / output as f64 } else { output as f64 / input as f64 } / self.tick_size;
+            let capped = if is_buy {
+                input as f64 / (ticks * (1.0 - EPS)).ceil().max(1.0) / self.tick_size
+            } else {
+                input as f64 * (ticks * (1.0 + EPS)).floor() * self.tick_size
+            };
+            output = output.min(capped as u64);
+        }
+        self.lot(output)
+    }
+
+    /// Quote `input` through `compute_swap` on the grid: the input is lot-floored before
+    /// the call and the output quantized after it.
+    pub fn quote(&self, is_buy: bool, input: u64, compute_swap: impl FnOnce(u64) -> u64) -> u64 {
+        if self.is_off() {
+            return compute_swap(input);
+        }
+        let input = self.lot(input);
+        if input == 0 {
+            return 0;
+        }
+        self.output(is_buy, input, compute_swap(input))
+    }
+}
+
+// ─── MEV Sandwich Attacker ────────────────────────────────────────────────────
+
+/// Attacker's planned front-run around one victim fill.
+#[derive(Clone, Copy, Debug)]
+pub struct SandwichPlan {
+    /// Front-run size, in the victim's input token (scaled)
+    pub front_input: u64,
+    /// Expected attacker profit in the victim's input token (unscaled)
+    pub expected_profit: f64,
+}
+
+/// Plan a sandwich around a victim fill of `victim_input` on one AMM.
+///
+/// The attacker front-runs in the victim's direction, lets the victim fill on the moved
+/// curve, then back-runs by selling its front-run proceeds. The front-run is capped so the
+/// victim still receives at least `victim_quote · (1 - tolerance)` — the extraction is
+/// bounded by the slippage the AMM's own quote leaves room for. Returns `None` when no
+/// profitable sandwich exists (e.g. fees exceed the price impact that can be captured).
+pub fn plan_sandwich<F>(
+    amm: &AmmState,
+    is_buy: bool,
+    victim_input: u64,
+    victim_quote: u64,
+    tolerance: f64,
+    compute_swap: F,   // (is_buy, input_scaled, rx, ry) → output_scaled
+) -> Option<SandwichPlan>
+where
+    F: Fn(bool, u64, u64, u64) -> u64,
+{
+    let min_victim_out = victim_quote as f64 * (1.0 - tolerance);
+
+    // Returns (victim_output, attacker_profit) for a front-run of `front_f` (unscaled)
+    let simulate = |front_f: f64| -> (f64, f64) {
+        let (mut rx, mut ry) = (amm.reserve_x, amm.reserve_y);
+        let front_in = (front_f * SCALE_F) as u64;
+        let front_out = compute_swap(is_buy, front_in, rx, ry);
+        apply_cpamm_trade(&mut rx, &mut ry, is_buy, front_in, front_out);
+        let victim_out = compute_swap(is_buy, victim_input, rx, ry);
+        apply_cpamm_trade(&mut rx, &mut ry, is_buy, victim_input, victim_out);
+        let back_out = compute_swap(!is_buy, front_out, rx, ry);
+        (victim_out as f64, (back_out as f64 - front_in as f64) / SCALE_F)
+    };
+
+    // Largest front-run that keeps the victim within tolerance (victim output is
+    // decreasing in the front-run size)
+    let reserve_in = if is_buy { amm.reserve_y } else { amm.reserve_x } as f64 / SCALE_F;
+    let mut lo = 0.0_f64;
+    let mut hi = reserve_in * 0.9;
+    if simulate(hi).0 >= min_victim_out {
+        lo = hi;
+    } else {
+        for _ in 0..60 {
+            let mid = 0.5 * (lo + hi);
+            if simulate(mid).0 >= min_victim_out { lo = mid; } else { hi = mid; }
+            if (hi - lo) / (hi + lo + 1e-12) < 1e-6 { break; }
+        }
+    }
+    let front_max = lo;
+    if front_max * SCALE_F < 1.0 { return None; }
+
+    let (best_front, best_profit) = golden_section_max(|f| simulate(f).1, 0.0, front_max, 50);
+    if best_profit <= 0.0 { return None; }
+
+    Some(SandwichPlan {
+        front_input: (best_front * SCALE_F) as u64,
+        expected_profit: best_profit,
+    })
 }
 
 // ─── Utilities ────────────────────────────────────────────────────────────────
@@ -261,28 +1150,30 @@ pub fn golden_section_max<F>(f: F, lo: f64, hi: f64, iters: usize) -> (f64, f64)
 where
     F: Fn(f64) -> f64,
 {
-    const PHI: f64 = 1.618033988749895;
-    let resphi = 2.0 - PHI;
+    // 1/φ: probes sit at 0.382 (c) and 0.618 (d) of [a, b]
+    const INV_PHI: f64 = 0.618033988749895;
 
     let mut a = lo;
     let mut b = hi;
-    let mut c = b - resphi * (b - a);
-    let mut d = a + resphi * (b - a);
+    let mut c = b - INV_PHI * (b - a);
+    let mut d = a + INV_PHI * (b - a);
     let mut fc = f(c);
     let mut fd = f(d);
 
     for _ in 0..iters {
         if fc < fd {
+            // The max lies right of c
             a = c;
             c = d;
             fc = fd;
-            d = a + resphi * (b - a);
+            d = a + INV_PHI * (b - a);
             fd = f(d);
         } else {
+            // The max lies left of d
             b = d;
             d = c;
             fd = fc;
-            c = b - resphi * (b - a);
+            c = b - INV_PHI * (b - a);
             fc = f(c);
         }
         if (b - a) / (b + a + 1e-14) < 1e-8 { break; }
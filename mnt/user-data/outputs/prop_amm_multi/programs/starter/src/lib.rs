@@ -19,8 +19,10 @@
 use prop_amm_submission_sdk::{
     AfterSwapContext, EpochContext, Storage, SwapContext,
     bps_to_wad, clamp_fee, cpamm_output_wad, read_f64, read_u64, write_f64, write_u64,
-    set_return_data_u64, set_storage, WAD,
+    set_return_data_u64, set_storage, ABI_VERSION, WAD, log,
 };
+#[cfg(not(target_os = "solana"))]
+use prop_amm_submission_sdk::drain_log;
 
 /// Displayed on the leaderboard.
 pub const NAME: &str = "Multi-AMM Vol-Adaptive Starter";
@@ -62,6 +64,23 @@ pub extern "C" fn __prop_amm_compute_swap(data: *const u8, len: usize) -> u64 {
     compute_swap(&ctx)
 }
 
+#[cfg(not(feature = "no-entrypoint"))]
+#[no_mangle]
+pub extern "C" fn __prop_amm_compute_swap_batch(
+    data: *const u8,
+    len: usize,
+    inputs: *const u64,
+    outputs: *mut u64,
+    n: usize,
+) {
+    let bytes = unsafe { core::slice::from_raw_parts(data, len) };
+    let inputs = unsafe { core::slice::from_raw_parts(inputs, n) };
+    let outputs = unsafe { core::slice::from_raw_parts_mut(outputs, n) };
+    if let Some(mut ctx) = SwapContext::from_bytes(bytes) {
+        ctx.quote_batch(inputs, outputs, compute_swap);
+    }
+}
+
 #[cfg(not(feature = "no-entrypoint"))]
 #[no_mangle]
 pub extern "C" fn __prop_amm_after_swap(data: *const u8, len: usize, storage_ptr: *mut u8) {
@@ -84,6 +103,12 @@ pub extern "C" fn __prop_amm_after_swap(data: *const u8, len: usize, storage_ptr
     }
 }
 
+#[cfg(not(feature = "no-entrypoint"))]
+#[no_mangle]
+pub extern "C" fn __prop_amm_abi_version() -> u32 {
+    ABI_VERSION
+}
+
 #[cfg(not(feature = "no-entrypoint"))]
 #[no_mangle]
 pub extern "C" fn __prop_amm_get_name(buf: *mut u8, max_len: usize) -> usize {
@@ -93,6 +118,22 @@ pub extern "C" fn __prop_amm_get_name(buf: *mut u8, max_len: usize) -> usize {
     n
 }
 
+#[cfg(not(feature = "no-entrypoint"))]
+#[no_mangle]
+pub extern "C" fn __prop_amm_get_model(buf: *mut u8, max_len: usize) -> usize {
+    let bytes = MODEL_USED.as_bytes();
+    let n = bytes.len().min(max_len);
+    unsafe { core::ptr::copy_nonoverlapping(bytes.as_ptr(), buf, n); }
+    n
+}
+
+/// Optional: hands `log!` output to the engine under `--debug-strategy`.
+#[cfg(all(not(feature = "no-entrypoint"), not(target_os = "solana")))]
+#[no_mangle]
+pub extern "C" fn __prop_amm_log(buf: *mut u8, max_len: usize) -> usize {
+    drain_log(unsafe { core::slice::from_raw_parts_mut(buf, max_len) })
+}
+
 // ─── compute_swap ─────────────────────────────────────────────────────────────
 
 /// Quote an output amount for a given input.
@@ -153,11 +194,9 @@ pub fn after_swap(ctx: &AfterSwapContext, storage: &mut Storage) {
     // ── Competitive context ───────────────────────────────────────────────────
     // Check if we are priced worse than competitors.
     // If spot prices of others are meaningfully different from ours, adjust.
-    let n_competing = ctx.n_strategies.saturating_sub(1) as usize;
     let mut mean_comp_spot = 0.0_f64;
     let mut valid_comps = 0u32;
-    for i in 0..n_competing.min(8) {
-        let sp = ctx.competing_spot_prices[i];
+    for &sp in &ctx.competing_spot_prices[..ctx.n_competing as usize] {
         if sp.is_finite() && sp > 0.0 {
             mean_comp_spot += sp as f64;
             valid_comps += 1;
@@ -245,6 +284,7 @@ pub fn on_epoch_boundary(ctx: &EpochContext, storage: &mut Storage) {
     write_u64(storage, S_ASK_FEE, new_ask);
     write_f64(storage, S_CAPITAL_WT, cw);
     write_u64(storage, S_EPOCH_NUM, ctx.epoch_number as u64);
+    log!("epoch {}: capital_weight={:.3} vol={:.5} bid={} ask={}", ctx.epoch_number, cw, reset_vol, new_bid, new_ask);
 }
 
 pub fn get_model_used() -> &'static str { MODEL_USED }
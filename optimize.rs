@@ -0,0 +1,270 @@
+//! Tuning a strategy's constants by search.
+//!
+//! A tunable is a `const` in the strategy source with a search range, given either by a
+//! trailing annotation
+//!
+//! ```text
+//! const FEE_BPS: u32 = 30; // tune: 10..100
+//! ```
+//!
+//! or by a JSON sidecar mapping names to `[min, max]`. `search` drives grid, random or
+//! CMA-ES search over the ranges; the caller evaluates each candidate, typically by
+//! recompiling the source with `apply` and running simulations.
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+/// One tunable constant and its inclusive search range.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tunable {
+    pub name: String,
+    pub min: f64,
+    pub max: f64,
+    /// Declared with an integer type: candidates are rounded
+    pub integer: bool,
+}
+
+impl Tunable {
+    /// Value at `u` ∈ [0, 1] along the range, rounded for integers.
+    pub fn value_at(&self, u: f64) -> f64 {
+        let v = self.min + u.clamp(0.0, 1.0) * (self.max - self.min);
+        if self.integer { v.round() } else { v }
+    }
+}
+
+/// Search strategy over the tunables' ranges.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SearchMethod {
+    /// Every combination of `points` evenly spaced values per tunable
+    Grid { points: usize },
+    /// `trials` uniform draws
+    Random { trials: usize },
+    /// Separable CMA-ES for `generations` generations of the default population size
+    Cmaes { generations: usize },
+}
+
+/// One evaluated candidate.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Trial {
+    /// Tunable values, in `tunables` order
+    pub values: Vec<f64>,
+    pub score: f64,
+}
+
+/// A `const NAME: TYPE = …;` declaration on `line`: `(name, type)`.
+fn const_decl(line: &str) -> Option<(&str, &str)> {
+    let rest = line.trim_start();
+    let rest = rest.strip_prefix("pub ").unwrap_or(rest).strip_prefix("const ")?;
+    let (name, rest) = rest.split_once(':')?;
+    let (ty, _) = rest.split_once('=')?;
+    Some((name.trim(), ty.trim()))
+}
+
+fn is_integer_type(ty: &str) -> bool {
+    matches!(ty, "u8" | "u16" | "u32" | "u64" | "u128" | "usize" | "i8" | "i16" | "i32" | "i64" | "i128" | "isize")
+}
+
+/// Tunables declared in `source` by `// tune: MIN..MAX` annotations, plus those named in
+/// a JSON `sidecar` (`{"NAME": [min, max]}`), which wins for constants in both.
+pub fn tunables(source: &str, sidecar: Option<&str>) -> Result<Vec<Tunable>, String> {
+    let ranges: std::collections::BTreeMap<String, [f64; 2]> = match sidecar {
+        Some(json) => serde_json::from_str(json).map_err(|e| format!("tunables sidecar: {e}"))?,
+        None => Default::default(),
+    };
+    let mut found = Vec::new();
+    for line in source.lines() {
+        let Some((name, ty)) = const_decl(line) else { continue };
+        let range = match ranges.get(name) {
+            Some(&[min, max]) => Some((min, max)),
+            None => match line.split_once("// tune:") {
+                Some((_, spec)) => {
+                    let (min, max) = spec.trim().split_once("..").ok_or_else(|| format!("{name}: expected `tune: MIN..MAX`"))?;
+                    let parse = |s: &str| s.trim().trim_start_matches('=').trim().replace('_', "").parse::<f64>();
+                    Some((parse(min).map_err(|e| format!("{name}: {e}"))?, parse(max).map_err(|e| format!("{name}: {e}"))?))
+                }
+                None => None,
+            },
+        };
+        if let Some((min, max)) = range {
+            if !(min.is_finite() && max.is_finite() && min < max) {
+                return Err(format!("{name}: empty range {min}..{max}"));
+            }
+            found.push(Tunable { name: name.to_string(), min, max, integer: is_integer_type(ty) });
+        }
+    }
+    if let Some(missing) = ranges.keys().find(|k| !found.iter().any(|t| &t.name == *k)) {
+        return Err(format!("sidecar names `{missing}`, which is not a const in the source"));
+    }
+    Ok(found)
+}
+
+/// `source` with each tunable's initializer replaced by its value in `values`.
+pub fn apply(source: &str, tunables: &[Tunable], values: &[f64]) -> String {
+    let mut out = String::with_capacity(source.len());
+    for line in source.split_inclusive('\n') {
+        let tuned = const_decl(line).and_then(|(name, _)| tunables.iter().position(|t| t.name == name));
+        match (tuned, line.find('='), line.find(';')) {
+            (Some(i), Some(eq), Some(semi)) if eq < semi => {
+                let literal = if tunables[i].integer { format!("{}", values[i] as i64) } else { format!("{:?}", values[i]) };
+                out.push_str(&line[..=eq]);
+                out.push(' ');
+                out.push_str(&literal);
+                out.push_str(&line[semi..]);
+            }
+            _ => out.push_str(line),
+        }
+    }
+    out
+}
+
+/// Search the tunables' ranges for the highest score. `evaluate` scores one candidate
+/// (values in `tunables` order), or returns `None` to stop the search early. Candidates
+/// that round to an already scored point are not re-evaluated. Returns every evaluated
+/// trial, best first.
+pub fn search(
+    tunables: &[Tunable],
+    method: SearchMethod,
+    seed: u64,
+    mut evaluate: impl FnMut(&[f64]) -> Option<f64>,
+) -> Vec<Trial> {
+    let n = tunables.len();
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    let mut trials: Vec<Trial> = Vec::new();
+    // Scores a point of the unit cube; `None` once the caller has stopped the search
+    let mut score = |u: &[f64], trials: &mut Vec<Trial>| -> Option<f64> {
+        let values: Vec<f64> = tunables.iter().zip(u).map(|(t, &u)| t.value_at(u)).collect();
+        if let Some(t) = trials.iter().find(|t| t.values == values) {
+            return Some(t.score);
+        }
+        let score = evaluate(&values)?;
+        trials.push(Trial { values, score });
+        Some(score)
+    };
+
+    match method {
+        SearchMethod::Grid { points } => {
+            let points = points.max(1);
+            let axis = |k: usize| if points == 1 { 0.5 } else { k as f64 / (points - 1) as f64 };
+            let total = points.checked_pow(n as u32).unwrap_or(usize::MAX);
+            for cell in 0..total {
+                let u: Vec<f64> = (0..n).map(|d| axis(cell / points.pow(d as u32) % points)).collect();
+                if score(&u, &mut trials).is_none() {
+                    break;
+                }
+            }
+        }
+        SearchMethod::Random { trials: count } => {
+            for _ in 0..count {
+                let u: Vec<f64> = (0..n).map(|_| rng.gen::<f64>()).collect();
+                if score(&u, &mut trials).is_none() {
+                    break;
+                }
+            }
+        }
+        SearchMethod::Cmaes { generations } => {
+            let mut es = SepCmaEs::new(n);
+            'generations: for _ in 0..generations {
+                let population = es.sample(&mut rng);
+                let mut scored = Vec::with_capacity(population.len());
+                for u in population {
+                    match score(&u, &mut trials) {
+                        Some(s) => scored.push((u, s)),
+                        None => break 'generations,
+                    }
+                }
+                es.update(scored);
+            }
+        }
+    }
+    trials.sort_by(|a, b| b.score.total_cmp(&a.score));
+    trials
+}
+
+/// Separable CMA-ES (diagonal covariance) maximizing over the unit cube, started at its
+/// centre. Samples are clamped to the cube and the update uses the clamped steps.
+struct SepCmaEs {
+    mean: Vec<f64>,
+    sigma: f64,
+    /// Diagonal of the covariance matrix
+    c: Vec<f64>,
+    p_sigma: Vec<f64>,
+    p_c: Vec<f64>,
+    weights: Vec<f64>,
+    mu_eff: f64,
+    lambda: usize,
+    generation: usize,
+}
+
+impl SepCmaEs {
+    fn new(n: usize) -> Self {
+        let lambda = 4 + (3.0 * (n.max(1) as f64).ln()).floor() as usize;
+        let mu = lambda / 2;
+        let raw: Vec<f64> = (1..=mu).map(|i| ((mu as f64 + 0.5).ln() - (i as f64).ln()).max(0.0)).collect();
+        let sum: f64 = raw.iter().sum();
+        let weights: Vec<f64> = raw.iter().map(|w| w / sum).collect();
+        let mu_eff = 1.0 / weights.iter().map(|w| w * w).sum::<f64>();
+        Self {
+            mean: vec![0.5; n],
+            sigma: 0.3,
+            c: vec![1.0; n],
+            p_sigma: vec![0.0; n],
+            p_c: vec![0.0; n],
+            weights,
+            mu_eff,
+            lambda,
+            generation: 0,
+        }
+    }
+
+    fn sample(&self, rng: &mut ChaCha8Rng) -> Vec<Vec<f64>> {
+        (0..self.lambda)
+            .map(|_| {
+                self.mean.iter().zip(&self.c)
+                    .map(|(&m, &c)| (m + self.sigma * c.sqrt() * rng.sample::<f64, _>(rand_distr::StandardNormal)).clamp(0.0, 1.0))
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn update(&mut self, mut scored: Vec<(Vec<f64>, f64)>) {
+        let n = self.mean.len() as f64;
+        if scored.len() < self.weights.len() || n == 0.0 {
+            return;
+        }
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+        let mu_eff = self.mu_eff;
+        let c_sigma = (mu_eff + 2.0) / (n + mu_eff + 5.0);
+        let d_sigma = 1.0 + 2.0 * (((mu_eff - 1.0) / (n + 1.0)).sqrt() - 1.0).max(0.0) + c_sigma;
+        let c_c = (4.0 + mu_eff / n) / (n + 4.0 + 2.0 * mu_eff / n);
+        // Separable variant: diagonal learning rates scaled up by (n + 2) / 3
+        let sep = (n + 2.0) / 3.0;
+        let c_1 = (2.0 / ((n + 1.3).powi(2) + mu_eff) * sep).min(1.0);
+        let c_mu = (2.0 * (mu_eff - 2.0 + 1.0 / mu_eff) / ((n + 2.0).powi(2) + mu_eff) * sep).min(1.0 - c_1);
+        let expected_norm = n.sqrt() * (1.0 - 1.0 / (4.0 * n) + 1.0 / (21.0 * n * n));
+
+        let steps: Vec<Vec<f64>> = scored.iter()
+            .take(self.weights.len())
+            .map(|(u, _)| u.iter().zip(&self.mean).map(|(x, m)| (x - m) / self.sigma).collect())
+            .collect();
+        let y_w: Vec<f64> = (0..self.mean.len())
+            .map(|d| self.weights.iter().zip(&steps).map(|(w, y)| w * y[d]).sum())
+            .collect();
+
+        for (d, &y) in y_w.iter().enumerate() {
+            self.mean[d] = (self.mean[d] + self.sigma * y).clamp(0.0, 1.0);
+            self.p_sigma[d] = (1.0 - c_sigma) * self.p_sigma[d]
+                + (c_sigma * (2.0 - c_sigma) * mu_eff).sqrt() * y / self.c[d].sqrt();
+        }
+        self.generation += 1;
+        let p_sigma_norm = self.p_sigma.iter().map(|p| p * p).sum::<f64>().sqrt();
+        let h_sigma = p_sigma_norm / (1.0 - (1.0 - c_sigma).powi(2 * self.generation as i32)).sqrt()
+            < (1.4 + 2.0 / (n + 1.0)) * expected_norm;
+        for (d, &y) in y_w.iter().enumerate() {
+            self.p_c[d] = (1.0 - c_c) * self.p_c[d]
+                + if h_sigma { (c_c * (2.0 - c_c) * mu_eff).sqrt() * y } else { 0.0 };
+            let rank_mu: f64 = self.weights.iter().zip(&steps).map(|(w, y)| w * y[d] * y[d]).sum();
+            self.c[d] = ((1.0 - c_1 - c_mu) * self.c[d] + c_1 * self.p_c[d] * self.p_c[d] + c_mu * rank_mu).max(1e-12);
+        }
+        self.sigma = (self.sigma * ((c_sigma / d_sigma) * (p_sigma_norm / expected_norm - 1.0)).exp()).min(1.0);
+    }
+}
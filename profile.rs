@@ -0,0 +1,133 @@
+//! Wall-time breakdown of a simulation.
+//!
+//! When `SimConfig::profile` is set, every strategy call is timed per strategy and entry
+//! point, and the engine's own work is timed per phase: retail routing, the arbitrage
+//! search and the epoch-boundary rebalance. Phase times exclude the strategy calls made
+//! inside them, so a slow strategy shows up under its own name rather than inflating
+//! routing. The result is returned in `SimResult::profile`, and `run_batch` sums it over
+//! the batch into `BatchResult::profile`.
+
+use std::time::{Duration, Instant};
+
+use crate::runner::{StrategyCall, StrategyRunner};
+
+/// Time spent in `count` occurrences of something.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Timing {
+    pub count: u64,
+    pub total: Duration,
+}
+
+impl Timing {
+    pub fn record(&mut self, elapsed: Duration) {
+        self.count += 1;
+        self.total += elapsed;
+    }
+
+    pub fn add(&mut self, other: &Timing) {
+        self.count += other.count;
+        self.total += other.total;
+    }
+
+    /// Mean time per occurrence (zero when there were none).
+    pub fn mean(&self) -> Duration {
+        if self.count == 0 { Duration::ZERO } else { Duration::from_nanos((self.total.as_nanos() / self.count as u128) as u64) }
+    }
+}
+
+/// One strategy's call times, by entry point.
+#[derive(Clone, Debug, Default)]
+pub struct StrategyProfile {
+    pub name: String,
+    /// Entry points called at least once, in `StrategyCall` order
+    pub calls: Vec<(StrategyCall, Timing)>,
+}
+
+impl StrategyProfile {
+    /// All of the strategy's calls together.
+    pub fn total(&self) -> Timing {
+        let mut total = Timing::default();
+        for (_, t) in &self.calls {
+            total.add(t);
+        }
+        total
+    }
+}
+
+/// Where a simulation's wall time went.
+#[derive(Clone, Debug, Default)]
+pub struct SimProfile {
+    /// Simulations folded in
+    pub simulations: usize,
+    /// Wall time of the simulations end to end
+    pub wall: Duration,
+    /// Strategy calls, per strategy in field order
+    pub strategies: Vec<StrategyProfile>,
+    /// Routing retail orders, per order
+    pub routing: Timing,
+    /// Arbitrage search and execution, per arb pass (one per step)
+    pub arb: Timing,
+    /// Epoch-boundary rebalancing, per rebalance
+    pub rebalancing: Timing,
+}
+
+impl SimProfile {
+    /// Strategy calls of every strategy together.
+    pub fn strategy_total(&self) -> Timing {
+        let mut total = Timing::default();
+        for s in &self.strategies {
+            total.add(&s.total());
+        }
+        total
+    }
+
+    /// Wall time outside strategy calls and the timed phases: price paths, order
+    /// generation, snapshots and bookkeeping.
+    pub fn other(&self) -> Duration {
+        let timed = self.strategy_total().total + self.routing.total + self.arb.total + self.rebalancing.total;
+        self.wall.saturating_sub(timed)
+    }
+
+    /// Fold another simulation's profile of the same field into this one.
+    pub fn add(&mut self, other: &SimProfile) {
+        self.simulations += other.simulations;
+        self.wall += other.wall;
+        self.routing.add(&other.routing);
+        self.arb.add(&other.arb);
+        self.rebalancing.add(&other.rebalancing);
+        if self.strategies.is_empty() {
+            self.strategies = other.strategies.iter().map(|s| StrategyProfile { name: s.name.clone(), calls: Vec::new() }).collect();
+        }
+        for (mine, theirs) in self.strategies.iter_mut().zip(&other.strategies) {
+            for &(call, timing) in &theirs.calls {
+                match mine.calls.iter_mut().find(|(c, _)| *c == call) {
+                    Some((_, t)) => t.add(&timing),
+                    None => mine.calls.push((call, timing)),
+                }
+            }
+            mine.calls.sort_by_key(|(call, _)| *call as u8);
+        }
+    }
+}
+
+/// Strategy call time of the whole field so far.
+fn strategy_time(runners: &[StrategyRunner]) -> Duration {
+    runners.iter().map(StrategyRunner::call_time).sum()
+}
+
+/// Times one phase of a simulation, net of the strategy calls made during it. Inert
+/// unless profiling is on.
+pub(crate) struct PhaseTimer(Option<(Instant, Duration)>);
+
+impl PhaseTimer {
+    pub(crate) fn start(on: bool, runners: &[StrategyRunner]) -> Self {
+        Self(on.then(|| (Instant::now(), strategy_time(runners))))
+    }
+
+    pub(crate) fn stop(self, runners: &[StrategyRunner], timing: Option<&mut Timing>) {
+        if let (Some((started, calls)), Some(timing)) = (self.0, timing) {
+            let elapsed = started.elapsed();
+            timing.record(elapsed.saturating_sub(strategy_time(runners).saturating_sub(calls)));
+        }
+    }
+}
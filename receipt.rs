@@ -0,0 +1,131 @@
+//! Signed submission receipts.
+//!
+//! `submit` records each run in a JSON receipt: the exact seeds, the full config and its
+//! hash, content hashes of the submitted files, and the engine and SDK it ran on. A host
+//! holding a secret key signs the receipt with HMAC-SHA256 over its canonical form —
+//! compact JSON with object keys sorted and the `signature` field left out — so pretty-
+//! printing or reordering the file does not break the signature, while changing any
+//! value does. The signature names its key by `key_id`, a hash that identifies the key
+//! without revealing it.
+
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use crate::compile::{SDK_LIB, SDK_VERSION, SDK_WIRE};
+use crate::types::{SimConfig, ABI_VERSION};
+
+/// Receipt field holding the signature
+pub const SIGNATURE_FIELD: &str = "signature";
+
+/// Signature scheme written to and accepted from receipts
+pub const SCHEME: &str = "hmac-sha256";
+
+/// Engine crate version
+pub const ENGINE_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Git commit the engine was built from, when the build could read it (see `build.rs`)
+pub const ENGINE_COMMIT: Option<&str> = option_env!("PROP_AMM_ENGINE_COMMIT");
+
+const BLOCK: usize = 64;
+
+/// HMAC-SHA256 (RFC 2104) of `message` under `key`.
+pub fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    let mut block = [0u8; BLOCK];
+    if key.len() > BLOCK {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+    let pad = |byte: u8| block.map(|b| b ^ byte);
+    let inner = Sha256::new().chain_update(pad(0x36)).chain_update(message).finalize();
+    Sha256::new().chain_update(pad(0x5c)).chain_update(inner).finalize().into()
+}
+
+/// SHA-256 of `bytes` (hex).
+pub fn sha256_hex(bytes: impl AsRef<[u8]>) -> String {
+    hex(&Sha256::digest(bytes))
+}
+
+/// Identifies a signing key without revealing it.
+pub fn key_id(key: &str) -> String {
+    sha256_hex(format!("prop-amm receipt key\0{key}"))[..16].to_string()
+}
+
+/// SHA-256 of `value`'s canonical JSON (hex).
+pub fn value_hash(value: &Value) -> String {
+    sha256_hex(serde_json::to_vec(value).expect("JSON value serializes"))
+}
+
+/// Hash of `config` as recorded in receipts (`value_hash` of its JSON).
+pub fn config_hash(config: &SimConfig) -> String {
+    value_hash(&serde_json::to_value(config).expect("SimConfig serializes"))
+}
+
+/// The engine build a receipt was produced by.
+pub fn engine_info() -> Value {
+    let binary = std::env::current_exe().ok().and_then(|exe| std::fs::read(exe).ok());
+    json!({
+        "version": ENGINE_VERSION,
+        "commit": ENGINE_COMMIT,
+        "abi_version": ABI_VERSION,
+        "binary_sha256": binary.map(sha256_hex),
+    })
+}
+
+/// The SDK strategies were built against: its version and a hash of its sources.
+pub fn sdk_info() -> Value {
+    json!({
+        "version": SDK_VERSION,
+        "source_sha256": sha256_hex([SDK_LIB, SDK_WIRE].concat()),
+    })
+}
+
+/// Bytes the signature covers: `receipt` as compact JSON without its signature. Object
+/// keys serialize sorted, so equal receipts give equal bytes however they were written.
+pub fn canonical_bytes(receipt: &Value) -> Vec<u8> {
+    let mut unsigned = receipt.clone();
+    if let Some(map) = unsigned.as_object_mut() {
+        map.remove(SIGNATURE_FIELD);
+    }
+    serde_json::to_vec(&unsigned).expect("JSON value serializes")
+}
+
+/// Sign `receipt` under `key`, replacing any earlier signature.
+pub fn sign(receipt: &mut Value, key: &str) {
+    let mac = hex(&hmac_sha256(key.as_bytes(), &canonical_bytes(receipt)));
+    if let Some(map) = receipt.as_object_mut() {
+        map.insert(SIGNATURE_FIELD.to_string(), json!({ "scheme": SCHEME, "key_id": key_id(key), "mac": mac }));
+    }
+}
+
+/// Check `receipt`'s signature against `key`.
+pub fn verify(receipt: &Value, key: &str) -> Result<(), String> {
+    let signature = receipt.get(SIGNATURE_FIELD).filter(|s| !s.is_null()).ok_or("receipt is not signed")?;
+    let field = |name: &str| signature.get(name).and_then(Value::as_str).ok_or(format!("signature has no `{name}`"));
+    let scheme = field("scheme")?;
+    if scheme != SCHEME {
+        return Err(format!("unsupported signature scheme `{scheme}`"));
+    }
+    if field("key_id")? != key_id(key) {
+        return Err(format!("signed with key {}, not with the key given ({})", field("key_id")?, key_id(key)));
+    }
+    let expected = hmac_sha256(key.as_bytes(), &canonical_bytes(receipt));
+    let mac = unhex(field("mac")?).ok_or("signature `mac` is not hex")?;
+    // Constant-time comparison
+    let diff = (mac.len() ^ expected.len()) | mac.iter().zip(&expected).fold(0, |d, (a, b)| d | (a ^ b) as usize);
+    if diff != 0 {
+        return Err("signature does not match the receipt's contents".to_string());
+    }
+    Ok(())
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn unhex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len()).step_by(2).map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok()).collect()
+}
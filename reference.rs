@@ -0,0 +1,244 @@
+//! Built-in reference opponents: a standard field any submission can be benchmarked
+//! against without compiling anything.
+//!
+//! Each is an `EngineStrategy`, so it runs in-process and keeps all state in its
+//! `storage` like a submitted strategy would. Instantiate one with `builtin(name)`, or
+//! list names in `SimConfig::opponents` to append them to every batch simulation.
+//!
+//! | name | behavior |
+//! |------|----------|
+//! | `fixed_10` … `fixed_100` | constant-product pool with a fixed fee, in steps of 10 bps |
+//! | `oracle_anchored` | anchors to the median competitor spot and widens the fee on the side its own price would be picked off |
+//! | `inventory_skew` | skews fees to pull its X inventory back to where the epoch started |
+//! | `chaos_monkey` | re-draws a pseudo-random fee (1–200 bps) after every trade |
+
+use std::path::PathBuf;
+
+use crate::market::cpamm_output;
+use crate::runner::{EngineStrategy, StrategyRunner};
+use crate::types::{AfterSwapPayload, EpochBoundaryPayload, STORAGE_SIZE};
+
+/// Fees the fixed-fee ladder spans (bps).
+pub const FIXED_LADDER_BPS: [u32; 10] = [10, 20, 30, 40, 50, 60, 70, 80, 90, 100];
+
+/// A representative field: a spread of fixed fees plus one of each adaptive quoter.
+pub const STANDARD_FIELD: [&str; 6] = ["fixed_10", "fixed_30", "fixed_100", "oracle_anchored", "inventory_skew", "chaos_monkey"];
+
+/// Every name `builtin` accepts.
+pub fn names() -> Vec<String> {
+    FIXED_LADDER_BPS
+        .iter()
+        .map(|bps| format!("fixed_{bps}"))
+        .chain(["oracle_anchored", "inventory_skew", "chaos_monkey"].map(String::from))
+        .collect()
+}
+
+/// Expand `field` into `STANDARD_FIELD` and check every other name against `names`.
+pub fn expand_names(requested: &[String]) -> Result<Vec<String>, String> {
+    let mut names = Vec::new();
+    for name in requested {
+        if name == "field" {
+            names.extend(STANDARD_FIELD.map(String::from));
+        } else if builtin(name).is_some() {
+            names.push(name.clone());
+        } else {
+            return Err(format!("unknown opponent `{name}`; available: field, {}", self::names().join(", ")));
+        }
+    }
+    Ok(names)
+}
+
+/// Strategy inputs from the command line, split by `resolve_inputs`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Inputs {
+    /// Sources and artifacts to build, in the order given
+    pub files: Vec<PathBuf>,
+    /// Built-in names, `field` expanded, in the order given without repeats
+    pub builtins: Vec<String>,
+}
+
+/// Split `inputs` into files and built-in reference names. An input is a name when no
+/// file by that name exists and it is `field` or a name `builtin` accepts, so a local
+/// file always wins; anything else is left for the build to report.
+pub fn resolve_inputs(inputs: &[PathBuf]) -> Inputs {
+    let mut resolved = Inputs::default();
+    for input in inputs {
+        let name = input.to_string_lossy();
+        let names = match expand_names(&[name.into_owned()]) {
+            Ok(names) if !input.exists() => names,
+            _ => {
+                resolved.files.push(input.clone());
+                continue;
+            }
+        };
+        for name in names {
+            if !resolved.builtins.contains(&name) {
+                resolved.builtins.push(name);
+            }
+        }
+    }
+    resolved
+}
+
+/// A runner for the built-in opponent `name` (see `names`), reported as `ref_<name>`.
+pub fn builtin(name: &str) -> Option<StrategyRunner> {
+    let display = format!("ref_{name}");
+    let runner = match name {
+        "oracle_anchored" => StrategyRunner::from_strategy(OracleAnchored { name: display }),
+        "inventory_skew" => StrategyRunner::from_strategy(InventorySkew { name: display }),
+        "chaos_monkey" => StrategyRunner::from_strategy(ChaosMonkey { name: display }),
+        _ => {
+            let bps: u32 = name.strip_prefix("fixed_")?.parse().ok()?;
+            if !FIXED_LADDER_BPS.contains(&bps) {
+                return None;
+            }
+            StrategyRunner::from_strategy(FixedFee { name: display, fee_bps: bps })
+        }
+    };
+    Some(runner)
+}
+
+fn quote(is_buy: bool, input: u64, rx: u64, ry: u64, fee_bps: u32) -> u64 {
+    if is_buy { cpamm_output(input, ry, rx, fee_bps) } else { cpamm_output(input, rx, ry, fee_bps) }
+}
+
+fn read_u64(storage: &[u8; STORAGE_SIZE], at: usize) -> u64 {
+    u64::from_le_bytes(storage[at..at + 8].try_into().unwrap())
+}
+
+fn write_u64(storage: &mut [u8; STORAGE_SIZE], at: usize, v: u64) {
+    storage[at..at + 8].copy_from_slice(&v.to_le_bytes());
+}
+
+// ─── Fixed-fee ladder ─────────────────────────────────────────────────────────
+
+struct FixedFee {
+    name: String,
+    fee_bps: u32,
+}
+
+impl EngineStrategy for FixedFee {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn compute_swap(&self, is_buy: bool, input: u64, rx: u64, ry: u64, _storage: &[u8; STORAGE_SIZE]) -> u64 {
+        quote(is_buy, input, rx, ry, self.fee_bps)
+    }
+}
+
+// ─── Oracle-anchored quoter ───────────────────────────────────────────────────
+
+/// Storage: [0..8] anchor price (f64 bits, Y per X; 0 until the first fill).
+struct OracleAnchored {
+    name: String,
+}
+
+const ORACLE_BASE_BPS: f64 = 20.0;
+const ORACLE_MAX_BPS: f64 = 1_000.0;
+
+impl EngineStrategy for OracleAnchored {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn compute_swap(&self, is_buy: bool, input: u64, rx: u64, ry: u64, storage: &[u8; STORAGE_SIZE]) -> u64 {
+        let anchor = f64::from_bits(read_u64(storage, 0));
+        let spot = ry as f64 / rx as f64;
+        // How far the trader is buying below (or selling above) the anchor
+        let gap = if anchor > 0.0 {
+            if is_buy { (anchor - spot) / anchor } else { (spot - anchor) / anchor }
+        } else {
+            0.0
+        };
+        let fee = (ORACLE_BASE_BPS + gap.max(0.0) * 10_000.0).min(ORACLE_MAX_BPS);
+        quote(is_buy, input, rx, ry, fee as u32)
+    }
+
+    fn after_swap(&self, payload: &AfterSwapPayload, storage: &mut [u8; STORAGE_SIZE]) {
+        let competing = payload.competing_spot_prices;
+        let mut spots: Vec<f32> = competing.into_iter().filter(|p| p.is_finite() && *p > 0.0).collect();
+        if spots.is_empty() {
+            return;
+        }
+        spots.sort_by(f32::total_cmp);
+        write_u64(storage, 0, (spots[spots.len() / 2] as f64).to_bits());
+    }
+}
+
+// ─── Inventory-skew market maker ──────────────────────────────────────────────
+
+/// Storage: [0..8] reserve_x at the start of the epoch (0 until the first hook).
+struct InventorySkew {
+    name: String,
+}
+
+const SKEW_BASE_BPS: f64 = 30.0;
+/// Fee shift per unit of relative inventory imbalance: 10% long X → ±100 bps
+const SKEW_BPS_PER_UNIT: f64 = 1_000.0;
+
+impl EngineStrategy for InventorySkew {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn compute_swap(&self, is_buy: bool, input: u64, rx: u64, ry: u64, storage: &[u8; STORAGE_SIZE]) -> u64 {
+        let baseline = read_u64(storage, 0);
+        let imbalance = if baseline > 0 { rx as f64 / baseline as f64 - 1.0 } else { 0.0 };
+        // Long X: cheaper for traders to take X (buys), dearer to add more (sells)
+        let skew = imbalance * SKEW_BPS_PER_UNIT;
+        let fee = if is_buy { SKEW_BASE_BPS - skew } else { SKEW_BASE_BPS + skew };
+        quote(is_buy, input, rx, ry, fee.clamp(5.0, 500.0) as u32)
+    }
+
+    fn after_swap(&self, payload: &AfterSwapPayload, storage: &mut [u8; STORAGE_SIZE]) {
+        if read_u64(storage, 0) == 0 {
+            // Pre-trade reserve_x on the first fill
+            let rx = if payload.side == 0 {
+                payload.reserve_x + payload.output_amount
+            } else {
+                payload.reserve_x.saturating_sub(payload.input_amount)
+            };
+            write_u64(storage, 0, rx);
+        }
+    }
+
+    fn epoch_boundary(&self, payload: &EpochBoundaryPayload, storage: &mut [u8; STORAGE_SIZE]) {
+        write_u64(storage, 0, payload.new_reserve_x);
+    }
+}
+
+// ─── Chaos monkey ─────────────────────────────────────────────────────────────
+
+/// Storage: [0..8] fills seen, [8..16] current fee (bps; 0 = not drawn yet).
+struct ChaosMonkey {
+    name: String,
+}
+
+impl EngineStrategy for ChaosMonkey {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn compute_swap(&self, is_buy: bool, input: u64, rx: u64, ry: u64, storage: &[u8; STORAGE_SIZE]) -> u64 {
+        let fee = match read_u64(storage, 8) {
+            0 => 30,
+            fee => fee as u32,
+        };
+        quote(is_buy, input, rx, ry, fee)
+    }
+
+    fn after_swap(&self, payload: &AfterSwapPayload, storage: &mut [u8; STORAGE_SIZE]) {
+        // Deterministic in the run's trade sequence, so seeds still reproduce
+        let fills = read_u64(storage, 0) + 1;
+        write_u64(storage, 0, fills);
+        write_u64(storage, 8, 1 + splitmix64(fills ^ payload.sim_step.rotate_left(32)) % 200);
+    }
+}
+
+fn splitmix64(mut z: u64) -> u64 {
+    z = z.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
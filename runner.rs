@@ -1,36 +1,370 @@
+use std::panic::{catch_unwind, AssertUnwindSafe};
 use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
 use libloading::Library;
+use serde::{Deserialize, Serialize};
 
+use crate::profile::Timing;
 use crate::types::{
-    AfterSwapPayload, EpochBoundaryPayload, STORAGE_SIZE,
-    TAG_EPOCH_BOUNDARY,
-    TAG_SWAP_BUY, TAG_SWAP_SELL,
+    AfterSwapPayload, EpochBoundaryPayload, ShockNewsPayload, SimConfig, StepDigestPayload, ABI_VERSION,
+    MIN_ABI_VERSION, STORAGE_SIZE,
 };
+use crate::wire;
 
 /// Function signatures exported by compiled strategy shared libraries.
 ///
 /// The CLI compiles each strategy to a native `.so`/`.dylib` with these symbols.
-/// We call them directly — no EVM overhead during simulation.
+/// We call them directly — no EVM overhead during simulation. A panic or fault inside a
+/// library aborts the whole process (a panic from another Rust runtime cannot be caught
+/// here); run untrusted libraries with `Runtime::Subprocess`.
 type ComputeSwapFn = unsafe extern "C" fn(data: *const u8, len: usize) -> u64;
-type AfterSwapFn   = unsafe extern "C" fn(data: *const u8, len: usize, storage: *mut u8);
-type GetNameFn     = unsafe extern "C" fn(buf: *mut u8, max_len: usize) -> usize;
+type AfterSwapFn = unsafe extern "C" fn(data: *const u8, len: usize, storage: *mut u8);
+type GetNameFn = unsafe extern "C" fn(buf: *mut u8, max_len: usize) -> usize;
+type AbiVersionFn = unsafe extern "C" fn() -> u32;
+/// Optional: quote `n` input sizes against one swap payload (its input field ignored)
+type ComputeSwapBatchFn = unsafe extern "C" fn(
+    data: *const u8,
+    len: usize,
+    inputs: *const u64,
+    outputs: *mut u64,
+    n: usize,
+);
 
-/// A loaded, callable strategy.
-pub struct StrategyRunner {
+/// Reject a strategy built against a payload layout this engine does not speak.
+fn check_abi_version(path: &Path, version: Option<u32>) -> Result<u32, Box<dyn std::error::Error>> {
+    let path = path.display();
+    match version {
+        None => Err(format!(
+            "{path}: no `__prop_amm_abi_version` export; rebuild against the current SDK (ABI v{ABI_VERSION})"
+        )
+        .into()),
+        Some(v) if v < MIN_ABI_VERSION => Err(format!(
+            "{path}: built against ABI v{v}, but this engine's oldest supported payload layout is \
+             v{MIN_ABI_VERSION}; rebuild against the current SDK (v{ABI_VERSION})"
+        )
+        .into()),
+        Some(v) if v > ABI_VERSION => Err(format!(
+            "{path}: built against ABI v{v}, newer than this engine's v{ABI_VERSION}; upgrade the engine"
+        )
+        .into()),
+        Some(v) => Ok(v),
+    }
+}
+
+/// Optional metadata exports share the `get_name` signature.
+const GET_MODEL_SYMBOL: &[u8] = b"__prop_amm_get_model\0";
+const GET_METADATA_SYMBOL: &[u8] = b"__prop_amm_get_metadata\0";
+/// Largest `__prop_amm_get_metadata` output read
+const METADATA_MAX: usize = 4096;
+/// Optional diagnostics drain, same signature as `get_name`: copies the strategy's
+/// pending `log!` output (newline-separated) into the buffer and clears it
+const LOG_SYMBOL: &[u8] = b"__prop_amm_log\0";
+/// Largest diagnostics output read after one call (the SDK buffer's capacity)
+const LOG_DRAIN_MAX: usize = 4096;
+/// Log lines a runner keeps per simulation; later lines are dropped
+pub const MAX_LOG_LINES: usize = 10_000;
+
+/// What a strategy reports about itself at load, beyond its name.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct StrategyMetadata {
+    /// `MODEL_USED`, from the optional `__prop_amm_get_model` export (`TAG_GET_MODEL`
+    /// for SBF programs)
+    pub model: Option<String>,
+    /// Free-form `key=value` pairs from the optional `__prop_amm_get_metadata` export,
+    /// which writes one pair per line
+    pub entries: Vec<(String, String)>,
+    /// Payload layout the strategy decodes (`__prop_amm_abi_version`). `None` for
+    /// in-process strategies, and for remote ones that send no `abi_version` metadata
+    /// line; those get the oldest layout.
+    pub abi_version: Option<u32>,
+}
+
+impl StrategyMetadata {
+    /// Build from raw export output; an empty model counts as none, lines without `=`
+    /// are skipped, and an `abi_version` line sets `abi_version`.
+    fn from_exports(model: Option<&[u8]>, entries: Option<&[u8]>) -> Self {
+        let model = model.map(|m| String::from_utf8_lossy(m).trim().to_string()).filter(|m| !m.is_empty());
+        let mut entries: Vec<(String, String)> = entries
+            .map(|text| {
+                String::from_utf8_lossy(text)
+                    .lines()
+                    .filter_map(|line| line.split_once('='))
+                    .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+                    .filter(|(k, _)| !k.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+        let abi_version = entries.iter().position(|(k, _)| k == "abi_version").and_then(|i| entries.remove(i).1.parse().ok());
+        Self { model, entries, abi_version }
+    }
+
+    /// Entries back in export format, with the ABI version for the worker handshake.
+    fn entries_text(&self) -> String {
+        let version = self.abi_version.map(|v| format!("abi_version={v}\n"));
+        self.entries.iter().map(|(k, v)| format!("{k}={v}\n")).chain(version).collect()
+    }
+
+    /// Layout to encode hook payloads in for this strategy.
+    fn payload_version(&self) -> u32 {
+        self.abi_version.unwrap_or(MIN_ABI_VERSION)
+    }
+}
+
+/// Where a strategy executes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Runtime {
+    /// Native shared library, in-process with full host privileges (trusted code only)
+    #[default]
+    Native,
+    /// `wasm32-unknown-unknown` module under wasmtime, sandboxed with memory and fuel
+    /// limits (needs the `wasm` feature)
+    Wasm(WasmLimits),
+    /// Solana SBF program under the rbpf VM with compute-unit metering, as it would run
+    /// on-chain (needs the `sbf` feature)
+    Sbf(SbfLimits),
+    /// Native shared library loaded in a separate worker process, so a crash or abort
+    /// only takes down the worker (see `serve_worker`)
+    Subprocess,
+    /// External process or TCP peer speaking the worker protocol, named by a `.remote`
+    /// manifest (see `RemoteEndpoint`): strategies in any language, on any machine
+    Remote,
+}
+
+impl Runtime {
+    /// `.wasm` artifacts run sandboxed, BPF/SBF ELF programs in the SBF VM (each with
+    /// default limits) and `.remote` manifests remotely; anything else natively.
+    pub fn for_artifact(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("wasm") => return Runtime::Wasm(WasmLimits::default()),
+            Some("remote") => return Runtime::Remote,
+            _ => {}
+        }
+        // ELF e_machine at offset 18: EM_BPF (247) or EM_SBF (263)
+        let mut header = [0u8; 20];
+        let is_bpf = std::fs::File::open(path)
+            .and_then(|mut f| std::io::Read::read_exact(&mut f, &mut header))
+            .is_ok()
+            && header[..4] == *b"\x7fELF"
+            && matches!(u16::from_le_bytes([header[18], header[19]]), 247 | 263);
+        if is_bpf { Runtime::Sbf(SbfLimits::default()) } else { Runtime::Native }
+    }
+
+    /// Replace the per-call instruction limit: WASM fuel or SBF compute units.
+    pub fn with_instruction_limit(self, limit: u64) -> Self {
+        match self {
+            Runtime::Native | Runtime::Subprocess | Runtime::Remote => self,
+            Runtime::Wasm(l) => Runtime::Wasm(WasmLimits { fuel_per_call: limit, ..l }),
+            Runtime::Sbf(_) => Runtime::Sbf(SbfLimits { compute_units_per_call: limit }),
+        }
+    }
+}
+
+/// Per-call compute budget a `StrategyRunner` enforces (`SimConfig::call_budget`).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CallBudget {
+    /// Wall-clock limit per call, for any backend. A worker process still busy at the
+    /// deadline is killed (and its runner disabled) and a WASM call is interrupted;
+    /// in-process native code cannot be preempted, so there the limit is only checked
+    /// when the call returns and the result discarded. `with_runners` therefore moves
+    /// native strategies into worker processes whenever this is set.
+    pub wall_time: Option<Duration>,
+    /// Instructions per call for metered backends (WASM fuel, SBF compute units);
+    /// `None` keeps the runtime's default limits
+    pub instructions: Option<u64>,
+    pub penalty: BudgetPenalty,
+}
+
+/// What happens to a call that exceeds its budget.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BudgetPenalty {
+    /// The quote counts as a refusal (0) and hook storage writes are discarded
+    #[default]
+    Refuse,
+    /// As `Refuse`, and after `strikes` overruns every further call is refused, so a
+    /// spinning strategy cannot keep stalling the simulation
+    Disable { strikes: u32 },
+}
+
+/// How a `StrategyRunner` treats a quote that would pay out the whole output reserve or
+/// more (`SimConfig::quote_guard`). Such a fill would saturate the pool to zero
+/// reserves, so the quote never reaches the engine as given.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QuoteGuard {
+    pub action: OverQuoteAction,
+    /// Disable the strategy after this many over-quotes, disqualifying it from the run
+    pub disqualify_after: Option<u32>,
+}
+
+/// What replaces an impossible quote.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverQuoteAction {
+    /// Quote 0: the AMM refuses the trade
+    #[default]
+    Reject,
+    /// Pay out all but one unit of the output reserve
+    Clamp,
+}
+
+/// Strategy entry point a call went to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StrategyCall {
+    ComputeSwap,
+    /// One `compute_swap_batch` call, however many sizes it quoted
+    ComputeSwapBatch,
+    AfterSwap,
+    EpochBoundary,
+    ShockNews,
+    StepDigest,
+}
+
+/// Why a backend call produced no result.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum CallFailure {
+    /// Ran out of fuel / compute units
+    OverBudget,
+    /// Panicked, trapped or aborted for any other reason
+    Trapped,
+    /// The process hosting the strategy died; nothing further can be called
+    Crashed,
+    /// Wrote outside the storage it was handed (hardened mode only)
+    Tampered,
+    /// Outlived the wall-time budget and was cut off; the worker is gone
+    TimedOut,
+}
+
+/// Resource limits for a sandboxed WASM strategy.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct WasmLimits {
+    /// Largest linear memory the module may grow to
+    pub max_memory_bytes: usize,
+    /// Fuel (≈ WASM instructions) granted to each entry-point call; running out traps
+    /// the call, which then quotes 0 or leaves storage unchanged
+    pub fuel_per_call: u64,
+}
+
+impl Default for WasmLimits {
+    fn default() -> Self {
+        Self { max_memory_bytes: 64 << 20, fuel_per_call: 10_000_000 }
+    }
+}
+
+/// Compute limits for an SBF strategy.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SbfLimits {
+    /// Compute units granted to each entry-point call (the on-chain default is 200k);
+    /// exhausting them aborts the call, which then quotes 0 or leaves storage unchanged
+    pub compute_units_per_call: u64,
+}
+
+impl Default for SbfLimits {
+    fn default() -> Self {
+        Self { compute_units_per_call: 200_000 }
+    }
+}
+
+/// Executes a strategy's entry points on encoded wire payloads. A failed `after_swap`
+/// must leave `storage` untouched.
+trait Backend: Send + Sync {
+    fn compute_swap(&self, data: &[u8]) -> Result<u64, CallFailure>;
+    fn after_swap(&self, data: &[u8], storage: &mut [u8; STORAGE_SIZE]) -> Result<(), CallFailure>;
+
+    /// Quote every size in `inputs` against one swap payload into `outputs`.
+    fn compute_swap_batch(&self, data: &[u8], inputs: &[u64], outputs: &mut [u64]) -> Result<(), CallFailure> {
+        quote_each(self, data, inputs, outputs)
+    }
+
+    /// Fence every buffer handed to the strategy and fail calls that write outside
+    /// their storage. Only meaningful where the strategy shares the host's memory.
+    fn set_hardened(&mut self, _on: bool) {}
+
+    /// Cut off calls still running after `limit` (`CallBudget::wall_time`). Backends
+    /// that cannot be preempted ignore it; `metered` still checks on return.
+    fn set_deadline(&mut self, _limit: Option<Duration>) {}
+
+    /// Take the strategy's pending diagnostics output (see `LOG_SYMBOL`); empty where
+    /// the backend has no log channel.
+    fn drain_log(&self) -> Vec<u8> {
+        Vec::new()
+    }
+}
+
+/// `compute_swap_batch` as one `compute_swap` per size.
+fn quote_each<B: Backend + ?Sized>(backend: &B, data: &[u8], inputs: &[u64], outputs: &mut [u64]) -> Result<(), CallFailure> {
+    let mut buf = data.to_vec();
+    for (input, out) in inputs.iter().zip(outputs) {
+        wire::put_u64(&mut buf, wire::swap::INPUT_AMOUNT, *input);
+        *out = backend.compute_swap(&buf)?;
+    }
+    Ok(())
+}
+
+/// Canary bytes on each side of a buffer handed to a hardened native strategy.
+const GUARD_LEN: usize = 256;
+const GUARD_BYTE: u8 = 0xA5;
+
+/// A copy of `region` fenced by canaries: `[guard | region | guard]`. Writes that land
+/// in the fences, or in a region that should be read-only, show up on `check`.
+struct Guarded {
+    buf: Vec<u8>,
+    len: usize,
+}
+
+impl Guarded {
+    fn new(region: &[u8]) -> Self {
+        let mut buf = vec![GUARD_BYTE; GUARD_LEN + region.len() + GUARD_LEN];
+        buf[GUARD_LEN..GUARD_LEN + region.len()].copy_from_slice(region);
+        Self { buf, len: region.len() }
+    }
+
+    fn ptr(&mut self) -> *mut u8 {
+        self.buf[GUARD_LEN..].as_mut_ptr()
+    }
+
+    fn region(&self) -> &[u8] {
+        &self.buf[GUARD_LEN..GUARD_LEN + self.len]
+    }
+
+    /// `Tampered` if a fence changed or, when `read_only` is given, the region no
+    /// longer matches it.
+    fn check(&self, read_only: Option<&[u8]>) -> Result<(), CallFailure> {
+        let (head, tail) = (&self.buf[..GUARD_LEN], &self.buf[GUARD_LEN + self.len..]);
+        let fences_intact = head.iter().chain(tail).all(|&b| b == GUARD_BYTE);
+        if !fences_intact || read_only.is_some_and(|r| r != self.region()) {
+            return Err(CallFailure::Tampered);
+        }
+        Ok(())
+    }
+}
+
+/// A native strategy library, called directly through its C ABI.
+struct NativeBackend {
     /// Keep the library alive for the duration of the simulation
     _lib: Library,
     compute_swap: ComputeSwapFn,
     after_swap: AfterSwapFn,
-    pub name: String,
+    compute_swap_batch: Option<ComputeSwapBatchFn>,
+    log: Option<GetNameFn>,
+    hardened: bool,
 }
 
-impl StrategyRunner {
-    /// Load a compiled strategy shared library from disk.
-    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+impl NativeBackend {
+    fn load(path: &Path) -> Result<(Self, String, StrategyMetadata), Box<dyn std::error::Error>> {
         let lib = unsafe { Library::new(path)? };
 
+        let abi_version = unsafe { lib.get::<AbiVersionFn>(b"__prop_amm_abi_version\0") }.ok().map(|f| unsafe { f() });
+        let abi_version = check_abi_version(path, abi_version)?;
+
         let compute_swap: ComputeSwapFn = unsafe { *lib.get::<ComputeSwapFn>(b"__prop_amm_compute_swap\0")? };
         let after_swap: AfterSwapFn = unsafe { *lib.get::<AfterSwapFn>(b"__prop_amm_after_swap\0")? };
+        let compute_swap_batch = unsafe { lib.get::<ComputeSwapBatchFn>(b"__prop_amm_compute_swap_batch\0") }.ok().map(|f| *f);
+        let log = unsafe { lib.get::<GetNameFn>(LOG_SYMBOL) }.ok().map(|f| *f);
         let get_name: GetNameFn = unsafe { *lib.get::<GetNameFn>(b"__prop_amm_get_name\0")? };
 
         // Read strategy name
@@ -38,15 +372,452 @@ impl StrategyRunner {
         let name_len = unsafe { get_name(name_buf.as_mut_ptr(), name_buf.len()) };
         let name = String::from_utf8_lossy(&name_buf[..name_len]).to_string();
 
-        Ok(Self {
-            _lib: lib,
-            compute_swap,
-            after_swap,
+        let read_optional = |symbol: &[u8], max_len: usize| {
+            let get = unsafe { lib.get::<GetNameFn>(symbol) }.ok()?;
+            let mut buf = vec![0u8; max_len];
+            let len = unsafe { get(buf.as_mut_ptr(), buf.len()) }.min(max_len);
+            buf.truncate(len);
+            Some(buf)
+        };
+        let metadata = StrategyMetadata {
+            abi_version: Some(abi_version),
+            ..StrategyMetadata::from_exports(
+                read_optional(GET_MODEL_SYMBOL, name_buf.len()).as_deref(),
+                read_optional(GET_METADATA_SYMBOL, METADATA_MAX).as_deref(),
+            )
+        };
+
+        Ok((Self { _lib: lib, compute_swap, after_swap, compute_swap_batch, log, hardened: false }, name, metadata))
+    }
+}
+
+impl Backend for NativeBackend {
+    fn compute_swap(&self, data: &[u8]) -> Result<u64, CallFailure> {
+        if !self.hardened {
+            return Ok(unsafe { (self.compute_swap)(data.as_ptr(), data.len()) });
+        }
+        let mut payload = Guarded::new(data);
+        let out = unsafe { (self.compute_swap)(payload.ptr(), data.len()) };
+        payload.check(Some(data))?;
+        Ok(out)
+    }
+
+    fn after_swap(&self, data: &[u8], storage: &mut [u8; STORAGE_SIZE]) -> Result<(), CallFailure> {
+        if !self.hardened {
+            unsafe { (self.after_swap)(data.as_ptr(), data.len(), storage.as_mut_ptr()) };
+            return Ok(());
+        }
+        let (mut payload, mut fenced) = (Guarded::new(data), Guarded::new(storage));
+        unsafe { (self.after_swap)(payload.ptr(), data.len(), fenced.ptr()) };
+        payload.check(Some(data))?;
+        fenced.check(None)?;
+        storage.copy_from_slice(fenced.region());
+        Ok(())
+    }
+
+    fn compute_swap_batch(&self, data: &[u8], inputs: &[u64], outputs: &mut [u64]) -> Result<(), CallFailure> {
+        // Hardened batches go through the fenced single-quote path
+        let Some(batch) = self.compute_swap_batch.filter(|_| !self.hardened) else {
+            return quote_each(self, data, inputs, outputs);
+        };
+        let n = inputs.len().min(outputs.len());
+        unsafe { batch(data.as_ptr(), data.len(), inputs.as_ptr(), outputs.as_mut_ptr(), n) };
+        Ok(())
+    }
+
+    fn set_hardened(&mut self, on: bool) {
+        self.hardened = on;
+    }
+
+    fn drain_log(&self) -> Vec<u8> {
+        let Some(log) = self.log else { return Vec::new() };
+        let mut buf = vec![0u8; LOG_DRAIN_MAX];
+        let len = unsafe { log(buf.as_mut_ptr(), buf.len()) }.min(LOG_DRAIN_MAX);
+        buf.truncate(len);
+        buf
+    }
+}
+
+// ─── In-process strategies ───────────────────────────────────────────────────
+
+/// A strategy written directly against the engine in Rust: no shared library, no wire
+/// encoding. Wrap one with `StrategyRunner::from_strategy` to run it next to compiled
+/// submissions. Calls are metered like any other runner's, and a panic is caught and
+/// counted as a fault.
+///
+/// Methods take `&self` because, as for compiled strategies, all per-AMM state belongs in
+/// `storage`: the engine owns it, snapshots it and delays its visibility (see
+/// `SimConfig::storage_latency_trades`). The hooks default to doing nothing.
+pub trait EngineStrategy: Send + Sync {
+    fn name(&self) -> &str;
+
+    /// Model or tooling the strategy was built with, if it reports one.
+    fn model(&self) -> Option<&str> {
+        None
+    }
+
+    /// Extra `key=value` metadata (see `StrategyMetadata::entries`).
+    fn metadata(&self) -> Vec<(String, String)> {
+        Vec::new()
+    }
+
+    /// Output amount for `input` (buy: Y in, X out; sell: X in, Y out); 0 refuses.
+    fn compute_swap(
+        &self,
+        is_buy: bool,
+        input: u64,
+        reserve_x: u64,
+        reserve_y: u64,
+        storage: &[u8; STORAGE_SIZE],
+    ) -> u64;
+
+    fn after_swap(&self, _payload: &AfterSwapPayload, _storage: &mut [u8; STORAGE_SIZE]) {}
+
+    fn epoch_boundary(&self, _payload: &EpochBoundaryPayload, _storage: &mut [u8; STORAGE_SIZE]) {}
+
+    fn shock_news(&self, _payload: &ShockNewsPayload, _storage: &mut [u8; STORAGE_SIZE]) {}
+
+    fn step_digest(&self, _payload: &StepDigestPayload, _storage: &mut [u8; STORAGE_SIZE]) {}
+
+    /// Quote every size in `inputs` into `outputs`; one `compute_swap` each by default.
+    fn compute_swap_batch(
+        &self,
+        is_buy: bool,
+        inputs: &[u64],
+        reserve_x: u64,
+        reserve_y: u64,
+        storage: &[u8; STORAGE_SIZE],
+        outputs: &mut [u64],
+    ) {
+        for (&input, out) in inputs.iter().zip(outputs) {
+            *out = self.compute_swap(is_buy, input, reserve_x, reserve_y, storage);
+        }
+    }
+}
+
+/// How a runner reaches its strategy.
+enum Exec {
+    /// Compiled artifact, called with encoded wire payloads
+    Wire(Box<dyn Backend>),
+    /// `EngineStrategy` called directly with typed payloads
+    InProcess(Box<dyn EngineStrategy>),
+}
+
+/// A loaded, callable strategy.
+pub struct StrategyRunner {
+    exec: Exec,
+    pub name: String,
+    pub metadata: StrategyMetadata,
+    budget: CallBudget,
+    /// Simulation step the engine is on, stamped onto budget overruns and faults
+    step: AtomicU64,
+    overruns: Mutex<Vec<(u64, StrategyCall)>>,
+    faults: Mutex<Vec<(u64, StrategyCall)>>,
+    tampers: Mutex<Vec<(u64, StrategyCall)>>,
+    quote_guard: QuoteGuard,
+    over_quotes: Mutex<Vec<(u64, StrategyCall)>>,
+    debug_log: bool,
+    logs: Mutex<Vec<(u64, StrategyCall, String)>>,
+    disabled: AtomicBool,
+    profiling: bool,
+    timings: Mutex<Vec<(StrategyCall, Timing)>>,
+}
+
+impl StrategyRunner {
+    /// Load a compiled strategy from disk, in the runtime its extension implies
+    /// (see `Runtime::for_artifact`).
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::load_with(path, Runtime::for_artifact(path))
+    }
+
+    /// Load a compiled strategy to execute under `runtime`.
+    pub fn load_with(path: &Path, runtime: Runtime) -> Result<Self, Box<dyn std::error::Error>> {
+        let (backend, name, metadata): (Box<dyn Backend>, String, StrategyMetadata) = match runtime {
+            Runtime::Native => {
+                let (b, name, metadata) = NativeBackend::load(path)?;
+                (Box::new(b), name, metadata)
+            }
+            #[cfg(feature = "wasm")]
+            Runtime::Wasm(limits) => {
+                let (b, name, metadata) = wasm::WasmBackend::load(path, limits)?;
+                (Box::new(b), name, metadata)
+            }
+            #[cfg(not(feature = "wasm"))]
+            Runtime::Wasm(_) => {
+                return Err(format!("{}: WASM strategies need the `wasm` feature", path.display()).into());
+            }
+            #[cfg(feature = "sbf")]
+            Runtime::Sbf(limits) => {
+                let (b, name, metadata) = sbf::SbfBackend::load(path, limits)?;
+                (Box::new(b), name, metadata)
+            }
+            #[cfg(not(feature = "sbf"))]
+            Runtime::Sbf(_) => {
+                return Err(format!("{}: SBF programs need the `sbf` feature", path.display()).into());
+            }
+            Runtime::Subprocess => {
+                let (b, name, metadata) = subprocess::SubprocessBackend::spawn(path)?;
+                (Box::new(b), name, metadata)
+            }
+            Runtime::Remote => {
+                let spec = std::fs::read_to_string(path)?;
+                let endpoint = RemoteEndpoint::parse(&spec).map_err(|e| format!("{}: {e}", path.display()))?;
+                // Relative programs and arguments resolve against the manifest
+                let dir = path.parent().filter(|d| !d.as_os_str().is_empty());
+                let (b, name, metadata) = subprocess::SubprocessBackend::remote(&endpoint, dir)?;
+                (Box::new(b), name, metadata)
+            }
+        };
+        Ok(Self::new(Exec::Wire(backend), name, metadata))
+    }
+
+    /// `load_with`, refusing an artifact that fails `audit_artifact`: for tournament play,
+    /// where submissions should not reach the network, filesystem, threads or clocks.
+    pub fn load_audited(path: &Path, runtime: Runtime) -> Result<Self, Box<dyn std::error::Error>> {
+        let audit = audit_artifact(path)?;
+        if !audit.passes() {
+            return Err(format!("{} failed the load-time audit: {}", path.display(), audit.violations.join("; ")).into());
+        }
+        Self::load_with(path, runtime)
+    }
+
+    /// Run a strategy that lives in another process or on another machine, speaking the
+    /// worker protocol (see `RemoteEndpoint`), through the same interface as a compiled one.
+    pub fn connect(endpoint: &RemoteEndpoint) -> Result<Self, Box<dyn std::error::Error>> {
+        let (backend, name, metadata) = subprocess::SubprocessBackend::remote(endpoint, None)?;
+        Ok(Self::new(Exec::Wire(Box::new(backend)), name, metadata))
+    }
+
+    /// Run an in-process Rust strategy through the same interface as a compiled one.
+    pub fn from_strategy(strategy: impl EngineStrategy + 'static) -> Self {
+        let name = strategy.name().to_string();
+        let metadata = StrategyMetadata {
+            model: strategy.model().map(str::to_string),
+            entries: strategy.metadata(),
+            abi_version: None,
+        };
+        Self::new(Exec::InProcess(Box::new(strategy)), name, metadata)
+    }
+
+    fn new(exec: Exec, name: String, metadata: StrategyMetadata) -> Self {
+        Self {
+            exec,
             name,
-        })
+            metadata,
+            budget: CallBudget::default(),
+            step: AtomicU64::new(0),
+            overruns: Mutex::new(Vec::new()),
+            faults: Mutex::new(Vec::new()),
+            tampers: Mutex::new(Vec::new()),
+            quote_guard: QuoteGuard::default(),
+            over_quotes: Mutex::new(Vec::new()),
+            debug_log: false,
+            logs: Mutex::new(Vec::new()),
+            disabled: AtomicBool::new(false),
+            profiling: false,
+            timings: Mutex::new(Vec::new()),
+        }
     }
 
-    /// Call compute_swap. Builds the wire payload inline.
+    /// Enforce `budget` on every further call. Its instruction limit only applies at
+    /// load; see `Runtime::with_instruction_limit`.
+    pub fn set_budget(&mut self, budget: CallBudget) {
+        self.budget = budget;
+        if let Exec::Wire(backend) = &mut self.exec {
+            backend.set_deadline(budget.wall_time);
+        }
+    }
+
+    /// Collect the strategy's `__prop_amm_log` output after every call (see
+    /// `take_logs`). Only native libraries loaded in-process have the channel.
+    pub fn set_debug_log(&mut self, on: bool) {
+        self.debug_log = on;
+    }
+
+    /// Time every further call (see `take_timings`).
+    pub fn set_profiling(&mut self, on: bool) {
+        self.profiling = on;
+    }
+
+    /// Handle quotes that exceed the output reserve per `guard` from here on.
+    pub fn set_quote_guard(&mut self, guard: QuoteGuard) {
+        self.quote_guard = guard;
+    }
+
+    /// Fence the buffers handed to a compiled strategy and treat any write outside its
+    /// storage as tampering (see `take_tampers`). Native libraries check canaries around
+    /// the payload and storage; a worker process does the same on its side. WASM and
+    /// SBF strategies cannot address host memory, so this is a no-op for them.
+    pub fn set_hardened(&mut self, on: bool) {
+        if let Exec::Wire(backend) = &mut self.exec {
+            backend.set_hardened(on);
+        }
+    }
+
+    /// Apply the per-runner settings of `config`: call budget, quote guard, hardened
+    /// storage, strategy logs and profiling.
+    pub fn configure(&mut self, config: &SimConfig) {
+        self.set_budget(config.call_budget);
+        self.set_quote_guard(config.quote_guard);
+        self.set_hardened(config.harden_storage);
+        self.set_debug_log(config.strategy_logs);
+        self.set_profiling(config.profile);
+    }
+
+    /// Tell the runner which simulation step its calls belong to.
+    pub fn set_step(&self, step: u64) {
+        self.step.store(step, Ordering::Relaxed);
+    }
+
+    /// Drain the `(step, call)` of every budget overrun so far.
+    pub fn take_overruns(&self) -> Vec<(u64, StrategyCall)> {
+        std::mem::take(&mut *self.overruns.lock().unwrap())
+    }
+
+    /// Whether every further call is refused: after `BudgetPenalty::Disable` strikes, a
+    /// crash, tampering or `QuoteGuard::disqualify_after` over-quotes.
+    pub fn is_disabled(&self) -> bool {
+        self.disabled.load(Ordering::Relaxed)
+    }
+
+    /// Drain the `(step, call)` of every call that panicked, trapped or crashed so far.
+    pub fn take_faults(&self) -> Vec<(u64, StrategyCall)> {
+        std::mem::take(&mut *self.faults.lock().unwrap())
+    }
+
+    /// Drain the `(step, call)` of every call caught writing outside its storage.
+    pub fn take_tampers(&self) -> Vec<(u64, StrategyCall)> {
+        std::mem::take(&mut *self.tampers.lock().unwrap())
+    }
+
+    /// Drain the `(step, call, line)` of every diagnostics line logged so far, up to
+    /// `MAX_LOG_LINES`.
+    pub fn take_logs(&self) -> Vec<(u64, StrategyCall, String)> {
+        std::mem::take(&mut *self.logs.lock().unwrap())
+    }
+
+    /// Drain the `(step, call)` of every quote that exceeded the output reserve; a batch
+    /// call appears once per offending size.
+    pub fn take_over_quotes(&self) -> Vec<(u64, StrategyCall)> {
+        std::mem::take(&mut *self.over_quotes.lock().unwrap())
+    }
+
+    /// Drain the wall time of every call made since profiling was turned on, by entry
+    /// point in `StrategyCall` order. Refused calls are not timed.
+    pub fn take_timings(&self) -> Vec<(StrategyCall, Timing)> {
+        let mut timings = std::mem::take(&mut *self.timings.lock().unwrap());
+        timings.sort_by_key(|(call, _)| *call as u8);
+        timings
+    }
+
+    /// Wall time of the calls `take_timings` would drain.
+    pub fn call_time(&self) -> Duration {
+        self.timings.lock().unwrap().iter().map(|(_, t)| t.total).sum()
+    }
+
+    /// Apply the quote guard to `output`, quoted against `reserve_out`.
+    fn guard_quote(&self, call: StrategyCall, output: u64, reserve_out: u64) -> u64 {
+        if output == 0 || output < reserve_out {
+            return output;
+        }
+        let mut over_quotes = self.over_quotes.lock().unwrap();
+        over_quotes.push((self.step.load(Ordering::Relaxed), call));
+        if self.quote_guard.disqualify_after.is_some_and(|n| over_quotes.len() >= n as usize) {
+            self.disabled.store(true, Ordering::Relaxed);
+        }
+        match self.quote_guard.action {
+            OverQuoteAction::Reject => 0,
+            OverQuoteAction::Clamp => reserve_out.saturating_sub(1),
+        }
+    }
+
+    /// Run one backend call under the budget; `None` if it was refused, over budget,
+    /// trapped, crashed or tampered. A crash, tampering or a worker cut off at the
+    /// wall-time deadline disables the runner. Panics that unwind into this frame count
+    /// as traps: those of an in-process `EngineStrategy` and of the host side of a call
+    /// (VM or pipe handling). A native library's panic or fault cannot unwind across its
+    /// C ABI and aborts the process; only `Runtime::Subprocess` contains it.
+    fn metered<T>(&self, call: StrategyCall, f: impl FnOnce() -> Result<T, CallFailure>) -> Option<T> {
+        if self.disabled.load(Ordering::Relaxed) {
+            return None;
+        }
+        let started = self.budget.wall_time.map(|limit| (Instant::now(), limit));
+        let timed = self.profiling.then(Instant::now);
+        let result = catch_unwind(AssertUnwindSafe(f)).unwrap_or(Err(CallFailure::Trapped));
+        if let Some(t) = timed {
+            let elapsed = t.elapsed();
+            let mut timings = self.timings.lock().unwrap();
+            match timings.iter_mut().find(|(c, _)| *c == call) {
+                Some((_, timing)) => timing.record(elapsed),
+                None => timings.push((call, Timing { count: 1, total: elapsed })),
+            }
+        }
+        if self.debug_log {
+            self.collect_log(call);
+        }
+        if result.as_ref().err() == Some(&CallFailure::Tampered) {
+            self.tampers.lock().unwrap().push((self.step.load(Ordering::Relaxed), call));
+            self.disabled.store(true, Ordering::Relaxed);
+            return None;
+        }
+        if let Err(failure @ (CallFailure::Trapped | CallFailure::Crashed)) = result {
+            self.faults.lock().unwrap().push((self.step.load(Ordering::Relaxed), call));
+            if failure == CallFailure::Crashed {
+                self.disabled.store(true, Ordering::Relaxed);
+            }
+            return None;
+        }
+        if result.as_ref().err() == Some(&CallFailure::TimedOut) {
+            self.overruns.lock().unwrap().push((self.step.load(Ordering::Relaxed), call));
+            self.disabled.store(true, Ordering::Relaxed);
+            return None;
+        }
+        let over_time = started.is_some_and(|(t, limit)| t.elapsed() > limit);
+        if over_time || result.as_ref().err() == Some(&CallFailure::OverBudget) {
+            let mut overruns = self.overruns.lock().unwrap();
+            overruns.push((self.step.load(Ordering::Relaxed), call));
+            if let BudgetPenalty::Disable { strikes } = self.budget.penalty {
+                if overruns.len() >= strikes as usize {
+                    self.disabled.store(true, Ordering::Relaxed);
+                }
+            }
+            return None;
+        }
+        result.ok()
+    }
+
+    /// Move whatever the strategy logged during `call` into `logs`.
+    fn collect_log(&self, call: StrategyCall) {
+        let Exec::Wire(backend) = &self.exec else { return };
+        let raw = backend.drain_log();
+        if raw.is_empty() {
+            return;
+        }
+        let step = self.step.load(Ordering::Relaxed);
+        let mut logs = self.logs.lock().unwrap();
+        for line in String::from_utf8_lossy(&raw).lines().filter(|l| !l.is_empty()) {
+            if logs.len() >= MAX_LOG_LINES {
+                break;
+            }
+            logs.push((step, call, line.to_string()));
+        }
+    }
+
+    /// Run a storage-mutating hook, rolling storage back if the call is discarded.
+    fn hook(
+        &self,
+        call: StrategyCall,
+        storage: &mut [u8; STORAGE_SIZE],
+        f: impl FnOnce(&mut [u8; STORAGE_SIZE]) -> Result<(), CallFailure>,
+    ) {
+        let before = *storage;
+        if self.metered(call, || f(storage)).is_none() {
+            *storage = before;
+        }
+    }
+
+    /// Call compute_swap. Builds the wire payload inline for compiled strategies. A quote
+    /// that would drain the output reserve is handled by the runner's `QuoteGuard`.
     pub fn compute_swap(
         &self,
         is_buy: bool,
@@ -55,15 +826,86 @@ impl StrategyRunner {
         reserve_y: u64,
         storage: &[u8; STORAGE_SIZE],
     ) -> u64 {
-        // Wire layout: [tag(1), input(8), rx(8), ry(8), storage(1024)] = 1049 bytes
-        let mut buf = [0u8; 1 + 8 + 8 + 8 + STORAGE_SIZE];
-        buf[0] = if is_buy { TAG_SWAP_BUY } else { TAG_SWAP_SELL };
-        buf[1..9].copy_from_slice(&input.to_le_bytes());
-        buf[9..17].copy_from_slice(&reserve_x.to_le_bytes());
-        buf[17..25].copy_from_slice(&reserve_y.to_le_bytes());
-        buf[25..25 + STORAGE_SIZE].copy_from_slice(storage);
+        let output = self.compute_swap_unguarded(is_buy, input, reserve_x, reserve_y, storage);
+        self.guard_quote(StrategyCall::ComputeSwap, output, if is_buy { reserve_x } else { reserve_y })
+    }
+
+    fn compute_swap_unguarded(
+        &self,
+        is_buy: bool,
+        input: u64,
+        reserve_x: u64,
+        reserve_y: u64,
+        storage: &[u8; STORAGE_SIZE],
+    ) -> u64 {
+        let backend = match &self.exec {
+            Exec::Wire(backend) => backend,
+            Exec::InProcess(s) => {
+                return self.metered(StrategyCall::ComputeSwap, || {
+                    Ok(s.compute_swap(is_buy, input, reserve_x, reserve_y, storage))
+                })
+                .unwrap_or(0);
+            }
+        };
+        let mut buf = [0u8; wire::swap::len(ABI_VERSION)];
+        let len = wire::encode_compute_swap(is_buy, input, reserve_x, reserve_y, storage, self.metadata.payload_version(), &mut buf);
 
-        unsafe { (self.compute_swap)(buf.as_ptr(), buf.len()) }
+        self.metered(StrategyCall::ComputeSwap, || backend.compute_swap(&buf[..len])).unwrap_or(0)
+    }
+
+    /// Quote several input sizes against the same state in one strategy call (through
+    /// `__prop_amm_compute_swap_batch` when exported). The batch is metered as a single
+    /// call; if it is refused, every size quotes 0.
+    pub fn compute_swap_batch(
+        &self,
+        is_buy: bool,
+        inputs: &[u64],
+        reserve_x: u64,
+        reserve_y: u64,
+        storage: &[u8; STORAGE_SIZE],
+    ) -> Vec<u64> {
+        let mut outputs = vec![0u64; inputs.len()];
+        let quoted = match &self.exec {
+            Exec::InProcess(s) => self.metered(StrategyCall::ComputeSwapBatch, || {
+                s.compute_swap_batch(is_buy, inputs, reserve_x, reserve_y, storage, &mut outputs);
+                Ok(())
+            }),
+            Exec::Wire(backend) => {
+                let mut buf = [0u8; wire::swap::len(ABI_VERSION)];
+                let len = wire::encode_compute_swap(is_buy, 0, reserve_x, reserve_y, storage, self.metadata.payload_version(), &mut buf);
+                self.metered(StrategyCall::ComputeSwapBatch, || backend.compute_swap_batch(&buf[..len], inputs, &mut outputs))
+            }
+        };
+        if quoted.is_none() {
+            outputs.fill(0);
+        }
+        let reserve_out = if is_buy { reserve_x } else { reserve_y };
+        for out in &mut outputs {
+            *out = self.guard_quote(StrategyCall::ComputeSwapBatch, *out, reserve_out);
+        }
+        outputs
+    }
+
+    /// Send `compute_swap` malformed payloads: every truncation of a valid one, then
+    /// `rounds` random byte strings of the valid length (drawn from `seed`). Quotes are
+    /// discarded; traps and crashes are recorded as faults like in any other call.
+    /// Returns the number of payloads sent, or `None` for in-process strategies, which
+    /// take no payload.
+    pub fn fuzz_compute_swap(&self, rounds: usize, seed: u64) -> Option<usize> {
+        use rand::{Rng, SeedableRng};
+
+        let Exec::Wire(backend) = &self.exec else { return None };
+        let mut valid = [0u8; wire::swap::len(ABI_VERSION)];
+        let storage = [0u8; STORAGE_SIZE];
+        let len = wire::encode_compute_swap(true, 1_000_000_000, 100_000_000_000, 10_000_000_000_000, &storage, self.metadata.payload_version(), &mut valid);
+        let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(seed);
+        let random = (0..rounds).map(|_| (0..len).map(|_| rng.gen::<u8>()).collect::<Vec<u8>>());
+        let mut sent = 0;
+        for payload in (0..len).map(|n| valid[..n].to_vec()).chain(random) {
+            self.metered(StrategyCall::ComputeSwap, || backend.compute_swap(&payload));
+            sent += 1;
+        }
+        Some(sent)
     }
 
     /// Call after_swap with the enriched payload. Storage may be mutated.
@@ -72,11 +914,21 @@ impl StrategyRunner {
         payload: &AfterSwapPayload,
         storage: &mut [u8; STORAGE_SIZE],
     ) {
-        // Serialize AfterSwapPayload to bytes.  We use a manual packed layout to match
-        // what wincode/pinocchio strategies expect at each byte offset.
-        let mut buf = vec![0u8; std::mem::size_of::<AfterSwapPayload>()];
-        encode_after_swap_payload(payload, storage, &mut buf);
-        unsafe { (self.after_swap)(buf.as_ptr(), buf.len(), storage.as_mut_ptr()) }
+        let backend = match &self.exec {
+            Exec::Wire(backend) => backend,
+            Exec::InProcess(s) => {
+                return self.hook(StrategyCall::AfterSwap, storage, |st| {
+                    s.after_swap(payload, st);
+                    Ok(())
+                });
+            }
+        };
+        // Hand-encoded (`wire`) so every field sits at its documented offset whatever
+        // Rust's struct layout, in the layout the strategy was built against.
+        let version = self.metadata.payload_version();
+        let mut buf = vec![0u8; wire::after_swap::len(version)];
+        wire::encode_after_swap(payload, storage, version, &mut buf);
+        self.hook(StrategyCall::AfterSwap, storage, |st| backend.after_swap(&buf, st))
     }
 
     /// Call the epoch boundary hook. Storage may be mutated.
@@ -85,77 +937,1309 @@ impl StrategyRunner {
         payload: &EpochBoundaryPayload,
         storage: &mut [u8; STORAGE_SIZE],
     ) {
-        let mut buf = vec![0u8; std::mem::size_of::<EpochBoundaryPayload>()];
-        encode_epoch_boundary_payload(payload, storage, &mut buf);
-        unsafe { (self.after_swap)(buf.as_ptr(), buf.len(), storage.as_mut_ptr()) }
-    }
-}
-
-// ─── Payload Serializers ──────────────────────────────────────────────────────
-// We hand-encode to guarantee the exact byte offsets documented in types.rs,
-// regardless of Rust's struct layout decisions.
-
-fn write_u8(buf: &mut [u8], offset: &mut usize, v: u8) {
-    buf[*offset] = v;
-    *offset += 1;
-}
-fn write_u32(buf: &mut [u8], offset: &mut usize, v: u32) {
-    buf[*offset..*offset+4].copy_from_slice(&v.to_le_bytes());
-    *offset += 4;
-}
-fn write_u64(buf: &mut [u8], offset: &mut usize, v: u64) {
-    buf[*offset..*offset+8].copy_from_slice(&v.to_le_bytes());
-    *offset += 8;
-}
-fn write_f32(buf: &mut [u8], offset: &mut usize, v: f32) {
-    buf[*offset..*offset+4].copy_from_slice(&v.to_le_bytes());
-    *offset += 4;
-}
-fn write_f64(buf: &mut [u8], offset: &mut usize, v: f64) {
-    buf[*offset..*offset+8].copy_from_slice(&v.to_le_bytes());
-    *offset += 8;
-}
-
-fn encode_after_swap_payload(p: &AfterSwapPayload, storage: &[u8; STORAGE_SIZE], buf: &mut Vec<u8>) {
-    // Ensure capacity: 92 header + 1024 storage = 1116 bytes
-    buf.resize(92 + STORAGE_SIZE, 0);
-    let mut off = 0;
-
-    write_u8(buf, &mut off, p.tag);                 //  0  tag
-    write_u8(buf, &mut off, p.side);                //  1  side
-    write_u64(buf, &mut off, p.input_amount);       //  2  input_amount
-    write_u64(buf, &mut off, p.output_amount);      // 10  output_amount
-    write_u64(buf, &mut off, p.reserve_x);          // 18  reserve_x
-    write_u64(buf, &mut off, p.reserve_y);          // 26  reserve_y
-    write_u64(buf, &mut off, p.sim_step);           // 34  sim_step
-    write_u32(buf, &mut off, p.epoch_step);         // 42  epoch_step
-    write_u32(buf, &mut off, p.epoch_number);       // 46  epoch_number
-    write_u8(buf, &mut off, p.n_strategies);        // 50  n_strategies
-    write_u8(buf, &mut off, p.strategy_index);      // 51  strategy_index
-    write_f32(buf, &mut off, p.flow_captured);      // 52  flow_captured
-    write_f32(buf, &mut off, p.capital_weight);     // 56  capital_weight
-    let competing_spot_prices = p.competing_spot_prices;
-    for sp in competing_spot_prices {               // 60..92  competing_spot_prices[8]
-        write_f32(buf, &mut off, sp);
-    }
-    // 92: storage
-    buf[92..92 + STORAGE_SIZE].copy_from_slice(storage);
-}
-
-fn encode_epoch_boundary_payload(p: &EpochBoundaryPayload, storage: &[u8; STORAGE_SIZE], buf: &mut Vec<u8>) {
-    // 41 header bytes + 1024 storage
-    buf.resize(41 + STORAGE_SIZE, 0);
-    let mut off = 0;
-
-    write_u8(buf, &mut off, TAG_EPOCH_BOUNDARY);    //  0  tag
-    write_u32(buf, &mut off, p.epoch_number);       //  1  epoch_number
-    write_u64(buf, &mut off, p.new_reserve_x);      //  5  new_reserve_x
-    write_u64(buf, &mut off, p.new_reserve_y);      // 13  new_reserve_y
-    write_f64(buf, &mut off, p.epoch_edge);         // 21  epoch_edge
-    write_f64(buf, &mut off, p.cumulative_edge);    // 29  cumulative_edge
-    write_f32(buf, &mut off, p.capital_weight);     // 37  capital_weight
-    // 41: storage
-    buf[41..41 + STORAGE_SIZE].copy_from_slice(storage);
+        let backend = match &self.exec {
+            Exec::Wire(backend) => backend,
+            Exec::InProcess(s) => {
+                return self.hook(StrategyCall::EpochBoundary, storage, |st| {
+                    s.epoch_boundary(payload, st);
+                    Ok(())
+                });
+            }
+        };
+        let version = self.metadata.payload_version();
+        let mut buf = vec![0u8; wire::epoch_boundary::len(version)];
+        wire::encode_epoch_boundary(payload, storage, version, &mut buf);
+        self.hook(StrategyCall::EpochBoundary, storage, |st| backend.after_swap(&buf, st))
+    }
+
+    /// Call the shock-news hook (one step before a price jump). Storage may be mutated.
+    pub fn shock_news(
+        &self,
+        payload: &ShockNewsPayload,
+        storage: &mut [u8; STORAGE_SIZE],
+    ) {
+        let backend = match &self.exec {
+            Exec::Wire(backend) => backend,
+            Exec::InProcess(s) => {
+                return self.hook(StrategyCall::ShockNews, storage, |st| {
+                    s.shock_news(payload, st);
+                    Ok(())
+                });
+            }
+        };
+        let version = self.metadata.payload_version();
+        let mut buf = vec![0u8; wire::shock_news::len(version)];
+        wire::encode_shock_news(payload, storage, version, &mut buf);
+        self.hook(StrategyCall::ShockNews, storage, |st| backend.after_swap(&buf, st))
+    }
+
+    /// Call the pool-wide step digest hook. Storage may be mutated.
+    pub fn step_digest(
+        &self,
+        payload: &StepDigestPayload,
+        storage: &mut [u8; STORAGE_SIZE],
+    ) {
+        let backend = match &self.exec {
+            Exec::Wire(backend) => backend,
+            Exec::InProcess(s) => {
+                return self.hook(StrategyCall::StepDigest, storage, |st| {
+                    s.step_digest(payload, st);
+                    Ok(())
+                });
+            }
+        };
+        let version = self.metadata.payload_version();
+        let mut buf = vec![0u8; wire::step_digest::len(version)];
+        wire::encode_step_digest(payload, storage, version, &mut buf);
+        self.hook(StrategyCall::StepDigest, storage, |st| backend.after_swap(&buf, st))
+    }
+}
+
+// ─── Load-time audit ──────────────────────────────────────────────────────────
+
+/// Dynamic imports a strategy audited for tournament play may not link against, by
+/// category. Plain `open`/`read`/`write` are not listed: the Rust standard library
+/// imports them for panic and backtrace output in every build, so this is a net for
+/// deliberate capabilities, not a sandbox.
+pub const DISALLOWED_IMPORTS: &[(&str, &[&str])] = &[
+    ("network", &[
+        "socket", "connect", "bind", "listen", "accept", "accept4", "getaddrinfo", "gethostbyname",
+        "send", "sendto", "sendmsg", "recv", "recvfrom", "recvmsg",
+    ]),
+    ("filesystem", &[
+        "fopen", "fopen64", "openat", "openat64", "creat", "creat64", "mkdir", "mkdirat", "rmdir",
+        "unlink", "unlinkat", "rename", "renameat", "opendir", "fdopendir", "readdir", "readdir64",
+        "ftruncate", "ftruncate64",
+    ]),
+    ("threads", &["pthread_create", "clone", "clone3", "fork", "vfork", "execve", "posix_spawn"]),
+    ("time", &["clock_gettime", "gettimeofday", "time", "clock", "times", "mach_absolute_time"]),
+];
+
+/// Largest writable static footprint (`.data`, `.bss` and TLS) an audited strategy may
+/// carry; state belongs in the engine-managed storage.
+pub const MAX_STATIC_BYTES: u64 = 1 << 20;
+
+/// What `audit_artifact` found in a native library.
+#[derive(Clone, Debug, Default)]
+pub struct ArtifactAudit {
+    /// Every undefined dynamic symbol, unversioned
+    pub imports: Vec<String>,
+    /// Writable static data, bytes
+    pub static_bytes: u64,
+    /// One line per disallowed import or oversized static footprint
+    pub violations: Vec<String>,
+}
+
+impl ArtifactAudit {
+    pub fn passes(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+/// Scan a native strategy library's dynamic imports and static allocations against
+/// `DISALLOWED_IMPORTS` and `MAX_STATIC_BYTES`. WASM and SBF artifacts (and remote
+/// manifests) cannot reach these capabilities through the engine's runtimes and audit
+/// clean.
+pub fn audit_artifact(path: &Path) -> Result<ArtifactAudit, Box<dyn std::error::Error>> {
+    use object::{Object, ObjectSection, SectionKind};
+
+    if !matches!(Runtime::for_artifact(path), Runtime::Native) {
+        return Ok(ArtifactAudit::default());
+    }
+    let data = std::fs::read(path)?;
+    let file = object::File::parse(&*data).map_err(|e| format!("{}: not a native library ({e})", path.display()))?;
+
+    let macho = matches!(file.format(), object::BinaryFormat::MachO);
+    let mut imports: Vec<String> = file
+        .imports()?
+        .iter()
+        .map(|i| {
+            let name = String::from_utf8_lossy(i.name());
+            let name = name.split('@').next().unwrap_or_default();
+            // Mach-O symbols carry a leading underscore
+            if macho { name.strip_prefix('_').unwrap_or(name) } else { name }.to_string()
+        })
+        .collect();
+    imports.sort();
+    imports.dedup();
+
+    let static_bytes = file
+        .sections()
+        .filter(|s| matches!(s.kind(), SectionKind::Data | SectionKind::UninitializedData | SectionKind::Tls | SectionKind::UninitializedTls))
+        .map(|s| s.size())
+        .sum();
+
+    let mut violations: Vec<String> = DISALLOWED_IMPORTS
+        .iter()
+        .flat_map(|(category, symbols)| {
+            symbols.iter().filter(|s| imports.binary_search_by(|i| i.as_str().cmp(s)).is_ok()).map(move |s| format!("{category} import `{s}`"))
+        })
+        .collect();
+    if static_bytes > MAX_STATIC_BYTES {
+        violations.push(format!("{static_bytes} bytes of static data (limit {MAX_STATIC_BYTES})"));
+    }
+    Ok(ArtifactAudit { imports, static_bytes, violations })
+}
+
+// ─── Determinism check ────────────────────────────────────────────────────────
+
+/// A `compute_swap` quote that came back different for an identical payload.
+#[derive(Clone, Debug)]
+pub struct Nondeterminism {
+    pub is_buy: bool,
+    pub input: u64,
+    pub reserve_x: u64,
+    pub reserve_y: u64,
+    /// Quote from the first pass over the probe grid
+    pub expected: u64,
+    pub got: u64,
+    /// How the differing quote was obtained (repeated call, reversed order, fresh instance)
+    pub pass: &'static str,
+}
+
+impl std::fmt::Display for Nondeterminism {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "compute_swap({}, input={}, rx={}, ry={}) returned {} then {} on a {}; quotes must be a pure \
+             function of the payload (no clocks, RNG or global state)",
+            if self.is_buy { "buy" } else { "sell" },
+            self.input,
+            self.reserve_x,
+            self.reserve_y,
+            self.expected,
+            self.got,
+            self.pass,
+        )
+    }
+}
+
+impl std::error::Error for Nondeterminism {}
+
+/// Identical-payload calls per probe in the repeated pass.
+const DETERMINISM_REPEATS: usize = 3;
+
+/// Check that `runner` quotes as a pure function of the payload. A grid of swap probes
+/// (both directions, several sizes, reserve states and storage contents) is quoted once,
+/// then again with each probe repeated back to back, then in reverse order, and finally
+/// on `fresh` — a second instance of the same strategy that shares no state with the
+/// first (for native libraries, load it with `Runtime::Subprocess`, since a second
+/// `dlopen` in this process returns the same globals). Returns the first mismatch.
+pub fn check_determinism(runner: &StrategyRunner, fresh: &StrategyRunner) -> Result<(), Nondeterminism> {
+    let mut patterned = [0u8; STORAGE_SIZE];
+    for (i, b) in patterned.iter_mut().enumerate() {
+        *b = (i * 31 % 251) as u8;
+    }
+    let storages = [[0u8; STORAGE_SIZE], patterned];
+    let reserves = [(100, 10_000), (37, 4_100), (2_500, 240_000)];
+    let mut probes = Vec::new();
+    for storage in &storages {
+        for &(rx, ry) in &reserves {
+            for is_buy in [true, false] {
+                for frac in [1e-6, 1e-3, 0.02, 0.2] {
+                    let reserve_in: u64 = if is_buy { ry } else { rx };
+                    let input = (reserve_in as f64 * frac * 1e9) as u64;
+                    probes.push((is_buy, input.max(1), rx * 1_000_000_000, ry * 1_000_000_000, storage));
+                }
+            }
+        }
+    }
+
+    let quote = |r: &StrategyRunner, &(is_buy, input, rx, ry, storage): &(bool, u64, u64, u64, &[u8; STORAGE_SIZE])| {
+        r.compute_swap(is_buy, input, rx, ry, storage)
+    };
+    let expected: Vec<u64> = probes.iter().map(|p| quote(runner, p)).collect();
+    let mismatch = |k: usize, got: u64, pass: &'static str| {
+        let (is_buy, input, reserve_x, reserve_y, _) = probes[k];
+        Nondeterminism { is_buy, input, reserve_x, reserve_y, expected: expected[k], got, pass }
+    };
+
+    // Give clock-driven quotes a chance to drift before the second pass
+    std::thread::sleep(Duration::from_millis(10));
+    for (k, p) in probes.iter().enumerate() {
+        for _ in 0..DETERMINISM_REPEATS {
+            let got = quote(runner, p);
+            if got != expected[k] {
+                return Err(mismatch(k, got, "repeated call"));
+            }
+        }
+    }
+    for (k, p) in probes.iter().enumerate().rev() {
+        let got = quote(runner, p);
+        if got != expected[k] {
+            return Err(mismatch(k, got, "call in reversed order"));
+        }
+    }
+    for (k, p) in probes.iter().enumerate() {
+        let got = quote(fresh, p);
+        if got != expected[k] {
+            return Err(mismatch(k, got, "fresh instance"));
+        }
+    }
+    Ok(())
+}
+
+// ─── Subprocess backend ───────────────────────────────────────────────────────
+
+/// A strategy served outside the engine over the worker protocol, in any language.
+///
+/// The protocol is length-prefixed frames (`len: u32 LE`, then `len` bytes) in both
+/// directions. The strategy first sends three frames: its name, its model (empty if
+/// none) and `key=value` metadata lines, including `abi_version=N` to receive ABI vN
+/// payloads (the oldest supported layout otherwise). It then answers each request frame `[op, ..]`
+/// with `[status, ..]`: 0 success, 1 over budget, 2 failed call, 3 tampering (hardened only):
+///
+/// | op | request after the op byte | successful response after the status byte |
+/// |----|---------------------------|-------------------------------------------|
+/// | 0 compute_swap | swap payload | quote, u64 LE |
+/// | 1 after_swap | storage (1024), hook payload | updated storage (1024) |
+/// | 2 compute_swap_batch | n: u32 LE, n inputs (u64 LE), swap payload | n quotes (u64 LE) |
+/// | 3 set_hardened | on: u8 | nothing |
+///
+/// Payloads are the wire payloads compiled strategies receive (see `types.rs`). Frames
+/// over 1 MiB are refused, and a strategy that has not sent its handshake or a reply
+/// after 60 s (or its wall-time budget) is cut off and treated as crashed.
+/// `scripts/remote_strategy.py` is a reference implementation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RemoteEndpoint {
+    /// Spawn `argv` and talk over its stdin/stdout
+    Command(Vec<String>),
+    /// Connect to a strategy server at `host:port`; each runner opens its own connection
+    Tcp(String),
+}
+
+impl RemoteEndpoint {
+    /// Parse a `.remote` manifest: `command <program> [args..]` or `tcp <host:port>`,
+    /// whitespace-separated on the first non-empty, non-`#` line.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let line = spec
+            .lines()
+            .map(str::trim)
+            .find(|l| !l.is_empty() && !l.starts_with('#'))
+            .ok_or("empty remote manifest")?;
+        let mut words = line.split_whitespace();
+        match (words.next(), words.collect::<Vec<_>>()) {
+            (Some("command"), argv) if !argv.is_empty() => Ok(Self::Command(argv.into_iter().map(str::to_string).collect())),
+            (Some("tcp"), addr) if addr.len() == 1 => Ok(Self::Tcp(addr[0].to_string())),
+            _ => Err(format!("expected `command <program> [args..]` or `tcp <host:port>`, got `{line}`")),
+        }
+    }
+}
+
+/// Argument a worker process is started with, ahead of the artifact path. Binaries that
+/// load strategies with `Runtime::Subprocess` must hand `[WORKER_ARG, artifact]` to
+/// `serve_worker`; the CLI does. `PROP_AMM_WORKER` overrides the worker executable,
+/// which is otherwise the current one.
+pub const WORKER_ARG: &str = "__strategy-worker";
+
+/// Serve one strategy over stdin/stdout until the host closes the pipe. The artifact
+/// loads in-process here, in whatever runtime its extension implies, so if it crashes
+/// only this worker dies.
+pub fn serve_worker(artifact: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let mut runner = StrategyRunner::load(artifact)?;
+    let Exec::Wire(backend) = &mut runner.exec else { unreachable!("loaded runners are compiled") };
+    let mut input = std::io::BufReader::new(std::io::stdin().lock());
+    let mut output = std::io::BufWriter::new(std::io::stdout().lock());
+    subprocess::serve(&mut **backend, &runner.name, &runner.metadata, &mut input, &mut output)
+}
+
+mod subprocess {
+    //! The worker protocol (documented on `RemoteEndpoint`), over a worker process's
+    //! pipes or a TCP socket. Hardened mode is a worker-side setting, so tampering is
+    //! detected in the worker.
+
+    use std::io::{self, BufRead, BufReader, Read, Write};
+    use std::net::{Shutdown, TcpStream};
+    use std::path::{Path, PathBuf};
+    use std::process::{Child, Command, Stdio};
+    use std::sync::{Arc, Condvar, Mutex};
+    use std::time::{Duration, Instant};
+
+    use super::{Backend, CallFailure, RemoteEndpoint, StrategyMetadata, WORKER_ARG};
+    use crate::types::STORAGE_SIZE;
+
+    const OP_COMPUTE_SWAP: u8 = 0;
+    const OP_AFTER_SWAP: u8 = 1;
+    /// `[op, n: u32 LE, inputs (8n), payload..]` → `[status, outputs (8n)]`
+    const OP_COMPUTE_SWAP_BATCH: u8 = 2;
+    /// `[op, on]` → `[status]`: toggle hardened mode in the worker
+    const OP_SET_HARDENED: u8 = 3;
+
+    const STATUS_OK: u8 = 0;
+    const STATUS_OVER_BUDGET: u8 = 1;
+    const STATUS_TRAPPED: u8 = 2;
+    const STATUS_TAMPERED: u8 = 3;
+
+    /// Largest frame either side accepts. Real frames are a payload plus storage or a
+    /// quote grid, far below this; a bigger length prefix means the peer is broken.
+    const MAX_FRAME: usize = 1 << 20;
+    /// How long the host waits for the handshake or a reply when no wall-time budget is
+    /// set, before it treats the worker as hung and cuts it off
+    const REPLY_TIMEOUT: Duration = Duration::from_secs(60);
+
+    /// Send a frame in a single write.
+    fn write_frame(w: &mut impl Write, parts: &[&[u8]]) -> io::Result<()> {
+        let len: usize = parts.iter().map(|p| p.len()).sum();
+        let mut frame = Vec::with_capacity(4 + len);
+        frame.extend_from_slice(&(len as u32).to_le_bytes());
+        for part in parts {
+            frame.extend_from_slice(part);
+        }
+        w.write_all(&frame)?;
+        w.flush()
+    }
+
+    fn read_frame(r: &mut impl Read, buf: &mut Vec<u8>) -> io::Result<()> {
+        let mut len = [0u8; 4];
+        r.read_exact(&mut len)?;
+        let len = u32::from_le_bytes(len) as usize;
+        if len > MAX_FRAME {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{len}-byte frame exceeds {MAX_FRAME}")));
+        }
+        buf.resize(len, 0);
+        r.read_exact(buf)
+    }
+
+    fn status(failure: CallFailure) -> u8 {
+        match failure {
+            CallFailure::OverBudget | CallFailure::TimedOut => STATUS_OVER_BUDGET,
+            CallFailure::Trapped | CallFailure::Crashed => STATUS_TRAPPED,
+            CallFailure::Tampered => STATUS_TAMPERED,
+        }
+    }
+
+    /// Worker side of the protocol.
+    pub(super) fn serve(
+        backend: &mut dyn Backend,
+        name: &str,
+        metadata: &StrategyMetadata,
+        input: &mut impl BufRead,
+        output: &mut impl Write,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        write_frame(output, &[name.as_bytes()])?;
+        write_frame(output, &[metadata.model.as_deref().unwrap_or("").as_bytes()])?;
+        write_frame(output, &[metadata.entries_text().as_bytes()])?;
+        let mut request = Vec::new();
+        loop {
+            match read_frame(input, &mut request) {
+                Ok(()) => {}
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+                Err(e) => return Err(e.into()),
+            }
+            match request.first().copied() {
+                Some(OP_COMPUTE_SWAP) => match backend.compute_swap(&request[1..]) {
+                    Ok(out) => write_frame(output, &[&[STATUS_OK], &out.to_le_bytes()])?,
+                    Err(f) => write_frame(output, &[&[status(f)]])?,
+                },
+                Some(OP_AFTER_SWAP) if request.len() > STORAGE_SIZE => {
+                    let mut storage = [0u8; STORAGE_SIZE];
+                    storage.copy_from_slice(&request[1..1 + STORAGE_SIZE]);
+                    match backend.after_swap(&request[1 + STORAGE_SIZE..], &mut storage) {
+                        Ok(()) => write_frame(output, &[&[STATUS_OK], &storage])?,
+                        Err(f) => write_frame(output, &[&[status(f)]])?,
+                    }
+                }
+                Some(OP_COMPUTE_SWAP_BATCH) if request.len() >= 5 => {
+                    let n = u32::from_le_bytes(request[1..5].try_into()?) as usize;
+                    let Some(raw) = request.get(5..5 + 8 * n) else { return Err("malformed worker request".into()) };
+                    let inputs: Vec<u64> = raw.chunks_exact(8).map(|c| u64::from_le_bytes(c.try_into().unwrap())).collect();
+                    let mut outputs = vec![0u64; n];
+                    match backend.compute_swap_batch(&request[5 + 8 * n..], &inputs, &mut outputs) {
+                        Ok(()) => {
+                            let bytes: Vec<u8> = outputs.iter().flat_map(|o| o.to_le_bytes()).collect();
+                            write_frame(output, &[&[STATUS_OK], &bytes])?
+                        }
+                        Err(f) => write_frame(output, &[&[status(f)]])?,
+                    }
+                }
+                Some(OP_SET_HARDENED) if request.len() == 2 => {
+                    backend.set_hardened(request[1] != 0);
+                    write_frame(output, &[&[STATUS_OK]])?
+                }
+                _ => return Err("malformed worker request".into()),
+            }
+        }
+    }
+
+    /// How a worker is stopped from the host side: kill the process we started, or shut
+    /// the socket to a TCP peer. Either way a read blocked on it returns.
+    enum Cutoff {
+        Process(Arc<Mutex<Child>>),
+        Socket(TcpStream),
+    }
+
+    impl Cutoff {
+        fn cut(&self) {
+            match self {
+                Cutoff::Process(child) => {
+                    let _ = child.lock().unwrap().kill();
+                }
+                Cutoff::Socket(stream) => {
+                    let _ = stream.shutdown(Shutdown::Both);
+                }
+            }
+        }
+
+        /// Cut the worker off and reap the process.
+        fn close(&self) {
+            self.cut();
+            if let Cutoff::Process(child) = self {
+                let _ = child.lock().unwrap().wait();
+            }
+        }
+    }
+
+    /// Deadline the watchdog enforces, and whether it had to.
+    #[derive(Default)]
+    struct Watch {
+        deadline: Option<Instant>,
+        fired: bool,
+        closed: bool,
+    }
+
+    /// A thread that cuts a worker off when a call outlives its deadline, so the host
+    /// is never stuck on a strategy that spins or wedges.
+    struct Watchdog {
+        shared: Arc<(Mutex<Watch>, Condvar)>,
+    }
+
+    impl Watchdog {
+        fn start(cutoff: Arc<Cutoff>) -> Self {
+            let shared = Arc::new((Mutex::new(Watch::default()), Condvar::new()));
+            let watched = Arc::clone(&shared);
+            std::thread::spawn(move || {
+                let (lock, wake) = &*watched;
+                let mut watch = lock.lock().unwrap();
+                while !watch.closed {
+                    watch = match watch.deadline {
+                        Some(deadline) if Instant::now() >= deadline => {
+                            cutoff.cut();
+                            watch.deadline = None;
+                            watch.fired = true;
+                            watch
+                        }
+                        Some(deadline) => wake.wait_timeout(watch, deadline - Instant::now()).unwrap().0,
+                        None => wake.wait(watch).unwrap(),
+                    };
+                }
+            });
+            Self { shared }
+        }
+
+        fn arm(&self, limit: Duration) {
+            let (lock, wake) = &*self.shared;
+            lock.lock().unwrap().deadline = Some(Instant::now() + limit);
+            wake.notify_one();
+        }
+
+        /// Stand down after a call; whether the worker was cut off during it.
+        fn disarm(&self) -> bool {
+            let mut watch = self.shared.0.lock().unwrap();
+            watch.deadline = None;
+            std::mem::take(&mut watch.fired)
+        }
+    }
+
+    impl Drop for Watchdog {
+        fn drop(&mut self) {
+            let (lock, wake) = &*self.shared;
+            lock.lock().unwrap().closed = true;
+            wake.notify_one();
+        }
+    }
+
+    struct Worker {
+        cutoff: Arc<Cutoff>,
+        watchdog: Watchdog,
+        /// Wall-time limit per call (`Backend::set_deadline`)
+        deadline: Option<Duration>,
+        writer: Box<dyn Write + Send>,
+        reader: BufReader<Box<dyn Read + Send>>,
+        response: Vec<u8>,
+    }
+
+    impl Worker {
+        /// One request/response round trip; `Crashed` if the worker is gone, answers out
+        /// of protocol or hangs past `REPLY_TIMEOUT`, `TimedOut` if it overran the
+        /// wall-time budget and the watchdog cut it off.
+        fn call(&mut self, request: &[&[u8]]) -> Result<&[u8], CallFailure> {
+            self.watchdog.arm(self.deadline.unwrap_or(REPLY_TIMEOUT));
+            let exchanged = write_frame(&mut self.writer, request).and_then(|()| read_frame(&mut self.reader, &mut self.response));
+            if self.watchdog.disarm() {
+                return Err(if self.deadline.is_some() { CallFailure::TimedOut } else { CallFailure::Crashed });
+            }
+            exchanged.map_err(|_| CallFailure::Crashed)?;
+            match self.response.split_first() {
+                Some((&STATUS_OK, out)) => Ok(out),
+                Some((&STATUS_OVER_BUDGET, _)) => Err(CallFailure::OverBudget),
+                Some((&STATUS_TRAPPED, _)) => Err(CallFailure::Trapped),
+                Some((&STATUS_TAMPERED, _)) => Err(CallFailure::Tampered),
+                _ => Err(CallFailure::Crashed),
+            }
+        }
+    }
+
+    /// A strategy behind the worker protocol: a worker process or a remote peer. Calls
+    /// are serialized over its pipes or socket.
+    pub(super) struct SubprocessBackend {
+        inner: Mutex<Worker>,
+    }
+
+    impl SubprocessBackend {
+        /// Load `path` in a worker process running this binary (or `PROP_AMM_WORKER`).
+        pub(super) fn spawn(path: &Path) -> Result<(Self, String, StrategyMetadata), Box<dyn std::error::Error>> {
+            let exe = match std::env::var_os("PROP_AMM_WORKER") {
+                Some(exe) => PathBuf::from(exe),
+                None => std::env::current_exe()?,
+            };
+            let mut command = Command::new(exe);
+            command.arg(WORKER_ARG).arg(path);
+            Self::start(command)
+                .map_err(|e| format!("{}: worker exited before loading the strategy ({e})", path.display()).into())
+        }
+
+        /// Spawn or connect to a remote strategy; a command runs in `dir` if given.
+        pub(super) fn remote(
+            endpoint: &RemoteEndpoint,
+            dir: Option<&Path>,
+        ) -> Result<(Self, String, StrategyMetadata), Box<dyn std::error::Error>> {
+            match endpoint {
+                RemoteEndpoint::Command(argv) => {
+                    let mut command = Command::new(&argv[0]);
+                    command.args(&argv[1..]);
+                    if let Some(dir) = dir {
+                        command.current_dir(dir);
+                    }
+                    Self::start(command).map_err(|e| format!("remote strategy `{}` failed to start ({e})", argv.join(" ")).into())
+                }
+                RemoteEndpoint::Tcp(addr) => {
+                    let stream = TcpStream::connect(addr).map_err(|e| format!("remote strategy at {addr}: {e}"))?;
+                    stream.set_nodelay(true)?;
+                    let reader: Box<dyn Read + Send> = Box::new(stream.try_clone()?);
+                    let cutoff = Cutoff::Socket(stream.try_clone()?);
+                    Self::handshake(cutoff, Box::new(stream), BufReader::new(reader))
+                        .map_err(|e| format!("remote strategy at {addr} closed before the handshake ({e})").into())
+                }
+            }
+        }
+
+        fn start(mut command: Command) -> io::Result<(Self, String, StrategyMetadata)> {
+            let mut child = command.stdin(Stdio::piped()).stdout(Stdio::piped()).spawn()?;
+            let stdin = child.stdin.take().ok_or_else(|| io::Error::other("worker stdin unavailable"))?;
+            let stdout = child.stdout.take().ok_or_else(|| io::Error::other("worker stdout unavailable"))?;
+            let reader: Box<dyn Read + Send> = Box::new(stdout);
+            Self::handshake(Cutoff::Process(Arc::new(Mutex::new(child))), Box::new(stdin), BufReader::new(reader))
+        }
+
+        /// Read the name, model and metadata frames within `REPLY_TIMEOUT`; the worker is
+        /// cut off on failure.
+        fn handshake(
+            cutoff: Cutoff,
+            writer: Box<dyn Write + Send>,
+            mut reader: BufReader<Box<dyn Read + Send>>,
+        ) -> io::Result<(Self, String, StrategyMetadata)> {
+            let cutoff = Arc::new(cutoff);
+            let watchdog = Watchdog::start(Arc::clone(&cutoff));
+            watchdog.arm(REPLY_TIMEOUT);
+            let (mut name, mut model, mut entries) = (Vec::new(), Vec::new(), Vec::new());
+            let handshake = read_frame(&mut reader, &mut name)
+                .and_then(|()| read_frame(&mut reader, &mut model))
+                .and_then(|()| read_frame(&mut reader, &mut entries));
+            let handshake = match (watchdog.disarm(), handshake) {
+                (true, _) => Err(io::Error::new(io::ErrorKind::TimedOut, "no handshake")),
+                (false, result) => result,
+            };
+            if let Err(e) = handshake {
+                cutoff.close();
+                return Err(e);
+            }
+            let name = String::from_utf8_lossy(&name).to_string();
+            let metadata = StrategyMetadata::from_exports(Some(&model), Some(&entries));
+            let worker = Worker { cutoff, watchdog, deadline: None, writer, reader, response: Vec::new() };
+            Ok((Self { inner: Mutex::new(worker) }, name, metadata))
+        }
+    }
+
+    impl Backend for SubprocessBackend {
+        fn compute_swap(&self, data: &[u8]) -> Result<u64, CallFailure> {
+            let mut worker = self.inner.lock().unwrap();
+            let out = worker.call(&[&[OP_COMPUTE_SWAP], data])?;
+            let out: [u8; 8] = out.try_into().map_err(|_| CallFailure::Crashed)?;
+            Ok(u64::from_le_bytes(out))
+        }
+
+        fn after_swap(&self, data: &[u8], storage: &mut [u8; STORAGE_SIZE]) -> Result<(), CallFailure> {
+            let mut worker = self.inner.lock().unwrap();
+            let out = worker.call(&[&[OP_AFTER_SWAP], &storage[..], data])?;
+            storage.copy_from_slice(out.get(..STORAGE_SIZE).ok_or(CallFailure::Crashed)?);
+            Ok(())
+        }
+
+        fn compute_swap_batch(&self, data: &[u8], inputs: &[u64], outputs: &mut [u64]) -> Result<(), CallFailure> {
+            let raw: Vec<u8> = inputs.iter().flat_map(|i| i.to_le_bytes()).collect();
+            let mut worker = self.inner.lock().unwrap();
+            let out = worker.call(&[&[OP_COMPUTE_SWAP_BATCH], &(inputs.len() as u32).to_le_bytes(), &raw, data])?;
+            if out.len() != 8 * inputs.len() {
+                return Err(CallFailure::Crashed);
+            }
+            for (o, chunk) in outputs.iter_mut().zip(out.chunks_exact(8)) {
+                *o = u64::from_le_bytes(chunk.try_into().unwrap());
+            }
+            Ok(())
+        }
+
+        fn set_hardened(&mut self, on: bool) {
+            // A dead worker surfaces as `Crashed` on the next real call
+            let _ = self.inner.get_mut().unwrap().call(&[&[OP_SET_HARDENED, on as u8]]);
+        }
+
+        fn set_deadline(&mut self, limit: Option<Duration>) {
+            self.inner.get_mut().unwrap().deadline = limit;
+        }
+    }
+
+    impl Drop for SubprocessBackend {
+        fn drop(&mut self) {
+            if let Ok(worker) = self.inner.get_mut() {
+                worker.cutoff.close();
+            }
+        }
+    }
+}
+
+// ─── WASM backend ─────────────────────────────────────────────────────────────
+
+#[cfg(feature = "wasm")]
+mod wasm {
+    use std::path::Path;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use wasmtime::{Config, Engine, Instance, Memory, Module, Store, StoreLimits, StoreLimitsBuilder, Trap, TypedFunc};
+
+    use super::{check_abi_version, Backend, CallFailure, StrategyMetadata, WasmLimits, METADATA_MAX};
+    use crate::types::STORAGE_SIZE;
+
+    /// Host scratch layout inside the page the host grows at load: payload, then storage,
+    /// then the name buffer. The guest allocator never hands out memory it did not grow
+    /// itself, so the page stays the host's.
+    const PAYLOAD_OFF: usize = 0;
+    const STORAGE_OFF: usize = 4096;
+    const NAME_OFF: usize = STORAGE_OFF + STORAGE_SIZE;
+    const NAME_MAX: usize = 128;
+    /// Epoch period while a wall-time deadline is set
+    const TICK: Duration = Duration::from_millis(1);
+
+    struct Guest {
+        store: Store<StoreLimits>,
+        memory: Memory,
+        scratch: usize,
+        compute_swap: TypedFunc<(i32, i32), i64>,
+        after_swap: TypedFunc<(i32, i32, i32), ()>,
+    }
+
+    /// A `wasm32-unknown-unknown` strategy exporting the same three symbols as a native
+    /// library, plus its `memory`. Calls are serialized; each gets fresh fuel, and with
+    /// a wall-time deadline a ticker thread advances the engine epoch so a call still
+    /// running at the deadline is interrupted.
+    pub(super) struct WasmBackend {
+        inner: Mutex<Guest>,
+        fuel_per_call: u64,
+        engine: Engine,
+        /// Epoch ticks each call may run for; 1 with no ticker, which then never expires
+        deadline_ticks: u64,
+        ticker: Option<Arc<AtomicBool>>,
+    }
+
+    impl WasmBackend {
+        pub(super) fn load(path: &Path, limits: WasmLimits) -> Result<(Self, String, StrategyMetadata), Box<dyn std::error::Error>> {
+            let mut config = Config::new();
+            config.consume_fuel(true);
+            config.epoch_interruption(true);
+            let engine = Engine::new(&config)?;
+            let module = Module::from_file(&engine, path)?;
+
+            let mut store = Store::new(&engine, StoreLimitsBuilder::new().memory_size(limits.max_memory_bytes).build());
+            store.limiter(|l| l);
+            store.set_fuel(limits.fuel_per_call)?;
+            store.set_epoch_deadline(1);
+            let instance = Instance::new(&mut store, &module, &[])?;
+
+            let abi_version = match instance.get_typed_func::<(), i32>(&mut store, "__prop_amm_abi_version") {
+                Ok(f) => Some(f.call(&mut store, ())? as u32),
+                Err(_) => None,
+            };
+            let abi_version = check_abi_version(path, abi_version)?;
+
+            let memory = instance.get_memory(&mut store, "memory").ok_or("module exports no `memory`")?;
+            let compute_swap = instance.get_typed_func(&mut store, "__prop_amm_compute_swap")?;
+            let after_swap = instance.get_typed_func(&mut store, "__prop_amm_after_swap")?;
+            let get_name: TypedFunc<(i32, i32), i32> = instance.get_typed_func(&mut store, "__prop_amm_get_name")?;
+
+            let scratch = memory.grow(&mut store, 1)? as usize * 65_536;
+            let len = get_name.call(&mut store, ((scratch + NAME_OFF) as i32, NAME_MAX as i32))?;
+            let mut name_buf = [0u8; NAME_MAX];
+            memory.read(&store, scratch + NAME_OFF, &mut name_buf)?;
+            let name = String::from_utf8_lossy(&name_buf[..(len as usize).min(NAME_MAX)]).to_string();
+
+            // Optional metadata exports write into the same name region
+            let mut read_optional = |export: &str, max_len: usize| {
+                let get: TypedFunc<(i32, i32), i32> = instance.get_typed_func(&mut store, export).ok()?;
+                let len = get.call(&mut store, ((scratch + NAME_OFF) as i32, max_len as i32)).ok()?;
+                let mut buf = vec![0u8; (len.max(0) as usize).min(max_len)];
+                memory.read(&store, scratch + NAME_OFF, &mut buf).ok()?;
+                Some(buf)
+            };
+            let model = read_optional("__prop_amm_get_model", NAME_MAX);
+            let entries = read_optional("__prop_amm_get_metadata", METADATA_MAX);
+            let metadata = StrategyMetadata {
+                abi_version: Some(abi_version),
+                ..StrategyMetadata::from_exports(model.as_deref(), entries.as_deref())
+            };
+
+            let inner = Guest { store, memory, scratch, compute_swap, after_swap };
+            let backend = Self {
+                inner: Mutex::new(inner),
+                fuel_per_call: limits.fuel_per_call,
+                engine,
+                deadline_ticks: 1,
+                ticker: None,
+            };
+            Ok((backend, name, metadata))
+        }
+
+        /// Refill fuel and restart the epoch deadline for the next call.
+        fn refill(&self, store: &mut Store<StoreLimits>) -> bool {
+            store.set_epoch_deadline(self.deadline_ticks);
+            store.set_fuel(self.fuel_per_call).is_ok()
+        }
+    }
+
+    impl Drop for WasmBackend {
+        fn drop(&mut self) {
+            if let Some(stop) = self.ticker.take() {
+                stop.store(true, Ordering::Relaxed);
+            }
+        }
+    }
+
+    impl Backend for WasmBackend {
+        fn compute_swap(&self, data: &[u8]) -> Result<u64, CallFailure> {
+            let mut guard = self.inner.lock().unwrap();
+            let i = &mut *guard;
+            let ptr = i.scratch + PAYLOAD_OFF;
+            if data.len() > STORAGE_OFF || !self.refill(&mut i.store) || i.memory.write(&mut i.store, ptr, data).is_err() {
+                return Err(CallFailure::Trapped);
+            }
+            let out = i.compute_swap.call(&mut i.store, (ptr as i32, data.len() as i32)).map_err(failure)?;
+            Ok(out as u64)
+        }
+
+        fn after_swap(&self, data: &[u8], storage: &mut [u8; STORAGE_SIZE]) -> Result<(), CallFailure> {
+            let mut guard = self.inner.lock().unwrap();
+            let i = &mut *guard;
+            let (ptr, storage_ptr) = (i.scratch + PAYLOAD_OFF, i.scratch + STORAGE_OFF);
+            if data.len() > STORAGE_OFF
+                || !self.refill(&mut i.store)
+                || i.memory.write(&mut i.store, ptr, data).is_err()
+                || i.memory.write(&mut i.store, storage_ptr, storage).is_err()
+            {
+                return Err(CallFailure::Trapped);
+            }
+            // A trapped hook (out of fuel, memory, or panicked) leaves storage untouched
+            i.after_swap.call(&mut i.store, (ptr as i32, data.len() as i32, storage_ptr as i32)).map_err(failure)?;
+            i.memory.read(&i.store, storage_ptr, storage).map_err(|_| CallFailure::Trapped)
+        }
+
+        fn set_deadline(&mut self, limit: Option<Duration>) {
+            if let Some(stop) = self.ticker.take() {
+                stop.store(true, Ordering::Relaxed);
+            }
+            self.deadline_ticks = 1;
+            let Some(limit) = limit else { return };
+            self.deadline_ticks = (limit.as_micros() / TICK.as_micros()).max(1) as u64 + 1;
+            let stop = Arc::new(AtomicBool::new(false));
+            let (engine, stopped) = (self.engine.clone(), Arc::clone(&stop));
+            std::thread::spawn(move || {
+                while !stopped.load(Ordering::Relaxed) {
+                    std::thread::sleep(TICK);
+                    engine.increment_epoch();
+                }
+            });
+            self.ticker = Some(stop);
+        }
+    }
+
+    fn failure(err: wasmtime::Error) -> CallFailure {
+        match err.downcast_ref::<Trap>() {
+            Some(Trap::OutOfFuel | Trap::Interrupt) => CallFailure::OverBudget,
+            _ => CallFailure::Trapped,
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        /// A one-page module exporting the strategy ABI, whose `compute_swap` runs `body`
+        /// (raw instructions, without the closing `end`). The other entry points return
+        /// at once and the name is empty.
+        fn module(label: &str, body: &[u8], limits: WasmLimits) -> WasmBackend {
+            fn section(id: u8, bytes: &[u8]) -> Vec<u8> {
+                [&[id, bytes.len() as u8][..], bytes].concat()
+            }
+            fn export(name: &str, kind: u8, index: u8) -> Vec<u8> {
+                [&[name.len() as u8][..], name.as_bytes(), &[kind, index]].concat()
+            }
+            fn code(body: &[u8]) -> Vec<u8> {
+                [&[body.len() as u8 + 2, 0][..], body, &[0x0b]].concat()
+            }
+            // () -> i32, (i32, i32) -> i64, (i32, i32, i32) -> (), (i32, i32) -> i32
+            let types = [4, 0x60, 0, 1, 0x7f, 0x60, 2, 0x7f, 0x7f, 1, 0x7e, 0x60, 3, 0x7f, 0x7f, 0x7f, 0, 0x60, 2, 0x7f, 0x7f, 1, 0x7f];
+            let exports = [
+                &[5][..],
+                &export("__prop_amm_abi_version", 0, 0),
+                &export("__prop_amm_compute_swap", 0, 1),
+                &export("__prop_amm_after_swap", 0, 2),
+                &export("__prop_amm_get_name", 0, 3),
+                &export("memory", 2, 0),
+            ]
+            .concat();
+            let abi_version = [0x41, crate::wire::ABI_VERSION as u8];
+            let codes = [&[4][..], &code(&abi_version), &code(body), &code(&[]), &code(&[0x41, 0])].concat();
+            let wasm = [
+                &b"\0asm\x01\0\0\0"[..],
+                &section(1, &types),
+                &section(3, &[4, 0, 1, 2, 3]),
+                &section(5, &[1, 0, 1]),
+                &section(7, &exports),
+                &section(10, &codes),
+            ]
+            .concat();
+            let path = std::env::temp_dir().join(format!("prop-amm-wasm-{label}-{}.wasm", std::process::id()));
+            std::fs::write(&path, wasm).unwrap();
+            let loaded = WasmBackend::load(&path, limits);
+            std::fs::remove_file(&path).unwrap();
+            loaded.unwrap().0
+        }
+
+        /// `loop br 0 end unreachable`: spins until stopped
+        const SPIN: &[u8] = &[0x03, 0x40, 0x0c, 0, 0x0b, 0];
+
+        #[test]
+        fn running_out_of_fuel_is_over_budget() {
+            let backend = module("fuel", SPIN, WasmLimits { fuel_per_call: 10_000, ..WasmLimits::default() });
+            assert_eq!(backend.compute_swap(&[0]), Err(CallFailure::OverBudget));
+            // Fuel is refilled per call, so the next one fails the same way rather than at once
+            assert_eq!(backend.compute_swap(&[0]), Err(CallFailure::OverBudget));
+        }
+
+        #[test]
+        fn wall_time_deadline_interrupts_a_call() {
+            let mut backend = module("deadline", SPIN, WasmLimits { fuel_per_call: u64::MAX, ..WasmLimits::default() });
+            backend.set_deadline(Some(Duration::from_millis(20)));
+            let started = std::time::Instant::now();
+            assert_eq!(backend.compute_swap(&[0]), Err(CallFailure::OverBudget));
+            assert!(started.elapsed() < Duration::from_secs(5), "interrupted after {:?}", started.elapsed());
+        }
+
+        #[test]
+        fn traps_are_reported_and_the_module_stays_usable() {
+            let backend = module("trap", &[0], WasmLimits::default());
+            assert_eq!(backend.compute_swap(&[0]), Err(CallFailure::Trapped));
+            assert_eq!(backend.compute_swap(&[0]), Err(CallFailure::Trapped));
+            let mut storage = [7; STORAGE_SIZE];
+            assert_eq!(backend.after_swap(&[0], &mut storage), Ok(()));
+            assert_eq!(storage, [7; STORAGE_SIZE]);
+        }
+
+        #[test]
+        fn memory_growth_stops_at_the_limit() {
+            // `memory.grow 16`, quoting the old size in pages or -1 if refused
+            let grow = [0x41, 16, 0x40, 0, 0xac];
+            // The module's page plus the host's scratch page leave room for two more
+            let tight = module("tight", &grow, WasmLimits { max_memory_bytes: 4 * 65_536, ..WasmLimits::default() });
+            assert_eq!(tight.compute_swap(&[0]), Ok(u64::MAX));
+            let roomy = module("roomy", &grow, WasmLimits::default());
+            assert_eq!(roomy.compute_swap(&[0]), Ok(2));
+        }
+    }
+}
+
+// ─── SBF backend ──────────────────────────────────────────────────────────────
+
+#[cfg(feature = "sbf")]
+mod sbf {
+    use std::path::Path;
+    use std::sync::Arc;
+
+    use solana_rbpf::aligned_memory::AlignedMemory;
+    use solana_rbpf::declare_builtin_function;
+    use solana_rbpf::ebpf;
+    use solana_rbpf::elf::Executable;
+    use solana_rbpf::error::{EbpfError, ProgramResult};
+    use solana_rbpf::memory_region::{AccessType, MemoryMapping, MemoryRegion};
+    use solana_rbpf::program::{BuiltinFunction, BuiltinProgram, FunctionRegistry};
+    use solana_rbpf::verifier::RequisiteVerifier;
+    use solana_rbpf::vm::{Config, ContextObject, EbpfVm};
+
+    use super::{check_abi_version, Backend, CallFailure, SbfLimits, StrategyMetadata};
+    use crate::types::{STORAGE_SIZE, TAG_GET_ABI_VERSION, TAG_GET_MODEL};
+
+    type SyscallResult = Result<u64, Box<dyn std::error::Error>>;
+
+    /// Per-call VM context: the compute meter and whatever `sol_set_return_data` stored.
+    pub(super) struct SbfContext {
+        remaining: u64,
+        /// A syscall found the meter too low for its cost
+        exhausted: bool,
+        return_data: Vec<u8>,
+    }
+
+    /// Solana's default syscall costs in compute units: the flat charge per syscall, the
+    /// floor for memory syscalls, and the bytes per unit memory and return data are
+    /// charged at
+    const SYSCALL_BASE_COST: u64 = 100;
+    const MEM_OP_BASE_COST: u64 = 10;
+    const CPI_BYTES_PER_UNIT: u64 = 250;
+
+    /// Take `cost` units off the meter, failing the call like the on-chain runtime does
+    /// when fewer are left.
+    fn charge(ctx: &mut SbfContext, cost: u64) -> Result<(), Box<dyn std::error::Error>> {
+        if cost > ctx.remaining {
+            ctx.remaining = 0;
+            ctx.exhausted = true;
+            return Err("compute budget exceeded".into());
+        }
+        ctx.remaining -= cost;
+        Ok(())
+    }
+
+    /// What a memory syscall over `n` bytes costs
+    fn mem_op_cost(n: u64) -> u64 {
+        MEM_OP_BASE_COST.max(n / CPI_BYTES_PER_UNIT)
+    }
+
+    impl ContextObject for SbfContext {
+        fn trace(&mut self, _state: [u64; 12]) {}
+
+        fn consume(&mut self, amount: u64) {
+            self.remaining = self.remaining.saturating_sub(amount);
+        }
+
+        fn get_remaining(&self) -> u64 {
+            self.remaining
+        }
+    }
+
+    fn host_slice<'a>(mapping: &MemoryMapping, access: AccessType, addr: u64, len: u64) -> Result<&'a mut [u8], Box<dyn std::error::Error>> {
+        let host = Result::from(mapping.map(access, addr, len))?;
+        Ok(unsafe { std::slice::from_raw_parts_mut(host as *mut u8, len as usize) })
+    }
+
+    declare_builtin_function!(
+        /// `sol_set_return_data(addr, len)`: how `compute_swap` hands back its quote
+        SyscallSetReturnData,
+        fn rust(
+            ctx: &mut SbfContext,
+            addr: u64,
+            len: u64,
+            _c: u64,
+            _d: u64,
+            _e: u64,
+            mapping: &mut MemoryMapping,
+        ) -> SyscallResult {
+            charge(ctx, SYSCALL_BASE_COST + len / CPI_BYTES_PER_UNIT)?;
+            ctx.return_data = host_slice(mapping, AccessType::Load, addr, len.min(1024))?.to_vec();
+            Ok(0)
+        }
+    );
+
+    declare_builtin_function!(
+        /// `sol_log_(addr, len)`: charged, then discarded
+        SyscallLog,
+        fn rust(
+            ctx: &mut SbfContext,
+            _addr: u64,
+            len: u64,
+            _c: u64,
+            _d: u64,
+            _e: u64,
+            _mapping: &mut MemoryMapping,
+        ) -> SyscallResult {
+            charge(ctx, SYSCALL_BASE_COST.max(len))?;
+            Ok(0)
+        }
+    );
+
+    declare_builtin_function!(
+        /// `sol_log_64_` / `sol_log_compute_units_`: charged, then discarded
+        SyscallLogFlat,
+        fn rust(
+            ctx: &mut SbfContext,
+            _a: u64,
+            _b: u64,
+            _c: u64,
+            _d: u64,
+            _e: u64,
+            _mapping: &mut MemoryMapping,
+        ) -> SyscallResult {
+            charge(ctx, SYSCALL_BASE_COST)?;
+            Ok(0)
+        }
+    );
+
+    declare_builtin_function!(
+        /// `sol_log_data(fields, n)`: charged per field and byte, then discarded
+        SyscallLogData,
+        fn rust(
+            ctx: &mut SbfContext,
+            fields: u64,
+            n: u64,
+            _c: u64,
+            _d: u64,
+            _e: u64,
+            mapping: &mut MemoryMapping,
+        ) -> SyscallResult {
+            charge(ctx, SYSCALL_BASE_COST)?;
+            // Each field is a (pointer, length) pair of u64s
+            let descriptors = host_slice(mapping, AccessType::Load, fields, n.saturating_mul(16))?;
+            let bytes: u64 = descriptors.chunks_exact(16).map(|d| u64::from_le_bytes(d[8..].try_into().unwrap())).fold(0, u64::saturating_add);
+            charge(ctx, SYSCALL_BASE_COST.saturating_mul(n).saturating_add(bytes))?;
+            Ok(0)
+        }
+    );
+
+    declare_builtin_function!(
+        /// `abort` / `sol_panic_`: fails the call
+        SyscallAbort,
+        fn rust(
+            _ctx: &mut SbfContext,
+            _a: u64,
+            _b: u64,
+            _c: u64,
+            _d: u64,
+            _e: u64,
+            _mapping: &mut MemoryMapping,
+        ) -> SyscallResult {
+            Err("program aborted".into())
+        }
+    );
+
+    declare_builtin_function!(
+        /// `sol_memcpy_` / `sol_memmove_(dst, src, n)`
+        SyscallMemmove,
+        fn rust(
+            ctx: &mut SbfContext,
+            dst: u64,
+            src: u64,
+            n: u64,
+            _d: u64,
+            _e: u64,
+            mapping: &mut MemoryMapping,
+        ) -> SyscallResult {
+            charge(ctx, mem_op_cost(n))?;
+            let src = host_slice(mapping, AccessType::Load, src, n)?.to_vec();
+            host_slice(mapping, AccessType::Store, dst, n)?.copy_from_slice(&src);
+            Ok(0)
+        }
+    );
+
+    declare_builtin_function!(
+        /// `sol_memset_(dst, byte, n)`
+        SyscallMemset,
+        fn rust(
+            ctx: &mut SbfContext,
+            dst: u64,
+            byte: u64,
+            n: u64,
+            _d: u64,
+            _e: u64,
+            mapping: &mut MemoryMapping,
+        ) -> SyscallResult {
+            charge(ctx, mem_op_cost(n))?;
+            host_slice(mapping, AccessType::Store, dst, n)?.fill(byte as u8);
+            Ok(0)
+        }
+    );
+
+    declare_builtin_function!(
+        /// `sol_memcmp_(a, b, n, result_ptr)`
+        SyscallMemcmp,
+        fn rust(
+            ctx: &mut SbfContext,
+            a: u64,
+            b: u64,
+            n: u64,
+            out: u64,
+            _e: u64,
+            mapping: &mut MemoryMapping,
+        ) -> SyscallResult {
+            charge(ctx, mem_op_cost(n))?;
+            let lhs = host_slice(mapping, AccessType::Load, a, n)?;
+            let rhs = host_slice(mapping, AccessType::Load, b, n)?;
+            let ord = lhs.iter().zip(rhs.iter()).map(|(x, y)| *x as i32 - *y as i32).find(|d| *d != 0).unwrap_or(0);
+            host_slice(mapping, AccessType::Store, out, 4)?.copy_from_slice(&ord.to_le_bytes());
+            Ok(0)
+        }
+    );
+
+    const HEAP_SIZE: usize = 32 * 1024;
+    /// Bytes before the storage account's data in the serialized input: account count,
+    /// then dup/signer/writable/executable flags, padding, key, owner, lamports, data len
+    const ACCOUNT_DATA_OFF: usize = 8 + 8 + 32 + 32 + 8 + 8;
+    /// Realloc headroom the loader reserves after every account's data
+    const MAX_PERMITTED_DATA_INCREASE: usize = 10 * 1024;
+
+    /// Serialize `data` as instruction data for a program invoked with one writable
+    /// account holding `storage`, in the aligned Solana loader input layout.
+    fn serialize_input(data: &[u8], storage: &[u8; STORAGE_SIZE]) -> AlignedMemory<{ ebpf::HOST_ALIGN }> {
+        let mut buf = Vec::with_capacity(ACCOUNT_DATA_OFF + STORAGE_SIZE + MAX_PERMITTED_DATA_INCREASE + data.len() + 64);
+        buf.extend_from_slice(&1u64.to_le_bytes());          // account count
+        buf.extend_from_slice(&[0xff, 0, 1, 0]);               // non-dup, signer, writable, executable
+        buf.extend_from_slice(&(STORAGE_SIZE as u32).to_le_bytes()); // original data len
+        buf.extend_from_slice(&[0u8; 32]);                     // key
+        buf.extend_from_slice(&[0u8; 32]);                     // owner
+        buf.extend_from_slice(&0u64.to_le_bytes());            // lamports
+        buf.extend_from_slice(&(STORAGE_SIZE as u64).to_le_bytes());
+        buf.extend_from_slice(storage);
+        buf.resize(buf.len() + MAX_PERMITTED_DATA_INCREASE, 0);
+        buf.resize(buf.len().next_multiple_of(8), 0);
+        buf.extend_from_slice(&0u64.to_le_bytes());            // rent epoch
+        buf.extend_from_slice(&(data.len() as u64).to_le_bytes());
+        buf.extend_from_slice(data);
+        buf.extend_from_slice(&[0u8; 32]);                     // program id
+        AlignedMemory::from_slice(&buf)
+    }
+
+    /// A compiled Solana program exporting the standard `entrypoint`. The payload is its
+    /// instruction data and storage its one writable account; a quote comes back through
+    /// `sol_set_return_data`. The strategy name is the file stem; its ABI version is the
+    /// u32 it returns for `TAG_GET_ABI_VERSION`, and its model whatever it returns for
+    /// `TAG_GET_MODEL`, if anything.
+    pub(super) struct SbfBackend {
+        executable: Executable<SbfContext>,
+        compute_units: u64,
+    }
+
+    /// The program loader, with the syscalls strategies may use.
+    fn loader() -> Result<Arc<BuiltinProgram<SbfContext>>, Box<dyn std::error::Error>> {
+        let mut syscalls = FunctionRegistry::<BuiltinFunction<SbfContext>>::default();
+        syscalls.register_function_hashed(*b"sol_set_return_data", SyscallSetReturnData::vm)?;
+        syscalls.register_function_hashed(*b"sol_log_", SyscallLog::vm)?;
+        syscalls.register_function_hashed(*b"sol_log_64_", SyscallLogFlat::vm)?;
+        syscalls.register_function_hashed(*b"sol_log_compute_units_", SyscallLogFlat::vm)?;
+        syscalls.register_function_hashed(*b"sol_log_data", SyscallLogData::vm)?;
+        syscalls.register_function_hashed(*b"abort", SyscallAbort::vm)?;
+        syscalls.register_function_hashed(*b"sol_panic_", SyscallAbort::vm)?;
+        syscalls.register_function_hashed(*b"sol_memcpy_", SyscallMemmove::vm)?;
+        syscalls.register_function_hashed(*b"sol_memmove_", SyscallMemmove::vm)?;
+        syscalls.register_function_hashed(*b"sol_memset_", SyscallMemset::vm)?;
+        syscalls.register_function_hashed(*b"sol_memcmp_", SyscallMemcmp::vm)?;
+        Ok(Arc::new(BuiltinProgram::new_loader(Config::default(), syscalls)))
+    }
+
+    impl SbfBackend {
+        pub(super) fn load(path: &Path, limits: SbfLimits) -> Result<(Self, String, StrategyMetadata), Box<dyn std::error::Error>> {
+            let elf = std::fs::read(path)?;
+            let backend = Self::new(Executable::from_elf(&elf, loader()?)?, limits)?;
+
+            let name = path.file_stem().and_then(|s| s.to_str()).unwrap_or("sbf").trim_start_matches("lib").to_string();
+            let abi_version = backend.invoke(&[TAG_GET_ABI_VERSION], &mut [0u8; STORAGE_SIZE], false).ok()
+                .and_then(|ret| Some(u32::from_le_bytes(ret.get(..4)?.try_into().ok()?)));
+            let abi_version = check_abi_version(path, abi_version)?;
+            let model = backend.invoke(&[TAG_GET_MODEL], &mut [0u8; STORAGE_SIZE], false).ok();
+            let metadata = StrategyMetadata { abi_version: Some(abi_version), ..StrategyMetadata::from_exports(model.as_deref(), None) };
+            Ok((backend, name, metadata))
+        }
+
+        /// Verify `executable` (and JIT-compile it where supported)
+        #[allow(unused_mut)]
+        fn new(mut executable: Executable<SbfContext>, limits: SbfLimits) -> Result<Self, Box<dyn std::error::Error>> {
+            executable.verify::<RequisiteVerifier>()?;
+            #[cfg(all(not(target_os = "windows"), target_arch = "x86_64"))]
+            executable.jit_compile()?;
+            Ok(Self { executable, compute_units: limits.compute_units_per_call })
+        }
+
+        /// Run the entrypoint once and return its return data
+        fn invoke(&self, data: &[u8], storage: &mut [u8; STORAGE_SIZE], write_back: bool) -> Result<Vec<u8>, CallFailure> {
+            let config = self.executable.get_config();
+            let version = self.executable.get_sbpf_version();
+            let mut input = serialize_input(data, storage);
+            let mut stack = AlignedMemory::<{ ebpf::HOST_ALIGN }>::zero_filled(config.stack_size());
+            let mut heap = AlignedMemory::<{ ebpf::HOST_ALIGN }>::zero_filled(HEAP_SIZE);
+            let stack_gap = if !version.dynamic_stack_frames() && config.enable_stack_frame_gaps {
+                config.stack_frame_size as u64
+            } else {
+                0
+            };
+            let stack_len = stack.len();
+            let regions = vec![
+                self.executable.get_ro_region(),
+                MemoryRegion::new_writable_gapped(stack.as_slice_mut(), ebpf::MM_STACK_START, stack_gap),
+                MemoryRegion::new_writable(heap.as_slice_mut(), ebpf::MM_HEAP_START),
+                MemoryRegion::new_writable(input.as_slice_mut(), ebpf::MM_INPUT_START),
+            ];
+            let mapping = MemoryMapping::new(regions, config, version).map_err(|_| CallFailure::Trapped)?;
+
+            let mut ctx = SbfContext { remaining: self.compute_units, exhausted: false, return_data: Vec::new() };
+            let mut vm = EbpfVm::new(self.executable.get_loader().clone(), version, &mut ctx, mapping, stack_len);
+            let jit = cfg!(all(not(target_os = "windows"), target_arch = "x86_64"));
+            let (_, result) = vm.execute_program(&self.executable, !jit);
+            match result {
+                ProgramResult::Ok(0) => {}
+                ProgramResult::Err(EbpfError::ExceededMaxInstructions) => return Err(CallFailure::OverBudget),
+                ProgramResult::Err(_) if ctx.exhausted => return Err(CallFailure::OverBudget),
+                _ => return Err(CallFailure::Trapped),
+            }
+            if write_back {
+                storage.copy_from_slice(&input.as_slice()[ACCOUNT_DATA_OFF..ACCOUNT_DATA_OFF + STORAGE_SIZE]);
+            }
+            Ok(ctx.return_data)
+        }
+    }
+
+    impl Backend for SbfBackend {
+        fn compute_swap(&self, data: &[u8]) -> Result<u64, CallFailure> {
+            // The quote payload already carries a storage snapshot after its header
+            let mut storage = [0u8; STORAGE_SIZE];
+            if let Some(snapshot) = data.get(crate::wire::swap::STORAGE..crate::wire::swap::STORAGE + STORAGE_SIZE) {
+                storage.copy_from_slice(snapshot);
+            }
+            let ret = self.invoke(data, &mut storage, false)?;
+            Ok(ret.get(..8).map_or(0, |r| u64::from_le_bytes(r.try_into().unwrap())))
+        }
+
+        fn after_swap(&self, data: &[u8], storage: &mut [u8; STORAGE_SIZE]) -> Result<(), CallFailure> {
+            self.invoke(data, storage, true).map(|_| ())
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use solana_rbpf::assembler::assemble;
+
+        use super::*;
+
+        /// A program that clears `n` heap bytes with `sol_memset_`, then returns
+        fn memset_program(n: u64, compute_units: u64) -> SbfBackend {
+            let heap = ebpf::MM_HEAP_START >> 32;
+            let src = format!("mov64 r1, {heap}\nlsh64 r1, 32\nmov64 r2, 0\nmov64 r3, {n}\nsyscall sol_memset_\nmov64 r0, 0\nexit");
+            SbfBackend::new(assemble(&src, loader().unwrap()).unwrap(), SbfLimits { compute_units_per_call: compute_units }).unwrap()
+        }
+
+        #[test]
+        fn syscalls_are_charged_compute_units() {
+            let mut storage = [0; STORAGE_SIZE];
+            // Seven instructions and a 10-unit memset fit in 50 units
+            assert_eq!(memset_program(64, 50).invoke(&[0], &mut storage, false), Ok(Vec::new()));
+            // 25k bytes cost 100 units: over a budget the instructions alone stay far under
+            assert_eq!(memset_program(25_000, 50).invoke(&[0], &mut storage, false), Err(CallFailure::OverBudget));
+            assert_eq!(memset_program(25_000, 200).invoke(&[0], &mut storage, false), Ok(Vec::new()));
+        }
+    }
 }
 
 // ─── Normalizer (built-in CPAMM, no external lib) ────────────────────────────
@@ -173,3 +2257,72 @@ impl NormalizerRunner {
         else       { cpamm_output(input, rx, ry, self.fee_bps) }
     }
 }
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    unsafe extern "C" fn quote_len(_data: *const u8, len: usize) -> u64 {
+        len as u64
+    }
+
+    unsafe extern "C" fn scribble_payload(data: *const u8, len: usize) -> u64 {
+        *(data as *mut u8) ^= 0xff;
+        len as u64
+    }
+
+    unsafe extern "C" fn bump_storage(_data: *const u8, _len: usize, storage: *mut u8) {
+        *storage.add(STORAGE_SIZE - 1) += 1;
+    }
+
+    unsafe extern "C" fn overrun_storage(_data: *const u8, _len: usize, storage: *mut u8) {
+        *storage = 9;
+        *storage.add(STORAGE_SIZE) = 0;
+    }
+
+    unsafe extern "C" fn underrun_storage(_data: *const u8, _len: usize, storage: *mut u8) {
+        *storage.sub(1) = 0;
+    }
+
+    unsafe extern "C" fn scribble_payload_after(data: *const u8, len: usize, _storage: *mut u8) {
+        scribble_payload(data, len);
+    }
+
+    unsafe extern "C" fn keep_storage(_data: *const u8, _len: usize, _storage: *mut u8) {}
+
+    /// A hardened native backend over entry points defined in this test binary
+    fn hardened(compute_swap: ComputeSwapFn, after_swap: AfterSwapFn) -> NativeBackend {
+        let lib = libloading::os::unix::Library::this().into();
+        NativeBackend { _lib: lib, compute_swap, after_swap, compute_swap_batch: None, log: None, hardened: true }
+    }
+
+    #[test]
+    fn hardened_calls_catch_writes_outside_storage() {
+        let payload = [1u8; 64];
+        let mut storage = [0u8; STORAGE_SIZE];
+
+        let honest = hardened(quote_len, bump_storage);
+        assert_eq!(honest.compute_swap(&payload), Ok(64));
+        assert_eq!(honest.after_swap(&payload, &mut storage), Ok(()));
+        assert_eq!(storage[STORAGE_SIZE - 1], 1);
+        let mut quotes = [0; 3];
+        assert_eq!(honest.compute_swap_batch(&payload, &[1, 2, 3], &mut quotes), Ok(()));
+        assert_eq!(quotes, [64; 3]);
+
+        // Past either end of storage: caught, and storage keeps its previous contents
+        for after_swap in [overrun_storage as AfterSwapFn, underrun_storage] {
+            let backend = hardened(quote_len, after_swap);
+            assert_eq!(backend.after_swap(&payload, &mut storage), Err(CallFailure::Tampered));
+            assert_eq!(storage[0], 0);
+            assert_eq!(storage[STORAGE_SIZE - 1], 1);
+        }
+
+        // The payload is read-only in both entry points
+        let backend = hardened(scribble_payload, keep_storage);
+        assert_eq!(backend.compute_swap(&payload), Err(CallFailure::Tampered));
+        let mut quotes = [0; 2];
+        assert_eq!(backend.compute_swap_batch(&payload, &[1, 2], &mut quotes), Err(CallFailure::Tampered));
+        let backend = hardened(quote_len, scribble_payload_after);
+        assert_eq!(backend.after_swap(&payload, &mut storage), Err(CallFailure::Tampered));
+    }
+}
@@ -0,0 +1,96 @@
+//! Starter strategies for `prop-amm-multi new`.
+//!
+//! Each template in `templates/` is a complete, self-contained strategy source: the SDK
+//! entry points, a storage layout table and local tests. `{{NAME}}`, `{{FILE}}` and
+//! `{{TEST_COMMAND}}` are filled in at render time, and so is every ABI value the
+//! templates use (`{{ABI_VERSION}}`, `{{swap::RESERVE_X}}`, ...), from `wire`, so a new
+//! strategy always decodes the layout this engine sends.
+
+use crate::types::REBALANCE_REQUEST_SLOT;
+use crate::wire::{self, after_swap, epoch_boundary, swap};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Template {
+    /// Fee tracks an EMA of realized volatility
+    VolAdaptive,
+    /// One fixed fee on both sides
+    FixedFee,
+    /// Fees skewed to pull X inventory back to its epoch-start level
+    InventorySkew,
+}
+
+impl Template {
+    pub const ALL: [Template; 3] = [Template::VolAdaptive, Template::FixedFee, Template::InventorySkew];
+
+    fn source(self) -> &'static str {
+        match self {
+            Template::VolAdaptive => include_str!("templates/vol_adaptive.rs"),
+            Template::FixedFee => include_str!("templates/fixed_fee.rs"),
+            Template::InventorySkew => include_str!("templates/inventory_skew.rs"),
+        }
+    }
+}
+
+/// A strategy name usable as a file stem, a crate name (rustc infers it from the stem)
+/// and a Rust string.
+pub fn check_name(name: &str) -> Result<(), String> {
+    let valid = name.starts_with(|c: char| c.is_ascii_alphabetic()) && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if valid { Ok(()) } else { Err(format!("invalid strategy name `{name}`: use letters, digits and `_`, starting with a letter")) }
+}
+
+/// Strategy source for a single file `<name>.rs`, tested with plain `rustc --test`.
+pub fn render_file(template: Template, name: &str) -> String {
+    let file = format!("{name}.rs");
+    let tests = format!("rustc --edition 2021 --test {file} -o {name}_tests && ./{name}_tests");
+    render(template, name, &file, &tests)
+}
+
+/// `src/lib.rs` of a cargo project in directory `name`, tested with `cargo test`.
+pub fn render_crate(template: Template, name: &str) -> String {
+    render(template, name, &format!("{name}/src/lib.rs"), &format!("cd {name} && cargo test"))
+}
+
+/// `Cargo.toml` for `render_crate`: a standalone cdylib package.
+pub fn cargo_manifest(name: &str) -> String {
+    format!(
+        "[package]\nname = \"{name}\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n\
+         [lib]\ncrate-type = [\"cdylib\"]\npath = \"src/lib.rs\"\n\n\
+         # Not part of any enclosing workspace\n[workspace]\n\n\
+         [profile.release]\nopt-level = 3\n"
+    )
+}
+
+/// The `wire` values templates refer to by placeholder.
+fn abi_values() -> [(&'static str, usize); 21] {
+    [
+        ("ABI_VERSION", wire::ABI_VERSION as usize),
+        ("STORAGE_SIZE", wire::STORAGE_SIZE),
+        ("REBALANCE_REQUEST_SLOT", REBALANCE_REQUEST_SLOT),
+        ("TAG_SWAP_BUY", wire::TAG_SWAP_BUY as usize),
+        ("TAG_SWAP_SELL", wire::TAG_SWAP_SELL as usize),
+        ("TAG_AFTER_SWAP", wire::TAG_AFTER_SWAP as usize),
+        ("TAG_EPOCH_BOUNDARY", wire::TAG_EPOCH_BOUNDARY as usize),
+        ("swap::INPUT_AMOUNT", swap::INPUT_AMOUNT),
+        ("swap::RESERVE_X", swap::RESERVE_X),
+        ("swap::RESERVE_Y", swap::RESERVE_Y),
+        ("swap::STORAGE", swap::STORAGE),
+        ("after_swap::SIDE", after_swap::SIDE),
+        ("after_swap::INPUT_AMOUNT", after_swap::INPUT_AMOUNT),
+        ("after_swap::OUTPUT_AMOUNT", after_swap::OUTPUT_AMOUNT),
+        ("after_swap::RESERVE_X", after_swap::RESERVE_X),
+        ("after_swap::RESERVE_Y", after_swap::RESERVE_Y),
+        ("after_swap::CAPITAL_WEIGHT", after_swap::CAPITAL_WEIGHT),
+        ("after_swap::LEN", after_swap::len(wire::ABI_VERSION)),
+        ("epoch_boundary::EPOCH_NUMBER", epoch_boundary::EPOCH_NUMBER),
+        ("epoch_boundary::NEW_RESERVE_X", epoch_boundary::NEW_RESERVE_X),
+        ("epoch_boundary::CAPITAL_WEIGHT", epoch_boundary::CAPITAL_WEIGHT),
+    ]
+}
+
+fn render(template: Template, name: &str, file: &str, test_command: &str) -> String {
+    let mut source = template.source().replace("{{NAME}}", name).replace("{{FILE}}", file).replace("{{TEST_COMMAND}}", test_command);
+    for (key, value) in abi_values() {
+        source = source.replace(&format!("{{{{{key}}}}}"), &value.to_string());
+    }
+    source
+}
@@ -0,0 +1,124 @@
+"""Reference remote strategy: a fixed-fee constant-product AMM served over the worker protocol.
+
+Point the engine at it with a `.remote` manifest, e.g. `fixed_fee.remote` containing
+
+    command python3 remote_strategy.py --fee-bps 30
+
+(relative paths resolve against the manifest's directory), or serve it over TCP with
+`python3 remote_strategy.py --listen 127.0.0.1:9000` and a manifest of
+`tcp 127.0.0.1:9000`. Protocol reference: `RemoteEndpoint` in runner.rs.
+"""
+
+from __future__ import annotations
+
+import argparse
+import socketserver
+import struct
+import sys
+from typing import BinaryIO
+
+OP_COMPUTE_SWAP = 0
+OP_AFTER_SWAP = 1
+OP_COMPUTE_SWAP_BATCH = 2
+OP_SET_HARDENED = 3
+
+STATUS_OK = 0
+STATUS_TRAPPED = 2
+
+TAG_SWAP_BUY = 0
+STORAGE_SIZE = 1024
+
+
+def cpamm_output(amount_in: int, reserve_in: int, reserve_out: int, fee_bps: int) -> int:
+    effective = amount_in * (10_000 - fee_bps) // 10_000
+    if reserve_in + effective == 0:
+        return 0
+    return reserve_out * effective // (reserve_in + effective)
+
+
+class FixedFee:
+    def __init__(self, fee_bps: int) -> None:
+        self.fee_bps = fee_bps
+        self.name = f"remote_fixed_{fee_bps}bps"
+        self.model = "python-reference"
+        self.metadata = "language=python"
+
+    def compute_swap(self, payload: bytes) -> int:
+        tag = payload[0]
+        amount_in, rx, ry = struct.unpack_from("<QQQ", payload, 1)
+        if tag == TAG_SWAP_BUY:
+            return cpamm_output(amount_in, ry, rx, self.fee_bps)
+        return cpamm_output(amount_in, rx, ry, self.fee_bps)
+
+    def after_swap(self, payload: bytes, storage: bytearray) -> None:
+        """Hooks (after_swap, epoch boundary, shock news, step digest) may update storage."""
+
+
+def read_frame(stream: BinaryIO) -> bytes | None:
+    header = stream.read(4)
+    if len(header) < 4:
+        return None
+    (length,) = struct.unpack("<I", header)
+    return stream.read(length)
+
+
+def write_frame(stream: BinaryIO, body: bytes) -> None:
+    stream.write(struct.pack("<I", len(body)) + body)
+    stream.flush()
+
+
+def serve(strategy: FixedFee, reader: BinaryIO, writer: BinaryIO) -> None:
+    write_frame(writer, strategy.name.encode())
+    write_frame(writer, strategy.model.encode())
+    write_frame(writer, strategy.metadata.encode())
+    while (request := read_frame(reader)) is not None:
+        op, body = request[0], request[1:]
+        try:
+            if op == OP_COMPUTE_SWAP:
+                write_frame(writer, bytes([STATUS_OK]) + struct.pack("<Q", strategy.compute_swap(body)))
+            elif op == OP_AFTER_SWAP:
+                storage = bytearray(body[:STORAGE_SIZE])
+                strategy.after_swap(body[STORAGE_SIZE:], storage)
+                write_frame(writer, bytes([STATUS_OK]) + bytes(storage))
+            elif op == OP_COMPUTE_SWAP_BATCH:
+                (n,) = struct.unpack_from("<I", body)
+                inputs = struct.unpack_from(f"<{n}Q", body, 4)
+                payload = bytearray(body[4 + 8 * n :])
+                quotes = []
+                for amount_in in inputs:
+                    struct.pack_into("<Q", payload, 1, amount_in)
+                    quotes.append(strategy.compute_swap(bytes(payload)))
+                write_frame(writer, bytes([STATUS_OK]) + struct.pack(f"<{n}Q", *quotes))
+            elif op == OP_SET_HARDENED:
+                write_frame(writer, bytes([STATUS_OK]))
+            else:
+                raise ValueError(f"unknown op {op}")
+        except Exception as exc:  # a failed call, not a dead worker
+            print(f"remote strategy: {exc}", file=sys.stderr)
+            write_frame(writer, bytes([STATUS_TRAPPED]))
+
+
+def main() -> None:
+    parser = argparse.ArgumentParser(description=__doc__.splitlines()[0])
+    parser.add_argument("--fee-bps", type=int, default=30)
+    parser.add_argument("--listen", metavar="HOST:PORT", help="serve over TCP instead of stdio")
+    args = parser.parse_args()
+    strategy = FixedFee(args.fee_bps)
+
+    if args.listen is None:
+        serve(strategy, sys.stdin.buffer, sys.stdout.buffer)
+        return
+
+    host, port = args.listen.rsplit(":", 1)
+
+    class Handler(socketserver.StreamRequestHandler):
+        def handle(self) -> None:
+            serve(strategy, self.rfile, self.wfile)
+
+    # One engine thread per connection
+    with socketserver.ThreadingTCPServer((host, int(port)), Handler) as server:
+        server.serve_forever()
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,377 @@
+//! Self-hosted competition backend (the `serve` feature).
+//!
+//! A small HTTP server accepts strategy sources, queues them, and evaluates them one at a
+//! time on a worker thread through an `Evaluator` supplied by the host (the CLI scores
+//! each upload against the reference field). Submissions and their results live in a
+//! SQLite database, so the leaderboard survives restarts and submissions still queued or
+//! running when the server stopped are evaluated again when it starts.
+//!
+//! Uploads are untrusted. Sources that could read host files or environment at compile
+//! time are refused before they are stored (`forbidden_construct`); the CLI compiles
+//! the rest under the tournament audit and runs them in worker processes.
+//!
+//! Endpoints (JSON responses):
+//!  - `POST /api/strategies[?name=…]` — body is the strategy source; answers `202` with
+//!    the submission id
+//!  - `GET /api/strategies/{id}` — status, timings, error and results of a submission
+//!  - `GET /api/leaderboard` — each name's best evaluated submission, by mean edge
+//!  - `GET /healthz` — liveness and queue depth
+
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde_json::{json, Value};
+use tiny_http::{Header, Response, Server};
+
+use crate::receipt::sha256_hex;
+
+/// Largest accepted upload, in bytes.
+pub const MAX_UPLOAD_BYTES: usize = 256 * 1024;
+
+/// Longest stored name, in characters: a `?name=` longer than this is refused, and a
+/// strategy's self-reported name is cut to it.
+pub const MAX_NAME_CHARS: usize = 64;
+
+/// Compile-time macros that read files or the build environment into the artifact
+const FORBIDDEN_MACROS: [&str; 5] = ["include", "include_str", "include_bytes", "env", "option_env"];
+
+/// What in `source` could make the compiler read files or environment on the host: one
+/// of `FORBIDDEN_MACROS` (however it is spelled: `r#env!`, `core::env !`), a `#[path]`
+/// attribute or an out-of-line `mod name;`. Identifiers are matched in comments and
+/// strings too, which refuses a few harmless sources rather than miss a real use.
+pub fn forbidden_construct(source: &str) -> Option<String> {
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+    let mut tokens = Vec::new();
+    let mut rest = source;
+    while let Some(c) = rest.chars().next() {
+        let len = if is_ident(c) { rest.find(|c| !is_ident(c)).unwrap_or(rest.len()) } else { c.len_utf8() };
+        if !c.is_whitespace() {
+            tokens.push(&rest[..len]);
+        }
+        rest = &rest[len..];
+    }
+    for (i, window) in tokens.windows(2).enumerate() {
+        let (word, next) = (window[0], window[1]);
+        if next == "!" && FORBIDDEN_MACROS.contains(&word) {
+            return Some(format!("`{word}!` reads the host at compile time"));
+        }
+        // `#[path = …]` and `#![path = …]`
+        let attribute = i >= 2 && tokens[i - 1] == "[" && (tokens[i - 2] == "#" || tokens[i - 2] == "!");
+        if word == "path" && attribute {
+            return Some("`#[path]` attributes are not allowed".to_string());
+        }
+        if word == "mod" && tokens.get(i + 2) == Some(&";") {
+            return Some(format!("`mod {next};` would load another file; keep modules inline"));
+        }
+    }
+    None
+}
+
+/// `name` cut to `MAX_NAME_CHARS` characters.
+fn clip_name(name: &str) -> String {
+    name.chars().take(MAX_NAME_CHARS).collect()
+}
+
+/// Outcome of evaluating one submission.
+#[derive(Clone, Debug)]
+pub struct Evaluation {
+    /// Name the strategy reports for itself
+    pub strategy: String,
+    /// The leaderboard score
+    pub mean_edge: f64,
+    /// Everything else worth serving, stored as given
+    pub details: Value,
+}
+
+/// Compiles and scores the strategy source at a path. Errors are stored and served as
+/// the submission's failure reason.
+pub type Evaluator = dyn Fn(&Path) -> Result<Evaluation, String> + Send + Sync;
+
+/// Where `serve` listens and keeps its state.
+#[derive(Clone, Debug)]
+pub struct ServeOptions {
+    /// Listen address, e.g. `127.0.0.1:8080`
+    pub addr: String,
+    /// SQLite database, created on first use
+    pub db: PathBuf,
+    /// Directory uploaded sources are written to before evaluation
+    pub work_dir: PathBuf,
+}
+
+/// Submission state
+const QUEUED: &str = "queued";
+const RUNNING: &str = "running";
+const DONE: &str = "done";
+const FAILED: &str = "failed";
+
+fn now() -> i64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() as i64)
+}
+
+fn db_err(e: rusqlite::Error) -> String {
+    format!("database: {e}")
+}
+
+/// Submissions and results, in SQLite.
+pub struct Store {
+    conn: Mutex<Connection>,
+}
+
+impl Store {
+    /// Open (or create) the database at `path`.
+    pub fn open(path: &Path) -> Result<Self, String> {
+        Self::with(Connection::open(path).map_err(db_err)?)
+    }
+
+    /// A store that lives only as long as the process.
+    pub fn in_memory() -> Result<Self, String> {
+        Self::with(Connection::open_in_memory().map_err(db_err)?)
+    }
+
+    fn with(conn: Connection) -> Result<Self, String> {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS submissions (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT,
+                source TEXT NOT NULL,
+                source_sha256 TEXT NOT NULL,
+                status TEXT NOT NULL,
+                error TEXT,
+                submitted_at INTEGER NOT NULL,
+                started_at INTEGER,
+                finished_at INTEGER,
+                strategy TEXT,
+                mean_edge REAL,
+                details TEXT
+            );
+            CREATE INDEX IF NOT EXISTS submissions_status ON submissions (status);",
+        )
+        .map_err(db_err)?;
+        Ok(Self { conn: Mutex::new(conn) })
+    }
+
+    /// Queue `source` under `name` (or, without one, under the name the strategy reports).
+    pub fn insert(&self, name: Option<&str>, source: &str) -> Result<i64, String> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO submissions (name, source, source_sha256, status, submitted_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![name, source, sha256_hex(source), QUEUED, now()],
+        )
+        .map_err(db_err)?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    /// Submissions not yet evaluated, oldest first, with any left running by an earlier
+    /// server put back in the queue.
+    pub fn pending(&self) -> Result<Vec<i64>, String> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("UPDATE submissions SET status = ?1, started_at = NULL WHERE status = ?2", params![QUEUED, RUNNING])
+            .map_err(db_err)?;
+        let mut stmt = conn.prepare("SELECT id FROM submissions WHERE status = ?1 ORDER BY id").map_err(db_err)?;
+        let ids = stmt.query_map([QUEUED], |row| row.get(0)).map_err(db_err)?;
+        ids.collect::<Result<_, _>>().map_err(db_err)
+    }
+
+    /// Submissions waiting in the queue.
+    pub fn queued(&self) -> Result<i64, String> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row("SELECT COUNT(*) FROM submissions WHERE status = ?1", [QUEUED], |row| row.get(0)).map_err(db_err)
+    }
+
+    /// Mark `id` running and return its source.
+    pub fn start(&self, id: i64) -> Result<Option<String>, String> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("UPDATE submissions SET status = ?1, started_at = ?2 WHERE id = ?3", params![RUNNING, now(), id])
+            .map_err(db_err)?;
+        conn.query_row("SELECT source FROM submissions WHERE id = ?1", [id], |row| row.get(0))
+            .optional()
+            .map_err(db_err)
+    }
+
+    /// Record how the evaluation of `id` ended.
+    pub fn finish(&self, id: i64, outcome: &Result<Evaluation, String>) -> Result<(), String> {
+        let conn = self.conn.lock().unwrap();
+        match outcome {
+            Ok(e) => conn.execute(
+                "UPDATE submissions SET status = ?1, finished_at = ?2, strategy = ?3, mean_edge = ?4, details = ?5,
+                 name = COALESCE(name, ?3) WHERE id = ?6",
+                params![DONE, now(), clip_name(&e.strategy), e.mean_edge, e.details.to_string(), id],
+            ),
+            Err(error) => conn.execute(
+                "UPDATE submissions SET status = ?1, finished_at = ?2, error = ?3 WHERE id = ?4",
+                params![FAILED, now(), error, id],
+            ),
+        }
+        .map_err(db_err)?;
+        Ok(())
+    }
+
+    /// A submission as served by `GET /api/strategies/{id}`.
+    pub fn get(&self, id: i64) -> Result<Option<Value>, String> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            "SELECT id, name, source_sha256, status, error, submitted_at, started_at, finished_at, strategy, mean_edge, details
+             FROM submissions WHERE id = ?1",
+            [id],
+            |row| {
+                let details: Option<String> = row.get(10)?;
+                Ok(json!({
+                    "id": row.get::<_, i64>(0)?,
+                    "name": row.get::<_, Option<String>>(1)?,
+                    "source_sha256": row.get::<_, String>(2)?,
+                    "status": row.get::<_, String>(3)?,
+                    "error": row.get::<_, Option<String>>(4)?,
+                    "submitted_at": row.get::<_, i64>(5)?,
+                    "started_at": row.get::<_, Option<i64>>(6)?,
+                    "finished_at": row.get::<_, Option<i64>>(7)?,
+                    "strategy": row.get::<_, Option<String>>(8)?,
+                    "mean_edge": row.get::<_, Option<f64>>(9)?,
+                    "details": details.and_then(|d| serde_json::from_str::<Value>(&d).ok()),
+                }))
+            },
+        )
+        .optional()
+        .map_err(db_err)
+    }
+
+    /// Each name's best evaluated submission, ranked by mean edge.
+    pub fn leaderboard(&self) -> Result<Vec<Value>, String> {
+        let conn = self.conn.lock().unwrap();
+        // SQLite takes the bare columns from the row holding the MAX
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, name, MAX(mean_edge), submitted_at, COUNT(*) FROM submissions
+                 WHERE status = ?1 GROUP BY name ORDER BY 3 DESC, 1",
+            )
+            .map_err(db_err)?;
+        let rows = stmt
+            .query_map([DONE], |row| {
+                Ok(json!({
+                    "id": row.get::<_, i64>(0)?,
+                    "name": row.get::<_, Option<String>>(1)?,
+                    "mean_edge": row.get::<_, f64>(2)?,
+                    "submitted_at": row.get::<_, i64>(3)?,
+                    "submissions": row.get::<_, i64>(4)?,
+                }))
+            })
+            .map_err(db_err)?;
+        let mut entries = rows.collect::<Result<Vec<Value>, _>>().map_err(db_err)?;
+        for (i, entry) in entries.iter_mut().enumerate() {
+            entry["rank"] = json!(i + 1);
+        }
+        Ok(entries)
+    }
+}
+
+/// Evaluate queued submission `id`: write its source into `work_dir` and score it.
+fn evaluate_one(store: &Store, work_dir: &Path, evaluate: &Evaluator, id: i64) -> Result<(), String> {
+    let Some(source) = store.start(id)? else { return Ok(()) };
+    let path = work_dir.join(format!("submission_{id}.rs"));
+    let outcome = std::fs::write(&path, source)
+        .map_err(|e| format!("failed to write {}: {e}", path.display()))
+        .and_then(|()| evaluate(&path));
+    store.finish(id, &outcome)
+}
+
+/// Serve the API until the process is stopped. Submissions are evaluated in order, one at
+/// a time, by `evaluate`.
+pub fn serve(opts: &ServeOptions, evaluate: Box<Evaluator>) -> Result<(), String> {
+    std::fs::create_dir_all(&opts.work_dir).map_err(|e| format!("{}: {e}", opts.work_dir.display()))?;
+    let store = Arc::new(Store::open(&opts.db)?);
+    let server = Server::http(&opts.addr).map_err(|e| format!("cannot listen on {}: {e}", opts.addr))?;
+
+    let (queue, jobs) = mpsc::channel::<i64>();
+    for id in store.pending()? {
+        queue.send(id).expect("worker is alive");
+    }
+    let worker_store = Arc::clone(&store);
+    let work_dir = opts.work_dir.clone();
+    std::thread::spawn(move || {
+        for id in jobs {
+            if let Err(e) = evaluate_one(&worker_store, &work_dir, evaluate.as_ref(), id) {
+                eprintln!("submission {id}: {e}");
+            }
+        }
+    });
+
+    let content_type = Header::from_bytes("Content-Type", "application/json").expect("valid header");
+    for mut request in server.incoming_requests() {
+        let mut body = Vec::new();
+        // One byte past the limit tells an oversized upload from one that fits exactly
+        let read = request.as_reader().take(MAX_UPLOAD_BYTES as u64 + 1).read_to_end(&mut body);
+        let (status, reply) = match read {
+            Ok(_) => handle(&store, &queue, request.method().as_str(), request.url(), &body),
+            Err(e) => (400, json!({ "error": format!("cannot read the request body: {e}") })),
+        };
+        let response = Response::from_string(reply.to_string()).with_status_code(status).with_header(content_type.clone());
+        let _ = request.respond(response);
+    }
+    Ok(())
+}
+
+/// Answer one request: status code and JSON body. New submissions are sent to `queue`.
+pub fn handle(store: &Store, queue: &mpsc::Sender<i64>, method: &str, url: &str, body: &[u8]) -> (u16, Value) {
+    let error = |status: u16, message: String| (status, json!({ "error": message }));
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    let outcome = match (method, segments.as_slice()) {
+        ("GET", ["healthz"]) => store.queued().map(|queued| (200, json!({ "status": "ok", "queued": queued }))),
+        ("GET", ["api", "leaderboard"]) => store.leaderboard().map(|entries| (200, json!({ "entries": entries }))),
+        ("GET", ["api", "strategies", id]) => match id.parse::<i64>() {
+            Ok(id) => store.get(id).map(|found| found.map_or_else(|| error(404, format!("no submission {id}")), |s| (200, s))),
+            Err(_) => Ok(error(404, format!("no submission {id}"))),
+        },
+        ("POST", ["api", "strategies"]) => {
+            if body.len() > MAX_UPLOAD_BYTES {
+                return error(413, format!("uploads are limited to {MAX_UPLOAD_BYTES} bytes"));
+            }
+            let Ok(source) = std::str::from_utf8(body) else {
+                return error(400, "the body must be the strategy source, as UTF-8".to_string());
+            };
+            if source.trim().is_empty() {
+                return error(400, "empty upload".to_string());
+            }
+            if let Some(reason) = forbidden_construct(source) {
+                return error(400, format!("refused: {reason}"));
+            }
+            let name = query_param(query, "name").filter(|n| !n.trim().is_empty());
+            if name.as_ref().is_some_and(|n| n.chars().count() > MAX_NAME_CHARS) {
+                return error(400, format!("names are limited to {MAX_NAME_CHARS} characters"));
+            }
+            store.insert(name.as_deref(), source).and_then(|id| {
+                queue.send(id).map_err(|_| "the evaluation worker has stopped".to_string())?;
+                Ok((202, json!({ "id": id, "status": QUEUED })))
+            })
+        }
+        (_, ["healthz"] | ["api", "leaderboard"] | ["api", "strategies", ..]) => Ok(error(405, format!("{method} not allowed on {path}"))),
+        _ => Ok(error(404, format!("no route {path}"))),
+    };
+    outcome.unwrap_or_else(|e| error(500, e))
+}
+
+/// The percent-decoded value of `key` in a query string.
+fn query_param(query: &str, key: &str) -> Option<String> {
+    let value = query.split('&').find_map(|pair| pair.strip_prefix(key)?.strip_prefix('='))?;
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' => match value.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                Some(byte) => {
+                    out.push(byte);
+                    i += 2;
+                }
+                None => out.push(b'%'),
+            },
+            b => out.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8(out).ok()
+}
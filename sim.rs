@@ -6,137 +6,540 @@
 //!   3. Strategy state persistence across epoch boundaries (TAG_EPOCH_BOUNDARY hook)
 //!   4. Enriched AfterSwap payload exposing competitive context to each strategy
 
-use rand::SeedableRng;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::time::Instant;
+
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
 use rand_chacha::ChaCha8Rng;
+use serde::Serialize;
 
-use crate::capital::rebalance_capital;
+use crate::capital::{
+    apply_capital_flow, apply_rebalance_request, initial_weights, rebalance_capital_audited, rebalance_capital_with,
+    recenter_at_fair, CapitalFlows, ConservationAudit, RebalanceMode,
+};
+use crate::events::{read_slots, AmmSnapshot, BudgetEvent, EventLog, FaultEvent, HaltEvent, StepSnapshot, StrategyLogEvent, TamperEvent, TradeEvent, TradeKind};
 use crate::market::{
-    gbm_step, generate_retail_orders, optimal_arb_trade, route_order_n_amms,
-    apply_cpamm_trade,
+    assign_limit_prices, gbm_step, generate_retail_orders_clustered, mark_rfq_orders,
+    optimal_arb_trade, route_order_naive, route_order_on_curves, route_order_rfq, route_order_with_limit, apply_cpamm_trade, demand_multiplier, plan_sandwich,
+    sample_shock,
+    FlowState, OrderKind, Quantization, RetailOrder, SandwichPlan, SizeClustering, TwapBook, VolRegime,
 };
-use crate::runner::{NormalizerRunner, StrategyRunner};
+use crate::profile::{PhaseTimer, SimProfile, StrategyProfile};
+use crate::reference;
+use crate::runner::{NormalizerRunner, Runtime, StrategyMetadata, StrategyRunner};
+use crate::trace::{SimTrace, TraceFill, TraceOrder, TraceStep};
 use crate::types::{
-    AfterSwapPayload, AmmState, EpochBoundaryPayload, EpochSummary, SimConfig,
-    SCALE_F, TAG_AFTER_SWAP, TAG_EPOCH_BOUNDARY,
+    AfterSwapPayload, AmmState, EpochBoundaryPayload, EpochSummary, ShockEvent, ShockNewsPayload,
+    SimConfig, StepDigestPayload, StepOrdering, MAX_STRATEGIES, SCALE_F, TAG_AFTER_SWAP, TAG_EPOCH_BOUNDARY,
+    TAG_SHOCK_NEWS, TAG_STEP_DIGEST,
 };
-use crate::market::MarketParams;
+use crate::market::{MarketParams, NormalizerParams, ParamDynamics};
 
 // ─── Simulation Result ────────────────────────────────────────────────────────
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
 pub struct StrategyResult {
     pub name: String,
+    /// Model and key/value metadata the strategy reported at load
+    pub metadata: StrategyMetadata,
+    /// Run edge in `SimConfig::numeraire`, each accrual valued at its own fair price
     pub final_edge: f64,
+    /// Net X and Y trade legs valued in `SimConfig::numeraire` at the final fair price
+    pub final_marked_edge: f64,
     pub epoch_summaries: Vec<EpochSummary>,
     pub final_capital_weight: f64,
+    /// Mark-to-market inventory PnL over the run (Y), separate from edge
+    pub final_inventory_pnl: f64,
+    /// Largest peak-to-trough fall of the cumulative-edge path (≥ 0, in the numeraire of
+    /// `final_edge`)
+    pub max_drawdown: f64,
+    /// Downside deviation of per-epoch edge: √(mean(min(0, edge)²)) over all epochs, in
+    /// the numeraire of `final_edge`
+    pub downside_deviation: f64,
+    /// Retail flow captured over the run (Y-equivalent at fair)
+    pub retail_volume: f64,
+    /// Y edge as a fraction of the initial pool value (at the initial fair), annualized at
+    /// `SimConfig::step_dt_seconds` per step (simple, not compounded); `None` when the
+    /// step length is unset
+    pub annualized_return: Option<f64>,
+    /// Fee revenue over the whole run divided by the mean pool value, both in Y at the
+    /// fair price of the moment: a plain fraction per run, not annualized (0 for a run
+    /// with no steps)
+    pub fee_yield: f64,
+    /// Mean effective spread paid by retail takers versus fair (bps, positive = worse)
+    pub mean_effective_spread_bps: f64,
+    /// Mean and median taker price improvement versus the best normalizer quote (bps)
+    pub mean_price_improvement_bps: f64,
+    pub median_price_improvement_bps: f64,
+    /// Calls discarded for exceeding `SimConfig::call_budget`
+    pub budget_overruns: u64,
+    /// Calls that panicked, trapped or crashed the strategy's worker
+    pub faults: u64,
+    /// Quotes that would have paid out the whole output reserve (`SimConfig::quote_guard`)
+    pub over_quotes: u64,
+    /// Retail orders the router kept away from this AMM because its quotes were
+    /// non-monotone or wildly non-concave (`market::degenerate_quotes`; checked with
+    /// `SimConfig::degenerate_quote_guard` or batch-quoted routing)
+    pub degenerate_orders: u64,
+    /// Caught writing outside its storage (`SimConfig::harden_storage`) or over-quoting
+    /// `QuoteGuard::disqualify_after` times; the strategy was disabled at that point and
+    /// should not be ranked
+    pub disqualified: bool,
 }
 
 #[derive(Clone, Debug)]
 pub struct SimResult {
+    /// Seed the simulation ran with
+    pub seed: u64,
     pub strategies: Vec<StrategyResult>,
+    /// Mean edge across normalizer venues
     pub normalizer_edge: f64,
+    /// Edge of each normalizer venue (`SimConfig::n_normalizers` entries)
+    pub normalizer_edges: Vec<f64>,
+    /// Total Y skimmed by the aggregator take-rate (`SimConfig::routing_fee_bps`)
+    pub routing_fees: f64,
+    /// Total protocol take on strategy fee revenue (`SimConfig::protocol_fee_share`, Y)
+    pub protocol_fees: f64,
+    /// Capital weights per strategy at the start of each epoch: the initial split, then
+    /// the result of every rebalance
+    pub capital_weights: Vec<Vec<f64>>,
+    /// Net external capital deposited (> 0) or withdrawn (< 0) over the run (Y)
+    pub net_capital_flow: f64,
+    /// One-way capital turnover summed over rebalances (½ Σ |Δ weight| per epoch)
+    pub capital_turnover: f64,
+    /// Total turnover cost paid on moved capital (`SimConfig::turnover_cost_bps`, Y)
+    pub turnover_costs: f64,
+    /// Total profit extracted by the sandwich attacker (Y)
+    pub sandwich_profit: f64,
+    /// Total filled retail volume across all AMMs (Y-equivalent at fair)
+    pub retail_volume: f64,
+    /// Retail volume left unfilled pool-wide in each epoch that ended in a rebalance, in
+    /// step with `StrategyResult::epoch_summaries` (Y-equivalent at fair): by limit prices,
+    /// drained pools or excluded quotes
+    pub unfilled_volume: Vec<f64>,
+    /// Price shocks that landed during the run
+    pub shocks: Vec<ShockEvent>,
+    /// Every executed trade, when `SimConfig::record_trades` is set (empty otherwise)
+    pub trades: Vec<TradeEvent>,
+    /// Circuit-breaker halts, in trigger order (empty when off)
+    pub halts: Vec<HaltEvent>,
+    /// Strategy calls over `SimConfig::call_budget`, grouped by strategy (empty when off)
+    pub budget_events: Vec<BudgetEvent>,
+    /// Strategy calls that panicked, trapped or crashed, grouped by strategy
+    pub fault_events: Vec<FaultEvent>,
+    /// Strategy calls that wrote outside their storage, when `SimConfig::harden_storage`
+    /// is set
+    pub tamper_events: Vec<TamperEvent>,
+    /// Diagnostics lines from native strategies, grouped by strategy, when
+    /// `SimConfig::strategy_logs` is set (at most `runner::MAX_LOG_LINES` each)
+    pub strategy_logs: Vec<StrategyLogEvent>,
+    /// Rebalances whose fair-value capital drifted beyond tolerance, when
+    /// `SimConfig::assert_invariants` is set
+    pub conservation_violations: Vec<ConservationAudit>,
+    /// Per-AMM state every `SimConfig::snapshot_stride` steps (empty when off)
+    pub snapshots: Vec<StepSnapshot>,
+    /// Market path + order stream + routing decisions, when `SimConfig::record_trace` is set
+    pub trace: Option<SimTrace>,
+    /// Market parameters as sampled for step 0
     pub market_params: MarketParams,
+    /// Regime in force from each epoch boundary onward, under non-`Fixed`
+    /// `SimConfig::param_dynamics` (empty otherwise)
+    pub market_regimes: Vec<MarketParams>,
+    /// Wall-time breakdown, when `SimConfig::profile` is set
+    pub profile: Option<SimProfile>,
+    /// The run was stopped early by a `CancelToken`; results cover only the steps run
+    pub cancelled: bool,
+}
+
+/// Cooperative cancellation flag shared between a controller (e.g. a Ctrl-C handler)
+/// and running simulations, which stop after their current step.
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken(std::sync::Arc<std::sync::atomic::AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(std::sync::atomic::Ordering::Relaxed)
+    }
 }
 
 // ─── Core Simulation ──────────────────────────────────────────────────────────
 
 /// Run one complete multi-epoch simulation with N strategies + 1 normalizer.
 ///
-/// `runners` — one compiled StrategyRunner per strategy (loaded before calling).
-/// The normalizer is always appended as the last AMM internally.
+/// `runners` — one compiled StrategyRunner per strategy (loaded before calling). The
+/// runners are shared, so the simulation cannot apply `config`'s per-runner settings
+/// (call budget, quote guard and the like) itself: call `StrategyRunner::configure` on
+/// each first, as `with_runners` does. The normalizer is always appended as the last AMM
+/// internally. `config` should have passed `SimConfig::validate`; fails, before the
+/// first step, only if a plug-in allocator no longer loads.
 pub fn run_simulation(
     runners: &[StrategyRunner],
     config: &SimConfig,
     seed: u64,
-) -> SimResult {
+) -> Result<SimResult, String> {
+    simulate(runners, config, seed, None, &CancelToken::default())
+}
+
+/// `run_simulation` that stops after the current step once `cancel` fires.
+/// The returned result then covers only the steps run and has `cancelled` set.
+pub fn run_simulation_cancellable(
+    runners: &[StrategyRunner],
+    config: &SimConfig,
+    seed: u64,
+    cancel: &CancelToken,
+) -> Result<SimResult, String> {
+    simulate(runners, config, seed, None, cancel)
+}
+
+/// Re-run a (possibly different) strategy set against a recorded trace.
+///
+/// Market parameters, the fair-price path, shocks and the retail order stream are taken
+/// verbatim from `trace`; only the strategies' quotes — and therefore routing — differ.
+/// The trace's length overrides `config.total_steps`.
+pub fn replay_simulation(
+    runners: &[StrategyRunner],
+    config: &SimConfig,
+    trace: &SimTrace,
+) -> Result<SimResult, String> {
+    simulate(runners, config, trace.seed, Some(trace), &CancelToken::default())
+}
+
+fn simulate(
+    runners: &[StrategyRunner],
+    config: &SimConfig,
+    seed: u64,
+    replay: Option<&SimTrace>,
+    cancel: &CancelToken,
+) -> Result<SimResult, String> {
+    let started = Instant::now();
+    let mut allocator = config.allocator.build()?;
     let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    // Draws a trace does not record (router noise, capital flows) come from their own
+    // stream, so a replay makes them in the same sequence as the recorded run
+    let mut flow_rng = ChaCha8Rng::seed_from_u64(seed);
+    flow_rng.set_stream(1);
 
     // ── 1. Sample market parameters ────────────────────────────────────────────
-    let params = MarketParams::sample(&mut rng);
-    let norm = NormalizerRunner { fee_bps: params.norm_fee_bps };
+    let mut params = match replay {
+        Some(trace) => trace.params.clone(),
+        None => {
+            let mut params = MarketParams::sample(&mut rng);
+            // Pinned values replace the draws after the fact, so the RNG stream is unchanged
+            if let Some(sigma) = config.annual_volatility.and_then(|vol| config.sigma_from_annual(vol)) {
+                params.sigma = sigma;
+            }
+            if let Some(lambda) = config.orders_per_minute.and_then(|rate| config.lambda_from_per_minute(rate)) {
+                params.lambda = lambda;
+            }
+            params.sigma = config.sigma_per_step.unwrap_or(params.sigma);
+            params.lambda = config.orders_per_step.unwrap_or(params.lambda);
+            params.norm_fee_bps = config.norm_fee_bps.unwrap_or(params.norm_fee_bps);
+            params.norm_liquidity_mult = config.norm_liquidity_mult.unwrap_or(params.norm_liquidity_mult);
+            params.arrival_curve = config.arrival_curve;
+            params.clustering = SizeClustering::sample(config.size_clustering, &mut rng);
+            params.extra_normalizers = (1..config.n_normalizers)
+                .map(|_| NormalizerParams::sample(&mut rng))
+                .collect();
+            params
+        }
+    };
+    let initial_params = params.clone();
+    let total_steps = replay.map_or(config.total_steps, |t| t.steps.len());
+    let norm_params = params.normalizers();
+    let norms: Vec<NormalizerRunner> = norm_params
+        .iter()
+        .map(|p| NormalizerRunner { fee_bps: p.fee_bps })
+        .collect();
 
     // ── 2. Initialise AMM states ───────────────────────────────────────────────
-    // Strategies share equal initial capital; normalizer gets its sampled multiplier.
+    // Strategies get base capital scaled by their initial weight (equal by default);
+    // normalizers get their sampled multipliers.
     let n_strat = runners.len();
+    let names: Vec<&str> = runners.iter().map(|r| r.name.as_str()).collect();
+    let weights = initial_weights(&names, config);
 
     let mut strat_amms: Vec<AmmState> = runners.iter().enumerate().map(|(i, r)| {
-        let mut s = AmmState::new(config.base_reserve_x, config.base_reserve_y, i as u8, &r.name);
-        s.capital_weight = 1.0 / n_strat as f64;
+        let scale = if config.initial_weights.is_empty() { 1.0 } else { weights[i] * n_strat as f64 };
+        let (rx, ry) = config.initial_reserves(scale);
+        let mut s = AmmState::new(rx, ry, i as u8, &r.name);
+        s.capital_weight = weights[i];
+        s.set_storage_latency(config.storage_latency_trades);
         s
     }).collect();
 
-    let norm_rx = ((config.base_reserve_x as f64) * params.norm_liquidity_mult) as u64;
-    let norm_ry = ((config.base_reserve_y as f64) * params.norm_liquidity_mult) as u64;
-    let mut norm_amm = AmmState::new(norm_rx, norm_ry, n_strat as u8, "Normalizer");
+    let mut fair_price = config.initial_fair_price();
+    let initial_values: Vec<f64> = strat_amms.iter().map(|a| a.value_at(fair_price)).collect();
+
+    let mut norm_amms: Vec<AmmState> = norm_params.iter().enumerate().map(|(k, p)| {
+        let base_x = config.initial_price.map_or(config.base_reserve_x as f64, |price| config.base_reserve_y as f64 / price);
+        let rx = (base_x * p.liquidity_mult) as u64;
+        let ry = ((config.base_reserve_y as f64) * p.liquidity_mult) as u64;
+        let name = if k == 0 { "Normalizer".to_string() } else { format!("Normalizer{}", k + 1) };
+        AmmState::new(rx, ry, (n_strat + k) as u8, &name)
+    }).collect();
 
     // ── 3. Epoch tracking ──────────────────────────────────────────────────────
     let mut all_epoch_summaries: Vec<Vec<EpochSummary>> = vec![vec![]; n_strat];
 
-    let mut fair_price = config.base_reserve_y as f64 / config.base_reserve_x as f64;
+    let mut routing_fees = 0.0_f64;
+    let mut epoch_unfilled_y = 0.0_f64;
+    let mut unfilled_volume = Vec::new();
+    let mut epoch_rfq_requests = 0u64;
+    let mut sandwich_profit = 0.0_f64;
+    let mut retail_volume = 0.0_f64;
+    let mut twap_book = TwapBook::default();
+    let mut flow_state = FlowState::default();
+    let mut log = EventLog::new(config.record_trades);
+    let mut halts: Vec<HaltEvent> = Vec::new();
+    let mut conservation_violations: Vec<ConservationAudit> = Vec::new();
+    let mut snapshots: Vec<StepSnapshot> = Vec::new();
+    let mut trace_steps: Vec<TraceStep> = Vec::new();
+    let mut market_regimes: Vec<MarketParams> = Vec::new();
+    let mut edge_paths: Vec<EdgePath> = vec![EdgePath::default(); n_strat];
+    let mut pool_value_sums = vec![0.0; n_strat];
+    let mut capital_weights = vec![strat_amms.iter().map(|a| a.capital_weight).collect::<Vec<f64>>()];
+    let mut taker_spreads: Vec<Vec<f64>> = vec![Vec::new(); n_strat];
+    let mut taker_improvements: Vec<Vec<f64>> = vec![Vec::new(); n_strat];
+    let mut degenerate_orders = vec![0u64; n_strat];
+    let mut shocks: Vec<ShockEvent> = Vec::new();
+    // No news can precede step 0, so its shock (if any) is only decided here
+    let mut next_shock = match replay {
+        Some(trace) => trace.shock_at(0),
+        None => sample_shock(0, &config.shock_schedule, config.shock_prob, config.shock_size, &mut rng),
+    };
+
+    let mut profile = config.profile.then(|| SimProfile { simulations: 1, ..SimProfile::default() });
 
     // ── 4. Main simulation loop ────────────────────────────────────────────────
-    for step in 0..config.total_steps {
+    let mut cancelled = false;
+    for step in 0..total_steps {
+        if cancel.is_cancelled() {
+            cancelled = true;
+            break;
+        }
+        for runner in runners {
+            runner.set_step(step as u64);
+        }
+
         // ── 4a. Price step ────────────────────────────────────────────────────
-        fair_price = gbm_step(fair_price, params.sigma, &mut rng);
-
-        // ── 4b. Arbitrage each strategy AMM ───────────────────────────────────
-        for idx in 0..n_strat {
-            let strat_snapshot = strat_amms.to_vec();
-            let runner = &runners[idx];
-            let amm = &mut strat_amms[idx];
-            let cs = |is_buy: bool, input: u64, rx: u64, ry: u64| -> u64 {
-                runner.compute_swap(is_buy, input, rx, ry, &amm.storage)
-            };
+        let prev_fair = fair_price;
+        if let Some(trace) = replay {
+            fair_price = trace.steps[step].fair_price;
+            if let Some(jump) = next_shock.take() {
+                shocks.push(ShockEvent { step, jump });
+            }
+            next_shock = trace.shock_at(step + 1);
+        } else {
+            fair_price = gbm_step(fair_price, params.sigma, &mut rng);
 
-            if let Some((is_buy, arb_in, arb_out)) =
-                optimal_arb_trade(amm, fair_price, config.arb_profit_floor, cs)
-            {
-                amm.accrue_edge(
-                    if is_buy { arb_out } else { arb_in },
-                    if is_buy { arb_in } else { arb_out },
-                    is_buy,
-                    fair_price,
+            // Shock decided (and optionally announced) on the previous step lands now
+            if let Some(jump) = next_shock.take() {
+                fair_price *= (1.0 + jump).max(1e-9);
+                shocks.push(ShockEvent { step, jump });
+            }
+            next_shock = sample_shock(
+                step + 1, &config.shock_schedule, config.shock_prob, config.shock_size, &mut rng,
+            );
+        }
+        for amm in strat_amms.iter_mut().chain(norm_amms.iter_mut()) {
+            amm.mark_to_market(prev_fair, fair_price);
+        }
+        for (sum, amm) in pool_value_sums.iter_mut().zip(&strat_amms) {
+            *sum += amm.value_at(fair_price);
+        }
+        if let (Some(jump), true) = (next_shock, config.shock_news) {
+            for (runner, amm) in runners.iter().zip(strat_amms.iter_mut()) {
+                let payload = ShockNewsPayload {
+                    tag: TAG_SHOCK_NEWS,
+                    shock_step: (step + 1) as u64,
+                    magnitude: jump.abs(),
+                    storage: amm.storage,
+                };
+                runner.shock_news(&payload, &mut amm.storage);
+            }
+        }
+
+        // ── 4b. Retail order generation ───────────────────────────────────────
+        let (orders, events) = if let Some(trace) = replay {
+            let recorded = &trace.steps[step];
+            let orders: Vec<RetailOrder> = recorded.orders.iter().map(|t| t.order.clone()).collect();
+            let mut events: Vec<StepEvent> = (0..orders.len()).map(StepEvent::Retail).collect();
+            events.insert((recorded.arb_position as usize).min(orders.len()), StepEvent::Arb);
+            (orders, events)
+        } else {
+            let phase = (step % config.epoch_len) as f64 / config.epoch_len as f64;
+            let mut orders = generate_retail_orders_clustered(&params, phase, &mut flow_state, &mut rng);
+            if config.twap_prob > 0.0 || !twap_book.parents.is_empty() {
+                orders = twap_book.process(
+                    orders, config.twap_prob, config.twap_slices, config.twap_size_mult, &mut rng,
                 );
-                apply_cpamm_trade(&mut amm.reserve_x, &mut amm.reserve_y, is_buy, arb_in, arb_out);
-
-                // Notify strategy of arb trade
-                dispatch_after_swap(
-                    runner, amm, is_buy, arb_in, arb_out,
-                    step as u64, step as u32 % config.epoch_len as u32,
-                    (step / config.epoch_len) as u32,
-                    0.0, // arb trade: not a retail split
-                    &strat_snapshot, &norm_amm,
-                    n_strat,
+            }
+            if config.rfq_prob > 0.0 {
+                mark_rfq_orders(&mut orders, config.rfq_prob, &mut rng);
+            }
+            if config.limit_order_prob > 0.0 {
+                assign_limit_prices(
+                    &mut orders, fair_price, config.limit_order_prob, config.limit_tolerance_mean, &mut rng,
                 );
             }
+
+            // Intra-step order of arbitrage and retail routing
+            let mut events: Vec<StepEvent> = Vec::with_capacity(orders.len() + 1);
+            match config.step_ordering {
+                StepOrdering::ArbFirst => {
+                    events.push(StepEvent::Arb);
+                    events.extend((0..orders.len()).map(StepEvent::Retail));
+                }
+                StepOrdering::RetailFirst => {
+                    events.extend((0..orders.len()).map(StepEvent::Retail));
+                    events.push(StepEvent::Arb);
+                }
+                StepOrdering::RandomInterleave => {
+                    events.push(StepEvent::Arb);
+                    events.extend((0..orders.len()).map(StepEvent::Retail));
+                    events.shuffle(&mut rng);
+                }
+            }
+            (orders, events)
+        };
+
+        // ── 4c. Arbitrage + retail routing in the configured intra-step order ─
+        let mut trace_step = TraceStep { fair_price, arb_position: 0, orders: Vec::new() };
+        let (mut step_arbs, mut step_retail_y, mut step_net_flow_y) = (0u32, 0.0, 0.0);
+
+        for (position, event) in events.into_iter().enumerate() {
+            match event {
+                StepEvent::Arb => {
+                    trace_step.arb_position = position as u32;
+                    let timer = PhaseTimer::start(config.profile, runners);
+                    step_arbs += arb_all(
+                        &mut strat_amms, &mut norm_amms, &norms, runners, fair_price, step, config,
+                        &mut log,
+                    );
+                    timer.stop(runners, profile.as_mut().map(|p| &mut p.arb));
+                }
+                StepEvent::Retail(k) => {
+                    let order = &orders[k];
+                    let timer = PhaseTimer::start(config.profile, runners);
+                    let outcome = route_retail_order(
+                        order,
+                        &mut strat_amms,
+                        &mut norm_amms,
+                        &norms,
+                        runners,
+                        fair_price,
+                        step,
+                        config,
+                        &mut flow_rng,
+                        &mut log,
+                    );
+                    timer.stop(runners, profile.as_mut().map(|p| &mut p.routing));
+                    routing_fees += outcome.routing_fee;
+                    epoch_unfilled_y += outcome.unfilled_y;
+                    sandwich_profit += outcome.sandwich_profit_y;
+                    retail_volume += outcome.filled_y;
+                    step_retail_y += outcome.filled_y;
+                    for &amm in outcome.excluded.iter().filter(|&&amm| amm < n_strat) {
+                        degenerate_orders[amm] += 1;
+                    }
+                    for &(amm, spread, improvement) in &outcome.taker_fills {
+                        taker_spreads[amm].push(spread);
+                        taker_improvements[amm].push(improvement);
+                    }
+                    step_net_flow_y += if order.is_buy { outcome.filled_y } else { -outcome.filled_y };
+                    if order.kind == OrderKind::Rfq {
+                        epoch_rfq_requests += 1;
+                    }
+                    if config.record_trace {
+                        let fills = outcome.allocations.iter().enumerate()
+                            .filter(|(_, &(input, _))| input > 0)
+                            .map(|(amm, &(input, output))| TraceFill { amm: amm as u8, input, output })
+                            .collect();
+                        trace_step.orders.push(TraceOrder { order: order.clone(), fills });
+                    }
+                }
+            }
+            if config.kill_reserve_floor > 0.0 {
+                apply_kill_switch(&mut strat_amms, config, step);
+            }
+        }
+        if config.record_trace {
+            trace_steps.push(trace_step);
         }
 
-        // Arbitrage normalizer (plain CPAMM)
-        arb_normalizer(&mut norm_amm, &norm, fair_price, config.arb_profit_floor);
+        if config.circuit_breaker_band > 0.0 {
+            apply_circuit_breaker(&mut strat_amms, fair_price, config, step, &mut halts);
+        }
 
-        // ── 4c. Retail order routing ──────────────────────────────────────────
-        let orders = generate_retail_orders(&params, &mut rng);
-        for order in &orders {
-            route_retail_order(
-                order.is_buy,
-                order.size_y,
-                &mut strat_amms,
-                &mut norm_amm,
-                &norm,
-                runners,
-                fair_price,
-                step,
-                config,
-            );
+        // Pool-wide step digest, identical for every strategy
+        if config.step_digest {
+            for (runner, amm) in runners.iter().zip(strat_amms.iter_mut()) {
+                let payload = StepDigestPayload {
+                    tag: TAG_STEP_DIGEST,
+                    sim_step: step as u64,
+                    retail_notional: step_retail_y,
+                    net_retail_flow: step_net_flow_y,
+                    arb_count: step_arbs,
+                    storage: amm.storage,
+                };
+                runner.step_digest(&payload, &mut amm.storage);
+            }
         }
 
         // ── 4d. Epoch boundary ────────────────────────────────────────────────
         let at_epoch_end = (step + 1) % config.epoch_len == 0;
-        let last_step = step == config.total_steps - 1;
+        let last_step = step == total_steps - 1;
+
+        for (path, amm) in edge_paths.iter_mut().zip(&strat_amms) {
+            let edge = amm.edge_in(config.numeraire);
+            path.update(edge);
+            if at_epoch_end || last_step {
+                path.close_epoch(edge);
+            }
+        }
 
         if at_epoch_end && !last_step {
+            let timer = PhaseTimer::start(config.profile, runners);
             let epoch_number = ((step + 1) / config.epoch_len) as u32;
-            let summaries = rebalance_capital(&mut strat_amms, config, epoch_number - 1);
+            let recentered: Vec<(f64, f64)> = match config.rebalance_mode {
+                RebalanceMode::PreserveSpot => vec![(0.0, 0.0); strat_amms.len()],
+                RebalanceMode::InventoryNeutral => strat_amms
+                    .iter_mut()
+                    .map(|amm| recenter_at_fair(amm, fair_price, config.rebalance_cost_bps))
+                    .collect(),
+            };
+            let mut summaries = if config.assert_invariants {
+                let (summaries, audit) = rebalance_capital_audited(
+                    &mut strat_amms, config, epoch_number - 1, allocator.as_mut(), fair_price,
+                );
+                if audit.check(config.conservation_tolerance).is_err() {
+                    conservation_violations.push(audit);
+                }
+                summaries
+            } else {
+                rebalance_capital_with(&mut strat_amms, config, epoch_number - 1, allocator.as_mut())
+            };
+            for (s, (transfer, cost)) in summaries.iter_mut().zip(recentered) {
+                s.inventory_transfer = transfer;
+                s.recenter_cost = cost;
+            }
+            capital_weights.push(strat_amms.iter().map(|a| a.capital_weight).collect());
+            if config.capital_flows != CapitalFlows::None {
+                let fraction = config.capital_flows.fraction(epoch_number - 1, &mut flow_rng);
+                for (s, flow) in summaries.iter_mut().zip(apply_capital_flow(&mut strat_amms, fraction)) {
+                    s.capital_flow = flow;
+                }
+            }
 
             // Notify each strategy of epoch boundary + new capital
             for (idx, (runner, amm)) in runners.iter().zip(strat_amms.iter_mut()).enumerate() {
@@ -151,132 +554,682 @@ pub fn run_simulation(
                     storage: amm.storage, // placeholder — real storage passed via runner
                 };
                 runner.epoch_boundary(&payload, &mut amm.storage);
+                if config.strategy_rebalancing {
+                    summaries[idx].rebalance_cost =
+                        apply_rebalance_request(amm, fair_price, config.rebalance_cost_bps);
+                }
             }
 
-            for (idx, s) in summaries.into_iter().enumerate() {
+            unfilled_volume.push(epoch_unfilled_y);
+            for (idx, mut s) in summaries.into_iter().enumerate() {
+                s.rfq_requests = epoch_rfq_requests;
                 all_epoch_summaries[idx].push(s);
             }
+            epoch_unfilled_y = 0.0;
+            epoch_rfq_requests = 0;
+
+            // Regime change for the next epoch (a replay's order stream already reflects it)
+            if replay.is_none() && config.param_dynamics != ParamDynamics::Fixed {
+                params.evolve(config.param_dynamics, &mut rng);
+                market_regimes.push(params.clone());
+            }
+            timer.stop(runners, profile.as_mut().map(|p| &mut p.rebalancing));
+        }
+
+        // ── 4e. State snapshot ────────────────────────────────────────────────
+        if config.snapshot_stride > 0 && step % config.snapshot_stride == 0 {
+            snapshots.push(StepSnapshot {
+                step,
+                fair_price,
+                amms: strat_amms.iter().map(|amm| AmmSnapshot {
+                    storage: if config.snapshot_storage { amm.storage.to_vec() } else { Vec::new() },
+                    ..amm.into()
+                })
+                .chain(norm_amms.iter().map(Into::into))
+                .collect(),
+            });
         }
     }
 
     // ── 5. Build result ────────────────────────────────────────────────────────
+    let budget_events: Vec<BudgetEvent> = runners.iter().enumerate()
+        .flat_map(|(amm, r)| r.take_overruns().into_iter().map(move |(step, call)| BudgetEvent { amm, step, call }))
+        .collect();
+    let fault_events: Vec<FaultEvent> = runners.iter().enumerate()
+        .flat_map(|(amm, r)| r.take_faults().into_iter().map(move |(step, call)| FaultEvent { amm, step, call }))
+        .collect();
+    let tamper_events: Vec<TamperEvent> = runners.iter().enumerate()
+        .flat_map(|(amm, r)| r.take_tampers().into_iter().map(move |(step, call)| TamperEvent { amm, step, call }))
+        .collect();
+    let mut strategy_logs: Vec<StrategyLogEvent> = runners.iter().enumerate()
+        .flat_map(|(amm, r)| r.take_logs().into_iter().map(move |(step, call, line)| StrategyLogEvent { amm, step, call, line }))
+        .collect();
+    strategy_logs.sort_by_key(|e| e.step);
+    let over_quotes: Vec<u64> = runners.iter().map(|r| r.take_over_quotes().len() as u64).collect();
     let strategies: Vec<StrategyResult> = strat_amms.iter().enumerate().map(|(i, amm)| {
+        let over_quote_strikes = config.quote_guard.disqualify_after.is_some_and(|n| over_quotes[i] >= n as u64);
         StrategyResult {
             name: amm.name.clone(),
-            final_edge: amm.cumulative_edge,
+            metadata: runners[i].metadata.clone(),
+            final_edge: amm.edge_in(config.numeraire),
+            final_marked_edge: amm.marked_legs(config.numeraire, fair_price),
             epoch_summaries: all_epoch_summaries[i].clone(),
             final_capital_weight: amm.capital_weight,
+            final_inventory_pnl: amm.cumulative_inventory_pnl,
+            max_drawdown: edge_paths[i].max_drawdown,
+            downside_deviation: edge_paths[i].downside_deviation(),
+            retail_volume: amm.cumulative_retail_volume,
+            annualized_return: config.annualize_return(amm.cumulative_edge / initial_values[i], total_steps),
+            fee_yield: if pool_value_sums[i] > 0.0 {
+                amm.cumulative_fee_revenue * total_steps as f64 / pool_value_sums[i]
+            } else {
+                0.0
+            },
+            mean_effective_spread_bps: mean(&taker_spreads[i]),
+            mean_price_improvement_bps: mean(&taker_improvements[i]),
+            median_price_improvement_bps: median(&mut taker_improvements[i]),
+            budget_overruns: budget_events.iter().filter(|e| e.amm == i).count() as u64,
+            faults: fault_events.iter().filter(|e| e.amm == i).count() as u64,
+            over_quotes: over_quotes[i],
+            degenerate_orders: degenerate_orders[i],
+            disqualified: over_quote_strikes || tamper_events.iter().any(|e| e.amm == i),
         }
     }).collect();
 
-    SimResult {
+    if let Some(profile) = &mut profile {
+        profile.wall = started.elapsed();
+        profile.strategies = runners.iter()
+            .map(|r| StrategyProfile { name: r.name.clone(), calls: r.take_timings() })
+            .collect();
+    }
+
+    let trace = config.record_trace.then(|| SimTrace {
+        seed,
+        params: initial_params.clone(),
+        shocks: shocks.clone(),
+        steps: trace_steps,
+    });
+
+    Ok(SimResult {
+        seed,
         strategies,
-        normalizer_edge: norm_amm.cumulative_edge,
-        market_params: params,
+        normalizer_edge: norm_amms.iter().map(|a| a.edge_in(config.numeraire)).sum::<f64>()
+            / norm_amms.len() as f64,
+        normalizer_edges: norm_amms.iter().map(|a| a.edge_in(config.numeraire)).collect(),
+        routing_fees,
+        protocol_fees: all_epoch_summaries.iter().flatten().map(|s| s.protocol_fee).sum(),
+        capital_weights,
+        net_capital_flow: all_epoch_summaries.iter().flatten().map(|s| s.capital_flow).sum(),
+        capital_turnover: all_epoch_summaries.iter().flatten().map(|s| s.turnover).sum::<f64>() / 2.0,
+        turnover_costs: all_epoch_summaries.iter().flatten().map(|s| s.turnover_cost).sum(),
+        sandwich_profit,
+        retail_volume,
+        unfilled_volume,
+        shocks,
+        trades: log.into_events(),
+        halts,
+        budget_events,
+        fault_events,
+        tamper_events,
+        strategy_logs,
+        conservation_violations,
+        snapshots,
+        trace,
+        market_params: initial_params,
+        market_regimes,
+        profile,
+        cancelled,
+    })
+}
+
+/// Running statistics of one strategy's cumulative-edge path.
+#[derive(Clone, Debug, Default)]
+struct EdgePath {
+    peak: f64,
+    max_drawdown: f64,
+    epoch_start_edge: f64,
+    epoch_edges: Vec<f64>,
+}
+
+impl EdgePath {
+    fn update(&mut self, edge: f64) {
+        self.peak = self.peak.max(edge);
+        self.max_drawdown = self.max_drawdown.max(self.peak - edge);
+    }
+
+    fn close_epoch(&mut self, edge: f64) {
+        self.epoch_edges.push(edge - self.epoch_start_edge);
+        self.epoch_start_edge = edge;
+    }
+
+    fn downside_deviation(&self) -> f64 {
+        if self.epoch_edges.is_empty() { return 0.0; }
+        let sq = self.epoch_edges.iter().map(|e| e.min(0.0).powi(2)).sum::<f64>();
+        (sq / self.epoch_edges.len() as f64).sqrt()
     }
 }
 
+fn mean(xs: &[f64]) -> f64 {
+    if xs.is_empty() { 0.0 } else { xs.iter().sum::<f64>() / xs.len() as f64 }
+}
+
+fn median(xs: &mut [f64]) -> f64 {
+    if xs.is_empty() { return 0.0; }
+    xs.sort_by(f64::total_cmp);
+    let mid = xs.len() / 2;
+    if xs.len().is_multiple_of(2) { 0.5 * (xs[mid - 1] + xs[mid]) } else { xs[mid] }
+}
+
+// ─── Arbitrage (all strategies + normalizer) ─────────────────────────────────
+
+/// One unit of intra-step work, ordered per `SimConfig::step_ordering`.
+enum StepEvent {
+    /// Arbitrage pass over every AMM
+    Arb,
+    /// Route the k-th retail order of this step
+    Retail(usize),
+}
+
+/// Size the arbitrage trade against one strategy AMM at its current reserves.
+fn plan_arb(
+    amm: &AmmState,
+    runner: &StrategyRunner,
+    fair_price: f64,
+    config: &SimConfig,
+) -> Option<(bool, u64, u64)> {
+    if amm.frozen_at.is_some() {
+        return None;
+    }
+    let q = config.quantization;
+    let cs = |is_buy: bool, input: u64, rx: u64, ry: u64| -> u64 {
+        q.quote(is_buy, input, |input| runner.compute_swap(is_buy, input, rx, ry, amm.quote_storage()))
+    };
+    optimal_arb_trade(amm, fair_price, config.arb_profit_floor, config.sanity_points(), cs)
+        .map(|(is_buy, input, output)| (is_buy, q.lot(input), output))
+        .filter(|&(_, input, _)| input > 0)
+}
+
+/// Arbitrage every strategy AMM against the fair price, then the normalizers.
+/// Returns the number of arbitrage trades executed.
+#[allow(clippy::too_many_arguments)]
+fn arb_all(
+    strat_amms: &mut [AmmState],
+    norm_amms: &mut [AmmState],
+    norms: &[NormalizerRunner],
+    runners: &[StrategyRunner],
+    fair_price: f64,
+    step: usize,
+    config: &SimConfig,
+    log: &mut EventLog,
+) -> u32 {
+    let n_strat = strat_amms.len();
+    let mut arb_count = 0;
+
+    // An arb only moves its own AMM, so it is sized the same in either mode. What
+    // simultaneous mode changes is the competitor reserves every after_swap sees: the
+    // pre-arb snapshot, so no strategy learns of arbs run before it by index.
+    let common_snapshot = config.simultaneous_arb.then(|| strat_amms.to_vec());
+
+    for idx in 0..n_strat {
+        if strat_amms[idx].frozen_at.is_some() { continue; }
+        let sequential_snapshot;
+        let strat_snapshot = match &common_snapshot {
+            Some(snapshot) => snapshot,
+            None => {
+                sequential_snapshot = strat_amms.to_vec();
+                &sequential_snapshot
+            }
+        };
+        let runner = &runners[idx];
+        let plan = plan_arb(&strat_amms[idx], runner, fair_price, config);
+        let amm = &mut strat_amms[idx];
+
+        if let Some((is_buy, arb_in, arb_out)) = plan {
+            let fee = amm.record_fill(is_buy, arb_in, arb_out, fair_price, true);
+            amm.settle_fill(is_buy, arb_in, arb_out, fee, config.fees_outside_reserves);
+            arb_count += 1;
+
+            // Notify strategy of arb trade
+            dispatch_after_swap(
+                runner, amm, is_buy, arb_in, arb_out,
+                step as u64, step as u32 % config.epoch_len as u32,
+                (step / config.epoch_len) as u32,
+                0.0, // arb trade: not a retail split
+                strat_snapshot, norm_amms,
+                n_strat + norm_amms.len(),
+            );
+            log.record(TradeEvent {
+                step, amm: idx, kind: TradeKind::Arb, is_buy,
+                input: arb_in, output: arb_out, fair_price, flow_share: 0.0,
+                slots: read_slots(&amm.storage, &config.trade_slots),
+            });
+        }
+    }
+
+    // Arbitrage normalizers (plain CPAMMs)
+    for (k, (norm_amm, norm)) in norm_amms.iter_mut().zip(norms).enumerate() {
+        if let Some((is_buy, input, output)) =
+            arb_normalizer(norm_amm, norm, fair_price, config.arb_profit_floor, config.quantization)
+        {
+            arb_count += 1;
+            log.record(TradeEvent {
+                step, amm: n_strat + k, kind: TradeKind::Arb, is_buy,
+                input, output, fair_price, flow_share: 0.0, slots: Vec::new(),
+            });
+        }
+    }
+    arb_count
+}
+
 // ─── Retail Order Routing (N strategies + normalizer) ────────────────────────
 
+/// Pool-level side effects of routing one retail order.
+struct RetailOutcome {
+    /// Aggregator fee skimmed before routing (Y)
+    routing_fee: f64,
+    /// Volume left unfilled by the order's limit price (Y-equivalent at fair)
+    unfilled_y: f64,
+    /// Sandwich attacker profit extracted around this order (Y)
+    sandwich_profit_y: f64,
+    /// Filled retail volume after elasticity (Y-equivalent at fair)
+    filled_y: f64,
+    /// Router allocation `(input, quoted_output)` per AMM
+    allocations: Vec<(u64, u64)>,
+    /// Taker quality of each strategy fill: `(amm, effective_spread_bps, price_improvement_bps)`
+    taker_fills: Vec<(usize, f64, f64)>,
+    /// AMMs the router left out for degenerate quotes (see `RoutingResult::excluded`)
+    excluded: Vec<usize>,
+}
+
+/// Route one retail order across all AMMs.
 #[allow(clippy::too_many_arguments)]
 fn route_retail_order(
-    is_buy: bool,
-    size_y: f64,       // order size in Y-denomination (unscaled)
+    order: &RetailOrder,
     strat_amms: &mut [AmmState],
-    norm_amm: &mut AmmState,
-    norm: &NormalizerRunner,
+    norm_amms: &mut [AmmState],
+    norms: &[NormalizerRunner],
     runners: &[StrategyRunner],
     fair_price: f64,
     step: usize,
     config: &SimConfig,
-) {
+    rng: &mut ChaCha8Rng,
+    log: &mut EventLog,
+) -> RetailOutcome {
+    let is_buy = order.is_buy;
     let n_strat = strat_amms.len();
-    // Total N+1 AMMs: strategies + normalizer
+    // Total N+K AMMs: strategies + normalizers
     // We route across all of them simultaneously.
 
     // We need to gather a snapshot of reserves for the router call
-    // (immutable view), then apply mutations after.
-    let all_amm_refs: Vec<AmmState> = strat_amms
+    // (immutable view), then apply mutations after. AMMs frozen by the kill switch,
+    // halted by the circuit breaker or over their inventory-skew limit on this side are
+    // left out, so router indices map back through `active`.
+    let total_n = n_strat + norm_amms.len();
+    let active: Vec<usize> = (0..total_n)
+        .filter(|&i| {
+            i >= n_strat
+                || (strat_amms[i].frozen_at.is_none()
+                    && !strat_amms[i].halted
+                    && !strat_amms[i].skew_blocks(is_buy, fair_price, config.min_inventory_share))
+        })
+        .collect();
+    let all_amm_refs: Vec<AmmState> = active
         .iter()
-        .cloned()
-        .chain(std::iter::once(norm_amm.clone()))
+        .map(|&i| if i < n_strat { strat_amms[i].clone() } else { norm_amms[i - n_strat].clone() })
         .collect();
 
-    let total_n = all_amm_refs.len();
-
-    // Unified compute_swap: dispatches to strategy runner or normalizer by index
+    // Unified compute_swap: dispatches to strategy runner or normalizer by router index
     // We pass reserves explicitly so the router sees the current state.
-    let compute_for_router = |amm_idx: usize, is_b: bool, input: u64, rx: u64, ry: u64| -> u64 {
-        if amm_idx < n_strat {
-            runners[amm_idx].compute_swap(is_b, input, rx, ry, &strat_amms[amm_idx].storage)
+    let q = config.quantization;
+    let compute_for_router = |router_idx: usize, is_b: bool, input: u64, rx: u64, ry: u64| -> u64 {
+        let amm_idx = active[router_idx];
+        q.quote(is_b, input, |input| {
+            if amm_idx < n_strat {
+                runners[amm_idx].compute_swap(is_b, input, rx, ry, strat_amms[amm_idx].quote_storage())
+            } else {
+                norms[amm_idx - n_strat].compute_swap(is_b, input, rx, ry)
+            }
+        })
+    };
+    let batch_for_router = |router_idx: usize, is_b: bool, inputs: &[u64], rx: u64, ry: u64| -> Vec<u64> {
+        let amm_idx = active[router_idx];
+        let lots: Vec<u64> = inputs.iter().map(|&input| q.lot(input)).collect();
+        let outputs: Vec<u64> = if amm_idx < n_strat {
+            runners[amm_idx].compute_swap_batch(is_b, &lots, rx, ry, strat_amms[amm_idx].quote_storage())
+        } else {
+            lots.iter().map(|&input| norms[amm_idx - n_strat].compute_swap(is_b, input, rx, ry)).collect()
+        };
+        if q.is_off() {
+            return outputs;
+        }
+        lots.iter().zip(outputs).map(|(&input, output)| q.output(is_b, input, output)).collect()
+    };
+    let route_optimal = |total_input: f64| {
+        if config.batch_quote_points > 0 {
+            route_order_on_curves(
+                &all_amm_refs, is_buy, total_input, order.min_marginal(), config.router_reserve_floor, config.batch_quote_points,
+                batch_for_router, compute_for_router,
+            )
         } else {
-            norm.compute_swap(is_b, input, rx, ry)
+            route_order_with_limit(
+                &all_amm_refs, is_buy, total_input, order.min_marginal(), config.router_reserve_floor, config.sanity_points(),
+                compute_for_router,
+            )
         }
     };
 
+    // Aggregator take-rate: the routing fee is skimmed before any AMM sees the order.
+    let routing_fee = order.size_y * config.routing_fee_bps as f64 / 10_000.0;
+    let size_y = order.size_y - routing_fee;
+
     // Convert size_y to appropriate input depending on direction.
     // is_buy=true: trader buys X, pays Y → Y is input, size_y is direct
     // is_buy=false: trader sells X for Y → X is input. Approx X size = size_y / fair_price
-    let total_input = if is_buy { size_y } else { size_y / fair_price };
+    let mut total_input = if is_buy { size_y } else { size_y / fair_price };
 
-    let routing = route_order_n_amms(
-        &all_amm_refs,
-        is_buy,
-        total_input,
-        compute_for_router,
-    );
+    // Elastic demand: the order grows when the pool set beats the best single-normalizer
+    // quote (flow that would otherwise go to an outside venue priced like a normalizer).
+    if config.demand_elasticity != 0.0 {
+        let probe = route_optimal(total_input);
+        let norm_out = norms.iter().zip(norm_amms.iter())
+            .map(|(norm, amm)| {
+                q.quote(is_buy, (total_input * SCALE_F) as u64, |input| {
+                    norm.compute_swap(is_buy, input, amm.reserve_x, amm.reserve_y)
+                })
+            })
+            .max()
+            .unwrap_or(0);
+        total_input *= demand_multiplier(
+            probe.total_output, norm_out, config.demand_elasticity, config.demand_max_mult,
+        );
+    }
+
+    // A `router_noise` fraction of orders skips the optimal router (unsophisticated flow).
+    let naive = config.router_noise > 0.0 && rng.gen_bool(config.router_noise.min(1.0));
+
+    let routing = if order.kind == OrderKind::Rfq {
+        route_order_rfq(
+            &all_amm_refs,
+            is_buy,
+            total_input,
+            order.limit_price,
+            compute_for_router,
+        )
+    } else if naive {
+        route_order_naive(
+            &all_amm_refs,
+            is_buy,
+            total_input,
+            config.naive_routing,
+            rng,
+            compute_for_router,
+        )
+    } else {
+        route_optimal(total_input)
+    };
 
     let total_input_scaled = (total_input * SCALE_F) as u64;
 
+    // Sandwich attacker targets the AMM receiving the largest allocation of a large order
+    let sandwich = if config.sandwich_enabled && order.size_y >= config.sandwich_min_order_y {
+        (0..active.len())
+            .max_by_key(|&i| routing.allocations[i].0)
+            .filter(|&i| routing.allocations[i].0 > 0)
+            .and_then(|target| {
+                let (victim_in, victim_quote) = routing.allocations[target];
+                let cs = |is_b: bool, input: u64, rx: u64, ry: u64| -> u64 {
+                    compute_for_router(target, is_b, input, rx, ry)
+                };
+                plan_sandwich(
+                    &all_amm_refs[target], is_buy, victim_in, victim_quote,
+                    config.sandwich_victim_tolerance, cs,
+                )
+                .map(|plan| (active[target], SandwichPlan { front_input: q.lot(plan.front_input), ..plan }))
+            })
+    } else {
+        None
+    };
+    let mut sandwich_profit_y = 0.0;
+    let mut taker_fills = Vec::new();
+
+    let mut allocations = vec![(0u64, 0u64); total_n];
+    for (router_idx, &amm_idx) in active.iter().enumerate() {
+        let (input, output) = routing.allocations[router_idx];
+        allocations[amm_idx] = (q.lot(input), output);
+    }
+
     // Apply trades and accounting
     for amm_idx in 0..total_n {
-        let (input_scaled, output_scaled) = routing.allocations[amm_idx];
+        let (input_scaled, output_scaled) = allocations[amm_idx];
         if input_scaled == 0 { continue; }
 
-            let flow_captured = input_scaled as f32 / total_input_scaled.max(1) as f32;
+        let flow_captured = input_scaled as f32 / total_input_scaled.max(1) as f32;
+        let edge_before = if amm_idx < n_strat { strat_amms[amm_idx].cumulative_edge } else { 0.0 };
 
-        if amm_idx < n_strat {
-            let strat_snapshot = strat_amms.to_vec();
+        match sandwich {
+            Some((target, plan)) if target == amm_idx => {
+                // Front-run (same direction), victim re-quoted on moved reserves, back-run
+                let front_out = quote_amm(amm_idx, is_buy, plan.front_input, strat_amms, norm_amms, norms, runners, q);
+                execute_fill(amm_idx, is_buy, plan.front_input, front_out, 0.0, TradeKind::SandwichFront,
+                             strat_amms, norm_amms, runners, fair_price, step, config, log);
+
+                let victim_out = quote_amm(amm_idx, is_buy, input_scaled, strat_amms, norm_amms, norms, runners, q);
+                if amm_idx < n_strat {
+                    let norm_out = best_normalizer_quote(is_buy, input_scaled, norm_amms, norms, q);
+                    let (spread, improvement) = taker_quality(is_buy, input_scaled, victim_out, fair_price, norm_out);
+                    taker_fills.push((amm_idx, spread, improvement));
+                }
+                execute_fill(amm_idx, is_buy, input_scaled, victim_out, flow_captured, order.kind.into(),
+                             strat_amms, norm_amms, runners, fair_price, step, config, log);
+
+                let back_out = quote_amm(amm_idx, !is_buy, front_out, strat_amms, norm_amms, norms, runners, q);
+                execute_fill(amm_idx, !is_buy, front_out, back_out, 0.0, TradeKind::SandwichBack,
+                             strat_amms, norm_amms, runners, fair_price, step, config, log);
+
+                // Attacker PnL in the order's input token, valued in Y
+                let profit = (back_out as f64 - plan.front_input as f64) / SCALE_F;
+                sandwich_profit_y = if is_buy { profit } else { profit * fair_price };
+                if amm_idx < n_strat {
+                    strat_amms[amm_idx].epoch_sandwiches += 1;
+                } else {
+                    norm_amms[amm_idx - n_strat].epoch_sandwiches += 1;
+                }
+            }
+            _ => {
+                if amm_idx < n_strat {
+                    let norm_out = best_normalizer_quote(is_buy, input_scaled, norm_amms, norms, q);
+                    let (spread, improvement) = taker_quality(is_buy, input_scaled, output_scaled, fair_price, norm_out);
+                    taker_fills.push((amm_idx, spread, improvement));
+                }
+                execute_fill(amm_idx, is_buy, input_scaled, output_scaled, flow_captured, order.kind.into(),
+                             strat_amms, norm_amms, runners, fair_price, step, config, log);
+            }
+        }
+
+        if order.kind == OrderKind::Rfq && amm_idx < n_strat {
             let amm = &mut strat_amms[amm_idx];
-            amm.accrue_edge(
-                if is_buy { output_scaled } else { input_scaled },
-                if is_buy { input_scaled }  else { output_scaled },
-                is_buy,
-                fair_price,
-            );
-            apply_cpamm_trade(&mut amm.reserve_x, &mut amm.reserve_y, is_buy, input_scaled, output_scaled);
+            amm.epoch_rfq_wins += 1;
+            amm.epoch_rfq_edge += amm.cumulative_edge - edge_before;
+        }
+    }
 
-            let epoch_step = step as u32 % config.epoch_len as u32;
-            let epoch_number = (step / config.epoch_len) as u32;
+    let to_y = |amount: f64| if is_buy { amount } else { amount * fair_price };
+    let unfilled = routing.unfilled_input as f64 / SCALE_F;
+    let filled = allocations.iter().map(|&(inp, _)| inp as f64 / SCALE_F).sum::<f64>();
+    RetailOutcome {
+        routing_fee,
+        unfilled_y: to_y(unfilled),
+        sandwich_profit_y,
+        filled_y: to_y(filled),
+        allocations,
+        taker_fills,
+        excluded: routing.excluded.iter().map(|&router_idx| active[router_idx]).collect(),
+    }
+}
 
-            dispatch_after_swap(
-                &runners[amm_idx],
-                amm,
-                is_buy,
-                input_scaled,
-                output_scaled,
-                step as u64,
-                epoch_step,
-                epoch_number,
-                flow_captured,
-                &strat_snapshot,
-                norm_amm,
-                total_n,
-            );
+/// Best output any normalizer would give for `input` at its current reserves.
+fn best_normalizer_quote(
+    is_buy: bool,
+    input: u64,
+    norm_amms: &[AmmState],
+    norms: &[NormalizerRunner],
+    q: Quantization,
+) -> u64 {
+    norms.iter().zip(norm_amms)
+        .map(|(norm, amm)| q.quote(is_buy, input, |input| norm.compute_swap(is_buy, input, amm.reserve_x, amm.reserve_y)))
+        .max()
+        .unwrap_or(0)
+}
+
+/// Taker-side quality of a fill, in bps: effective spread paid versus fair (positive =
+/// worse than fair) and price improvement versus the normalizer quote (positive = better).
+pub fn taker_quality(is_buy: bool, input: u64, output: u64, fair_price: f64, norm_out: u64) -> (f64, f64) {
+    if input == 0 || output == 0 {
+        return (0.0, 0.0);
+    }
+    let spread = if is_buy {
+        (input as f64 / output as f64 / fair_price - 1.0) * 10_000.0
+    } else {
+        (1.0 - output as f64 / input as f64 / fair_price) * 10_000.0
+    };
+    let improvement = if norm_out > 0 { (output as f64 / norm_out as f64 - 1.0) * 10_000.0 } else { 0.0 };
+    (spread, improvement)
+}
+
+/// Freeze any active strategy AMM whose reserves fell below `SimConfig::kill_reserve_floor`.
+fn apply_kill_switch(strat_amms: &mut [AmmState], config: &SimConfig, step: usize) {
+    let floor_x = (config.base_reserve_x as f64 * config.kill_reserve_floor) as u64;
+    let floor_y = (config.base_reserve_y as f64 * config.kill_reserve_floor) as u64;
+    for amm in strat_amms.iter_mut().filter(|a| a.frozen_at.is_none()) {
+        if amm.reserve_x < floor_x || amm.reserve_y < floor_y {
+            amm.frozen_at = Some(step);
+        }
+    }
+}
+
+/// Count consecutive out-of-band steps per strategy AMM; halt routing after
+/// `circuit_breaker_steps` of them and resume once the spot is back inside the band.
+fn apply_circuit_breaker(
+    strat_amms: &mut [AmmState],
+    fair_price: f64,
+    config: &SimConfig,
+    step: usize,
+    halts: &mut Vec<HaltEvent>,
+) {
+    for (idx, amm) in strat_amms.iter_mut().enumerate() {
+        let deviation = (amm.spot_price() / fair_price - 1.0).abs();
+        if deviation > config.circuit_breaker_band {
+            amm.band_breach_steps += 1;
+            if !amm.halted && amm.band_breach_steps >= config.circuit_breaker_steps {
+                amm.halted = true;
+                halts.push(HaltEvent { amm: idx, step, deviation, resumed_at: None });
+            }
         } else {
-            // Normalizer accounting
-            norm_amm.accrue_edge(
-                if is_buy { output_scaled } else { input_scaled },
-                if is_buy { input_scaled }  else { output_scaled },
-                is_buy,
-                fair_price,
-            );
-            apply_cpamm_trade(&mut norm_amm.reserve_x, &mut norm_amm.reserve_y,
-                               is_buy, input_scaled, output_scaled);
+            amm.band_breach_steps = 0;
+            if amm.halted {
+                amm.halted = false;
+                if let Some(h) = halts.iter_mut().rev().find(|h| h.amm == idx) {
+                    h.resumed_at = Some(step);
+                }
+            }
         }
     }
 }
 
+/// Quote AMM `amm_idx` (strategy or normalizer) at its current reserves and storage.
+#[allow(clippy::too_many_arguments)]
+fn quote_amm(
+    amm_idx: usize,
+    is_buy: bool,
+    input: u64,
+    strat_amms: &[AmmState],
+    norm_amms: &[AmmState],
+    norms: &[NormalizerRunner],
+    runners: &[StrategyRunner],
+    q: Quantization,
+) -> u64 {
+    let n_strat = strat_amms.len();
+    q.quote(is_buy, input, |input| {
+        if amm_idx < n_strat {
+            let amm = &strat_amms[amm_idx];
+            runners[amm_idx].compute_swap(is_buy, input, amm.reserve_x, amm.reserve_y, amm.quote_storage())
+        } else {
+            let amm = &norm_amms[amm_idx - n_strat];
+            norms[amm_idx - n_strat].compute_swap(is_buy, input, amm.reserve_x, amm.reserve_y)
+        }
+    })
+}
+
+/// Execute one fill on AMM `amm_idx` (strategy or normalizer): accrue edge at fair,
+/// update reserves and, for strategies, dispatch TAG_AFTER_SWAP.
+#[allow(clippy::too_many_arguments)]
+fn execute_fill(
+    amm_idx: usize,
+    is_buy: bool,
+    input_scaled: u64,
+    output_scaled: u64,
+    flow_captured: f32,
+    kind: TradeKind,
+    strat_amms: &mut [AmmState],
+    norm_amms: &mut [AmmState],
+    runners: &[StrategyRunner],
+    fair_price: f64,
+    step: usize,
+    config: &SimConfig,
+    log: &mut EventLog,
+) {
+    let n_strat = strat_amms.len();
+    let total_n = n_strat + norm_amms.len();
+
+    let slots = if amm_idx < n_strat {
+        let strat_snapshot = strat_amms.to_vec();
+        let amm = &mut strat_amms[amm_idx];
+        let fee = amm.record_fill(is_buy, input_scaled, output_scaled, fair_price, kind.is_informed());
+        amm.settle_fill(is_buy, input_scaled, output_scaled, fee, config.fees_outside_reserves);
+        if !kind.is_informed() {
+            amm.epoch_flow_share_sum += flow_captured as f64;
+        }
+
+        let epoch_step = step as u32 % config.epoch_len as u32;
+        let epoch_number = (step / config.epoch_len) as u32;
+
+        dispatch_after_swap(
+            &runners[amm_idx],
+            amm,
+            is_buy,
+            input_scaled,
+            output_scaled,
+            step as u64,
+            epoch_step,
+            epoch_number,
+            flow_captured,
+            &strat_snapshot,
+            norm_amms,
+            total_n,
+        );
+        read_slots(&amm.storage, &config.trade_slots)
+    } else {
+        // Normalizer accounting
+        let norm_amm = &mut norm_amms[amm_idx - n_strat];
+        norm_amm.record_fill(is_buy, input_scaled, output_scaled, fair_price, kind.is_informed());
+        apply_cpamm_trade(&mut norm_amm.reserve_x, &mut norm_amm.reserve_y,
+                           is_buy, input_scaled, output_scaled);
+        Vec::new()
+    };
+
+    log.record(TradeEvent {
+        step,
+        amm: amm_idx,
+        kind,
+        is_buy,
+        input: input_scaled,
+        output: output_scaled,
+        fair_price,
+        flow_share: flow_captured as f64,
+        slots,
+    });
+}
+
 // ─── AfterSwap Dispatch ───────────────────────────────────────────────────────
 
 #[allow(clippy::too_many_arguments)]
@@ -291,20 +1244,18 @@ fn dispatch_after_swap(
     epoch_number: u32,
     flow_captured: f32,
     all_strat: &[AmmState],
-    norm: &AmmState,
+    norms: &[AmmState],
     total_n: usize,
 ) {
-    // Build competing spot prices (all other AMMs)
-    let mut competing = [f32::NAN; 8];
-    let mut slot = 0;
-    for s in all_strat {
-        if s.strategy_index != amm.strategy_index && slot < 8 {
-            competing[slot] = s.spot_price() as f32;
-            slot += 1;
-        }
-    }
-    if slot < 8 {
-        competing[slot] = norm.spot_price() as f32;
+    // Build competing spot prices (all other AMMs: strategies, then normalizers)
+    let mut competing = [f32::NAN; MAX_STRATEGIES];
+    let mut competing_reserve_y = [f32::NAN; MAX_STRATEGIES];
+    let others = all_strat.iter().filter(|s| s.strategy_index != amm.strategy_index).chain(norms);
+    let mut n_competing = 0;
+    for ((slot, depth), s) in competing.iter_mut().zip(&mut competing_reserve_y).zip(others) {
+        *slot = s.spot_price() as f32;
+        *depth = s.reserve_y as f32;
+        n_competing += 1;
     }
 
     let payload = AfterSwapPayload {
@@ -321,20 +1272,30 @@ fn dispatch_after_swap(
         strategy_index: amm.strategy_index,
         flow_captured,
         capital_weight: amm.capital_weight as f32,
+        n_competing,
         competing_spot_prices: competing,
+        competing_reserve_y,
         storage: amm.storage,
     };
 
     runner.after_swap(&payload, &mut amm.storage);
+    amm.publish_storage();
 }
 
 // ─── Normalizer Arb (inline, no library call) ─────────────────────────────────
 
-fn arb_normalizer(norm: &mut AmmState, runner: &NormalizerRunner, fair_price: f64, floor: f64) {
+/// Returns the executed trade `(is_buy, input_scaled, output_scaled)`, if any.
+fn arb_normalizer(
+    norm: &mut AmmState,
+    runner: &NormalizerRunner,
+    fair_price: f64,
+    floor: f64,
+    q: Quantization,
+) -> Option<(bool, u64, u64)> {
     use crate::market::golden_section_max;
 
-    let spot = norm.spot_price();
-    let is_buy = spot > fair_price;
+    // Buy X where it trades below fair, sell it where it trades above
+    let is_buy = norm.spot_price() < fair_price;
 
     let max_in = if is_buy {
         norm.reserve_y as f64 * 0.9 / SCALE_F
@@ -345,23 +1306,21 @@ fn arb_normalizer(norm: &mut AmmState, runner: &NormalizerRunner, fair_price: f6
     let profit_fn = |input_f: f64| -> f64 {
         let input_scaled = (input_f * SCALE_F) as u64;
         if input_scaled == 0 { return 0.0; }
-        let out = runner.compute_swap(is_buy, input_scaled, norm.reserve_x, norm.reserve_y);
+        let out = q.quote(is_buy, input_scaled, |input| runner.compute_swap(is_buy, input, norm.reserve_x, norm.reserve_y));
         let out_f = out as f64 / SCALE_F;
         if is_buy { out_f * fair_price - input_f } else { out_f - input_f * fair_price }
     };
 
     let (best_in, best_profit) = golden_section_max(profit_fn, 0.0, max_in, 50);
-    if best_profit < floor || best_in < 1.0 / SCALE_F { return; }
+    if best_profit < floor || best_in < 1.0 / SCALE_F { return None; }
 
-    let input_scaled = (best_in * SCALE_F) as u64;
-    let out_scaled = runner.compute_swap(is_buy, input_scaled, norm.reserve_x, norm.reserve_y);
+    let input_scaled = q.lot((best_in * SCALE_F) as u64);
+    let out_scaled = q.quote(is_buy, input_scaled, |input| runner.compute_swap(is_buy, input, norm.reserve_x, norm.reserve_y));
+    if input_scaled == 0 { return None; }
 
-    norm.accrue_edge(
-        if is_buy { out_scaled } else { input_scaled },
-        if is_buy { input_scaled } else { out_scaled },
-        is_buy, fair_price,
-    );
+    norm.record_fill(is_buy, input_scaled, out_scaled, fair_price, true);
     apply_cpamm_trade(&mut norm.reserve_x, &mut norm.reserve_y, is_buy, input_scaled, out_scaled);
+    Some((is_buy, input_scaled, out_scaled))
 }
 
 // ─── Parallel Multi-simulation Runner ────────────────────────────────────────
@@ -374,55 +1333,730 @@ pub fn run_parallel(
     config: &SimConfig,
     n_sims: usize,
     seed_start: u64,
-) -> Vec<AggregatedResult> {
-    let results: Vec<SimResult> = (0..n_sims)
-        .into_par_iter()
-        .map(|i| {
-            // Each thread loads its own strategy runners (libloading is not Send)
-            let runners: Vec<StrategyRunner> = runner_paths
-                .iter()
-                .map(|p| StrategyRunner::load(p).expect("strategy load failed"))
-                .collect();
-            run_simulation(&runners, config, seed_start + i as u64)
+) -> Result<Vec<AggregatedResult>, String> {
+    Ok(run_batch(runner_paths, config, n_sims, seed_start, &BatchOptions::default())?.aggregated)
+}
+
+/// A cached runner: artifact, self-play copy number (`None` without self-play) and the
+/// runtime it was loaded in, and whether it had to pass `SimConfig::audit_artifacts`.
+type RunnerKey = (std::path::PathBuf, Option<usize>, Runtime, bool);
+
+/// An artifact's modification time and size when it was loaded, for noticing rebuilds
+/// (`None` when hot reload is off or the file could not be stat'ed).
+type ArtifactStamp = Option<(std::time::SystemTime, u64)>;
+
+thread_local! {
+    /// Runners this thread has loaded, kept between simulations so each worker thread
+    /// loads every artifact once per batch instead of once per simulation
+    static RUNNER_CACHE: RefCell<HashMap<RunnerKey, (ArtifactStamp, StrategyRunner)>> = RefCell::new(HashMap::new());
+}
+
+fn artifact_stamp(path: &std::path::Path) -> ArtifactStamp {
+    let meta = std::fs::metadata(path).ok()?;
+    Some((meta.modified().ok()?, meta.len()))
+}
+
+/// Load a cached runner's artifact. With `snapshot`, a native library is loaded from a
+/// private copy: the dynamic loader hands back the already-open library for a path it
+/// has seen, so reloading a rebuilt artifact in place would keep running the old code
+/// while any thread still holds it. An audited key's artifact must pass
+/// `runner::audit_artifact`.
+fn load_runner(key: &RunnerKey, snapshot: bool) -> Result<StrategyRunner, Box<dyn std::error::Error>> {
+    static SNAPSHOTS: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+    let (path, copy, runtime, audit) = key;
+    let load = |path: &std::path::Path, runtime: Runtime| {
+        if *audit { StrategyRunner::load_audited(path, runtime) } else { StrategyRunner::load_with(path, runtime) }
+    };
+    let mut runner = if snapshot && *runtime == Runtime::Native {
+        let n = SNAPSHOTS.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        let ext = path.extension().unwrap_or_default().to_string_lossy();
+        let copy_path = std::env::temp_dir().join(format!("{stem}-reload-{}-{n}.{ext}", std::process::id()));
+        std::fs::copy(path, &copy_path)?;
+        let loaded = load(&copy_path, *runtime);
+        // The mapping outlives the file
+        let _ = std::fs::remove_file(&copy_path);
+        loaded?
+    } else {
+        load(path, *runtime)?
+    };
+    if let Some(k) = copy {
+        runner.name = format!("{} #{}", runner.name, k);
+    }
+    Ok(runner)
+}
+
+/// Run `f` with one runner per artifact, or `SimConfig::self_play_copies` independently
+/// competing copies of each. Runners come from this thread's cache and go back to it
+/// afterwards; one its last simulation left disabled (budget strikes, a crashed worker)
+/// is reloaded, and with `hot_reload` so is one whose artifact's mtime or size changed
+/// since it was loaded; if the new artifact fails to load (say, mid-rebuild) the old
+/// runner is kept and the reload retried next simulation. `SimConfig::opponents` follow
+/// the loaded runners; the batch entry points check them with `check_field` first.
+/// Fails, without calling `f`, if an artifact with no cached runner does not load (a
+/// subprocess worker that cannot spawn, an unreachable remote).
+fn with_runners<R>(
+    runner_paths: &[std::path::PathBuf],
+    config: &SimConfig,
+    hot_reload: bool,
+    f: impl FnOnce(&[StrategyRunner]) -> R,
+) -> Result<R, String> {
+    let copies = config.self_play_copies.max(1);
+    let keys: Vec<RunnerKey> = runner_paths
+        .iter()
+        .flat_map(|p| (1..=copies).map(move |k| (p, k)))
+        .map(|(p, k)| {
+            let mut runtime = match Runtime::for_artifact(p) {
+                // In-process native code cannot be cut off at the wall-time deadline
+                Runtime::Native if config.isolate_strategies || config.call_budget.wall_time.is_some() => Runtime::Subprocess,
+                runtime => runtime,
+            };
+            if let Some(limit) = config.call_budget.instructions {
+                runtime = runtime.with_instruction_limit(limit);
+            }
+            (p.clone(), (copies > 1).then_some(k), runtime, config.audit_artifacts)
         })
         .collect();
 
-    aggregate_results(results)
+    let (stamps, runners): (Vec<ArtifactStamp>, Vec<StrategyRunner>) = keys
+        .iter()
+        .map(|key| -> Result<_, String> {
+            let stamp = if hot_reload { artifact_stamp(&key.0) } else { None };
+            let cached = RUNNER_CACHE.with(|c| c.borrow_mut().remove(key)).filter(|(_, r)| !r.is_disabled());
+            let (stamp, mut runner) = match cached {
+                Some((loaded, runner)) if !hot_reload || loaded == stamp => (loaded, runner),
+                Some((loaded, runner)) => match load_runner(key, true) {
+                    Ok(reloaded) => (stamp, reloaded),
+                    Err(_) => (loaded, runner),
+                },
+                None => {
+                    let runner = load_runner(key, hot_reload)
+                        .map_err(|e| format!("strategy {} failed to load: {e}", key.0.display()))?;
+                    (stamp, runner)
+                }
+            };
+            runner.configure(config);
+            Ok((stamp, runner))
+        })
+        .collect::<Result<Vec<_>, String>>()?
+        .into_iter()
+        .unzip();
+
+    // Reference opponents hold no resources, so they are built fresh each time
+    let mut runners = runners;
+    runners.extend(config.opponents.iter().map(|name| {
+        let mut runner = reference::builtin(name).expect("opponents are checked before the batch");
+        runner.configure(config);
+        runner
+    }));
+
+    let out = f(&runners);
+    runners.truncate(keys.len());
+    RUNNER_CACHE.with(|c| c.borrow_mut().extend(keys.into_iter().zip(stamps.into_iter().zip(runners))));
+    Ok(out)
+}
+
+/// Drop every worker thread's cached runners for `artifacts`, unloading them (closing
+/// worker processes and connections). The batch runners call this when they finish.
+pub fn release_runners(artifacts: &[std::path::PathBuf]) {
+    rayon::broadcast(|_| RUNNER_CACHE.with(|c| c.borrow_mut().retain(|key, _| !artifacts.contains(&key.0))));
+}
+
+/// Optional hooks and outputs for `run_batch`.
+#[derive(Clone, Copy, Default)]
+pub struct BatchOptions<'a> {
+    /// Called after every completed simulation
+    pub on_progress: Option<&'a (dyn Fn(&SimProgress) + Sync)>,
+    /// Stops in-flight simulations after their current step
+    pub cancel: Option<&'a CancelToken>,
+    /// Also return every completed `SimResult` (memory grows with `n_sims`)
+    pub keep_results: bool,
+    /// Check each artifact's mtime and size before every simulation and reload the ones
+    /// that were rebuilt, so a long benchmark loop picks up edits. Aggregates then mix
+    /// strategy versions; simulations already running finish on the old one.
+    pub hot_reload: bool,
+    /// Run exactly these seeds instead of `seed_start..seed_start + n_sims`
+    pub seeds: Option<&'a [u64]>,
+}
+
+/// Outcome of a `run_batch`.
+#[derive(Clone, Debug)]
+pub struct BatchResult {
+    /// Aggregates over the simulations that ran to completion
+    pub aggregated: Vec<AggregatedResult>,
+    pub completed: usize,
+    /// The batch was cancelled before all simulations completed
+    pub partial: bool,
+    /// Completed results in the order of `seeds`, when `BatchOptions::keep_results` is set
+    pub results: Vec<SimResult>,
+    /// Seeds of the completed simulations, in the order they were listed in
+    /// `BatchOptions::seeds` (ascending without one)
+    pub seeds: Vec<u64>,
+    /// Wall-time breakdown summed over the completed simulations, when
+    /// `SimConfig::profile` is set
+    pub profile: Option<SimProfile>,
 }
 
+/// Interim state of a `run_batch`.
 #[derive(Clone, Debug)]
+pub struct SimProgress {
+    pub completed: usize,
+    pub total: usize,
+    /// Running mean final edge per strategy over the completed simulations
+    pub mean_edge: Vec<(String, f64)>,
+}
+
+/// `Err` naming every `SimConfig::opponents` entry that is not a built-in reference
+/// strategy, or if `n_artifacts` artifacts with their self-play copies and the
+/// opponents come to more strategies than the after-swap payload has competitor slots
+/// (`MAX_STRATEGIES`), or to more AMMs with the normalizers than a `u8` can index.
+fn check_field(n_artifacts: usize, config: &SimConfig) -> Result<(), String> {
+    let unknown: Vec<String> = config
+        .opponents
+        .iter()
+        .filter(|name| reference::builtin(name).is_none())
+        .map(|name| format!("`{name}`"))
+        .collect();
+    if !unknown.is_empty() {
+        return Err(format!("unknown reference opponent {}", unknown.join(", ")));
+    }
+    let copies = config.self_play_copies.max(1);
+    let n = n_artifacts * copies + config.opponents.len();
+    if n > MAX_STRATEGIES {
+        return Err(format!(
+            "{n} strategies ({n_artifacts} × {copies} self-play copies + {} opponents) exceed the limit of {MAX_STRATEGIES}",
+            config.opponents.len()
+        ));
+    }
+    if n + config.n_normalizers > u8::MAX as usize + 1 {
+        return Err(format!("{n} strategies and {} normalizers overflow the u8 AMM index", config.n_normalizers));
+    }
+    Ok(())
+}
+
+/// Run `n_sims` simulations in parallel, folding each result into a streaming
+/// `Aggregator` as it completes so memory stays bounded. Fails if the field does not
+/// pass `check_field`, before any simulation starts, or if a strategy or a plug-in
+/// allocator fails to load on a worker thread, in which case no further simulations
+/// start.
+///
+/// The progress callback runs on rayon worker threads, serialized so `completed` only
+/// increases. Once cancelled, in-flight simulations stop after their current step and
+/// are dropped, pending ones never start, and the completed ones are aggregated.
+pub fn run_batch(
+    runner_paths: &[std::path::PathBuf],
+    config: &SimConfig,
+    n_sims: usize,
+    seed_start: u64,
+    opts: &BatchOptions,
+) -> Result<BatchResult, String> {
+    check_field(runner_paths.len(), config)?;
+    let never = CancelToken::default();
+    let cancel = opts.cancel.unwrap_or(&never);
+    let n_sims = opts.seeds.map_or(n_sims, <[u64]>::len);
+    let seed_of = |i: usize| opts.seeds.map_or(seed_start + i as u64, |seeds| seeds[i]);
+    let state = std::sync::Mutex::new((Aggregator::new(), Vec::<(usize, SimResult)>::new(), Vec::new(), None::<SimProfile>));
+
+    let outcome = (0..n_sims).into_par_iter().try_for_each(|i| -> Result<(), String> {
+        if cancel.is_cancelled() { return Ok(()); }
+        // Each thread loads its own strategy runners (libloading is not Send)
+        let result = with_runners(runner_paths, config, opts.hot_reload, |runners| {
+            run_simulation_cancellable(runners, config, seed_of(i), cancel)
+        })??;
+        if result.cancelled { return Ok(()); }
+
+        let mut guard = state.lock().unwrap_or_else(|e| e.into_inner());
+        let (agg, kept, done, profile) = &mut *guard;
+        agg.push(&result);
+        done.push(i);
+        if let Some(p) = &result.profile {
+            profile.get_or_insert_with(SimProfile::default).add(p);
+        }
+        if let Some(on_progress) = opts.on_progress {
+            on_progress(&SimProgress { completed: agg.count(), total: n_sims, mean_edge: agg.mean_edges() });
+        }
+        if opts.keep_results {
+            kept.push((i, result));
+        }
+        Ok(())
+    });
+
+    release_runners(runner_paths);
+    outcome?;
+    let (agg, mut kept, mut done, profile) = state.into_inner().unwrap_or_else(|e| e.into_inner());
+    kept.sort_by_key(|(i, _)| *i);
+    done.sort_unstable();
+    Ok(BatchResult {
+        aggregated: agg.finish(),
+        completed: agg.count(),
+        partial: agg.count() < n_sims,
+        results: kept.into_iter().map(|(_, r)| r).collect(),
+        seeds: done.into_iter().map(seed_of).collect(),
+        profile,
+    })
+}
+
+/// Parse a seed-set file: integers or half-open `A..B` ranges, separated by whitespace or
+/// commas, with `#` starting a comment. Order is kept; a repeated seed is an error, since
+/// it would weigh one market twice.
+pub fn parse_seeds(text: &str) -> Result<Vec<u64>, String> {
+    let mut seeds = Vec::new();
+    let mut seen = std::collections::HashSet::new();
+    for (n, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("");
+        for token in line.split(|c: char| c.is_whitespace() || c == ',').filter(|t| !t.is_empty()) {
+            let parse = |s: &str| s.parse::<u64>().map_err(|e| format!("line {}: `{token}`: {e}", n + 1));
+            let range = match token.split_once("..") {
+                Some((a, b)) => parse(a)?..parse(b)?,
+                None => parse(token).map(|s| s..s.saturating_add(1))?,
+            };
+            if range.is_empty() {
+                return Err(format!("line {}: empty range `{token}`", n + 1));
+            }
+            for seed in range {
+                if !seen.insert(seed) {
+                    return Err(format!("line {}: seed {seed} is listed twice", n + 1));
+                }
+                seeds.push(seed);
+            }
+        }
+    }
+    if seeds.is_empty() {
+        return Err("no seeds listed".to_string());
+    }
+    Ok(seeds)
+}
+
+/// `n` sealed hold-out seeds derived from a host `key`, none of them in `public`. The
+/// seeds cannot be predicted without the key, so tuning on the public set cannot fit
+/// them; publishing `holdout_commitment(key)` pins the key without revealing it.
+pub fn holdout_seeds(key: &str, n: usize, public: &[u64]) -> Vec<u64> {
+    use sha2::{Digest, Sha256};
+
+    let mut seeds = Vec::with_capacity(n);
+    let mut counter = 0u64;
+    while seeds.len() < n {
+        let digest = Sha256::new().chain_update(b"prop-amm holdout seed").chain_update(key).chain_update(counter.to_le_bytes()).finalize();
+        let seed = u64::from_le_bytes(digest[..8].try_into().unwrap());
+        if !public.contains(&seed) && !seeds.contains(&seed) {
+            seeds.push(seed);
+        }
+        counter += 1;
+    }
+    seeds
+}
+
+/// Hex SHA-256 commitment to a hold-out `key`, safe to publish.
+pub fn holdout_commitment(key: &str) -> String {
+    use sha2::{Digest, Sha256};
+
+    Sha256::new().chain_update(b"prop-amm holdout key").chain_update(key).finalize().iter().map(|b| format!("{b:02x}")).collect()
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
 pub struct AggregatedResult {
     pub name: String,
+    pub metadata: StrategyMetadata,
     pub mean_edge: f64,
     pub std_edge: f64,
     pub mean_final_capital_weight: f64,
     pub edge_vs_normalizer: f64,   // mean (strategy_edge - normalizer_edge)
     pub sharpe: f64,               // mean_edge / std_edge
+    pub mean_max_drawdown: f64,
+    pub sortino: f64,              // mean per-epoch edge / mean downside deviation
+    pub mean_fee_yield: f64,
+    pub mean_price_improvement_bps: f64,
+    /// Mean share of the pool's filled retail volume captured by this strategy
+    pub mean_flow_share: f64,
+    /// Second scoring track: mean over simulations of edge × flow share, so a venue
+    /// that quotes wide and rarely trades cannot rank on a few lucky fills
+    pub flow_weighted_edge: f64,
+    /// Mean `StrategyResult::annualized_return`; `None` without a step length
+    pub mean_annualized_return: Option<f64>,
+    /// Per-epoch means over every rebalanced epoch of every simulation: notional filled
+    /// where the trader bought / sold X (Y at fair), and fills by counterparty
+    pub mean_epoch_buy_volume: f64,
+    pub mean_epoch_sell_volume: f64,
+    pub mean_epoch_retail_trades: f64,
+    pub mean_epoch_arb_trades: f64,
+    /// Mean share of each retail order routed here, over epochs with retail fills
+    pub mean_epoch_fill_share: f64,
+    /// Largest single fill absorbed in any epoch of any simulation (Y at fair)
+    pub largest_trade: f64,
+    /// Mean capital weight at the start of each epoch, across simulations
+    pub mean_weight_trajectory: Vec<f64>,
+    /// Over-quotes summed across simulations (see `StrategyResult::over_quotes`)
+    pub over_quotes: u64,
+    /// Orders the router excluded the strategy from, summed across simulations
+    pub degenerate_orders: u64,
+    /// Simulations in which the strategy was disqualified for tampering or over-quoting
+    pub disqualified_sims: usize,
+}
+
+/// Welford running mean and (population) variance.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RunningStat {
+    n: u64,
+    mean: f64,
+    m2: f64,
+}
+
+impl RunningStat {
+    pub fn push(&mut self, x: f64) {
+        self.n += 1;
+        let delta = x - self.mean;
+        self.mean += delta / self.n as f64;
+        self.m2 += delta * (x - self.mean);
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.mean
+    }
+
+    pub fn variance(&self) -> f64 {
+        if self.n == 0 { 0.0 } else { self.m2 / self.n as f64 }
+    }
+
+    pub fn count(&self) -> u64 {
+        self.n
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+struct StrategyStats {
+    name: String,
+    metadata: StrategyMetadata,
+    edge: RunningStat,
+    norm_edge: RunningStat,
+    capital_weight: RunningStat,
+    max_drawdown: RunningStat,
+    downside_deviation: RunningStat,
+    epoch_edge: RunningStat,
+    fee_yield: RunningStat,
+    price_improvement: RunningStat,
+    flow_share: RunningStat,
+    flow_weighted_edge: RunningStat,
+    annualized_return: RunningStat,
+    buy_volume: RunningStat,
+    sell_volume: RunningStat,
+    retail_trades: RunningStat,
+    arb_trades: RunningStat,
+    fill_share: RunningStat,
+    largest_trade: f64,
+    weight_path: Vec<RunningStat>,
+    over_quotes: u64,
+    degenerate_orders: u64,
+    disqualified_sims: usize,
+}
+
+/// Streaming per-strategy aggregation of `SimResult`s, O(strategies) memory.
+#[derive(Clone, Debug, Default)]
+pub struct Aggregator {
+    count: usize,
+    strategies: Vec<StrategyStats>,
+}
+
+impl Aggregator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, sim: &SimResult) {
+        if self.strategies.is_empty() {
+            self.strategies = sim.strategies.iter()
+                .map(|s| StrategyStats { name: s.name.clone(), metadata: s.metadata.clone(), ..StrategyStats::default() })
+                .collect();
+        }
+        self.count += 1;
+        for (i, (stats, s)) in self.strategies.iter_mut().zip(&sim.strategies).enumerate() {
+            stats.edge.push(s.final_edge);
+            stats.norm_edge.push(sim.normalizer_edge);
+            stats.capital_weight.push(s.final_capital_weight);
+            stats.max_drawdown.push(s.max_drawdown);
+            stats.downside_deviation.push(s.downside_deviation);
+            stats.fee_yield.push(s.fee_yield);
+            stats.price_improvement.push(s.mean_price_improvement_bps);
+            let flow_share = if sim.retail_volume > 0.0 { s.retail_volume / sim.retail_volume } else { 0.0 };
+            stats.flow_share.push(flow_share);
+            stats.flow_weighted_edge.push(s.final_edge * flow_share);
+            if let Some(ret) = s.annualized_return {
+                stats.annualized_return.push(ret);
+            }
+            stats.over_quotes += s.over_quotes;
+            stats.degenerate_orders += s.degenerate_orders;
+            stats.disqualified_sims += s.disqualified as usize;
+            if stats.weight_path.len() < sim.capital_weights.len() {
+                stats.weight_path.resize(sim.capital_weights.len(), RunningStat::default());
+            }
+            for (stat, weights) in stats.weight_path.iter_mut().zip(&sim.capital_weights) {
+                stat.push(weights[i]);
+            }
+            for e in &s.epoch_summaries {
+                stats.buy_volume.push(e.buy_volume);
+                stats.sell_volume.push(e.sell_volume);
+                stats.retail_trades.push(e.retail_trade_count as f64);
+                stats.arb_trades.push(e.arb_trade_count as f64);
+                if e.retail_trade_count > 0 {
+                    stats.fill_share.push(e.mean_flow_share);
+                }
+                stats.largest_trade = stats.largest_trade.max(e.largest_trade);
+            }
+            // The final epoch is never rebalanced, so it has no summary
+            stats.epoch_edge.push(s.final_edge / (s.epoch_summaries.len() + 1) as f64);
+        }
+    }
+
+    /// Simulations folded in so far.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Running mean final edge per strategy.
+    pub fn mean_edges(&self) -> Vec<(String, f64)> {
+        self.strategies.iter().map(|s| (s.name.clone(), s.edge.mean())).collect()
+    }
+
+    pub fn finish(&self) -> Vec<AggregatedResult> {
+        self.strategies.iter().map(|s| {
+            let mean = s.edge.mean();
+            let std = s.edge.variance().sqrt();
+            let mean_down = s.downside_deviation.mean();
+            AggregatedResult {
+                name: s.name.clone(),
+                metadata: s.metadata.clone(),
+                mean_edge: mean,
+                std_edge: std,
+                mean_final_capital_weight: s.capital_weight.mean(),
+                edge_vs_normalizer: mean - s.norm_edge.mean(),
+                sharpe: if std > 0.0 { mean / std } else { 0.0 },
+                mean_max_drawdown: s.max_drawdown.mean(),
+                sortino: if mean_down > 0.0 { s.epoch_edge.mean() / mean_down } else { 0.0 },
+                mean_fee_yield: s.fee_yield.mean(),
+                mean_price_improvement_bps: s.price_improvement.mean(),
+                mean_flow_share: s.flow_share.mean(),
+                flow_weighted_edge: s.flow_weighted_edge.mean(),
+                mean_annualized_return: (s.annualized_return.count() > 0).then(|| s.annualized_return.mean()),
+                mean_epoch_buy_volume: s.buy_volume.mean(),
+                mean_epoch_sell_volume: s.sell_volume.mean(),
+                mean_epoch_retail_trades: s.retail_trades.mean(),
+                mean_epoch_arb_trades: s.arb_trades.mean(),
+                mean_epoch_fill_share: s.fill_share.mean(),
+                largest_trade: s.largest_trade,
+                mean_weight_trajectory: s.weight_path.iter().map(RunningStat::mean).collect(),
+                over_quotes: s.over_quotes,
+                degenerate_orders: s.degenerate_orders,
+                disqualified_sims: s.disqualified_sims,
+            }
+        }).collect()
+    }
+}
+
+/// Aggregate `results` separately per volatility regime of their step-0 market
+/// parameters, calm first. Regimes no simulation fell into are left out.
+pub fn aggregate_by_regime(results: &[SimResult]) -> Vec<(VolRegime, usize, Vec<AggregatedResult>)> {
+    let mut by_regime: std::collections::BTreeMap<VolRegime, Aggregator> = std::collections::BTreeMap::new();
+    for sim in results {
+        by_regime.entry(sim.market_params.vol_regime()).or_default().push(sim);
+    }
+    by_regime.into_iter().map(|(regime, agg)| (regime, agg.count(), agg.finish())).collect()
+}
+
+// ─── Round-robin Tournament ──────────────────────────────────────────────────
+
+/// Head-to-head win counts between strategies that met in the same simulation.
+#[derive(Clone, Debug)]
+pub struct PairwiseMatrix {
+    pub names: Vec<String>,
+    /// `wins[i][j]`: games in which `i` finished with more edge than `j` (ties count ½)
+    pub wins: Vec<Vec<f64>>,
+    /// `games[i][j]`: simulations in which `i` and `j` competed together
+    pub games: Vec<Vec<u32>>,
+}
+
+impl PairwiseMatrix {
+    pub fn new(names: Vec<String>) -> Self {
+        let n = names.len();
+        Self { names, wins: vec![vec![0.0; n]; n], games: vec![vec![0; n]; n] }
+    }
+
+    /// Record one simulation: `members[k]` is the global index of the strategy that
+    /// finished with `edges[k]`.
+    pub fn record(&mut self, members: &[usize], edges: &[f64]) {
+        for (a, &i) in members.iter().enumerate() {
+            for (b, &j) in members.iter().enumerate() {
+                if a == b { continue; }
+                self.games[i][j] += 1;
+                self.wins[i][j] += match edges[a].partial_cmp(&edges[b]) {
+                    Some(std::cmp::Ordering::Greater) => 1.0,
+                    Some(std::cmp::Ordering::Equal) => 0.5,
+                    _ => 0.0,
+                };
+            }
+        }
+    }
+
+    /// Fraction of shared games `i` won against `j` (NaN if they never met).
+    pub fn win_rate(&self, i: usize, j: usize) -> f64 {
+        if self.games[i][j] == 0 { f64::NAN } else { self.wins[i][j] / self.games[i][j] as f64 }
+    }
+}
+
+/// Free-for-all leaderboard plus the pairwise dominance matrix.
+#[derive(Clone, Debug)]
+pub struct TournamentResult {
+    pub leaderboard: Vec<AggregatedResult>,
+    pub pairwise: PairwiseMatrix,
+}
+
+/// Run every `subset_size`-subset of the strategies (2 = round-robin pairs) on the same
+/// `n_sims` seeds, tallying head-to-head wins between runners, alongside a free-for-all
+/// batch.
+pub fn run_round_robin(
+    runner_paths: &[std::path::PathBuf],
+    config: &SimConfig,
+    n_sims: usize,
+    seed_start: u64,
+    subset_size: usize,
+) -> Result<TournamentResult, String> {
+    let leaderboard = run_parallel(runner_paths, config, n_sims, seed_start)?;
+    // The matrix is indexed by runner, in the leaderboard's order: each artifact's
+    // self-play copies in turn, then the opponents, who play in every subset
+    let names: Vec<String> = leaderboard.iter().map(|r| r.name.clone()).collect();
+    let copies = config.self_play_copies.max(1);
+    let opponents = runner_paths.len() * copies..names.len();
+
+    let subsets = k_subsets(runner_paths.len(), subset_size.clamp(2, runner_paths.len().max(2)));
+    let matrix = std::sync::Mutex::new(PairwiseMatrix::new(names));
+
+    let outcome = subsets
+        .par_iter()
+        .flat_map(|subset| (0..n_sims).into_par_iter().map(move |i| (subset, i)))
+        .try_for_each(|(subset, i)| -> Result<(), String> {
+            let paths: Vec<std::path::PathBuf> = subset.iter().map(|&k| runner_paths[k].clone()).collect();
+            let members: Vec<usize> =
+                subset.iter().flat_map(|&k| k * copies..(k + 1) * copies).chain(opponents.clone()).collect();
+            let result = with_runners(&paths, config, false, |runners| run_simulation(runners, config, seed_start + i as u64))??;
+            let edges: Vec<f64> = result.strategies.iter().map(|s| s.final_edge).collect();
+            matrix.lock().unwrap_or_else(|e| e.into_inner()).record(&members, &edges);
+            Ok(())
+        });
+    release_runners(runner_paths);
+    outcome?;
+
+    Ok(TournamentResult {
+        leaderboard,
+        pairwise: matrix.into_inner().unwrap_or_else(|e| e.into_inner()),
+    })
+}
+
+/// All `k`-element subsets of `0..n` in lexicographic order.
+fn k_subsets(n: usize, k: usize) -> Vec<Vec<usize>> {
+    if k > n { return vec![]; }
+    let mut out = Vec::new();
+    let mut idx: Vec<usize> = (0..k).collect();
+    loop {
+        out.push(idx.clone());
+        // Advance the rightmost index that still has room
+        let Some(pos) = (0..k).rev().find(|&p| idx[p] < n - k + p) else { break };
+        idx[pos] += 1;
+        for p in pos + 1..k {
+            idx[p] = idx[p - 1] + 1;
+        }
+    }
+    out
+}
+
+// ─── Leave-one-out Marginal Contribution ─────────────────────────────────────
+
+/// Effect of one strategy's presence on the rest of the ecosystem, averaged over seeds:
+/// (run with everyone) − (same seed with this strategy removed).
+#[derive(Clone, Debug)]
+pub struct MarginalContribution {
+    pub name: String,
+    /// Change in total edge across all AMMs (strategies + normalizers)
+    pub pool_edge_delta: f64,
+    /// Change in the other strategies' combined edge
+    pub others_edge_delta: f64,
+    /// Change in the other strategies' combined retail flow (Y); negative = flow taken
+    pub others_flow_delta: f64,
 }
 
-fn aggregate_results(sims: Vec<SimResult>) -> Vec<AggregatedResult> {
-    if sims.is_empty() { return vec![]; }
-    let n_strat = sims[0].strategies.len();
-    let n = sims.len() as f64;
+/// Per-run totals needed for leave-one-out deltas.
+struct RunTotals {
+    names: Vec<String>,
+    pool_edge: f64,
+    edges: Vec<f64>,
+    volumes: Vec<f64>,
+}
 
-    (0..n_strat).map(|i| {
-        let edges: Vec<f64> = sims.iter().map(|s| s.strategies[i].final_edge).collect();
-        let norm_edges: Vec<f64> = sims.iter().map(|s| s.normalizer_edge).collect();
-        let weights: Vec<f64> = sims.iter().map(|s| s.strategies[i].final_capital_weight).collect();
+/// Re-run the same `n_sims` seeds with each strategy removed in turn and report each
+/// strategy's marginal effect on pool edge and on its competitors (none when `n_sims`
+/// is 0).
+pub fn run_leave_one_out(
+    runner_paths: &[std::path::PathBuf],
+    config: &SimConfig,
+    n_sims: usize,
+    seed_start: u64,
+) -> Result<Vec<MarginalContribution>, String> {
+    check_field(runner_paths.len(), config)?;
+    let n = runner_paths.len();
+    // Variant n is the full field; variant i omits strategy i
+    let runs: Result<Vec<(usize, usize, RunTotals)>, String> = (0..=n)
+        .into_par_iter()
+        .flat_map(|variant| (0..n_sims).into_par_iter().map(move |i| (variant, i)))
+        .map(|(variant, i)| {
+            let paths: Vec<std::path::PathBuf> = (0..n)
+                .filter(|&k| k != variant)
+                .map(|k| runner_paths[k].clone())
+                .collect();
+            let r = with_runners(&paths, config, false, |runners| run_simulation(runners, config, seed_start + i as u64))??;
+            let edges: Vec<f64> = r.strategies.iter().map(|s| s.final_edge).collect();
+            let totals = RunTotals {
+                names: r.strategies.iter().map(|s| s.name.clone()).collect(),
+                pool_edge: edges.iter().sum::<f64>() + r.normalizer_edges.iter().sum::<f64>(),
+                volumes: r.strategies.iter().map(|s| s.retail_volume).collect(),
+                edges,
+            };
+            Ok((variant, i, totals))
+        })
+        .collect();
+    release_runners(runner_paths);
+    let runs = runs?;
 
-        let mean = edges.iter().sum::<f64>() / n;
-        let var  = edges.iter().map(|e| (e - mean).powi(2)).sum::<f64>() / n;
-        let std  = var.sqrt();
-        let mean_norm = norm_edges.iter().sum::<f64>() / n;
-        let mean_wt   = weights.iter().sum::<f64>() / n;
+    let full: Vec<&RunTotals> = {
+        let mut by_seed: Vec<(usize, &RunTotals)> =
+            runs.iter().filter(|(v, _, _)| *v == n).map(|(_, i, t)| (*i, t)).collect();
+        by_seed.sort_by_key(|(i, _)| *i);
+        by_seed.into_iter().map(|(_, t)| t).collect()
+    };
+    let Some(loaded) = full.first() else { return Ok(Vec::new()) };
+    // Runners are indexed as in the full field, each artifact's self-play copies in
+    // turn; a contribution is named after the artifact, without the copy number
+    let copies = config.self_play_copies.max(1);
+    let name = |k: usize| {
+        let runner = &loaded.names[k * copies];
+        if copies > 1 { runner.strip_suffix(" #1").unwrap_or(runner).to_string() } else { runner.clone() }
+    };
 
-        AggregatedResult {
-            name: sims[0].strategies[i].name.clone(),
-            mean_edge: mean,
-            std_edge: std,
-            mean_final_capital_weight: mean_wt,
-            edge_vs_normalizer: mean - mean_norm,
-            sharpe: if std > 0.0 { mean / std } else { 0.0 },
+    Ok((0..n).map(|k| {
+        let mut pool = RunningStat::default();
+        let mut others_edge = RunningStat::default();
+        let mut others_flow = RunningStat::default();
+        for (_, i, without) in runs.iter().filter(|(v, _, _)| *v == k) {
+            let with = full[*i];
+            // Self-play copies of strategy k are all removed together
+            let others = |xs: &[f64]| -> f64 {
+                xs.iter().enumerate().filter(|(j, _)| j / copies != k).map(|(_, x)| x).sum()
+            };
+            pool.push(with.pool_edge - without.pool_edge);
+            others_edge.push(others(&with.edges) - without.edges.iter().sum::<f64>());
+            others_flow.push(others(&with.volumes) - without.volumes.iter().sum::<f64>());
+        }
+        MarginalContribution {
+            name: name(k),
+            pool_edge_delta: pool.mean(),
+            others_edge_delta: others_edge.mean(),
+            others_flow_delta: others_flow.mean(),
         }
-    }).collect()
+    }).collect())
 }
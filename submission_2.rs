@@ -17,6 +17,12 @@ pub extern "C" fn __prop_amm_compute_swap(data: *const u8, len: usize) -> u64 {
 #[no_mangle]
 pub extern "C" fn __prop_amm_after_swap(_data: *const u8, _len: usize, _storage_ptr: *mut u8) {}
 
+/// Payload layout this strategy decodes (the engine's `ABI_VERSION`)
+#[no_mangle]
+pub extern "C" fn __prop_amm_abi_version() -> u32 {
+    1
+}
+
 #[no_mangle]
 pub extern "C" fn __prop_amm_get_name(buf: *mut u8, max_len: usize) -> usize {
     let bytes = NAME.as_bytes();
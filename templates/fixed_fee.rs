@@ -0,0 +1,105 @@
+//! {{NAME}}: constant-product AMM charging one fixed fee on both sides.
+//!
+//! Validate and run against the reference field:
+//!
+//!     prop-amm-multi validate {{FILE}}
+//!     prop-amm-multi bench {{FILE}}
+//!
+//! Local tests: `{{TEST_COMMAND}}`
+//!
+//! Storage ({{STORAGE_SIZE}} bytes of u64 slots, persists for the whole simulation): unused
+//! by this template. Every slot below {{REBALANCE_REQUEST_SLOT}} is free; slot
+//! {{REBALANCE_REQUEST_SLOT}} is the rebalance request slot (leave it zero unless you want a
+//! rebalance at the next epoch boundary).
+
+const NAME: &str = "{{NAME}}";
+/// Fee charged on the input of every trade (bps)
+const FEE_BPS: u128 = 30; // tune: 5..100
+
+/// Payload layout (the engine's `wire.rs`, ABI v{{ABI_VERSION}}): the tag at byte 0, then
+/// fields at these offsets
+const ABI_VERSION: u32 = {{ABI_VERSION}};
+const TAG_SWAP_BUY: u8 = {{TAG_SWAP_BUY}};
+const TAG_SWAP_SELL: u8 = {{TAG_SWAP_SELL}};
+const SWAP_INPUT: usize = {{swap::INPUT_AMOUNT}};
+const SWAP_RESERVE_X: usize = {{swap::RESERVE_X}};
+const SWAP_RESERVE_Y: usize = {{swap::RESERVE_Y}};
+const SWAP_STORAGE: usize = {{swap::STORAGE}};
+
+/// Quote `input` against the pool: the trader buys X paying Y (`TAG_SWAP_BUY`) or sells
+/// X for Y (`TAG_SWAP_SELL`).
+#[no_mangle]
+pub extern "C" fn __prop_amm_compute_swap(data: *const u8, len: usize) -> u64 {
+    let bytes = unsafe { std::slice::from_raw_parts(data, len) };
+    if bytes.len() < SWAP_STORAGE {
+        return 0;
+    }
+    let is_buy = bytes[0] == TAG_SWAP_BUY;
+    let input = read_u64(bytes, SWAP_INPUT);
+    let (rx, ry) = (read_u64(bytes, SWAP_RESERVE_X), read_u64(bytes, SWAP_RESERVE_Y));
+
+    if is_buy {
+        cpamm_output(input, ry, rx, FEE_BPS)
+    } else {
+        cpamm_output(input, rx, ry, FEE_BPS)
+    }
+}
+
+/// Every hook lands here, told apart by the tag at byte 0: after a fill, at an epoch
+/// boundary, for shock news and for the step digest. `storage_ptr` is this strategy's
+/// read-write storage.
+#[no_mangle]
+pub extern "C" fn __prop_amm_after_swap(_data: *const u8, _len: usize, _storage_ptr: *mut u8) {}
+
+/// Payload layout this strategy decodes (the engine's `ABI_VERSION`)
+#[no_mangle]
+pub extern "C" fn __prop_amm_abi_version() -> u32 {
+    ABI_VERSION
+}
+
+#[no_mangle]
+pub extern "C" fn __prop_amm_get_name(buf: *mut u8, max_len: usize) -> usize {
+    let bytes = NAME.as_bytes();
+    let n = bytes.len().min(max_len);
+    unsafe { std::ptr::copy_nonoverlapping(bytes.as_ptr(), buf, n) };
+    n
+}
+
+fn read_u64(bytes: &[u8], at: usize) -> u64 {
+    bytes.get(at..at + 8).map_or(0, |b| u64::from_le_bytes(b.try_into().unwrap()))
+}
+
+fn cpamm_output(input: u64, reserve_in: u64, reserve_out: u64, fee_bps: u128) -> u64 {
+    if input == 0 || reserve_in == 0 || reserve_out == 0 {
+        return 0;
+    }
+    let input_eff = (input as u128) * (10_000 - fee_bps) / 10_000;
+    ((reserve_out as u128) * input_eff / (reserve_in as u128 + input_eff)) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STORAGE_SIZE: usize = {{STORAGE_SIZE}};
+
+    fn quote(is_buy: bool, input: u64, rx: u64, ry: u64) -> u64 {
+        let mut payload = vec![0; SWAP_STORAGE + STORAGE_SIZE];
+        payload[0] = if is_buy { TAG_SWAP_BUY } else { TAG_SWAP_SELL };
+        for (at, v) in [(SWAP_INPUT, input), (SWAP_RESERVE_X, rx), (SWAP_RESERVE_Y, ry)] {
+            payload[at..at + 8].copy_from_slice(&v.to_le_bytes());
+        }
+        __prop_amm_compute_swap(payload.as_ptr(), payload.len())
+    }
+
+    #[test]
+    fn quotes_are_positive_monotone_and_inside_the_reserves() {
+        let (rx, ry) = (100_000_000_000, 10_000_000_000_000);
+        for is_buy in [true, false] {
+            let small = quote(is_buy, 1_000_000_000, rx, ry);
+            let large = quote(is_buy, 5_000_000_000, rx, ry);
+            assert!(small > 0 && large > small);
+            assert!(large < if is_buy { rx } else { ry });
+        }
+    }
+}
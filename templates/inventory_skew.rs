@@ -0,0 +1,176 @@
+//! {{NAME}}: constant-product AMM that skews its fees to pull its X inventory back to
+//! where the epoch started.
+//!
+//! Validate and run against the reference field:
+//!
+//!     prop-amm-multi validate {{FILE}}
+//!     prop-amm-multi bench {{FILE}}
+//!
+//! Local tests: `{{TEST_COMMAND}}`
+//!
+//! Storage ({{STORAGE_SIZE}} bytes of u64 slots, persists for the whole simulation):
+//!
+//! | Slot | Field        | Type | Description                                  |
+//! |------|--------------|------|----------------------------------------------|
+//! | 0    | baseline_x   | u64  | reserve_x at the start of the epoch (0 = unset) |
+//!
+//! Every other slot below {{REBALANCE_REQUEST_SLOT}} is free; slot {{REBALANCE_REQUEST_SLOT}} is the
+//! rebalance request slot (leave it zero unless you want a rebalance at the next epoch
+//! boundary).
+
+const NAME: &str = "{{NAME}}";
+/// Fee with a balanced inventory (bps)
+const BASE_FEE_BPS: f64 = 30.0; // tune: 5..100
+/// Fee shift per unit of relative inventory imbalance: 10% long X → ±100 bps
+const SKEW_BPS_PER_UNIT: f64 = 1_000.0; // tune: 0..3000
+const MIN_FEE_BPS: f64 = 5.0;
+const MAX_FEE_BPS: f64 = 500.0;
+
+const STORAGE_SIZE: usize = {{STORAGE_SIZE}};
+const SLOT_BASELINE_X: usize = 0;
+
+/// Payload layout (the engine's `wire.rs`, ABI v{{ABI_VERSION}}): the tag at byte 0, then
+/// fields at these offsets
+const ABI_VERSION: u32 = {{ABI_VERSION}};
+const TAG_SWAP_BUY: u8 = {{TAG_SWAP_BUY}};
+const TAG_SWAP_SELL: u8 = {{TAG_SWAP_SELL}};
+const TAG_AFTER_SWAP: u8 = {{TAG_AFTER_SWAP}};
+const TAG_EPOCH_BOUNDARY: u8 = {{TAG_EPOCH_BOUNDARY}};
+const SWAP_INPUT: usize = {{swap::INPUT_AMOUNT}};
+const SWAP_RESERVE_X: usize = {{swap::RESERVE_X}};
+const SWAP_RESERVE_Y: usize = {{swap::RESERVE_Y}};
+const SWAP_STORAGE: usize = {{swap::STORAGE}};
+/// After a fill: side (0 = trader bought X), amounts and post-trade reserve_x
+const FILL_SIDE: usize = {{after_swap::SIDE}};
+const FILL_INPUT: usize = {{after_swap::INPUT_AMOUNT}};
+const FILL_OUTPUT: usize = {{after_swap::OUTPUT_AMOUNT}};
+const FILL_RESERVE_X: usize = {{after_swap::RESERVE_X}};
+/// At an epoch boundary: reserve_x after the rebalance
+const EPOCH_RESERVE_X: usize = {{epoch_boundary::NEW_RESERVE_X}};
+
+/// Quote `input` against the pool: the trader buys X paying Y (`TAG_SWAP_BUY`) or sells
+/// X for Y (`TAG_SWAP_SELL`).
+#[no_mangle]
+pub extern "C" fn __prop_amm_compute_swap(data: *const u8, len: usize) -> u64 {
+    let bytes = unsafe { std::slice::from_raw_parts(data, len) };
+    if bytes.len() < SWAP_STORAGE + STORAGE_SIZE {
+        return 0;
+    }
+    let is_buy = bytes[0] == TAG_SWAP_BUY;
+    let input = read_u64(bytes, SWAP_INPUT);
+    let (rx, ry) = (read_u64(bytes, SWAP_RESERVE_X), read_u64(bytes, SWAP_RESERVE_Y));
+    let storage = &bytes[SWAP_STORAGE..SWAP_STORAGE + STORAGE_SIZE];
+
+    let baseline = read_slot(storage, SLOT_BASELINE_X);
+    let imbalance = if baseline > 0 { rx as f64 / baseline as f64 - 1.0 } else { 0.0 };
+    // Long X: cheaper for traders to take X (buys), dearer to add more (sells)
+    let skew = imbalance * SKEW_BPS_PER_UNIT;
+    let fee = if is_buy { BASE_FEE_BPS - skew } else { BASE_FEE_BPS + skew };
+    let fee = fee.clamp(MIN_FEE_BPS, MAX_FEE_BPS) as u128;
+
+    if is_buy {
+        cpamm_output(input, ry, rx, fee)
+    } else {
+        cpamm_output(input, rx, ry, fee)
+    }
+}
+
+/// Every hook lands here, told apart by the tag at byte 0: after a fill, at an epoch
+/// boundary, for shock news and for the step digest. `storage_ptr` is this strategy's
+/// read-write storage.
+#[no_mangle]
+pub extern "C" fn __prop_amm_after_swap(data: *const u8, len: usize, storage_ptr: *mut u8) {
+    let bytes = unsafe { std::slice::from_raw_parts(data, len) };
+    let storage = unsafe { std::slice::from_raw_parts_mut(storage_ptr, STORAGE_SIZE) };
+    match bytes.first() {
+        Some(&TAG_AFTER_SWAP) if bytes.len() >= FILL_RESERVE_X + 8 && read_slot(storage, SLOT_BASELINE_X) == 0 => {
+            let (side, input) = (bytes[FILL_SIDE], read_u64(bytes, FILL_INPUT));
+            let (output, rx) = (read_u64(bytes, FILL_OUTPUT), read_u64(bytes, FILL_RESERVE_X));
+            // Pre-trade reserve_x on the first fill
+            let baseline = if side == 0 { rx + output } else { rx.saturating_sub(input) };
+            write_slot(storage, SLOT_BASELINE_X, baseline);
+        }
+        Some(&TAG_EPOCH_BOUNDARY) if bytes.len() >= EPOCH_RESERVE_X + 8 => {
+            write_slot(storage, SLOT_BASELINE_X, read_u64(bytes, EPOCH_RESERVE_X))
+        }
+        _ => {}
+    }
+}
+
+/// Payload layout this strategy decodes (the engine's `ABI_VERSION`)
+#[no_mangle]
+pub extern "C" fn __prop_amm_abi_version() -> u32 {
+    ABI_VERSION
+}
+
+#[no_mangle]
+pub extern "C" fn __prop_amm_get_name(buf: *mut u8, max_len: usize) -> usize {
+    let bytes = NAME.as_bytes();
+    let n = bytes.len().min(max_len);
+    unsafe { std::ptr::copy_nonoverlapping(bytes.as_ptr(), buf, n) };
+    n
+}
+
+fn read_u64(bytes: &[u8], at: usize) -> u64 {
+    bytes.get(at..at + 8).map_or(0, |b| u64::from_le_bytes(b.try_into().unwrap()))
+}
+
+fn read_slot(storage: &[u8], slot: usize) -> u64 {
+    read_u64(storage, slot * 8)
+}
+
+fn write_slot(storage: &mut [u8], slot: usize, value: u64) {
+    storage[slot * 8..slot * 8 + 8].copy_from_slice(&value.to_le_bytes());
+}
+
+fn cpamm_output(input: u64, reserve_in: u64, reserve_out: u64, fee_bps: u128) -> u64 {
+    if input == 0 || reserve_in == 0 || reserve_out == 0 {
+        return 0;
+    }
+    let input_eff = (input as u128) * (10_000 - fee_bps) / 10_000;
+    ((reserve_out as u128) * input_eff / (reserve_in as u128 + input_eff)) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RX: u64 = 100_000_000_000;
+    const RY: u64 = 10_000_000_000_000;
+
+    fn quote(is_buy: bool, input: u64, rx: u64, storage: &[u8; STORAGE_SIZE]) -> u64 {
+        let mut payload = vec![0; SWAP_STORAGE];
+        payload[0] = if is_buy { TAG_SWAP_BUY } else { TAG_SWAP_SELL };
+        for (at, v) in [(SWAP_INPUT, input), (SWAP_RESERVE_X, rx), (SWAP_RESERVE_Y, RY)] {
+            payload[at..at + 8].copy_from_slice(&v.to_le_bytes());
+        }
+        payload.extend_from_slice(storage);
+        __prop_amm_compute_swap(payload.as_ptr(), payload.len())
+    }
+
+    #[test]
+    fn quotes_are_positive_monotone_and_inside_the_reserves() {
+        let storage = [0; STORAGE_SIZE];
+        for is_buy in [true, false] {
+            let small = quote(is_buy, 1_000_000_000, RX, &storage);
+            let large = quote(is_buy, 5_000_000_000, RX, &storage);
+            assert!(small > 0 && large > small);
+            assert!(large < if is_buy { RX } else { RY });
+        }
+    }
+
+    #[test]
+    fn long_inventory_makes_buys_cheaper_than_sells() {
+        let mut storage = [0; STORAGE_SIZE];
+        let mut boundary = vec![0; EPOCH_RESERVE_X + 8];
+        boundary[0] = TAG_EPOCH_BOUNDARY;
+        boundary[EPOCH_RESERVE_X..].copy_from_slice(&RX.to_le_bytes());
+        __prop_amm_after_swap(boundary.as_ptr(), boundary.len(), storage.as_mut_ptr());
+        assert_eq!(read_slot(&storage, SLOT_BASELINE_X), RX);
+
+        // 5% long X: the buy fee drops below the base fee
+        let long = RX + RX / 20;
+        let neutral = quote(true, 1_000_000_000, long, &[0; STORAGE_SIZE]);
+        assert!(quote(true, 1_000_000_000, long, &storage) > neutral);
+    }
+}
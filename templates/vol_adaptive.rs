@@ -0,0 +1,214 @@
+//! {{NAME}}: constant-product AMM whose fee tracks an EMA of realized volatility, so it
+//! quotes tight in calm markets and wide when arbitrageurs are most dangerous.
+//!
+//! Validate and run against the reference field:
+//!
+//!     prop-amm-multi validate {{FILE}}
+//!     prop-amm-multi bench {{FILE}}
+//!
+//! Local tests: `{{TEST_COMMAND}}`
+//!
+//! Storage ({{STORAGE_SIZE}} bytes of u64 slots, persists for the whole simulation):
+//!
+//! | Slot | Field          | Type | Description                           |
+//! |------|----------------|------|---------------------------------------|
+//! | 2    | vol_estimate   | f64  | EMA of \|log return\| of the pool spot |
+//! | 3    | last_price     | f64  | Last observed spot price (Y per X)     |
+//! | 5    | trade_count    | u64  | Fills this epoch                      |
+//! | 6    | capital_weight | f64  | Most recent capital weight            |
+//! | 7    | epoch_number   | u64  | Current epoch index                   |
+//!
+//! Every other slot below {{REBALANCE_REQUEST_SLOT}} is free; slot {{REBALANCE_REQUEST_SLOT}} is the
+//! rebalance request slot (leave it zero unless you want a rebalance at the next epoch
+//! boundary).
+
+const NAME: &str = "{{NAME}}";
+/// Fee with zero measured volatility (bps)
+const BASE_FEE_BPS: f64 = 15.0; // tune: 5..60
+/// Fee added per bp of EMA |log return|
+const VOL_MULT: f64 = 2.0; // tune: 0..8
+/// EMA weight of each new observation
+const VOL_ALPHA: f64 = 0.05; // tune: 0.01..0.3
+const MIN_FEE_BPS: f64 = 5.0;
+const MAX_FEE_BPS: f64 = 300.0;
+
+const STORAGE_SIZE: usize = {{STORAGE_SIZE}};
+const SLOT_VOL: usize = 2;
+const SLOT_LAST_PRICE: usize = 3;
+const SLOT_TRADE_COUNT: usize = 5;
+const SLOT_CAPITAL_WEIGHT: usize = 6;
+const SLOT_EPOCH: usize = 7;
+
+/// Payload layout (the engine's `wire.rs`, ABI v{{ABI_VERSION}}): the tag at byte 0, then
+/// fields at these offsets
+const ABI_VERSION: u32 = {{ABI_VERSION}};
+const TAG_SWAP_BUY: u8 = {{TAG_SWAP_BUY}};
+const TAG_SWAP_SELL: u8 = {{TAG_SWAP_SELL}};
+const TAG_AFTER_SWAP: u8 = {{TAG_AFTER_SWAP}};
+const TAG_EPOCH_BOUNDARY: u8 = {{TAG_EPOCH_BOUNDARY}};
+const SWAP_INPUT: usize = {{swap::INPUT_AMOUNT}};
+const SWAP_RESERVE_X: usize = {{swap::RESERVE_X}};
+const SWAP_RESERVE_Y: usize = {{swap::RESERVE_Y}};
+const SWAP_STORAGE: usize = {{swap::STORAGE}};
+/// After a fill: post-trade reserves and the capital weight (f32)
+const FILL_RESERVE_X: usize = {{after_swap::RESERVE_X}};
+const FILL_RESERVE_Y: usize = {{after_swap::RESERVE_Y}};
+const FILL_CAPITAL_WEIGHT: usize = {{after_swap::CAPITAL_WEIGHT}};
+/// At an epoch boundary: the epoch number (u32) and the new capital weight (f32)
+const EPOCH_NUMBER: usize = {{epoch_boundary::EPOCH_NUMBER}};
+const EPOCH_CAPITAL_WEIGHT: usize = {{epoch_boundary::CAPITAL_WEIGHT}};
+
+/// Quote `input` against the pool: the trader buys X paying Y (`TAG_SWAP_BUY`) or sells
+/// X for Y (`TAG_SWAP_SELL`).
+#[no_mangle]
+pub extern "C" fn __prop_amm_compute_swap(data: *const u8, len: usize) -> u64 {
+    let bytes = unsafe { std::slice::from_raw_parts(data, len) };
+    if bytes.len() < SWAP_STORAGE + STORAGE_SIZE {
+        return 0;
+    }
+    let is_buy = bytes[0] == TAG_SWAP_BUY;
+    let input = read_u64(bytes, SWAP_INPUT);
+    let (rx, ry) = (read_u64(bytes, SWAP_RESERVE_X), read_u64(bytes, SWAP_RESERVE_Y));
+    let storage = &bytes[SWAP_STORAGE..SWAP_STORAGE + STORAGE_SIZE];
+
+    let vol_bps = read_f64(storage, SLOT_VOL) * 10_000.0;
+    let fee = (BASE_FEE_BPS + VOL_MULT * vol_bps).clamp(MIN_FEE_BPS, MAX_FEE_BPS) as u128;
+
+    if is_buy {
+        cpamm_output(input, ry, rx, fee)
+    } else {
+        cpamm_output(input, rx, ry, fee)
+    }
+}
+
+/// Every hook lands here, told apart by the tag at byte 0: after a fill, at an epoch
+/// boundary, for shock news and for the step digest. `storage_ptr` is this strategy's
+/// read-write storage.
+#[no_mangle]
+pub extern "C" fn __prop_amm_after_swap(data: *const u8, len: usize, storage_ptr: *mut u8) {
+    let bytes = unsafe { std::slice::from_raw_parts(data, len) };
+    let storage = unsafe { std::slice::from_raw_parts_mut(storage_ptr, STORAGE_SIZE) };
+    match bytes.first() {
+        Some(&TAG_AFTER_SWAP) if bytes.len() >= FILL_CAPITAL_WEIGHT + 4 => {
+            let (rx, ry) = (read_u64(bytes, FILL_RESERVE_X), read_u64(bytes, FILL_RESERVE_Y));
+            if rx == 0 {
+                return;
+            }
+            let price = ry as f64 / rx as f64;
+            let last = read_f64(storage, SLOT_LAST_PRICE);
+            if last > 0.0 {
+                let ret = (price / last).ln().abs();
+                let vol = read_f64(storage, SLOT_VOL);
+                write_f64(storage, SLOT_VOL, (1.0 - VOL_ALPHA) * vol + VOL_ALPHA * ret);
+            }
+            write_f64(storage, SLOT_LAST_PRICE, price);
+            write_slot(storage, SLOT_TRADE_COUNT, read_slot(storage, SLOT_TRADE_COUNT) + 1);
+            write_f64(storage, SLOT_CAPITAL_WEIGHT, read_f32(bytes, FILL_CAPITAL_WEIGHT) as f64);
+        }
+        Some(&TAG_EPOCH_BOUNDARY) if bytes.len() >= EPOCH_CAPITAL_WEIGHT + 4 => {
+            write_slot(storage, SLOT_EPOCH, read_u32(bytes, EPOCH_NUMBER) as u64 + 1);
+            write_slot(storage, SLOT_TRADE_COUNT, 0);
+            write_f64(storage, SLOT_CAPITAL_WEIGHT, read_f32(bytes, EPOCH_CAPITAL_WEIGHT) as f64);
+        }
+        _ => {}
+    }
+}
+
+/// Payload layout this strategy decodes (the engine's `ABI_VERSION`)
+#[no_mangle]
+pub extern "C" fn __prop_amm_abi_version() -> u32 {
+    ABI_VERSION
+}
+
+#[no_mangle]
+pub extern "C" fn __prop_amm_get_name(buf: *mut u8, max_len: usize) -> usize {
+    let bytes = NAME.as_bytes();
+    let n = bytes.len().min(max_len);
+    unsafe { std::ptr::copy_nonoverlapping(bytes.as_ptr(), buf, n) };
+    n
+}
+
+fn read_u64(bytes: &[u8], at: usize) -> u64 {
+    bytes.get(at..at + 8).map_or(0, |b| u64::from_le_bytes(b.try_into().unwrap()))
+}
+
+fn read_u32(bytes: &[u8], at: usize) -> u32 {
+    bytes.get(at..at + 4).map_or(0, |b| u32::from_le_bytes(b.try_into().unwrap()))
+}
+
+fn read_f32(bytes: &[u8], at: usize) -> f32 {
+    f32::from_bits(read_u32(bytes, at))
+}
+
+fn read_slot(storage: &[u8], slot: usize) -> u64 {
+    read_u64(storage, slot * 8)
+}
+
+fn write_slot(storage: &mut [u8], slot: usize, value: u64) {
+    storage[slot * 8..slot * 8 + 8].copy_from_slice(&value.to_le_bytes());
+}
+
+fn read_f64(storage: &[u8], slot: usize) -> f64 {
+    f64::from_bits(read_slot(storage, slot))
+}
+
+fn write_f64(storage: &mut [u8], slot: usize, value: f64) {
+    write_slot(storage, slot, value.to_bits());
+}
+
+fn cpamm_output(input: u64, reserve_in: u64, reserve_out: u64, fee_bps: u128) -> u64 {
+    if input == 0 || reserve_in == 0 || reserve_out == 0 {
+        return 0;
+    }
+    let input_eff = (input as u128) * (10_000 - fee_bps) / 10_000;
+    ((reserve_out as u128) * input_eff / (reserve_in as u128 + input_eff)) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RX: u64 = 100_000_000_000;
+    const RY: u64 = 10_000_000_000_000;
+
+    fn quote(is_buy: bool, input: u64, storage: &[u8; STORAGE_SIZE]) -> u64 {
+        let mut payload = vec![0; SWAP_STORAGE];
+        payload[0] = if is_buy { TAG_SWAP_BUY } else { TAG_SWAP_SELL };
+        for (at, v) in [(SWAP_INPUT, input), (SWAP_RESERVE_X, RX), (SWAP_RESERVE_Y, RY)] {
+            payload[at..at + 8].copy_from_slice(&v.to_le_bytes());
+        }
+        payload.extend_from_slice(storage);
+        __prop_amm_compute_swap(payload.as_ptr(), payload.len())
+    }
+
+    fn fill(storage: &mut [u8; STORAGE_SIZE], rx: u64, ry: u64) {
+        let mut payload = vec![0u8; {{after_swap::LEN}}];
+        payload[0] = TAG_AFTER_SWAP;
+        payload[FILL_RESERVE_X..FILL_RESERVE_X + 8].copy_from_slice(&rx.to_le_bytes());
+        payload[FILL_RESERVE_Y..FILL_RESERVE_Y + 8].copy_from_slice(&ry.to_le_bytes());
+        payload[FILL_CAPITAL_WEIGHT..FILL_CAPITAL_WEIGHT + 4].copy_from_slice(&0.5f32.to_le_bytes());
+        __prop_amm_after_swap(payload.as_ptr(), payload.len(), storage.as_mut_ptr());
+    }
+
+    #[test]
+    fn quotes_are_positive_monotone_and_inside_the_reserves() {
+        let storage = [0; STORAGE_SIZE];
+        for is_buy in [true, false] {
+            let small = quote(is_buy, 1_000_000_000, &storage);
+            let large = quote(is_buy, 5_000_000_000, &storage);
+            assert!(small > 0 && large > small);
+            assert!(large < if is_buy { RX } else { RY });
+        }
+    }
+
+    #[test]
+    fn volatility_widens_the_fee() {
+        let mut storage = [0; STORAGE_SIZE];
+        let calm = quote(true, 1_000_000_000, &storage);
+        fill(&mut storage, RX, RY);
+        fill(&mut storage, RX, RY + RY / 50);
+        assert!(read_f64(&storage, SLOT_VOL) > 0.0);
+        assert_eq!(read_slot(&storage, SLOT_TRADE_COUNT), 2);
+        assert!(quote(true, 1_000_000_000, &storage) < calm);
+    }
+}
@@ -4,11 +4,20 @@
 
 #[cfg(test)]
 mod integration {
-    use prop_amm_engine::capital::{risk_adjusted_score, softmax_weights};
+    use prop_amm_engine::capital::{risk_adjusted_score, softmax_weights, CapitalFlows};
     use prop_amm_engine::market::{
-        gbm_step, generate_retail_orders, cpamm_output, route_order_n_amms, MarketParams,
+        gbm_step, generate_retail_orders, cpamm_output, degenerate_quotes, route_order_n_amms, route_order_naive,
+        route_order_with_limit, route_order_on_curves, plan_sandwich, sample_shock, demand_multiplier,
+        generate_retail_orders_clustered, route_order_rfq, ArrivalCurve, FlowState, MarketParams, OrderKind, Quantization,
+        RetailOrder, SizeClustering, TwapBook, DUST_RESERVE_FLOOR, SANITY_GRID_POINTS, golden_section_max, optimal_arb_trade,
     };
-    use prop_amm_engine::types::{AmmState, SimConfig, SCALE, SCALE_F};
+    use prop_amm_engine::fingerprint::{find_duplicates, Fingerprint};
+    use prop_amm_engine::reference;
+    use prop_amm_engine::runner::{audit_artifact, check_determinism, EngineStrategy, OverQuoteAction, QuoteGuard, RemoteEndpoint, Runtime, StrategyCall, StrategyRunner};
+    use prop_amm_engine::sim::{replay_simulation, run_simulation, Aggregator};
+    use prop_amm_engine::types::{AfterSwapPayload, AmmState, NaiveRouting, Numeraire, ShockEvent, SimConfig, SCALE, SCALE_F, STORAGE_SIZE};
+    use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+    use std::sync::Arc;
     use rand::SeedableRng;
     use rand_chacha::ChaCha8Rng;
 
@@ -24,6 +33,23 @@ mod integration {
         }
     }
 
+    // ── Unit: Shock sampling ──────────────────────────────────────────────────
+
+    #[test]
+    fn shocks_follow_schedule_and_probability() {
+        let mut rng = ChaCha8Rng::seed_from_u64(11);
+        let schedule = [ShockEvent { step: 5, jump: -0.05 }];
+
+        assert_eq!(sample_shock(5, &schedule, 0.0, 0.02, &mut rng), Some(-0.05));
+        assert_eq!(sample_shock(6, &schedule, 0.0, 0.02, &mut rng), None);
+
+        // prob = 1 → every step shocks by ±size
+        for step in 0..50 {
+            let jump = sample_shock(step + 10, &schedule, 1.0, 0.02, &mut rng).unwrap();
+            assert_eq!(jump.abs(), 0.02);
+        }
+    }
+
     // ── Unit: CPAMM output monotone + concave ─────────────────────────────────
 
     #[test]
@@ -88,6 +114,591 @@ mod integration {
         }
     }
 
+    // ── Unit: Unequal pools are filled until their marginal rates meet ────────
+
+    #[test]
+    fn equimarginal_split_equalizes_unequal_pools() {
+        // Same X reserve, B priced 5% dearer: A alone takes the first ~250 Y, then both.
+        // Fails if the router's marginal probe shrinks back to one scaled unit.
+        let amms = [
+            AmmState::new(100 * SCALE, 10_000 * SCALE, 0, "A"),
+            AmmState::new(100 * SCALE, 10_500 * SCALE, 1, "B"),
+        ];
+        let compute = |_: usize, is_buy: bool, input: u64, rx: u64, ry: u64| -> u64 {
+            if is_buy { cpamm_output(input, ry, rx, 30) } else { cpamm_output(input, rx, ry, 30) }
+        };
+        let result = route_order_with_limit(&amms, true, 500.0, None, DUST_RESERVE_FLOOR, 0, compute);
+        let (a, b) = (result.allocations[0].0 as f64 / SCALE_F, result.allocations[1].0 as f64 / SCALE_F);
+        assert!((a + b - 500.0).abs() < 0.1, "input not conserved: {a:.3} + {b:.3}");
+
+        // dX/dY of a 30 bps CPAMM after taking y: γ·rx·ry / (ry + γy)²
+        let gamma = 0.997;
+        let marginal = |rx: f64, ry: f64, y: f64| gamma * rx * ry / (ry + gamma * y).powi(2);
+        let (ma, mb) = (marginal(100.0, 10_000.0, a), marginal(100.0, 10_500.0, b));
+        assert!((ma - mb).abs() / ma < 1e-3, "marginals differ: A {ma:.6} at {a:.1}, B {mb:.6} at {b:.1}");
+        assert!(a > 2.5 * b, "the cheaper pool should take most of the order: A {a:.1}, B {b:.1}");
+    }
+
+    // ── Unit: Curve routing from batch quotes tracks the exact router ─────────
+
+    #[test]
+    fn curve_router_tracks_exact_split() {
+        let amms: Vec<AmmState> = (0..3)
+            .map(|i| AmmState::new(100 * SCALE, 10_000 * SCALE, i as u8, &format!("AMM{i}")))
+            .collect();
+        let fees = [10u32, 30, 60];
+        let compute = |i: usize, is_buy: bool, input: u64, rx: u64, ry: u64| -> u64 {
+            if is_buy { cpamm_output(input, ry, rx, fees[i]) } else { cpamm_output(input, rx, ry, fees[i]) }
+        };
+        let batch = |i: usize, is_buy: bool, inputs: &[u64], rx: u64, ry: u64| -> Vec<u64> {
+            inputs.iter().map(|&x| compute(i, is_buy, x, rx, ry)).collect()
+        };
+
+        let exact = route_order_with_limit(&amms, true, 500.0, None, DUST_RESERVE_FLOOR, 0, compute);
+        let curves = route_order_on_curves(&amms, true, 500.0, None, DUST_RESERVE_FLOOR, 64, batch, compute);
+        for (e, c) in exact.allocations.iter().zip(&curves.allocations) {
+            let (e, c) = (e.0 as f64 / SCALE_F, c.0 as f64 / SCALE_F);
+            assert!((e - c).abs() < 0.5, "curve split drifted: exact={e:.3} curves={c:.3}");
+        }
+        let filled: f64 = curves.allocations.iter().map(|&(inp, _)| inp as f64 / SCALE_F).sum();
+        assert!((filled - 500.0).abs() < 0.1, "input not conserved: {filled:.4}");
+        // Cheapest venue still takes the largest share.
+        assert!(curves.allocations[0].0 > curves.allocations[2].0);
+    }
+
+    // ── Unit: Degenerate quotes are kept out of the equimarginal router ───────
+
+    #[test]
+    fn router_excludes_zero_then_huge_quoter() {
+        let grid: Vec<u64> = (1..=8).map(|k| k * 10 * SCALE).collect();
+        let curve: Vec<u64> = grid.iter().map(|&x| cpamm_output(x, 10_000 * SCALE, 100 * SCALE, 30)).collect();
+        assert!(!degenerate_quotes(&grid, &curve));
+        let mut cliff = vec![0; 8];
+        cliff[7] = 50 * SCALE;
+        assert!(degenerate_quotes(&grid, &cliff));
+        let shrinking: Vec<u64> = curve.iter().rev().copied().collect();
+        assert!(degenerate_quotes(&grid, &shrinking));
+        assert!(!degenerate_quotes(&grid, &[0; 8]), "refusing everything is allowed");
+
+        let amms: Vec<AmmState> = (0..3)
+            .map(|i| AmmState::new(100 * SCALE, 10_000 * SCALE, i as u8, &format!("AMM{i}")))
+            .collect();
+        // AMM 1 quotes nothing below 200 Y, then a third of its X reserve
+        let compute = |i: usize, is_buy: bool, input: u64, rx: u64, ry: u64| -> u64 {
+            match (i, is_buy) {
+                (1, true) if input < 200 * SCALE => 0,
+                (1, true) => rx / 3,
+                (_, true) => cpamm_output(input, ry, rx, 30),
+                (_, false) => cpamm_output(input, rx, ry, 30),
+            }
+        };
+        let batch = |i: usize, is_buy: bool, inputs: &[u64], rx: u64, ry: u64| -> Vec<u64> {
+            inputs.iter().map(|&x| compute(i, is_buy, x, rx, ry)).collect()
+        };
+        for routing in [
+            route_order_with_limit(&amms, true, 500.0, None, DUST_RESERVE_FLOOR, SANITY_GRID_POINTS, compute),
+            route_order_on_curves(&amms, true, 500.0, None, DUST_RESERVE_FLOOR, 16, batch, compute),
+        ] {
+            assert_eq!(routing.excluded, vec![1]);
+            assert_eq!(routing.allocations[1], (0, 0));
+            let filled: f64 = routing.allocations.iter().map(|&(inp, _)| inp as f64 / SCALE_F).sum();
+            assert!((filled - 500.0).abs() < 0.1 && routing.allocations[0].0 == routing.allocations[2].0);
+        }
+        assert!(route_order_with_limit(&amms, false, 5.0, None, DUST_RESERVE_FLOOR, SANITY_GRID_POINTS, compute).excluded.is_empty());
+        // The check is opt-in: without it the router quotes nobody on a grid
+        assert!(route_order_with_limit(&amms, true, 500.0, None, DUST_RESERVE_FLOOR, 0, compute).excluded.is_empty());
+    }
+
+    // ── Unit: Arb search trades a mispriced pool back to fair ─────────────────
+
+    #[test]
+    fn arb_search_trades_mispriced_cpamm_back_to_fair() {
+        let (peak, best) = golden_section_max(|x| -(x - 3.0) * (x - 3.0), 0.0, 10.0, 80);
+        assert!((peak - 3.0).abs() < 1e-6 && best.abs() < 1e-9, "golden section found {peak}");
+
+        // 100 X / 10,000 Y at 30 bps: spot 100, no-arb band ≈ 99.7..100.3
+        let amm = AmmState::new(100 * SCALE, 10_000 * SCALE, 0, "cpamm");
+        let compute = |is_buy: bool, input: u64, rx: u64, ry: u64| -> u64 {
+            if is_buy { cpamm_output(input, ry, rx, 30) } else { cpamm_output(input, rx, ry, 30) }
+        };
+        for fair in [90.0, 99.0, 101.0, 105.0, 120.0] {
+            for sanity in [0, SANITY_GRID_POINTS] {
+                let (is_buy, input, output) = optimal_arb_trade(&amm, fair, 0.01, sanity, compute)
+                    .unwrap_or_else(|| panic!("no arb at fair {fair}"));
+                // Cheap X is bought, dear X sold
+                assert_eq!(is_buy, fair > 100.0);
+                let (input_f, output_f) = (input as f64 / SCALE_F, output as f64 / SCALE_F);
+                let profit = if is_buy { output_f * fair - input_f } else { output_f - input_f * fair };
+                assert!(profit > 0.01, "unprofitable arb at fair {fair}: {profit}");
+                // The arb stops where the marginal price net of the fee meets fair
+                let (mut rx, mut ry) = (amm.reserve_x, amm.reserve_y);
+                prop_amm_engine::market::apply_cpamm_trade(&mut rx, &mut ry, is_buy, input, output);
+                let spot = ry as f64 / rx as f64;
+                assert!((spot / fair - 1.0).abs() < 0.004, "arb at fair {fair} left spot {spot}");
+            }
+        }
+        assert!(optimal_arb_trade(&amm, 99.9, 0.01, 0, compute).is_none(), "arbed inside the fee band");
+    }
+
+    // ── Unit: Drained pools are skipped by the router ─────────────────────────
+
+    #[test]
+    fn router_skips_drained_pools() {
+        let mut amms: Vec<AmmState> = (0..3)
+            .map(|i| AmmState::new(100 * SCALE, 10_000 * SCALE, i as u8, &format!("AMM{i}")))
+            .collect();
+        // AMM 1 has been bought down to a few hundred scaled units of X
+        amms[1].reserve_x = 400;
+        let probes = std::cell::Cell::new(0usize);
+        let compute = |i: usize, is_buy: bool, input: u64, rx: u64, ry: u64| -> u64 {
+            if i == 1 {
+                probes.set(probes.get() + 1);
+            }
+            if is_buy { cpamm_output(input, ry, rx, 30) } else { cpamm_output(input, rx, ry, 30) }
+        };
+
+        let result = route_order_n_amms(&amms, true, 200.0, compute);
+        assert_eq!(probes.get(), 0, "drained pool was quoted");
+        assert_eq!(result.allocations[1], (0, 0));
+        let filled: f64 = result.allocations.iter().map(|&(inp, _)| inp as f64 / SCALE_F).sum();
+        assert!((filled - 200.0).abs() < 1e-6, "input not conserved: {filled}");
+        assert!(result.unfilled_input < 10);
+
+        // With every pool drained the order goes unfilled rather than into dust
+        for amm in &mut amms {
+            amm.reserve_x = 400;
+        }
+        let result = route_order_n_amms(&amms, true, 200.0, compute);
+        assert!(result.allocations.iter().all(|&a| a == (0, 0)));
+        assert_eq!(result.unfilled_input, 200 * SCALE);
+    }
+
+    // ── Unit: Limit prices leave orders partially unfilled ────────────────────
+
+    #[test]
+    fn limit_price_caps_fill() {
+        let amms: Vec<AmmState> = (0..2)
+            .map(|i| AmmState::new(100 * SCALE, 10_000 * SCALE, i as u8, &format!("AMM{i}")))
+            .collect();
+        let compute = |_amm_idx: usize, is_buy: bool, input: u64, rx: u64, ry: u64| -> u64 {
+            if is_buy { cpamm_output(input, ry, rx, 30) }
+            else       { cpamm_output(input, rx, ry, 30) }
+        };
+
+        // Buy X at spot 100 with a 1% ceiling: the 30 bps fee plus ~70 bps of impact per pool
+        // allows ~2 × 35 Y through, far less than the 1000 Y requested.
+        let order = RetailOrder {
+            is_buy: true, size_y: 1_000.0, kind: OrderKind::Market, limit_price: Some(101.0),
+        };
+        let result = route_order_with_limit(&amms, true, order.size_y, order.min_marginal(), DUST_RESERVE_FLOOR, 0, compute);
+        let filled: f64 = result.allocations.iter().map(|&(inp, _)| inp as f64 / SCALE_F).sum();
+        let unfilled = result.unfilled_input as f64 / SCALE_F;
+        assert!(filled > 10.0 && filled < 100.0, "filled = {filled}");
+        assert!((filled + unfilled - 1_000.0).abs() < 1e-6);
+
+        // Limit below the fee-adjusted spot: nothing fills
+        let order = RetailOrder { limit_price: Some(100.1), ..order };
+        let result = route_order_with_limit(&amms, true, order.size_y, order.min_marginal(), DUST_RESERVE_FLOOR, 0, compute);
+        assert_eq!(result.total_output, 0);
+        assert_eq!(result.unfilled_input, 1_000 * SCALE);
+    }
+
+    // ── Unit: Sandwich attacker is deterred by fees ───────────────────────────
+
+    #[test]
+    fn sandwich_profitable_only_against_thin_fees() {
+        let amm = AmmState::new(100 * SCALE, 10_000 * SCALE, 0, "Victim");
+        let victim_in = 50 * SCALE; // 50 Y buy ≈ 1% price impact
+
+        let plan_at = |fee_bps: u32| {
+            let cs = move |is_buy: bool, input: u64, rx: u64, ry: u64| -> u64 {
+                if is_buy { cpamm_output(input, ry, rx, fee_bps) }
+                else       { cpamm_output(input, rx, ry, fee_bps) }
+            };
+            let quote = cs(true, victim_in, amm.reserve_x, amm.reserve_y);
+            plan_sandwich(&amm, true, victim_in, quote, 0.01, cs)
+        };
+
+        let thin = plan_at(5).expect("5 bps pool should be sandwichable");
+        assert!(thin.front_input > 0 && thin.expected_profit > 0.0);
+        assert!(plan_at(100).is_none(), "100 bps fee should deter a 1% tolerance sandwich");
+    }
+
+    // ── Unit: Tick / lot quantization ─────────────────────────────────────────
+
+    #[test]
+    fn quantization_rounds_prices_against_the_taker_and_floors_lots() {
+        let q = Quantization { tick_size: 1.0, lot_size: SCALE / 1_000 };
+        // Buy at 2.5 Y per X pays the 3.0 tick; sell at 2.5 receives the 2.0 tick
+        assert_eq!(q.output(true, 10 * SCALE, 4 * SCALE), 3_333 * SCALE / 1_000);
+        assert_eq!(q.output(false, 4 * SCALE, 10 * SCALE), 8 * SCALE);
+        // Prices already on the tick are left alone
+        assert_eq!(q.output(false, 4 * SCALE, 12 * SCALE), 12 * SCALE);
+        // The strategy is quoted the lot-floored input; sub-lot orders never reach it
+        assert_eq!(q.quote(false, 4 * SCALE + 7, |input| { assert_eq!(input, 4 * SCALE); 12 * SCALE }), 12 * SCALE);
+        assert_eq!(q.quote(true, 7, |_| unreachable!()), 0);
+        assert_eq!(Quantization::default().quote(true, 7, |input| input), 7);
+    }
+
+    // ── Unit: Constant tuning ─────────────────────────────────────────────────
+
+    #[test]
+    fn tunables_come_from_annotations_and_sidecar_and_rewrite_the_source() {
+        use prop_amm_engine::optimize::{apply, tunables};
+
+        let source = "const NAME: &str = \"t\";\nconst FEE_BPS: u128 = 30; // tune: 10..=90\npub const SKEW: f64 = 0.5;\n";
+        let found = tunables(source, Some(r#"{"SKEW": [0.0, 2.0]}"#)).unwrap();
+        assert_eq!(found.iter().map(|t| (t.name.as_str(), t.min, t.max, t.integer)).collect::<Vec<_>>(),
+            [("FEE_BPS", 10.0, 90.0, true), ("SKEW", 0.0, 2.0, false)]);
+        assert_eq!(found[0].value_at(0.26), 31.0);
+        assert_eq!(apply(source, &found, &[45.0, 1.25]),
+            "const NAME: &str = \"t\";\nconst FEE_BPS: u128 = 45; // tune: 10..=90\npub const SKEW: f64 = 1.25;\n");
+
+        assert!(tunables(source, Some(r#"{"MISSING": [0.0, 1.0]}"#)).is_err());
+        assert!(tunables("const X: u32 = 1; // tune: 5..5\n", None).is_err());
+    }
+
+    #[test]
+    fn every_search_method_approaches_the_optimum() {
+        use prop_amm_engine::optimize::{search, SearchMethod, Tunable};
+
+        let tunables = [
+            Tunable { name: "A".into(), min: 0.0, max: 100.0, integer: true },
+            Tunable { name: "B".into(), min: -1.0, max: 1.0, integer: false },
+        ];
+        let objective = |v: &[f64]| -((v[0] - 70.0) / 100.0).powi(2) - (v[1] - 0.2).powi(2);
+        for (method, tolerance) in [
+            (SearchMethod::Grid { points: 11 }, 0.0),
+            (SearchMethod::Random { trials: 200 }, 0.02),
+            (SearchMethod::Cmaes { generations: 30 }, 0.001),
+        ] {
+            let trials = search(&tunables, method, 3, |v| Some(objective(v)));
+            assert!(trials.windows(2).all(|w| w[0].score >= w[1].score), "{method:?} trials not best first");
+            assert!(trials[0].score >= -tolerance - 1e-12, "{method:?} best {:?}", trials[0]);
+        }
+
+        // Rounded duplicates are scored once, and `None` stops the search
+        let mut calls = 0;
+        let trials = search(&tunables[..1], SearchMethod::Grid { points: 1001 }, 0, |v| {
+            calls += 1;
+            (calls <= 50).then(|| objective(&[v[0], 0.2]))
+        });
+        assert_eq!((trials.len(), calls), (50, 51));
+    }
+
+    // ── Unit: Strategy scaffolding ────────────────────────────────────────────
+
+    #[test]
+    fn scaffolded_strategies_are_complete_and_tunable() {
+        use prop_amm_engine::optimize::tunables;
+        use prop_amm_engine::scaffold::{cargo_manifest, check_name, render_crate, render_file, Template};
+
+        for template in Template::ALL {
+            let source = render_file(template, "demo");
+            assert!(!source.contains("{{"), "{template:?} left a placeholder");
+            assert!(source.contains("const NAME: &str = \"demo\";"));
+            // Layout values come from `wire`, not the template text
+            assert!(source.contains(&format!("const ABI_VERSION: u32 = {};", prop_amm_engine::wire::ABI_VERSION)));
+            assert!(source.contains(&format!("const SWAP_STORAGE: usize = {};", prop_amm_engine::wire::swap::STORAGE)));
+            assert!(source.contains("rustc --edition 2021 --test demo.rs"));
+            for export in ["__prop_amm_compute_swap", "__prop_amm_after_swap", "__prop_amm_abi_version", "__prop_amm_get_name"] {
+                assert!(source.contains(&format!("pub extern \"C\" fn {export}(")), "{template:?} lacks {export}");
+            }
+            assert!(source.contains("#[cfg(test)]"));
+            assert!(!tunables(&source, None).unwrap().is_empty(), "{template:?} has no tunables");
+            assert!(render_crate(template, "demo").contains("`cd demo && cargo test`"));
+        }
+        assert!(cargo_manifest("demo").contains("crate-type = [\"cdylib\"]"));
+        assert!(check_name("my_strategy2").is_ok());
+        for bad in ["", "2fast", "has-dash", "../escape"] {
+            assert!(check_name(bad).is_err(), "{bad:?} accepted");
+        }
+    }
+
+    #[test]
+    fn sdk_and_allowlisted_crates_switch_to_a_cargo_build() {
+        use prop_amm_engine::compile::{cargo_deps, package_name, strategy_manifest};
+
+        assert_eq!(cargo_deps(include_str!("submission_0.rs")).unwrap(), None);
+        assert_eq!(cargo_deps("use prop_amm_submission_sdk::{SwapContext, ABI_VERSION};").unwrap(), Some(vec![]));
+        assert_eq!(
+            cargo_deps("use prop_amm_submission_sdk::wmul;\nfn f(x: f64) -> f64 { libm::sqrt(x) }").unwrap(),
+            Some(vec![("libm", "0.2.16")])
+        );
+        // Mentions that are not paths, and lookalike names, do not count
+        assert_eq!(cargo_deps("// uses libm-style math\nfn my_libm() {}\nconst X: &str = \"smallvec\";").unwrap(), None);
+        assert!(cargo_deps("extern crate serde;").is_err());
+
+        let manifest = strategy_manifest(&package_name("My-Strat"), "/sdk", &[("num_traits", "0.2.19")]);
+        assert!(manifest.contains("name = \"strategy_my_strat\""));
+        assert!(manifest.contains("prop_amm_submission_sdk = { path = \"/sdk\" }"));
+        assert!(manifest.contains("num-traits = \"=0.2.19\""));
+        assert!(manifest.contains("crate-type = [\"cdylib\"]"));
+    }
+
+    #[test]
+    fn mixed_inputs_resolve_to_files_and_builtins() {
+        use prop_amm_engine::reference::{expand_names, resolve_inputs, STANDARD_FIELD};
+        use std::path::PathBuf;
+
+        // A file named like a built-in is still a file
+        let dir = std::env::temp_dir().join(format!("prop_amm_inputs_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let shadow = dir.join("fixed_30");
+        std::fs::write(&shadow, "").unwrap();
+
+        let inputs: Vec<PathBuf> =
+            ["a.rs", "fixed_30", "wasm:b.rs", "lib/c.so", "field", "chaos_monkey", "fixed_35"].iter().map(PathBuf::from).chain([shadow.clone()]).collect();
+        let resolved = resolve_inputs(&inputs);
+        assert_eq!(resolved.files, ["a.rs", "wasm:b.rs", "lib/c.so", "fixed_35"].iter().map(PathBuf::from).chain([shadow]).collect::<Vec<_>>());
+        // `field` expanded in place, repeats dropped
+        let mut expected = vec!["fixed_30".to_string()];
+        expected.extend(STANDARD_FIELD.iter().filter(|&&n| n != "fixed_30").map(|n| n.to_string()));
+        assert_eq!(resolved.builtins, expected);
+
+        assert!(expand_names(&["fixed_35".to_string()]).unwrap_err().contains("unknown opponent `fixed_35`"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn parallel_builds_keep_file_order_and_report_every_failure() {
+        use prop_amm_engine::compile::{build_all, Compiled};
+        use std::path::{Path, PathBuf};
+        use std::sync::Mutex;
+
+        let files: Vec<PathBuf> = ["a.rs", "bad1.rs", "c.so", "bad2.rs", "e.rs"].iter().map(PathBuf::from).collect();
+        // Earlier files finish last, so completion order is the reverse of `files`
+        let build = |file: &Path| -> Result<(PathBuf, Compiled), String> {
+            let i = files.iter().position(|f| f == file).unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(10 * (files.len() - i) as u64));
+            let name = file.to_string_lossy();
+            match name.as_ref() {
+                n if n.starts_with("bad") => Err(format!("{n} failed")),
+                n if n.ends_with(".so") => Ok((file.to_path_buf(), Compiled::Prebuilt)),
+                n => Ok((PathBuf::from(format!("lib{n}.so")), Compiled::Built)),
+            }
+        };
+        let lines = Mutex::new(Vec::new());
+        let report = |line: String| lines.lock().unwrap().push(line);
+
+        assert_eq!(build_all(&files, build, &report), Err("bad1.rs failed".to_string()));
+        let lines = lines.into_inner().unwrap();
+        for file in &files {
+            let name = file.display().to_string();
+            assert_eq!(lines.iter().filter(|l| l.split(' ').nth(1) == Some(name.as_str())).count(), 1, "one status for {name}: {lines:?}");
+        }
+        assert!(lines.contains(&"[FAILED] bad1.rs".to_string()) && lines.contains(&"[FAILED] bad2.rs".to_string()));
+        assert!(lines.contains(&"[prebuilt] c.so".to_string()));
+        assert!(lines.last().unwrap().starts_with("Compiled 5 strategies"));
+
+        let good: Vec<PathBuf> = files.iter().filter(|f| !f.to_string_lossy().starts_with("bad")).cloned().collect();
+        let artifacts = build_all(&good, build, &|_| {}).unwrap();
+        assert_eq!(artifacts, ["liba.rs.so", "c.so", "libe.rs.so"].map(PathBuf::from));
+    }
+
+    #[test]
+    fn receipts_compare_by_name_with_significance_and_config_changes() {
+        use prop_amm_engine::compare::compare;
+
+        let receipt = |seed_start: u64, edge: f64, others: &[&str]| {
+            let mut strategies = vec![serde_json::json!({ "name": "mine", "mean_edge": edge, "std_edge": 10.0, "sharpe": 1.0 })];
+            strategies.extend(others.iter().map(|n| serde_json::json!({ "name": n, "mean_edge": 5.0 })));
+            serde_json::json!({
+                "simulations": 100,
+                "completed_simulations": 100,
+                "steps": 10_000,
+                "seed_start": seed_start,
+                "config": { "epoch_len": 1000, "market": { "volatility": 0.01 } },
+                "strategies": strategies,
+            })
+        };
+        let diff = compare(&receipt(0, 20.0, &["old"]), &receipt(100, 25.0, &["new"])).unwrap();
+
+        assert_eq!(diff.config.len(), 1);
+        assert_eq!((diff.config[0].key.as_str(), diff.config[0].b.as_deref()), ("seed_start", Some("100")));
+        assert_eq!((diff.only_a.clone(), diff.only_b.clone()), (vec!["old".to_string()], vec!["new".to_string()]));
+
+        // +5 edge over a standard error of √(2 · 10² / 100) = √2: z ≈ 3.54
+        let edge = &diff.strategies[0].metrics[0];
+        assert_eq!((edge.metric, edge.delta()), ("mean_edge", 5.0));
+        assert!((edge.z.unwrap() - 5.0 / 2f64.sqrt()).abs() < 1e-9);
+        assert_eq!(edge.flag(), "**");
+        let sharpe = &diff.strategies[0].metrics[1];
+        assert_eq!((sharpe.metric, sharpe.z, sharpe.flag()), ("sharpe", Some(0.0), ""));
+
+        let nested = serde_json::json!({ "config": { "epoch_len": 1000, "market": { "volatility": 0.02 } }, "strategies": [] });
+        let diff = compare(&receipt(0, 20.0, &[]), &nested).unwrap();
+        assert!(diff.config.iter().any(|c| c.key == "config.market.volatility"));
+        assert!(compare(&serde_json::json!({}), &nested).is_err());
+    }
+
+    #[test]
+    fn receipts_sign_canonically_and_reject_tampering() {
+        use prop_amm_engine::receipt::{config_hash, hmac_sha256, key_id, sign, verify};
+
+        // RFC 4231 test case 2
+        let mac = hmac_sha256(b"Jefe", b"what do ya want for nothing?");
+        let hex: String = mac.iter().map(|b| format!("{b:02x}")).collect();
+        assert_eq!(hex, "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843");
+        assert_eq!(hmac_sha256(&[7u8; 100], b"m"), hmac_sha256(&[7u8; 100], b"m"), "long keys are hashed first");
+
+        let config = SimConfig { sigma_per_step: Some(0.001_234_567_891_234_5), ..SimConfig::default() };
+        let mut receipt = serde_json::json!({
+            "seeds": [0, 1, u64::MAX],
+            "config": config,
+            "config_hash": config_hash(&config),
+            "strategies": [{ "name": "mine", "mean_edge": 12.345_678_901_234_567 }],
+            "signature": null,
+        });
+        assert!(verify(&receipt, "host").is_err(), "unsigned");
+        sign(&mut receipt, "host");
+        assert_eq!(receipt["signature"]["key_id"], key_id("host"));
+
+        // Written pretty, read back: same canonical bytes, floats included
+        let reread: serde_json::Value = serde_json::from_str(&serde_json::to_string_pretty(&receipt).unwrap()).unwrap();
+        verify(&reread, "host").unwrap();
+        assert!(verify(&reread, "other").unwrap_err().contains("signed with key"));
+
+        let mut tampered = reread.clone();
+        tampered["strategies"][0]["mean_edge"] = serde_json::json!(13.0);
+        assert!(verify(&tampered, "host").unwrap_err().contains("does not match"));
+        let mut tampered = reread;
+        tampered["seeds"][0] = serde_json::json!(5);
+        assert!(verify(&tampered, "host").is_err());
+    }
+
+    #[cfg(feature = "serve")]
+    #[test]
+    fn serve_queues_uploads_and_ranks_each_names_best() {
+        use prop_amm_engine::serve::{forbidden_construct, handle, Evaluation, Store, MAX_NAME_CHARS, MAX_UPLOAD_BYTES};
+
+        let store = Store::in_memory().unwrap();
+        let (queue, jobs) = std::sync::mpsc::channel();
+        let post = |name: &str, body: &[u8]| handle(&store, &queue, "POST", &format!("/api/strategies?name={name}"), body);
+
+        assert_eq!(post("alice%20b", b"fn a() {}"), (202, serde_json::json!({ "id": 1, "status": "queued" })));
+        assert_eq!(post("alice+b", b"fn b() {}").0, 202);
+        assert_eq!(post("bob", b"fn c() {}").0, 202);
+        assert_eq!(jobs.try_iter().collect::<Vec<i64>>(), [1, 2, 3]);
+        assert_eq!(post("x", b"  ").0, 400);
+        assert_eq!(post("x", &[0xff, 0xfe]).0, 400);
+        assert_eq!(post("x", &vec![b'a'; MAX_UPLOAD_BYTES + 1]).0, 413);
+        assert_eq!(post(&"n".repeat(MAX_NAME_CHARS + 1), b"fn e() {}").0, 400);
+        // Nothing that reads the host at compile time gets stored
+        for sneaky in [
+            "const NAME: &str = include_str!(\"/etc/hostname\");",
+            "const K: &[u8] = core::include_bytes !(\"/root/.ssh/id_rsa\");",
+            "const H: &str = r#env!(\"HOME\");",
+            "#[path = \"/etc/passwd\"] mod p;",
+            "mod submission_1;",
+        ] {
+            assert!(forbidden_construct(sneaky).is_some(), "{sneaky}");
+            assert_eq!(post("x", sneaky.as_bytes()).0, 400, "{sneaky}");
+        }
+        assert_eq!(forbidden_construct("mod inline { pub fn environment() {} }\nfn f() { let path = [0u8]; }"), None);
+        assert_eq!(handle(&store, &queue, "GET", "/healthz", b"").1["queued"], 3);
+
+        let scored = |edge: f64| Ok(Evaluation { strategy: "s".repeat(500), mean_edge: edge, details: serde_json::json!({ "rank_in_field": 1 }) });
+        for (id, outcome) in [(1, scored(5.0)), (2, scored(9.0)), (3, Err("rustc failed".to_string()))] {
+            assert!(store.start(id).unwrap().is_some());
+            store.finish(id, &outcome).unwrap();
+        }
+        let (status, detail) = handle(&store, &queue, "GET", "/api/strategies/2", b"");
+        assert_eq!((status, &detail["status"], &detail["name"], &detail["details"]["rank_in_field"]), (200, &"done".into(), &"alice b".into(), &1.into()));
+        assert_eq!(detail["strategy"].as_str().unwrap().len(), MAX_NAME_CHARS);
+        assert_eq!(handle(&store, &queue, "GET", "/api/strategies/3", b"").1["error"], "rustc failed");
+
+        // alice b's two submissions collapse to the better one; bob's failed one is unranked
+        let board = handle(&store, &queue, "GET", "/api/leaderboard", b"").1;
+        assert_eq!(board["entries"].as_array().unwrap().len(), 1);
+        assert_eq!((&board["entries"][0]["id"], &board["entries"][0]["submissions"], &board["entries"][0]["rank"]), (&2.into(), &2.into(), &1.into()));
+
+        assert_eq!(handle(&store, &queue, "GET", "/api/strategies/9", b"").0, 404);
+        assert_eq!(handle(&store, &queue, "GET", "/api/strategies/x", b"").0, 404);
+        assert_eq!(handle(&store, &queue, "PUT", "/api/leaderboard", b"").0, 405);
+        assert_eq!(handle(&store, &queue, "GET", "/nowhere", b"").0, 404);
+
+        // A restarted server picks up where the last one stopped
+        store.insert(None, "fn d() {}").unwrap();
+        store.start(4).unwrap();
+        assert_eq!(store.pending().unwrap(), [4]);
+    }
+
+    // ── Unit: Elastic demand grows with price improvement ─────────────────────
+
+    #[test]
+    fn elastic_demand_rewards_price_improvement() {
+        let norm_out = 1_000 * SCALE;
+        assert_eq!(demand_multiplier(1_010 * SCALE, norm_out, 0.0, 3.0), 1.0);
+
+        let small = demand_multiplier(1_002 * SCALE, norm_out, 50.0, 3.0);
+        let large = demand_multiplier(1_010 * SCALE, norm_out, 50.0, 3.0);
+        assert!(1.0 < small && small < large, "small={small} large={large}");
+        assert_eq!(demand_multiplier(1_100 * SCALE, norm_out, 50.0, 3.0), 3.0, "cap applies");
+    }
+
+    // ── Unit: RFQ is winner-take-all ──────────────────────────────────────────
+
+    #[test]
+    fn rfq_best_quote_takes_whole_order() {
+        let amms: Vec<AmmState> = (0..3)
+            .map(|i| AmmState::new(100 * SCALE, 10_000 * SCALE, i as u8, &format!("AMM{i}")))
+            .collect();
+        // AMM1 charges the lowest fee
+        let fees = [50u32, 10, 30];
+        let compute = |amm_idx: usize, is_buy: bool, input: u64, rx: u64, ry: u64| -> u64 {
+            if is_buy { cpamm_output(input, ry, rx, fees[amm_idx]) }
+            else       { cpamm_output(input, rx, ry, fees[amm_idx]) }
+        };
+
+        let result = route_order_rfq(&amms, false, 2.0, None, compute);
+        assert_eq!(result.allocations[0], (0, 0));
+        assert_eq!(result.allocations[1].0, 2 * SCALE);
+        assert_eq!(result.allocations[2], (0, 0));
+
+        // Sell 2 X with a floor above any achievable average price → unfilled
+        let result = route_order_rfq(&amms, false, 2.0, Some(100.0), compute);
+        assert_eq!(result.total_output, 0);
+        assert_eq!(result.unfilled_input, 2 * SCALE);
+    }
+
+    // ── Unit: Naive router ────────────────────────────────────────────────────
+
+    #[test]
+    fn naive_router_modes() {
+        // AMM1 has 3× the TVL of AMM0
+        let amms = vec![
+            AmmState::new(100 * SCALE, 10_000 * SCALE, 0, "Small"),
+            AmmState::new(300 * SCALE, 30_000 * SCALE, 1, "Large"),
+        ];
+        let compute = |_amm_idx: usize, is_buy: bool, input: u64, rx: u64, ry: u64| -> u64 {
+            if is_buy { cpamm_output(input, ry, rx, 30) }
+            else       { cpamm_output(input, rx, ry, 30) }
+        };
+        let mut rng = ChaCha8Rng::seed_from_u64(7);
+
+        // Proportional: 25% / 75% split
+        let result = route_order_naive(
+            &amms, true, 100.0, NaiveRouting::ProportionalToTvl, &mut rng, compute,
+        );
+        let small = result.allocations[0].0 as f64 / SCALE_F;
+        let large = result.allocations[1].0 as f64 / SCALE_F;
+        assert!((small - 25.0).abs() < 1e-6, "small share = {small}");
+        assert!((large - 75.0).abs() < 1e-6, "large share = {large}");
+
+        // Random single: exactly one AMM receives the whole order
+        for _ in 0..20 {
+            let result = route_order_naive(
+                &amms, true, 100.0, NaiveRouting::RandomSingle, &mut rng, compute,
+            );
+            let filled: Vec<u64> = result.allocations.iter()
+                .map(|&(inp, _)| inp)
+                .filter(|&inp| inp > 0)
+                .collect();
+            assert_eq!(filled, vec![100 * SCALE]);
+        }
+    }
+
     // ── Unit: Capital allocation ──────────────────────────────────────────────
 
     #[test]
@@ -107,6 +718,33 @@ mod integration {
         assert!((sum - 1.0).abs() < 1e-10);
     }
 
+    // ── Unit: Per-epoch parameter dynamics ────────────────────────────────────
+
+    #[test]
+    fn param_dynamics_change_regime_within_ranges() {
+        use prop_amm_engine::market::ParamDynamics;
+
+        let mut rng = ChaCha8Rng::seed_from_u64(8);
+        let initial = MarketParams::sample(&mut rng);
+
+        let mut fixed = initial.clone();
+        fixed.evolve(ParamDynamics::Fixed, &mut rng);
+        assert_eq!((fixed.sigma, fixed.lambda), (initial.sigma, initial.lambda));
+
+        for dynamics in [ParamDynamics::Resample, ParamDynamics::RandomWalk { vol: 0.5 }] {
+            let mut p = initial.clone();
+            for _ in 0..50 {
+                p.evolve(dynamics, &mut rng);
+                assert!((0.0001..=0.0070).contains(&p.sigma), "{dynamics:?} sigma {}", p.sigma);
+                assert!((0.4..=1.2).contains(&p.lambda), "{dynamics:?} lambda {}", p.lambda);
+                assert!((12.0..=28.0).contains(&p.order_size_mean));
+            }
+            assert_ne!(p.sigma, initial.sigma, "{dynamics:?} never moved");
+            // Venue parameters are per-simulation, not per-regime
+            assert_eq!(p.norm_fee_bps, initial.norm_fee_bps);
+        }
+    }
+
     // ── Unit: Retail order generation is Poisson ──────────────────────────────
 
     #[test]
@@ -118,6 +756,9 @@ mod integration {
             order_size_mean: 20.0,
             norm_fee_bps: 30,
             norm_liquidity_mult: 1.0,
+            arrival_curve: ArrivalCurve::Flat,
+            clustering: SizeClustering::Iid,
+            extra_normalizers: vec![],
         };
 
         let n_steps = 10_000;
@@ -133,6 +774,102 @@ mod integration {
         );
     }
 
+    // ── Unit: TWAP parent orders slice across steps ───────────────────────────
+
+    #[test]
+    fn twap_parent_slices_over_steps() {
+        let mut rng = ChaCha8Rng::seed_from_u64(3);
+        let mut book = TwapBook::default();
+        let fresh = vec![RetailOrder {
+            is_buy: false, size_y: 20.0, kind: OrderKind::Market, limit_price: None,
+        }];
+
+        // prob = 1 → promoted to a parent of 20 × 5 = 100 Y over 4 slices
+        let mut emitted = book.process(fresh, 1.0, 4, 5.0, &mut rng);
+        for _ in 0..5 {
+            emitted.extend(book.process(vec![], 1.0, 4, 5.0, &mut rng));
+        }
+
+        assert_eq!(emitted.len(), 4, "parent should emit exactly 4 slices");
+        assert!(emitted.iter().all(|o| !o.is_buy && o.kind == OrderKind::TwapSlice));
+        let total: f64 = emitted.iter().map(|o| o.size_y).sum();
+        assert!((total - 100.0).abs() < 1e-9, "slices sum to {total}");
+        assert!(book.parents.is_empty());
+    }
+
+    // ── Unit: Clustering models create regimes without moving the mean ────────
+
+    #[test]
+    fn clustering_models_preserve_mean_and_add_autocorrelation() {
+        let base = MarketParams {
+            sigma: 0.003,
+            lambda: 0.8,
+            order_size_mean: 20.0,
+            norm_fee_bps: 30,
+            norm_liquidity_mult: 1.0,
+            arrival_curve: ArrivalCurve::Flat,
+            clustering: SizeClustering::Iid,
+            extra_normalizers: vec![],
+        };
+        let n_steps = 20_000;
+
+        // Hawkes: mean arrivals ≈ λ, but per-step counts positively autocorrelated
+        let params = MarketParams {
+            clustering: SizeClustering::Hawkes { branching: 0.6, decay: 0.9 },
+            ..base.clone()
+        };
+        let mut rng = ChaCha8Rng::seed_from_u64(5);
+        let mut state = FlowState::default();
+        let counts: Vec<f64> = (0..n_steps)
+            .map(|_| generate_retail_orders_clustered(&params, 0.0, &mut state, &mut rng).len() as f64)
+            .collect();
+        let mean = counts.iter().sum::<f64>() / n_steps as f64;
+        assert!((mean - 0.8).abs() < 0.08, "Hawkes mean orders/step = {mean:.3}");
+        assert!(lag1_autocorr(&counts) > 0.05, "Hawkes counts should cluster");
+
+        // AR(1): per-step mean size is autocorrelated
+        let params = MarketParams {
+            lambda: 5.0,
+            clustering: SizeClustering::Ar1 { phi: 0.95, regime_vol: 0.6 },
+            ..base
+        };
+        let mut state = FlowState::default();
+        let sizes: Vec<f64> = (0..n_steps)
+            .map(|_| {
+                let orders = generate_retail_orders_clustered(&params, 0.0, &mut state, &mut rng);
+                orders.iter().map(|o| o.size_y.ln()).sum::<f64>() / orders.len().max(1) as f64
+            })
+            .collect();
+        assert!(lag1_autocorr(&sizes) > 0.2, "AR(1) log sizes should be autocorrelated");
+    }
+
+    fn lag1_autocorr(xs: &[f64]) -> f64 {
+        let n = xs.len() as f64;
+        let mean = xs.iter().sum::<f64>() / n;
+        let var = xs.iter().map(|x| (x - mean).powi(2)).sum::<f64>();
+        let cov = xs.windows(2).map(|w| (w[0] - mean) * (w[1] - mean)).sum::<f64>();
+        cov / var
+    }
+
+    // ── Unit: Intraday arrival curves preserve the mean rate ──────────────────
+
+    #[test]
+    fn arrival_curves_are_mean_preserving() {
+        let curves = [
+            ArrivalCurve::Flat,
+            ArrivalCurve::Sinusoidal { amplitude: 0.8 },
+            ArrivalCurve::UShaped { depth: 0.6 },
+        ];
+        for curve in curves {
+            let n = 1_000;
+            let mean: f64 = (0..n).map(|k| curve.multiplier(k as f64 / n as f64)).sum::<f64>() / n as f64;
+            assert!((mean - 1.0).abs() < 1e-9, "{curve:?} mean multiplier = {mean}");
+        }
+
+        let u = ArrivalCurve::UShaped { depth: 0.6 };
+        assert!(u.multiplier(0.0) > u.multiplier(0.5), "U-shape should be busiest at the open");
+    }
+
     // ── Integration: full epoch + rebalance ───────────────────────────────────
 
     #[test]
@@ -149,7 +886,7 @@ mod integration {
         // Total Y capital before rebalance
         let total_y_before: u64 = amms.iter().map(|a| a.reserve_y * 2).sum();
 
-        rebalance_capital(&mut amms, &config, 0);
+        rebalance_capital(&mut amms, &config, 0).unwrap();
 
         let total_y_after: u64 = amms.iter().map(|a| a.reserve_y * 2).sum();
 
@@ -173,4 +910,1539 @@ mod integration {
             amms[0].capital_weight, amms[3].capital_weight
         );
     }
+
+    // ── Unit: Mark-to-market accounting ───────────────────────────────────────
+
+    #[test]
+    fn value_change_splits_into_edge_and_inventory_pnl() {
+        use prop_amm_engine::market::apply_cpamm_trade;
+
+        let mut amm = AmmState::new(100 * SCALE, 10_000 * SCALE, 0, "A");
+        let value = |a: &AmmState, p: f64| a.reserve_x as f64 / SCALE_F * p + a.reserve_y as f64 / SCALE_F;
+        let v0 = value(&amm, 100.0);
+
+        // Price rises, then an arb buys 2.5 X for 300 Y
+        amm.mark_to_market(100.0, 110.0);
+        let (y_in, x_out) = (300 * SCALE, 2 * SCALE + SCALE / 2);
+        amm.accrue_edge(x_out, y_in, true, 110.0);
+        apply_cpamm_trade(&mut amm.reserve_x, &mut amm.reserve_y, true, y_in, x_out);
+        amm.mark_to_market(110.0, 115.0);
+
+        let v1 = value(&amm, 115.0);
+        assert!((v1 - v0 - (amm.epoch_edge + amm.epoch_inventory_pnl)).abs() < 1e-6);
+
+        // Holding 100 X through +15 would have made +1500; the pool sold X into the rally
+        assert!((amm.epoch_hold_pnl - 1500.0).abs() < 1e-9);
+        let summary = &prop_amm_engine::capital::rebalance_capital(
+            std::slice::from_mut(&mut amm), &SimConfig::default(), 0,
+        ).unwrap()[0];
+        assert!(summary.impermanent_loss > 0.0, "IL = {}", summary.impermanent_loss);
+        assert_eq!(amm.epoch_inventory_pnl, 0.0);
+        assert_eq!(amm.epoch_hold_pnl, 0.0);
+    }
+
+    #[test]
+    fn fills_split_edge_by_flow_and_accrue_implied_fee() {
+        let mut amm = AmmState::new(100 * SCALE, 10_000 * SCALE, 0, "A");
+        let fee_out = |input: u64| cpamm_output(input, amm.reserve_y, amm.reserve_x, 30);
+        let (retail_in, retail_out) = (50 * SCALE, fee_out(50 * SCALE));
+
+        amm.record_fill(true, retail_in, retail_out, 100.0, false);
+        // A 30 bps CPAMM quote implies ≈ 30 bps of the 50 Y notional as fee
+        assert!((amm.epoch_fee_revenue - 0.15).abs() < 2e-3, "fee = {}", amm.epoch_fee_revenue);
+        assert!(amm.epoch_retail_edge > 0.0);
+        assert_eq!(amm.epoch_arb_edge, 0.0);
+
+        // Informed fill paying 110 Y for 1 X worth 100 loses edge in the arb bucket
+        amm.record_fill(false, SCALE, 110 * SCALE, 100.0, true);
+        assert!((amm.epoch_arb_edge + 10.0).abs() < 1e-9);
+        assert!((amm.epoch_retail_edge + amm.epoch_arb_edge - amm.epoch_edge).abs() < 1e-12);
+
+        // Volume by trader side at fair, fills by counterparty
+        assert_eq!((amm.epoch_buy_volume, amm.epoch_sell_volume), (50.0, 100.0));
+        assert_eq!((amm.epoch_retail_trades, amm.epoch_arb_trades), (1, 1));
+        assert_eq!(amm.epoch_largest_trade, 100.0);
+    }
+
+    #[test]
+    fn replay_reproduces_noisy_routing_and_capital_flows() {
+        let runners = [
+            StrategyRunner::from_strategy(FixedFee::new("a", 25)),
+            StrategyRunner::from_strategy(FixedFee::new("b", 60)),
+        ];
+        let config = SimConfig {
+            total_steps: 600,
+            epoch_len: 150,
+            router_noise: 0.5,
+            capital_flows: CapitalFlows::Random { drift: 0.0, vol: 0.1 },
+            record_trace: true,
+            ..SimConfig::default()
+        };
+        let recorded = run_simulation(&runners, &config, 17).unwrap();
+        let trace = recorded.trace.as_ref().expect("trace recorded");
+        let replayed = replay_simulation(&runners, &config, trace).unwrap();
+        for (r, p) in recorded.strategies.iter().zip(&replayed.strategies) {
+            assert_eq!(r.final_edge, p.final_edge, "{}", r.name);
+            assert_eq!(r.final_capital_weight, p.final_capital_weight, "{}", r.name);
+        }
+    }
+
+    #[test]
+    fn fee_yield_is_finite_even_without_steps() {
+        let runners = [StrategyRunner::from_strategy(FixedFee::new("fee", 30))];
+        let config = SimConfig { total_steps: 400, epoch_len: 200, ..SimConfig::default() };
+        let result = run_simulation(&runners, &config, 3).unwrap();
+        let fee_yield = result.strategies[0].fee_yield;
+        // A 30 bps pool turns over a fraction of its value in 400 steps
+        assert!(fee_yield > 0.0 && fee_yield < 1.0, "fee yield {fee_yield}");
+
+        let config = SimConfig { total_steps: 0, ..config };
+        assert_eq!(run_simulation(&runners, &config, 3).unwrap().strategies[0].fee_yield, 0.0);
+    }
+
+    #[test]
+    fn dual_token_accounting_values_legs_per_numeraire() {
+        let mut amm = AmmState::new(100 * SCALE, 10_000 * SCALE, 0, "A");
+        // AMM sells 1 X for 110 Y at fair 100 (+10 Y), then buys 1 X for 190 Y at fair 200 (+10 Y)
+        amm.accrue_edge(SCALE, 110 * SCALE, true, 100.0);
+        amm.accrue_edge(SCALE, 190 * SCALE, false, 200.0);
+
+        assert!((amm.edge_in(Numeraire::Y) - 20.0).abs() < 1e-9);
+        assert!((amm.edge_in(Numeraire::X) - (10.0 / 100.0 + 10.0 / 200.0)).abs() < 1e-12);
+        // Legs net to 0 X and -80 Y, whatever the marking price
+        assert!((amm.marked_legs(Numeraire::Y, 150.0) + 80.0).abs() < 1e-9);
+        assert!((amm.marked_legs(Numeraire::X, 160.0) + 0.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn edge_path_metrics_follow_the_numeraire() {
+        let runners = [
+            StrategyRunner::from_strategy(FixedFee::new("a", 10)),
+            StrategyRunner::from_strategy(FixedFee::new("b", 80)),
+        ];
+        let run = |numeraire| {
+            let config = SimConfig { total_steps: 2_000, epoch_len: 100, numeraire, ..SimConfig::default() };
+            let mut agg = Aggregator::new();
+            for seed in 0..4 {
+                agg.push(&run_simulation(&runners, &config, seed).unwrap());
+            }
+            agg.finish()
+        };
+        let (y, x) = (run(Numeraire::Y), run(Numeraire::X));
+        let price = SimConfig::default().initial_fair_price();
+        for (y, x) in y.iter().zip(&x) {
+            assert!(y.mean_max_drawdown > 0.0, "{} never drew down", y.name);
+            // X amounts are Y amounts over a price that wanders around its start
+            let scale = y.mean_max_drawdown / x.mean_max_drawdown;
+            assert!((0.5..2.0).contains(&(scale / price)), "{}: drawdown scale {scale}", y.name);
+            // Sortino is unitless, so it moves with the path but not by the price
+            assert!(y.sortino != 0.0 && (0.5..2.0).contains(&(x.sortino / y.sortino)), "{}: {} vs {}", y.name, x.sortino, y.sortino);
+        }
+    }
+
+    #[test]
+    fn storage_latency_delays_visibility_by_k_trades() {
+        let mut amm = AmmState::new(100 * SCALE, 10_000 * SCALE, 0, "A");
+        amm.set_storage_latency(2);
+        for trade in 1..=4u8 {
+            amm.storage[0] = trade; // after_swap writes
+            amm.publish_storage();
+            // Visible version is the one left k = 2 trades ago (initial zeros before that)
+            assert_eq!(amm.quote_storage()[0], trade.saturating_sub(2));
+        }
+
+        amm.set_storage_latency(0);
+        assert_eq!(amm.quote_storage()[0], 4, "latency 0 sees live storage");
+    }
+
+    #[test]
+    fn taker_quality_measures_spread_and_improvement_in_bps() {
+        use prop_amm_engine::sim::taker_quality;
+        // Buy 1 X for 101 Y at fair 100: 100 bps spread; normalizer would give 0.99 X
+        let (spread, improvement) = taker_quality(true, 101 * SCALE, SCALE, 100.0, SCALE / 100 * 99);
+        assert!((spread - 100.0).abs() < 1e-6, "spread = {spread}");
+        assert!((improvement - 10_000.0 / 99.0).abs() < 1e-6, "improvement = {improvement}");
+
+        // Sell 1 X for 99.5 Y at fair 100: 50 bps spread
+        let (spread, _) = taker_quality(false, SCALE, 99_500_000_000, 100.0, 0);
+        assert!((spread - 50.0).abs() < 1e-6, "spread = {spread}");
+    }
+
+    #[test]
+    fn inventory_skew_limit_blocks_only_the_depleting_side() {
+        // 10 X at fair 100 vs 9000 Y: X is 10% of pool value
+        let amm = AmmState::new(10 * SCALE, 9_000 * SCALE, 0, "A");
+        assert!((amm.inventory_share_x(100.0) - 0.1).abs() < 1e-12);
+        assert!(amm.skew_blocks(true, 100.0, 0.2), "buying X from an X-light pool is barred");
+        assert!(!amm.skew_blocks(false, 100.0, 0.2), "selling X into it is still allowed");
+        assert!(!amm.skew_blocks(true, 100.0, 0.0), "0.0 disables the limit");
+    }
+
+    // ── Unit: Streaming aggregation ───────────────────────────────────────────
+
+    #[test]
+    fn running_stat_matches_two_pass() {
+        use prop_amm_engine::sim::RunningStat;
+
+        let xs = [1e6 + 4.0, 1e6 + 7.0, 1e6 + 13.0, 1e6 + 16.0];
+        let mut stat = RunningStat::default();
+        xs.iter().for_each(|&x| stat.push(x));
+
+        let mean = xs.iter().sum::<f64>() / 4.0;
+        let var = xs.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / 4.0;
+        assert!((stat.mean() - mean).abs() < 1e-9);
+        assert!((stat.variance() - var).abs() < 1e-9, "{} vs {var}", stat.variance());
+    }
+
+    #[test]
+    fn seed_files_list_exact_seeds_and_batches_run_them() {
+        use prop_amm_engine::sim::{parse_seeds, run_batch, BatchOptions};
+
+        assert_eq!(parse_seeds("# finals\n9, 4 17\n100..103 # held out\n").unwrap(), vec![9, 4, 17, 100, 101, 102]);
+        assert!(parse_seeds("1 2\n0..3").unwrap_err().contains("listed twice"));
+        assert!(parse_seeds("5..5").is_err());
+        assert!(parse_seeds("seven").is_err());
+        assert!(parse_seeds("# nothing\n").is_err());
+
+        let mut config = SimConfig { total_steps: 200, epoch_len: 100, ..Default::default() };
+        config.opponents = vec!["fixed_30".to_string()];
+        let seeds = [42, 7];
+        let opts = BatchOptions { keep_results: true, seeds: Some(&seeds), ..Default::default() };
+        let batch = run_batch(&[], &config, 100, 0, &opts).unwrap();
+        assert_eq!((batch.completed, batch.partial), (2, false));
+        assert_eq!(batch.seeds, vec![42, 7]);
+        assert_eq!(batch.results.iter().map(|r| r.seed).collect::<Vec<_>>(), vec![42, 7]);
+    }
+
+    /// Serve a strategy that refuses every trade over the worker protocol, one thread per
+    /// connection. Returns its address and the number of connections still open.
+    fn refusing_tcp_strategy() -> (std::net::SocketAddr, Arc<AtomicU64>) {
+        use std::io::{Read, Write};
+
+        fn frame(w: &mut impl Write, body: &[u8]) -> std::io::Result<()> {
+            w.write_all(&[&(body.len() as u32).to_le_bytes()[..], body].concat())
+        }
+        fn serve(mut stream: std::net::TcpStream) -> std::io::Result<()> {
+            stream.set_nodelay(true)?;
+            for handshake in [&b"refuser"[..], b"", b""] {
+                frame(&mut stream, handshake)?;
+            }
+            loop {
+                let mut len = [0u8; 4];
+                stream.read_exact(&mut len)?;
+                let mut request = vec![0u8; u32::from_le_bytes(len) as usize];
+                stream.read_exact(&mut request)?;
+                let mut reply = vec![0u8];
+                match request[0] {
+                    0 => reply.extend([0; 8]),
+                    1 => reply.extend_from_slice(&request[1..1 + STORAGE_SIZE]),
+                    2 => reply.resize(1 + 8 * u32::from_le_bytes(request[1..5].try_into().unwrap()) as usize, 0),
+                    _ => {}
+                }
+                frame(&mut stream, &reply)?;
+            }
+        }
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let open = Arc::new(AtomicU64::new(0));
+        let counter = Arc::clone(&open);
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let counter = Arc::clone(&counter);
+                counter.fetch_add(1, Ordering::SeqCst);
+                std::thread::spawn(move || {
+                    let _ = serve(stream);
+                    counter.fetch_sub(1, Ordering::SeqCst);
+                });
+            }
+        });
+        (addr, open)
+    }
+
+    #[test]
+    fn batches_release_their_runners_when_done() {
+        use prop_amm_engine::sim::{run_batch, BatchOptions};
+
+        let (addr, open) = refusing_tcp_strategy();
+        let dir = std::env::temp_dir().join(format!("prop-amm-release-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let manifest = dir.join("refuser.remote");
+        std::fs::write(&manifest, format!("tcp {addr}\n")).unwrap();
+
+        let mut config = SimConfig { total_steps: 200, epoch_len: 100, ..Default::default() };
+        config.opponents = vec!["fixed_30".to_string()];
+        let batch = run_batch(std::slice::from_ref(&manifest), &config, 4, 0, &BatchOptions::default()).unwrap();
+        assert_eq!(batch.completed, 4);
+        assert_eq!(batch.aggregated[0].name, "refuser");
+
+        // Every cached connection is dropped once the batch returns
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        while open.load(Ordering::SeqCst) > 0 && std::time::Instant::now() < deadline {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert_eq!(open.load(Ordering::SeqCst), 0, "runners outlived their batch");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn unreachable_strategies_fail_the_batch_instead_of_panicking() {
+        use prop_amm_engine::sim::{run_batch, run_leave_one_out, run_round_robin, BatchOptions};
+
+        // Nothing listens on a port once its listener is dropped
+        let addr = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let dir = std::env::temp_dir().join(format!("prop-amm-unreachable-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let manifests: Vec<std::path::PathBuf> = ["a", "b"].iter().map(|n| dir.join(format!("{n}.remote"))).collect();
+        for m in &manifests {
+            std::fs::write(m, format!("tcp {addr}\n")).unwrap();
+        }
+
+        let config = SimConfig { total_steps: 100, epoch_len: 50, ..Default::default() };
+        let errors = [
+            run_batch(&manifests, &config, 3, 0, &BatchOptions::default()).map(|_| ()),
+            run_round_robin(&manifests, &config, 1, 0, 2).map(|_| ()),
+            run_leave_one_out(&manifests, &config, 1, 0).map(|_| ()),
+        ];
+        for err in errors {
+            let err = err.unwrap_err();
+            assert!(err.contains("failed to load"), "{err}");
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn vanished_allocator_plugin_fails_the_run_instead_of_panicking() {
+        use prop_amm_engine::capital::AllocatorKind;
+        use prop_amm_engine::sim::{run_batch, BatchOptions};
+
+        // As if the plug-in was deleted after `SimConfig::validate` passed
+        let allocator = AllocatorKind::Plugin { path: std::env::temp_dir().join("vanished-allocator.so") };
+        let config = SimConfig { total_steps: 100, epoch_len: 50, allocator, ..Default::default() };
+        let runners = [StrategyRunner::from_strategy(FixedFee::new("a", 30))];
+        let err = run_simulation(&runners, &config, 1).unwrap_err();
+        assert!(err.contains("vanished-allocator.so"), "{err}");
+
+        let (addr, _open) = refusing_tcp_strategy();
+        let dir = std::env::temp_dir().join(format!("prop-amm-vanished-allocator-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let manifest = dir.join("refuser.remote");
+        std::fs::write(&manifest, format!("tcp {addr}\n")).unwrap();
+        let err = run_batch(std::slice::from_ref(&manifest), &config, 3, 0, &BatchOptions::default()).unwrap_err();
+        assert!(err.starts_with("allocator plugin"), "{err}");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn self_play_runs_independent_copies_within_the_strategy_limit() {
+        use prop_amm_engine::sim::{run_batch, BatchOptions};
+        use prop_amm_engine::types::MAX_STRATEGIES;
+
+        let (addr, _open) = refusing_tcp_strategy();
+        let dir = std::env::temp_dir().join(format!("prop-amm-self-play-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let manifest = dir.join("refuser.remote");
+        std::fs::write(&manifest, format!("tcp {addr}\n")).unwrap();
+
+        let config = SimConfig {
+            total_steps: 200,
+            epoch_len: 100,
+            self_play_copies: 3,
+            opponents: vec!["fixed_30".to_string()],
+            ..Default::default()
+        };
+        let opts = BatchOptions { keep_results: true, ..BatchOptions::default() };
+        let batch = run_batch(std::slice::from_ref(&manifest), &config, 2, 0, &opts).unwrap();
+        let names: Vec<&str> = batch.results[0].strategies.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["refuser #1", "refuser #2", "refuser #3", "ref_fixed_30"]);
+
+        // Copies count against the competitor slots like any other strategy
+        let config = SimConfig { self_play_copies: MAX_STRATEGIES, ..config };
+        let err = run_batch(std::slice::from_ref(&manifest), &config, 1, 0, &opts).unwrap_err();
+        assert!(err.contains(&format!("{} strategies", MAX_STRATEGIES + 1)), "{err}");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn watch_reports_settled_saves_once() {
+        use prop_amm_engine::watch::{file_stamp, SaveDetector};
+        use std::time::{Duration, SystemTime};
+
+        let at = |secs: u64, len: u64| Some((SystemTime::UNIX_EPOCH + Duration::from_secs(secs), len));
+        let mut saves = SaveDetector::default();
+        assert!(!saves.observe(None), "a missing file is not a save");
+        assert!(!saves.observe(at(1, 10)), "a new stamp must hold still for a poll");
+        assert!(saves.observe(at(1, 10)));
+        assert!(!saves.observe(at(1, 10)), "one save is reported once");
+        // Still being written: the size moves between polls
+        assert!(!saves.observe(at(2, 4)));
+        assert!(!saves.observe(at(2, 12)));
+        assert!(saves.observe(at(2, 12)));
+        // Mid-save the file can vanish; coming back unchanged is not a new save
+        assert!(!saves.observe(None));
+        assert!(!saves.observe(at(2, 12)));
+
+        let path = std::env::temp_dir().join(format!("prop-amm-watch-{}.rs", std::process::id()));
+        std::fs::write(&path, "fn main() {}").unwrap();
+        assert_eq!(file_stamp(&path).map(|(_, len)| len), Some(12));
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(file_stamp(&path), None);
+    }
+
+    #[test]
+    fn watch_scores_rank_the_watched_strategy_and_diff_runs() {
+        use prop_amm_engine::sim::AggregatedResult;
+        use prop_amm_engine::watch::WatchScore;
+
+        let result = |name: &str, mean_edge: f64| AggregatedResult { name: name.into(), mean_edge, mean_flow_share: 0.25, ..Default::default() };
+        assert_eq!(WatchScore::of(&[]), None);
+        let before = WatchScore::of(&[result("mine", 1.0), result("a", 3.0), result("b", 2.0)]).unwrap();
+        assert_eq!((before.rank, before.field), (3, 3));
+        assert_eq!(before.values[0], 1.0);
+        assert_eq!(before.values[2], 25.0, "flow share is shown in percent");
+        let now = WatchScore::of(&[result("mine", 2.5), result("a", 3.0), result("b", 2.0)]).unwrap();
+        assert_eq!(now.rank, 2);
+
+        let first = before.render(None);
+        assert!(!first.contains("Before"));
+        assert!(first.lines().any(|l| l.starts_with("Rank") && l.ends_with("3/3")), "{first}");
+        let diff = now.render(Some(&before));
+        let line = |label: &str| diff.lines().find(|l| l.starts_with(label)).unwrap().split_whitespace().map(str::to_string).collect::<Vec<_>>();
+        assert_eq!(line("Mean edge")[2..], ["2.500", "1.000", "+1.500"]);
+        assert_eq!(line("Rank")[1..], ["2/3", "3/3", "+1"], "moving up is a positive change");
+    }
+
+    #[test]
+    fn holdout_seeds_are_keyed_sealed_and_disjoint() {
+        use prop_amm_engine::sim::{holdout_commitment, holdout_seeds};
+
+        let public: Vec<u64> = (0..100).collect();
+        let sealed = holdout_seeds("finals-key", 50, &public);
+        assert_eq!(sealed, holdout_seeds("finals-key", 50, &public), "same key, same seeds");
+        assert_eq!(sealed.len(), 50);
+        assert!(sealed.iter().all(|s| !public.contains(s)));
+        let mut unique = sealed.clone();
+        unique.sort_unstable();
+        unique.dedup();
+        assert_eq!(unique.len(), 50);
+        assert!(holdout_seeds("other-key", 50, &public).iter().all(|s| !sealed.contains(s)));
+
+        // Excluding a derived seed skips it without shifting the rest
+        let shifted = holdout_seeds("finals-key", 3, &sealed[..1]);
+        assert_eq!(shifted, sealed[1..4]);
+
+        let commitment = holdout_commitment("finals-key");
+        assert_eq!(commitment.len(), 64);
+        assert_ne!(commitment, holdout_commitment("other-key"));
+    }
+
+    #[test]
+    fn pairwise_matrix_tallies_head_to_head() {
+        use prop_amm_engine::sim::PairwiseMatrix;
+
+        let mut m = PairwiseMatrix::new(vec!["A".into(), "B".into(), "C".into()]);
+        m.record(&[0, 1], &[5.0, 3.0]);
+        m.record(&[0, 1], &[2.0, 2.0]);
+        m.record(&[1, 2], &[1.0, 4.0]);
+
+        assert_eq!(m.win_rate(0, 1), 0.75);
+        assert_eq!(m.win_rate(1, 0), 0.25);
+        assert_eq!(m.win_rate(2, 1), 1.0);
+        assert!(m.win_rate(0, 2).is_nan(), "A and C never met");
+    }
+
+    #[test]
+    fn round_robin_tallies_every_runner_of_each_subset() {
+        use prop_amm_engine::sim::run_round_robin;
+
+        let (addr, _open) = refusing_tcp_strategy();
+        let dir = std::env::temp_dir().join(format!("prop-amm-round-robin-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let manifests: Vec<std::path::PathBuf> = ["a", "b", "c"].iter().map(|n| dir.join(format!("{n}.remote"))).collect();
+        for m in &manifests {
+            std::fs::write(m, format!("tcp {addr}\n")).unwrap();
+        }
+
+        let config = SimConfig {
+            total_steps: 200,
+            epoch_len: 100,
+            self_play_copies: 2,
+            opponents: vec!["fixed_30".to_string()],
+            ..Default::default()
+        };
+        let m = run_round_robin(&manifests, &config, 1, 0, 2).unwrap().pairwise;
+        assert_eq!(m.names.len(), 7);
+        assert_eq!((m.names[1].as_str(), m.names[6].as_str()), ("refuser #2", "ref_fixed_30"));
+        // Copies of one artifact meet in its 2 pairs; copies of different ones in their 1;
+        // the opponent plays every pair
+        assert_eq!((m.games[0][1], m.games[0][2], m.games[3][5]), (2, 1, 1));
+        assert!((0..6).all(|i| m.games[i][6] == 2 && m.games[6][i] == 2), "{:?}", m.games);
+        assert_eq!(m.win_rate(6, 0) + m.win_rate(0, 6), 1.0);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn leave_one_out_names_each_artifact_once() {
+        use prop_amm_engine::sim::run_leave_one_out;
+
+        let (addr, _open) = refusing_tcp_strategy();
+        let dir = std::env::temp_dir().join(format!("prop-amm-leave-one-out-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let manifests: Vec<std::path::PathBuf> = ["a", "b"].iter().map(|n| dir.join(format!("{n}.remote"))).collect();
+        for m in &manifests {
+            std::fs::write(m, format!("tcp {addr}\n")).unwrap();
+        }
+
+        let config = SimConfig {
+            total_steps: 200,
+            epoch_len: 100,
+            self_play_copies: 2,
+            opponents: vec!["fixed_30".to_string()],
+            ..Default::default()
+        };
+        let contributions = run_leave_one_out(&manifests, &config, 1, 0).unwrap();
+        assert_eq!(contributions.iter().map(|c| c.name.as_str()).collect::<Vec<_>>(), ["refuser", "refuser"]);
+        assert!(contributions.iter().all(|c| c.pool_edge_delta.is_finite() && c.others_flow_delta.is_finite()));
+        assert!(run_leave_one_out(&manifests, &config, 0, 0).unwrap().is_empty());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    // ── Unit: Trace serialization ─────────────────────────────────────────────
+
+    #[test]
+    fn trace_round_trips_through_binary() {
+        use prop_amm_engine::trace::{SimTrace, TraceFill, TraceOrder, TraceStep};
+
+        let mut rng = ChaCha8Rng::seed_from_u64(5);
+        let mut params = MarketParams::sample(&mut rng);
+        params.clustering = SizeClustering::Hawkes { branching: 0.5, decay: 0.8 };
+        params.extra_normalizers = vec![prop_amm_engine::market::NormalizerParams { fee_bps: 45, liquidity_mult: 0.7 }];
+        let trace = SimTrace {
+            seed: 42,
+            params,
+            shocks: vec![ShockEvent { step: 1, jump: -0.05 }],
+            steps: vec![
+                TraceStep { fair_price: 100.0, arb_position: 0, orders: vec![] },
+                TraceStep {
+                    fair_price: 95.0,
+                    arb_position: 1,
+                    orders: vec![
+                        TraceOrder {
+                            order: RetailOrder { is_buy: true, size_y: 20.0, kind: OrderKind::Market, limit_price: None },
+                            fills: vec![
+                                TraceFill { amm: 0, input: 12 * SCALE, output: SCALE / 10 },
+                                TraceFill { amm: 2, input: 8 * SCALE, output: SCALE / 12 },
+                            ],
+                        },
+                        TraceOrder {
+                            order: RetailOrder { is_buy: false, size_y: 5.0, kind: OrderKind::Rfq, limit_price: Some(94.0) },
+                            fills: vec![],
+                        },
+                    ],
+                },
+            ],
+        };
+
+        let mut buf = Vec::new();
+        trace.write_to(&mut buf).unwrap();
+        let back = SimTrace::read_from(&mut buf.as_slice()).unwrap();
+
+        assert_eq!(back.seed, 42);
+        assert_eq!(back.params.norm_fee_bps, trace.params.norm_fee_bps);
+        assert_eq!(back.params.clustering, trace.params.clustering);
+        assert_eq!(back.params.normalizers(), trace.params.normalizers());
+        assert_eq!(back.shock_at(1), Some(-0.05));
+        assert_eq!(back.steps.len(), 2);
+        assert_eq!(back.steps[1].arb_position, 1);
+        assert_eq!(back.steps[1].orders[0].fills, trace.steps[1].orders[0].fills);
+        assert_eq!(back.steps[1].orders[1].order.kind, OrderKind::Rfq);
+        assert_eq!(back.steps[1].orders[1].order.limit_price, Some(94.0));
+        assert_eq!(back.steps[1].orders[0].order.limit_price, None);
+
+        // Truncated input is rejected rather than silently zero-filled
+        assert!(SimTrace::read_from(&mut &buf[..buf.len() - 3]).is_err());
+    }
+
+    // ── Unit: Wire payloads ───────────────────────────────────────────────────
+
+    fn patterned_storage() -> [u8; STORAGE_SIZE] {
+        std::array::from_fn(|i| (i * 7 % 253) as u8)
+    }
+
+    #[test]
+    fn after_swap_payload_round_trips_in_every_layout() {
+        use prop_amm_engine::wire::{self, after_swap};
+
+        let storage = patterned_storage();
+        let payload = AfterSwapPayload {
+            tag: wire::TAG_AFTER_SWAP,
+            side: 1,
+            input_amount: 3 * SCALE,
+            output_amount: 290 * SCALE,
+            reserve_x: 103 * SCALE,
+            reserve_y: 9_710 * SCALE,
+            sim_step: 1_234,
+            epoch_step: 234,
+            epoch_number: 1,
+            n_strategies: 12,
+            strategy_index: 3,
+            flow_captured: 0.25,
+            capital_weight: 0.125,
+            n_competing: 11,
+            competing_spot_prices: std::array::from_fn(|i| if i < 11 { 90.0 + i as f32 } else { f32::NAN }),
+            competing_reserve_y: std::array::from_fn(|i| if i < 11 { 1e12 * (i + 1) as f32 } else { f32::NAN }),
+            storage: [0; STORAGE_SIZE],
+        };
+        for version in 1..=wire::ABI_VERSION {
+            let mut buf = vec![0u8; after_swap::len(version)];
+            assert_eq!(wire::encode_after_swap(&payload, &storage, version, &mut buf), buf.len());
+            let (back, read_as) = wire::decode_after_swap(&buf).unwrap();
+            assert_eq!(read_as, version);
+            assert_eq!((back.side, { back.input_amount }, { back.sim_step }, { back.epoch_step }), (1, 3 * SCALE, 1_234, 234));
+            assert_eq!({ back.flow_captured }, 0.25);
+            assert_eq!({ back.storage }, storage);
+            let (prices, reserves) = (back.competing_spot_prices, back.competing_reserve_y);
+            let slots = after_swap::slots(version).min(11);
+            assert_eq!(prices[..slots], { payload.competing_spot_prices }[..slots]);
+            assert!(prices[11..].iter().all(|p| p.is_nan()));
+            assert_eq!(back.n_competing as usize, slots);
+            assert_eq!(reserves[0] == 1e12, version >= 3);
+        }
+        assert_eq!(after_swap::len(3), 1213);
+        assert_eq!(after_swap::len(4), 1214);
+        assert!(wire::decode_after_swap(&[0u8; 1200]).is_none());
+    }
+
+    #[test]
+    fn fixed_header_payloads_round_trip_and_carry_their_version() {
+        use prop_amm_engine::types::{EpochBoundaryPayload, ShockNewsPayload, StepDigestPayload};
+        use prop_amm_engine::wire::{self, epoch_boundary, shock_news, step_digest, swap};
+
+        let storage = patterned_storage();
+        for version in [3, wire::ABI_VERSION] {
+            let mut buf = vec![0u8; swap::len(version)];
+            wire::encode_compute_swap(true, 7 * SCALE, 100 * SCALE, 10_000 * SCALE, &storage, version, &mut buf);
+            let (p, read_as) = wire::decode_compute_swap(&buf).unwrap();
+            assert_eq!((p.tag, { p.input_amount }, { p.reserve_y }, read_as), (wire::TAG_SWAP_BUY, 7 * SCALE, 10_000 * SCALE, version));
+            assert_eq!({ p.storage }, storage);
+
+            let epoch = EpochBoundaryPayload {
+                tag: wire::TAG_EPOCH_BOUNDARY,
+                epoch_number: 4,
+                new_reserve_x: 80 * SCALE,
+                new_reserve_y: 8_000 * SCALE,
+                epoch_edge: -1.5,
+                cumulative_edge: 12.25,
+                capital_weight: 0.4,
+                storage: [0; STORAGE_SIZE],
+            };
+            let mut buf = vec![0u8; epoch_boundary::len(version)];
+            wire::encode_epoch_boundary(&epoch, &storage, version, &mut buf);
+            let (p, read_as) = wire::decode_epoch_boundary(&buf).unwrap();
+            assert_eq!(({ p.epoch_number }, { p.epoch_edge }, { p.cumulative_edge }, read_as), (4, -1.5, 12.25, version));
+
+            let news = ShockNewsPayload { tag: wire::TAG_SHOCK_NEWS, shock_step: 99, magnitude: 0.05, storage: [0; STORAGE_SIZE] };
+            let mut buf = vec![0u8; shock_news::len(version)];
+            wire::encode_shock_news(&news, &storage, version, &mut buf);
+            let (p, _) = wire::decode_shock_news(&buf).unwrap();
+            assert_eq!(({ p.shock_step }, { p.magnitude }), (99, 0.05));
+
+            let digest = StepDigestPayload {
+                tag: wire::TAG_STEP_DIGEST,
+                sim_step: 7,
+                retail_notional: 42.0,
+                net_retail_flow: -3.0,
+                arb_count: 2,
+                storage: [0; STORAGE_SIZE],
+            };
+            let mut buf = vec![0u8; step_digest::len(version)];
+            wire::encode_step_digest(&digest, &storage, version, &mut buf);
+            let (p, read_as) = wire::decode_step_digest(&buf).unwrap();
+            assert_eq!(({ p.retail_notional }, { p.net_retail_flow }, { p.arb_count }, read_as), (42.0, -3.0, 2, version));
+            assert_eq!(buf.last() == Some(&(version as u8)), version >= wire::VERSION_BYTE_SINCE);
+        }
+    }
+
+    // ── Integration: in-process strategies ────────────────────────────────────
+
+    struct FixedFee {
+        name: &'static str,
+        fee_bps: u32,
+        /// Panic on the next quote (once), to exercise fault handling
+        panic_once: AtomicBool,
+    }
+
+    impl FixedFee {
+        fn new(name: &'static str, fee_bps: u32) -> Self {
+            Self { name, fee_bps, panic_once: AtomicBool::new(false) }
+        }
+    }
+
+    impl EngineStrategy for FixedFee {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn model(&self) -> Option<&str> {
+            Some("hand-tuned")
+        }
+
+        fn compute_swap(&self, is_buy: bool, input: u64, rx: u64, ry: u64, _storage: &[u8; STORAGE_SIZE]) -> u64 {
+            if self.panic_once.swap(false, Ordering::Relaxed) {
+                panic!("{} blew up", self.name);
+            }
+            if is_buy { cpamm_output(input, ry, rx, self.fee_bps) } else { cpamm_output(input, rx, ry, self.fee_bps) }
+        }
+    }
+
+    #[test]
+    fn unfilled_volume_is_reported_once_per_epoch() {
+        let runners = [
+            StrategyRunner::from_strategy(FixedFee::new("cheap", 20)),
+            StrategyRunner::from_strategy(FixedFee::new("dear", 60)),
+        ];
+        let market = SimConfig { total_steps: 600, epoch_len: 200, ..SimConfig::default() };
+        let limits = SimConfig { limit_order_prob: 1.0, limit_tolerance_mean: 0.001, ..market.clone() };
+
+        let sim = run_simulation(&runners, &market, 7).unwrap();
+        assert_eq!(sim.unfilled_volume, [0.0; 2], "one entry per rebalance");
+        let sim = run_simulation(&runners, &limits, 7).unwrap();
+        assert_eq!(sim.unfilled_volume.len(), sim.strategies[0].epoch_summaries.len());
+        assert!(sim.unfilled_volume.iter().all(|&v| v > 0.0), "tight limits leave flow unfilled: {:?}", sim.unfilled_volume);
+    }
+
+    #[test]
+    fn in_process_strategies_run_a_full_simulation() {
+        let config = SimConfig { total_steps: 600, epoch_len: 200, ..SimConfig::default() };
+        let run = || {
+            let runners = [
+                StrategyRunner::from_strategy(FixedFee::new("cheap", 20)),
+                StrategyRunner::from_strategy(FixedFee::new("dear", 60)),
+            ];
+            run_simulation(&runners, &config, 7).unwrap()
+        };
+        let a = run();
+        assert_eq!(a.strategies.iter().map(|s| s.name.as_str()).collect::<Vec<_>>(), ["cheap", "dear"]);
+        assert!(a.strategies.iter().all(|s| s.retail_volume > 0.0), "both venues should see flow");
+        assert!(a.fault_events.is_empty());
+        assert_eq!(a.strategies[0].metadata.model.as_deref(), Some("hand-tuned"));
+
+        let b = run();
+        for (x, y) in a.strategies.iter().zip(&b.strategies) {
+            assert_eq!(x.final_edge, y.final_edge, "same seed must reproduce");
+        }
+    }
+
+    #[test]
+    fn in_process_panic_is_a_fault_not_an_abort() {
+        let strategy = FixedFee::new("flaky", 30);
+        strategy.panic_once.store(true, Ordering::Relaxed);
+        let runner = StrategyRunner::from_strategy(strategy);
+        let storage = [0u8; STORAGE_SIZE];
+
+        runner.set_step(5);
+        assert_eq!(runner.compute_swap(true, SCALE, 100 * SCALE, 10_000 * SCALE, &storage), 0);
+        assert_eq!(runner.take_faults(), vec![(5, StrategyCall::ComputeSwap)]);
+        // A panic is not a crash: the strategy keeps quoting
+        assert!(runner.compute_swap(true, SCALE, 100 * SCALE, 10_000 * SCALE, &storage) > 0);
+    }
+
+    /// Widens its fee a little on every quote: stateful, so not reproducible.
+    struct Drifting(AtomicU64);
+
+    impl EngineStrategy for Drifting {
+        fn name(&self) -> &str {
+            "drifting"
+        }
+
+        fn compute_swap(&self, is_buy: bool, input: u64, rx: u64, ry: u64, _storage: &[u8; STORAGE_SIZE]) -> u64 {
+            let fee = 30 + (self.0.fetch_add(1, Ordering::Relaxed) / 64) as u32;
+            if is_buy { cpamm_output(input, ry, rx, fee) } else { cpamm_output(input, rx, ry, fee) }
+        }
+    }
+
+    #[test]
+    fn determinism_check_flags_stateful_quotes() {
+        let pure = StrategyRunner::from_strategy(FixedFee::new("pure", 30));
+        let fresh = StrategyRunner::from_strategy(FixedFee::new("pure", 30));
+        assert!(check_determinism(&pure, &fresh).is_ok());
+
+        let drifting = StrategyRunner::from_strategy(Drifting(AtomicU64::new(0)));
+        let fresh = StrategyRunner::from_strategy(Drifting(AtomicU64::new(0)));
+        let err = check_determinism(&drifting, &fresh).expect_err("drifting fee must be caught");
+        assert_eq!(err.pass, "repeated call");
+        assert_ne!(err.expected, err.got);
+    }
+
+    /// Promises more than the pool holds on every quote.
+    struct Overquoter;
+
+    impl EngineStrategy for Overquoter {
+        fn name(&self) -> &str {
+            "overquoter"
+        }
+
+        fn compute_swap(&self, is_buy: bool, _input: u64, rx: u64, ry: u64, _storage: &[u8; STORAGE_SIZE]) -> u64 {
+            if is_buy { rx + 1 } else { ry + 1 }
+        }
+    }
+
+    #[test]
+    fn quote_guard_rejects_or_clamps_quotes_beyond_reserves() {
+        let storage = [0u8; STORAGE_SIZE];
+        let mut runner = StrategyRunner::from_strategy(Overquoter);
+        assert_eq!(runner.compute_swap(true, SCALE, 100 * SCALE, 10_000 * SCALE, &storage), 0);
+        runner.set_quote_guard(QuoteGuard { action: OverQuoteAction::Clamp, disqualify_after: Some(3) });
+        assert_eq!(runner.compute_swap(false, SCALE, 100 * SCALE, 10_000 * SCALE, &storage), 10_000 * SCALE - 1);
+        assert_eq!(runner.compute_swap_batch(true, &[SCALE, 2 * SCALE], 100 * SCALE, 10_000 * SCALE, &storage), vec![100 * SCALE - 1; 2]);
+        assert!(runner.is_disabled());
+        assert_eq!(runner.take_over_quotes().len(), 4);
+
+        // In a simulation the pool is never drained; with strikes the strategy is disqualified
+        let fair = StrategyRunner::from_strategy(FixedFee::new("fair", 30));
+        let config = SimConfig { total_steps: 400, epoch_len: 200, ..SimConfig::default() };
+        let runners = [StrategyRunner::from_strategy(Overquoter), fair];
+        let result = run_simulation(&runners, &config, 5).unwrap();
+        assert!(result.strategies[0].over_quotes > 0 && !result.strategies[0].disqualified);
+        assert!(result.strategies.iter().all(|s| s.final_edge.is_finite()));
+
+        let config = SimConfig { quote_guard: QuoteGuard { disqualify_after: Some(5), ..QuoteGuard::default() }, ..config };
+        let mut runners = [StrategyRunner::from_strategy(Overquoter), StrategyRunner::from_strategy(FixedFee::new("fair", 30))];
+        runners.iter_mut().for_each(|r| r.configure(&config));
+        let result = run_simulation(&runners, &config, 5).unwrap();
+        assert_eq!(result.strategies[0].over_quotes, 5);
+        assert!(result.strategies[0].disqualified && !result.strategies[1].disqualified);
+    }
+
+    /// Quotes a fixed fee until the order passes a tenth of the input reserve, then less
+    struct Cliff;
+
+    impl EngineStrategy for Cliff {
+        fn name(&self) -> &str {
+            "cliff"
+        }
+
+        fn compute_swap(&self, is_buy: bool, input: u64, rx: u64, ry: u64, storage: &[u8; STORAGE_SIZE]) -> u64 {
+            let (reserve_in, reserve_out) = if is_buy { (ry, rx) } else { (rx, ry) };
+            let out = FixedFee::new("f", 30).compute_swap(is_buy, input, rx, ry, storage);
+            if input > reserve_in / 10 { reserve_out / 100 } else { out }
+        }
+    }
+
+    #[test]
+    fn validation_checks_both_sides_across_reserves_and_payloads() {
+        use prop_amm_engine::validation::{check_payloads, check_quotes, Severity, ValidationReport};
+
+        let report = |runner: &StrategyRunner| {
+            let mut r = ValidationReport { strategy: runner.name.clone(), checks: check_quotes(runner) };
+            r.checks.push(check_payloads(runner, 0));
+            r
+        };
+        let failed = |r: &ValidationReport| r.checks.iter().filter(|c| !c.passed).map(|c| c.name).collect::<Vec<_>>();
+
+        let fair = report(&StrategyRunner::from_strategy(FixedFee::new("fair", 30)));
+        assert!(fair.passed() && failed(&fair).is_empty(), "{:?}", fair.checks);
+        assert!(fair.checks.iter().find(|c| c.name == "monotone").unwrap().probes >= 100);
+        // In-process strategies take no payload to fuzz
+        assert_eq!(fair.checks.last().unwrap().probes, 0);
+
+        let cliff = report(&StrategyRunner::from_strategy(Cliff));
+        assert_eq!(failed(&cliff), ["monotone"]);
+        assert!(!cliff.passed());
+
+        let over = report(&StrategyRunner::from_strategy(Overquoter));
+        // Every quote is refused by the quote guard, so nothing is positive either
+        assert_eq!(failed(&over), ["positive", "within_reserve"]);
+
+        // Warnings alone do not fail a strategy
+        let mut warned = fair.clone();
+        warned.push("concave", Severity::Warning, Some("kinked".to_string()));
+        assert!(warned.passed());
+    }
+
+    /// Records the most competitors any after-swap payload showed it, checking each has a
+    /// reserve alongside its price.
+    struct Watcher(Arc<AtomicU64>);
+
+    impl EngineStrategy for Watcher {
+        fn name(&self) -> &str {
+            "watcher"
+        }
+
+        fn compute_swap(&self, is_buy: bool, input: u64, rx: u64, ry: u64, _storage: &[u8; STORAGE_SIZE]) -> u64 {
+            if is_buy { cpamm_output(input, ry, rx, 30) } else { cpamm_output(input, rx, ry, 30) }
+        }
+
+        fn after_swap(&self, payload: &AfterSwapPayload, _storage: &mut [u8; STORAGE_SIZE]) {
+            let (competing, depths) = (payload.competing_spot_prices, payload.competing_reserve_y);
+            let filled = competing.iter().filter(|p| p.is_finite()).count();
+            assert_eq!(filled, payload.n_competing as usize);
+            assert!(depths[..filled].iter().all(|&d| d > 0.0) && depths[filled..].iter().all(|d| d.is_nan()));
+            self.0.fetch_max(filled as u64, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn after_swap_payload_carries_every_competitor_in_large_fields() {
+        const NAMES: [&str; 11] = ["f0", "f1", "f2", "f3", "f4", "f5", "f6", "f7", "f8", "f9", "f10"];
+        let watched = Arc::new(AtomicU64::new(0));
+        let mut runners = vec![StrategyRunner::from_strategy(Watcher(watched.clone()))];
+        runners.extend(NAMES.iter().zip(20..).map(|(name, fee)| StrategyRunner::from_strategy(FixedFee::new(name, fee))));
+        let config = SimConfig { total_steps: 300, epoch_len: 100, ..SimConfig::default() };
+        run_simulation(&runners, &config, 4).unwrap();
+        // 11 other strategies and the normalizer, beyond the old 8-slot limit
+        assert_eq!(watched.load(Ordering::Relaxed), 12);
+    }
+
+    /// Records the ratio of the first competitor's Y reserve to its own in each after-swap
+    /// payload, keeping the smallest and largest seen.
+    struct DepthProbe(Arc<[AtomicU64; 2]>);
+
+    impl EngineStrategy for DepthProbe {
+        fn name(&self) -> &str {
+            "depth"
+        }
+
+        fn compute_swap(&self, is_buy: bool, input: u64, rx: u64, ry: u64, _storage: &[u8; STORAGE_SIZE]) -> u64 {
+            if is_buy { cpamm_output(input, ry, rx, 30) } else { cpamm_output(input, rx, ry, 30) }
+        }
+
+        fn after_swap(&self, payload: &AfterSwapPayload, _storage: &mut [u8; STORAGE_SIZE]) {
+            let depths = payload.competing_reserve_y;
+            let ratio = depths[0] as f64 / payload.reserve_y as f64;
+            // Positive floats order like their bit patterns
+            self.0[0].fetch_min(ratio.to_bits(), Ordering::Relaxed);
+            self.0[1].fetch_max(ratio.to_bits(), Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn after_swap_payload_reports_competitor_depth() {
+        let seen = Arc::new([AtomicU64::new(f64::INFINITY.to_bits()), AtomicU64::new(0)]);
+        let runners = [StrategyRunner::from_strategy(DepthProbe(seen.clone()))];
+        // The only competitor is a normalizer with four times the strategy's liquidity
+        let config = SimConfig {
+            total_steps: 300,
+            epoch_len: 100,
+            norm_liquidity_mult: Some(4.0),
+            ..SimConfig::default()
+        };
+        run_simulation(&runners, &config, 8).unwrap();
+        let [low, high] = [0, 1].map(|i| f64::from_bits(seen[i].load(Ordering::Relaxed)));
+        assert!(low <= high, "no after-swap call");
+        assert!(low > 3.0 && high < 5.0, "depth ratio ranged {low}..{high}");
+    }
+
+    #[test]
+    fn epoch_summaries_split_volume_and_trades_by_side_and_counterparty() {
+        let runners = [
+            StrategyRunner::from_strategy(FixedFee::new("a", 30)),
+            StrategyRunner::from_strategy(FixedFee::new("b", 50)),
+        ];
+        // Pools open off fair, so arbitrageurs have work from the first step
+        let config = SimConfig { total_steps: 400, epoch_len: 100, initial_x_share: 0.6, ..SimConfig::default() };
+        let result = run_simulation(&runners, &config, 5).unwrap();
+        for s in result.strategies.iter().flat_map(|s| &s.epoch_summaries) {
+            assert_eq!(s.retail_trade_count + s.arb_trade_count, s.trade_count);
+            assert!(s.buy_volume >= 0.0 && s.sell_volume >= 0.0);
+            assert!(s.largest_trade <= s.buy_volume + s.sell_volume + 1e-9);
+            assert!((0.0..=1.0).contains(&s.mean_flow_share));
+            if s.retail_trade_count == 0 {
+                assert_eq!(s.mean_flow_share, 0.0);
+            }
+        }
+        let epochs: Vec<_> = result.strategies.iter().flat_map(|s| &s.epoch_summaries).collect();
+        assert!(epochs.iter().any(|s| s.retail_trade_count > 0 && s.mean_flow_share > 0.0));
+        assert!(result.strategies.iter().all(|s| s.epoch_summaries[0].arb_trade_count > 0), "mispriced pools must be arbed");
+
+        let mut agg = Aggregator::new();
+        agg.push(&result);
+        for (r, s) in agg.finish().iter().zip(&result.strategies) {
+            assert!(r.mean_epoch_arb_trades > 0.0);
+            let n = s.epoch_summaries.len() as f64;
+            let buys: f64 = s.epoch_summaries.iter().map(|e| e.buy_volume).sum();
+            assert!((r.mean_epoch_buy_volume - buys / n).abs() < 1e-9);
+            let largest = s.epoch_summaries.iter().map(|e| e.largest_trade).fold(0.0, f64::max);
+            assert_eq!(r.largest_trade, largest);
+        }
+    }
+
+    #[test]
+    fn strategy_logs_are_opt_in_and_native_only() {
+        assert!(!SimConfig::default().strategy_logs);
+        let config = SimConfig { total_steps: 200, epoch_len: 100, strategy_logs: true, ..SimConfig::default() };
+        let mut runners = [
+            StrategyRunner::from_strategy(FixedFee::new("a", 30)),
+            StrategyRunner::from_strategy(FixedFee::new("b", 50)),
+        ];
+        for r in &mut runners {
+            r.set_debug_log(true);
+        }
+        // In-process strategies have no `__prop_amm_log` export to drain
+        let result = run_simulation(&runners, &config, 3).unwrap();
+        assert!(result.strategy_logs.is_empty());
+        assert!(runners.iter().all(|r| r.take_logs().is_empty()));
+    }
+
+    #[test]
+    fn remote_manifests_parse_commands_and_tcp() {
+        let cmd = RemoteEndpoint::parse("# python strategy\n\ncommand python3 strat.py --fee-bps 30\n").unwrap();
+        assert_eq!(cmd, RemoteEndpoint::Command(vec!["python3".into(), "strat.py".into(), "--fee-bps".into(), "30".into()]));
+        assert_eq!(RemoteEndpoint::parse("tcp 10.0.0.7:9000").unwrap(), RemoteEndpoint::Tcp("10.0.0.7:9000".into()));
+        assert!(RemoteEndpoint::parse("tcp a:1 b:2").is_err());
+        assert!(RemoteEndpoint::parse("command").is_err());
+        assert!(RemoteEndpoint::parse("# nothing").is_err());
+        assert_eq!(Runtime::for_artifact(std::path::Path::new("fixed.remote")), Runtime::Remote);
+    }
+
+    /// A shell worker that completes the handshake (name `hang`, no model, no metadata)
+    /// and then runs `then`.
+    #[cfg(unix)]
+    fn sh_worker(then: &str) -> RemoteEndpoint {
+        let script = format!(r"printf '\004\000\000\000hang\000\000\000\000\000\000\000\000'; {then}");
+        RemoteEndpoint::Command(vec!["sh".into(), "-c".into(), script])
+    }
+
+    /// Never answers a call.
+    #[cfg(unix)]
+    fn hanging_worker() -> RemoteEndpoint {
+        sh_worker("exec sleep 60")
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn wall_time_budget_cuts_off_a_hanging_worker() {
+        use prop_amm_engine::runner::{BudgetPenalty, CallBudget};
+        use std::time::{Duration, Instant};
+
+        let mut runner = StrategyRunner::connect(&hanging_worker()).unwrap();
+        assert_eq!(runner.name, "hang");
+        let budget = CallBudget { wall_time: Some(Duration::from_millis(100)), penalty: BudgetPenalty::Refuse, ..CallBudget::default() };
+        runner.set_budget(budget);
+        let storage = [0u8; STORAGE_SIZE];
+
+        runner.set_step(3);
+        let started = Instant::now();
+        assert_eq!(runner.compute_swap(true, SCALE, 100 * SCALE, 10_000 * SCALE, &storage), 0);
+        assert!(started.elapsed() < Duration::from_secs(10), "the call must be cut off, not waited out");
+        assert_eq!(runner.take_overruns(), vec![(3, StrategyCall::ComputeSwap)]);
+        // The worker was killed, so even a lenient penalty disables the strategy
+        assert!(runner.is_disabled());
+        assert_eq!(runner.compute_swap(true, SCALE, 100 * SCALE, 10_000 * SCALE, &storage), 0);
+        assert!(runner.take_overruns().is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn crashed_or_garbled_workers_are_contained() {
+        use std::time::{Duration, Instant};
+
+        // Dies mid-call: a fault, and the strategy is out for the rest of the run
+        let runner = StrategyRunner::connect(&sh_worker("head -c 1 >/dev/null; exit 3")).unwrap();
+        let storage = [0u8; STORAGE_SIZE];
+        runner.set_step(9);
+        assert_eq!(runner.compute_swap(true, SCALE, 100 * SCALE, 10_000 * SCALE, &storage), 0);
+        assert_eq!(runner.take_faults(), vec![(9, StrategyCall::ComputeSwap)]);
+        assert!(runner.is_disabled() && runner.take_overruns().is_empty());
+
+        // A 2 GiB length prefix is refused outright, not allocated and waited on
+        let script = r"printf '\377\377\377\177'; exec sleep 60";
+        let started = Instant::now();
+        let err = StrategyRunner::connect(&RemoteEndpoint::Command(vec!["sh".into(), "-c".into(), script.into()]))
+            .err()
+            .expect("oversized handshake frame");
+        assert!(err.to_string().contains("exceeds"), "{err}");
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+
+    #[test]
+    fn sim_config_round_trips_through_toml_and_validates() {
+        use prop_amm_engine::capital::AllocatorKind;
+        use prop_amm_engine::types::StepOrdering;
+
+        let text = SimConfig::default().to_toml().unwrap();
+        assert_eq!(SimConfig::from_toml(&text).unwrap().to_toml().unwrap(), text);
+
+        let config = SimConfig::from_toml(
+            "total_steps = 2000\nepoch_len = 500\nstep_ordering = \"retail_first\"\nopponents = [\"fixed_30\"]\n\
+             [allocator.kelly]\nfraction = 0.5\n",
+        )
+        .unwrap();
+        assert_eq!((config.total_steps, config.epoch_len), (2000, 500));
+        assert_eq!(config.step_ordering, StepOrdering::RetailFirst);
+        assert_eq!(config.allocator, AllocatorKind::Kelly { fraction: 0.5 });
+        assert_eq!(config.min_capital_weight, SimConfig::default().min_capital_weight);
+
+        let err = SimConfig::from_toml("total_steps = 1000\nepoch_len = 300\nmin_capital_weight = 1.5\nopponents = [\"nobody\"]").unwrap_err();
+        assert!(err.contains("does not divide") && err.contains("min_capital_weight") && err.contains("`nobody`"), "{err}");
+        assert!(SimConfig::from_toml("epoch_len = \"long\"").is_err());
+    }
+
+    #[test]
+    fn step_time_scale_converts_market_units_and_annualizes() {
+        let config = SimConfig { step_dt_seconds: Some(0.4), ..SimConfig::default() };
+        let sigma = config.sigma_from_annual(0.8).unwrap();
+        assert!((config.annualized_sigma(sigma).unwrap() - 0.8).abs() < 1e-12);
+        assert!((config.lambda_from_per_minute(90.0).unwrap() - 0.6).abs() < 1e-12);
+        assert!((config.orders_per_minute_of(0.6).unwrap() - 90.0).abs() < 1e-9);
+        // A return earned over one day is 365.25× per year
+        let day = (86_400.0 / 0.4) as usize;
+        assert!((config.annualize_return(0.01, day).unwrap() - 3.6525).abs() < 1e-9);
+
+        let pinned = SimConfig {
+            total_steps: 200,
+            epoch_len: 100,
+            annual_volatility: Some(0.8),
+            orders_per_minute: Some(90.0),
+            ..config
+        };
+        assert!(pinned.validate().is_ok());
+        let runners = [StrategyRunner::from_strategy(FixedFee::new("a", 30))];
+        let result = run_simulation(&runners, &pinned, 11).unwrap();
+        assert_eq!(result.market_params.sigma, sigma);
+        assert_eq!(Some(result.market_params.lambda), pinned.lambda_from_per_minute(90.0));
+        let s = &result.strategies[0];
+        let expected = pinned.annualize_return(s.final_edge / 20_000.0, 200).unwrap();
+        assert!((s.annualized_return.unwrap() - expected).abs() < 1e-9);
+
+        // Without a step length nothing is annualized, and wall-clock units cannot be pinned
+        let per_step = SimConfig { total_steps: 200, epoch_len: 100, ..SimConfig::default() };
+        assert_eq!(per_step.annualize_return(0.01, 200), None);
+        let result = run_simulation(&runners, &per_step, 11).unwrap();
+        assert_eq!(result.strategies[0].annualized_return, None);
+        let err = SimConfig { orders_per_minute: Some(90.0), ..SimConfig::default() }.validate().unwrap_err();
+        assert!(err.contains("needs step_dt_seconds"), "{err}");
+
+        assert!(SimConfig { step_dt_seconds: Some(0.0), ..SimConfig::default() }.validate().is_err());
+        assert!(SimConfig { annual_volatility: Some(-0.1), ..SimConfig::default() }.validate().is_err());
+    }
+
+    #[test]
+    fn pinned_market_parameters_replace_the_draws() {
+        let config = SimConfig {
+            total_steps: 200,
+            epoch_len: 100,
+            step_dt_seconds: Some(1.0),
+            annual_volatility: Some(0.8),
+            sigma_per_step: Some(0.002),
+            orders_per_step: Some(0.9),
+            norm_fee_bps: Some(45),
+            norm_liquidity_mult: Some(1.5),
+            ..SimConfig::default()
+        };
+        assert!(config.validate().is_ok());
+        let runners = [StrategyRunner::from_strategy(FixedFee::new("a", 30))];
+        let pinned = run_simulation(&runners, &config, 3).unwrap();
+        let p = &pinned.market_params;
+        assert_eq!((p.sigma, p.lambda, p.norm_fee_bps, p.norm_liquidity_mult), (0.002, 0.9, 45, 1.5));
+
+        // Only the pinned values move: the rest of the seed's draws are unchanged
+        let sampled = run_simulation(&runners, &SimConfig { total_steps: 200, epoch_len: 100, ..SimConfig::default() }, 3).unwrap();
+        assert_eq!(p.order_size_mean, sampled.market_params.order_size_mean);
+
+        assert!(SimConfig { norm_fee_bps: Some(10_000), ..SimConfig::default() }.validate().is_err());
+        assert!(SimConfig { sigma_per_step: Some(0.0), ..SimConfig::default() }.validate().is_err());
+    }
+
+    #[test]
+    fn initial_pools_follow_configured_price_skew_and_weights() {
+        use prop_amm_engine::capital::InitialWeight;
+
+        let config = SimConfig {
+            total_steps: 200,
+            epoch_len: 100,
+            initial_price: Some(2_000.0),
+            initial_x_share: 0.3,
+            initial_weights: vec![InitialWeight { strategy: "incumbent".into(), weight: 0.5 }],
+            record_trades: true,
+            ..SimConfig::default()
+        };
+        assert!(config.validate().is_ok());
+        let (rx, ry) = config.initial_reserves(1.0);
+        let value = |rx: u64, ry: u64| (rx as f64 * 2_000.0 + ry as f64) / SCALE_F;
+        assert!((rx as f64 * 2_000.0 / SCALE_F / value(rx, ry) - 0.3).abs() < 1e-9);
+        assert!((value(rx, ry) - 20_000.0).abs() < 1e-6);
+
+        let runners = [
+            StrategyRunner::from_strategy(FixedFee::new("incumbent", 30)),
+            StrategyRunner::from_strategy(FixedFee::new("a", 30)),
+            StrategyRunner::from_strategy(FixedFee::new("b", 30)),
+        ];
+        let result = run_simulation(&runners, &config, 8).unwrap();
+        assert_eq!(result.capital_weights[0], vec![0.5, 0.25, 0.25]);
+        // Every pool starts short of X, spot ≈ 14,000 / 3 Y, so the first arb sells X in
+        // at a price between that and fair
+        let fill = result.trades.iter().find(|t| t.amm < 3).unwrap();
+        assert_eq!(fill.kind, prop_amm_engine::events::TradeKind::Arb);
+        assert!(!fill.is_buy);
+        assert!((fill.fair_price / 2_000.0 - 1.0).abs() < 0.05);
+        let price = fill.output as f64 / fill.input as f64;
+        assert!(price > fill.fair_price * 1.1 && price < 14_000.0 / 3.0, "first arb filled at {price}");
+
+        let parsed = SimConfig::from_toml("initial_price = 2000.0\n[[initial_weights]]\nstrategy = \"incumbent\"\nweight = 0.5\n").unwrap();
+        assert_eq!(parsed.initial_weights, config.initial_weights);
+        assert!(SimConfig { initial_x_share: 1.0, ..SimConfig::default() }.validate().is_err());
+        let greedy = vec![InitialWeight { strategy: "a".into(), weight: 0.8 }, InitialWeight { strategy: "b".into(), weight: 0.4 }];
+        assert!(SimConfig { initial_weights: greedy, ..SimConfig::default() }.validate().is_err());
+    }
+
+    #[test]
+    fn quantized_fills_land_on_lots_and_ticks() {
+        let (tick, lot) = (0.5, SCALE / 100);
+        let config = SimConfig {
+            total_steps: 400,
+            epoch_len: 200,
+            quantization: Quantization { tick_size: tick, lot_size: lot },
+            record_trades: true,
+            ..SimConfig::default()
+        };
+        assert!(config.validate().is_ok());
+        let runners = [
+            StrategyRunner::from_strategy(FixedFee::new("a", 30)),
+            StrategyRunner::from_strategy(FixedFee::new("b", 30)),
+        ];
+        let result = run_simulation(&runners, &config, 5).unwrap();
+        assert!(!result.trades.is_empty());
+        for t in &result.trades {
+            assert_eq!(t.input % lot, 0, "{t:?}");
+            assert_eq!(t.output % lot, 0, "{t:?}");
+            // Y per X: on the tick rounded against the taker, give or take the lot floor
+            let slack = (lot + 1) as f64;
+            let price = if t.is_buy { t.input as f64 / t.output as f64 } else { t.output as f64 / t.input as f64 };
+            if t.is_buy {
+                let past_tick = price - (price / tick + 1e-9).floor() * tick;
+                assert!(past_tick <= price * slack / t.output as f64 + 1e-9, "{t:?}");
+            } else {
+                let short_of_tick = (price / tick - 1e-9).ceil() * tick - price;
+                assert!(short_of_tick <= slack / t.input as f64 + 1e-9, "{t:?}");
+            }
+        }
+        let continuous = run_simulation(&runners, &SimConfig { quantization: Quantization::default(), ..config.clone() }, 5).unwrap();
+        assert_ne!(result.strategies[0].final_edge, continuous.strategies[0].final_edge);
+        assert!(SimConfig { quantization: Quantization { tick_size: -1.0, lot_size: 0 }, ..SimConfig::default() }.validate().is_err());
+    }
+
+    #[test]
+    fn leaderboard_tracks_rank_changes_across_runs() {
+        use prop_amm_engine::leaderboard::Leaderboard;
+
+        let config = SimConfig { total_steps: 200, epoch_len: 100, ..SimConfig::default() };
+        let runners = [
+            StrategyRunner::from_strategy(FixedFee::new("a", 30)),
+            StrategyRunner::from_strategy(FixedFee::new("b", 30)),
+            StrategyRunner::from_strategy(FixedFee::new("c", 30)),
+        ];
+        let mut agg = Aggregator::new();
+        agg.push(&run_simulation(&runners, &config, 1).unwrap());
+        let field = agg.finish();
+        let with_edges = |edges: &[(&str, f64)]| {
+            edges.iter()
+                .map(|&(name, edge)| {
+                    let mut r = field.iter().find(|r| r.name == name).unwrap().clone();
+                    r.mean_edge = edge;
+                    r
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let path = std::env::temp_dir().join(format!("leaderboard_{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mut board = Leaderboard::load(&path).unwrap();
+        board.record(&with_edges(&[("a", 3.0), ("b", 2.0), ("c", 1.0)]), 100);
+        board.save(&path).unwrap();
+
+        let mut board = Leaderboard::load(&path).unwrap();
+        board.record(&with_edges(&[("a", 1.0), ("c", 5.0)]), 200);
+        let standing = |board: &Leaderboard, name: &str| board.standings.iter().find(|s| s.name == name).unwrap().clone();
+        assert_eq!((board.runs, board.updated), (2, 200));
+        assert_eq!(board.standings.iter().map(|s| s.name.as_str()).collect::<Vec<_>>(), ["c", "a", "b"]);
+        assert_eq!((standing(&board, "c").rank, standing(&board, "c").rank_delta(), standing(&board, "c").best_rank), (Some(1), Some(2), 1));
+        assert_eq!(standing(&board, "a").rank_delta(), Some(-1));
+        assert_eq!((standing(&board, "b").rank, standing(&board, "b").rank_delta()), (None, None));
+
+        // A returning strategy is compared with its last ranked run
+        board.record(&with_edges(&[("b", 9.0), ("c", 5.0)]), 300);
+        assert_eq!((standing(&board, "b").rank, standing(&board, "b").rank_delta(), standing(&board, "b").appearances), (Some(1), Some(1), 2));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn strategy_dirs_follow_their_manifest_or_list_every_strategy() {
+        use prop_amm_engine::manifest::{inputs, parse, Backend, MANIFEST_FILE};
+        use std::path::PathBuf;
+
+        let dir = std::env::temp_dir().join(format!("strategies_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        for file in ["b.rs", "a.wasm", "c.remote", "notes.md", "leaderboard.json"] {
+            std::fs::write(dir.join(file), "").unwrap();
+        }
+        assert_eq!(inputs(&dir).unwrap(), ["a.wasm", "b.rs", "c.remote"].map(|f| dir.join(f)));
+
+        let manifest = "[[strategy]]\nname = \"wasm_b\"\npath = \"b.rs\"\nruntime = \"wasm\"\n\n\
+                        [[strategy]]\nname = \"off\"\npath = \"a.wasm\"\nenabled = false\n\n\
+                        [[strategy]]\nname = \"native_b\"\npath = \"b.rs\"\n";
+        let entries = parse(manifest).unwrap();
+        assert_eq!((entries[0].runtime, entries[1].enabled, entries[2].runtime), (Backend::Wasm, false, Backend::Native));
+        std::fs::write(dir.join(MANIFEST_FILE), manifest).unwrap();
+        assert_eq!(inputs(&dir).unwrap(), [PathBuf::from(format!("wasm:{}", dir.join("b.rs").display())), dir.join("b.rs")]);
+
+        assert!(parse("[[strategy]]\nname = \"x\"\npath = \"x.rs\"\n[[strategy]]\nname = \"x\"\npath = \"y.rs\"\n").unwrap_err().contains("twice"));
+        assert!(parse("[[strategy]]\nname = \"x\"\npath = \"x.rs\"\nruntime = \"jvm\"\n").is_err());
+        assert!(parse("[[strategy]]\nname = \"x\"\npath = \"x.rs\"\nenabeld = true\n").is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn results_serialize_with_every_field_and_their_seed() {
+        let config = SimConfig { total_steps: 200, epoch_len: 100, ..SimConfig::default() };
+        let runners = [StrategyRunner::from_strategy(FixedFee::new("a", 30))];
+        let result = run_simulation(&runners, &config, 9).unwrap();
+        assert_eq!(result.seed, 9);
+        let mut agg = Aggregator::new();
+        agg.push(&result);
+
+        let aggregated = serde_json::to_value(agg.finish()).unwrap();
+        assert_eq!(aggregated[0]["name"], "a");
+        assert_eq!(aggregated[0]["mean_edge"], result.strategies[0].final_edge);
+        assert!(aggregated[0]["mean_weight_trajectory"].is_array());
+        let strategy = serde_json::to_value(&result.strategies[0]).unwrap();
+        assert_eq!(strategy["epoch_summaries"].as_array().unwrap().len(), result.strategies[0].epoch_summaries.len());
+        assert_eq!(strategy["disqualified"], false);
+    }
+
+    #[test]
+    fn regime_aggregates_partition_simulations_by_volatility() {
+        use prop_amm_engine::market::VolRegime;
+        use prop_amm_engine::sim::aggregate_by_regime;
+
+        let mut params = MarketParams::sample(&mut ChaCha8Rng::seed_from_u64(1));
+        for (sigma, regime) in [(0.0005, VolRegime::Calm), (0.003, VolRegime::Normal), (0.0065, VolRegime::Volatile)] {
+            params.sigma = sigma;
+            assert_eq!(params.vol_regime(), regime);
+        }
+
+        let config = SimConfig { total_steps: 200, epoch_len: 100, ..SimConfig::default() };
+        let runners = [
+            StrategyRunner::from_strategy(FixedFee::new("a", 30)),
+            StrategyRunner::from_strategy(FixedFee::new("b", 50)),
+        ];
+        let results: Vec<_> = (0..12).map(|seed| run_simulation(&runners, &config, seed).unwrap()).collect();
+        let regimes = aggregate_by_regime(&results);
+        assert!(regimes.windows(2).all(|w| w[0].0 < w[1].0));
+        assert_eq!(regimes.iter().map(|(_, n, _)| n).sum::<usize>(), results.len());
+        for (regime, n, aggregated) in &regimes {
+            let members: Vec<_> = results.iter().filter(|r| r.market_params.vol_regime() == *regime).collect();
+            assert_eq!(members.len(), *n);
+            let mean = members.iter().map(|r| r.strategies[0].final_edge).sum::<f64>() / *n as f64;
+            assert!((aggregated[0].mean_edge - mean).abs() < 1e-9);
+        }
+    }
+
+    /// Counts its fills in the first storage slot.
+    struct FillCounter;
+
+    impl EngineStrategy for FillCounter {
+        fn name(&self) -> &str {
+            "counter"
+        }
+
+        fn compute_swap(&self, is_buy: bool, input: u64, rx: u64, ry: u64, _storage: &[u8; STORAGE_SIZE]) -> u64 {
+            if is_buy { cpamm_output(input, ry, rx, 30) } else { cpamm_output(input, rx, ry, 30) }
+        }
+
+        fn after_swap(&self, _payload: &AfterSwapPayload, storage: &mut [u8; STORAGE_SIZE]) {
+            let n = u64::from_le_bytes(storage[..8].try_into().unwrap());
+            storage[..8].copy_from_slice(&(n + 1).to_le_bytes());
+        }
+    }
+
+    #[test]
+    fn step_log_writes_every_step_trade_and_storage_change() {
+        use prop_amm_engine::events::write_step_log;
+
+        let config = SimConfig {
+            total_steps: 200,
+            epoch_len: 100,
+            record_trades: true,
+            snapshot_stride: 1,
+            snapshot_storage: true,
+            ..SimConfig::default()
+        };
+        let runners = [
+            StrategyRunner::from_strategy(FillCounter),
+            StrategyRunner::from_strategy(FixedFee::new("fixed", 30)),
+        ];
+        let result = run_simulation(&runners, &config, 9).unwrap();
+        let mut out = Vec::new();
+        write_step_log(&result, 9, &mut out).unwrap();
+        let lines: Vec<serde_json::Value> =
+            String::from_utf8(out).unwrap().lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+
+        assert_eq!(lines[0]["seed"], 9);
+        assert_eq!(lines[0]["strategies"], serde_json::json!(["counter", "fixed"]));
+        let steps = &lines[1..];
+        assert_eq!(steps.len(), config.total_steps);
+        assert!(steps.iter().enumerate().all(|(i, l)| l["step"] == i));
+        let trades: usize = steps.iter().map(|l| l["trades"].as_array().unwrap().len()).sum();
+        assert_eq!(trades, result.trades.len());
+
+        // Storage is logged up front, then only when the counter moves; a stateless
+        // strategy's never changes
+        let counter_fills = result.trades.iter().filter(|t| t.amm == 0).count();
+        let storage_lines = steps.iter().filter(|l| l["amms"][0].get("storage").is_some()).count();
+        assert!(storage_lines > 1 && storage_lines <= counter_fills + 1);
+        assert_eq!(steps.iter().filter(|l| l["amms"][1].get("storage").is_some()).count(), 1);
+        assert!(steps.iter().all(|l| l["amms"][2].get("storage").is_none()), "normalizers have no storage");
+    }
+
+    #[test]
+    fn trade_slots_follow_storage_after_every_fill() {
+        use prop_amm_engine::events::parse_slots;
+
+        assert_eq!(parse_slots("0..3, 7").unwrap(), vec![0, 1, 2, 7]);
+        assert!(parse_slots("3..3").is_err());
+        assert!(parse_slots("1,0..2").is_err(), "slot 1 twice");
+        assert!(parse_slots("120..129").is_err(), "past the last slot");
+        assert!(parse_slots(" ").is_err());
+
+        let config = SimConfig {
+            total_steps: 200,
+            epoch_len: 100,
+            record_trades: true,
+            trade_slots: parse_slots("1,0").unwrap(),
+            ..SimConfig::default()
+        };
+        let runners = [
+            StrategyRunner::from_strategy(FillCounter),
+            StrategyRunner::from_strategy(FixedFee::new("fixed", 30)),
+        ];
+        let result = run_simulation(&runners, &config, 9).unwrap();
+        let counter: Vec<&Vec<u64>> = result.trades.iter().filter(|t| t.amm == 0).map(|t| &t.slots).collect();
+        assert!(!counter.is_empty());
+        // Read after the fill's after_swap, so the n-th fill already sees count n
+        for (n, slots) in counter.iter().enumerate() {
+            assert_eq!(**slots, vec![0, n as u64 + 1]);
+        }
+        assert!(result.trades.iter().filter(|t| t.amm == 1).all(|t| t.slots == vec![0, 0]));
+        assert!(result.trades.iter().filter(|t| t.amm == 2).all(|t| t.slots.is_empty()), "normalizers have no storage");
+    }
+
+    #[test]
+    fn profile_times_every_strategy_call_and_phase() {
+        let runners = [
+            StrategyRunner::from_strategy(FillCounter),
+            StrategyRunner::from_strategy(FixedFee::new("fixed", 30)),
+        ];
+        let config = SimConfig { total_steps: 200, epoch_len: 100, record_trades: true, ..SimConfig::default() };
+        assert!(run_simulation(&runners, &config, 4).unwrap().profile.is_none(), "off by default");
+
+        let mut runners = runners;
+        for r in &mut runners {
+            r.set_profiling(true);
+        }
+        let result = run_simulation(&runners, &SimConfig { profile: true, ..config }, 4).unwrap();
+        let profile = result.profile.expect("profiled");
+        assert_eq!(profile.simulations, 1);
+        assert_eq!(profile.arb.count, 200, "one arb pass per step");
+        assert_eq!(profile.rebalancing.count, 1);
+        assert!(profile.routing.count > 0);
+        assert_eq!(profile.strategies.iter().map(|s| s.name.as_str()).collect::<Vec<_>>(), ["counter", "fixed"]);
+        let counter = &profile.strategies[0];
+        let fills = result.trades.iter().filter(|t| t.amm == 0).count() as u64;
+        assert_eq!(counter.calls.iter().find(|(c, _)| *c == StrategyCall::AfterSwap).unwrap().1.count, fills);
+        assert_eq!(counter.calls.iter().find(|(c, _)| *c == StrategyCall::EpochBoundary).unwrap().1.count, 1);
+        assert!(profile.strategy_total().total + profile.other() <= profile.wall);
+        assert!(runners.iter().all(|r| r.take_timings().is_empty()), "drained into the result");
+    }
+
+    #[test]
+    fn reference_opponents_instantiate_by_name_and_compete() {
+        use prop_amm_engine::sim::{run_batch, BatchOptions};
+
+        let storage = [0u8; STORAGE_SIZE];
+        let quotes: Vec<u64> = reference::FIXED_LADDER_BPS.iter()
+            .map(|bps| reference::builtin(&format!("fixed_{bps}")).unwrap())
+            .map(|r| r.compute_swap(true, SCALE, 100 * SCALE, 10_000 * SCALE, &storage))
+            .collect();
+        assert!(quotes.windows(2).all(|w| w[0] > w[1]), "wider fee must quote less: {quotes:?}");
+        assert!(reference::builtin("fixed_55").is_none() && reference::builtin("nope").is_none());
+
+        let runners: Vec<StrategyRunner> = reference::STANDARD_FIELD.iter().map(|n| reference::builtin(n).unwrap()).collect();
+        let config = SimConfig { total_steps: 600, epoch_len: 200, ..SimConfig::default() };
+        let result = run_simulation(&runners, &config, 11).unwrap();
+        assert_eq!(result.strategies[3].name, "ref_oracle_anchored");
+        assert!(result.strategies.iter().all(|s| s.final_edge.is_finite()));
+        assert!(result.fault_events.is_empty());
+        assert_eq!(reference::names().len(), reference::FIXED_LADDER_BPS.len() + 3);
+
+        // An unknown name fails the batch before any simulation runs
+        let config = SimConfig { opponents: vec!["fixed_30".into(), "nope".into()], ..config };
+        let err = run_batch(&[], &config, 2, 0, &BatchOptions::default()).unwrap_err();
+        assert_eq!(err, "unknown reference opponent `nope`");
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn load_audit_flags_thread_imports() {
+        // The test harness itself spawns threads, so its binary imports pthread_create
+        let audit = audit_artifact(&std::env::current_exe().unwrap()).unwrap();
+        assert!(!audit.passes());
+        assert!(audit.violations.iter().any(|v| v == "threads import `pthread_create`"), "{:?}", audit.violations);
+
+        let wasm = audit_artifact(std::path::Path::new("strategy.wasm")).unwrap();
+        assert!(wasm.passes() && wasm.imports.is_empty());
+    }
+
+    #[test]
+    fn fingerprints_see_through_renames_and_reformatting() {
+        let exe = std::env::current_exe().unwrap();
+        let original = include_str!("submission_0.rs");
+        let disguised = format!("// my own work\n{}", original.replace("cpamm_output", "curve").replace("submission_0_fixed_20bps", "totally_new"));
+        let fps: Vec<Fingerprint> = [original, &disguised, include_str!("submission_1.rs"), include_str!("reference.rs")]
+            .iter()
+            .map(|src| Fingerprint::of(src, &exe).unwrap())
+            .collect();
+        assert_eq!(fps[0].source_hash, fps[1].source_hash);
+        assert_eq!(fps[0].similarity(&fps[1]), 1.0);
+        // Same template, different fee and layout
+        assert!(fps[0].similarity(&fps[2]) > 0.8, "{}", fps[0].similarity(&fps[2]));
+        assert!(fps[0].similarity(&fps[3]) < 0.2, "{}", fps[0].similarity(&fps[3]));
+
+        // Every pair shares `exe`, so all are flagged as identical binaries
+        let dupes = find_duplicates(&fps[..2]);
+        assert_eq!(dupes.len(), 1);
+        assert!(dupes[0].identical_binary);
+    }
+
+    #[test]
+    fn fingerprints_flag_edited_copies_but_not_reparameterized_templates() {
+        // Distinct artifacts, so only the sources can make a pair
+        let dir = std::env::temp_dir().join(format!("prop-amm-fingerprint-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let original = include_str!("submission_0.rs");
+        // A redundant guard and a renamed helper: the same strategy, lightly disguised
+        let edited = original
+            .replace("cpamm_output", "quote_out")
+            .replace("    let denom =", "    if input_eff == 0 {\n        return 0;\n    }\n    let denom =");
+        assert_ne!(edited, original);
+        let sources = [original, &edited, include_str!("submission_1.rs"), include_str!("submission_2.rs")];
+        let fps: Vec<Fingerprint> = sources
+            .iter()
+            .enumerate()
+            .map(|(k, src)| {
+                let artifact = dir.join(format!("strategy_{k}.so"));
+                std::fs::write(&artifact, format!("build {k}")).unwrap();
+                Fingerprint::of(src, &artifact).unwrap()
+            })
+            .collect();
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert!(fps[0].similarity(&fps[1]) >= 0.9, "{}", fps[0].similarity(&fps[1]));
+        assert!(fps[0].similarity(&fps[2]) >= 0.9, "the shipped pair is textually close: {}", fps[0].similarity(&fps[2]));
+        assert!(!fps[0].same_literals(&fps[2]));
+        let dupes = find_duplicates(&fps);
+        assert_eq!(dupes.iter().map(|d| (d.a, d.b)).collect::<Vec<_>>(), vec![(0, 1)], "only the edited copy is a duplicate");
+        assert!(!dupes[0].identical_binary);
+    }
 }
@@ -0,0 +1,284 @@
+//! Deterministic simulation traces.
+//!
+//! A `SimTrace` captures everything exogenous to the strategies — the sampled market
+//! parameters, the fair-price path, landed shocks and the fully-processed retail order
+//! stream in execution order — plus the routing decisions made in the recorded run.
+//! `sim::replay_simulation` re-runs any strategy set against the identical market path
+//! and order stream, for apples-to-apples comparisons across submissions.
+//!
+//! Traces serialize to a compact little-endian binary format (`save` / `load`).
+
+use std::io::{Read, Write};
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+
+use crate::market::{
+    ArrivalCurve, MarketParams, NormalizerParams, OrderKind, RetailOrder, SizeClustering,
+};
+use crate::types::ShockEvent;
+
+const TRACE_MAGIC: &[u8; 4] = b"PAMT";
+const TRACE_VERSION: u8 = 2;
+
+/// One fill of a retail order on one AMM, as routed in the recorded run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TraceFill {
+    /// AMM index: strategies are 0..n_strat, normalizers follow
+    pub amm: u8,
+    pub input: u64,
+    pub output: u64,
+}
+
+/// One retail order and how the recorded run routed it.
+#[derive(Clone, Debug)]
+pub struct TraceOrder {
+    pub order: RetailOrder,
+    pub fills: Vec<TraceFill>,
+}
+
+/// Everything the market did during one step.
+#[derive(Clone, Debug)]
+pub struct TraceStep {
+    /// Fair price after the GBM step and any landed shock
+    pub fair_price: f64,
+    /// Position of the arbitrage pass among this step's events
+    pub arb_position: u32,
+    /// Retail orders in execution order
+    pub orders: Vec<TraceOrder>,
+}
+
+/// Full recorded simulation: market path + order stream + routing decisions.
+#[derive(Clone, Debug)]
+pub struct SimTrace {
+    pub seed: u64,
+    pub params: MarketParams,
+    pub shocks: Vec<ShockEvent>,
+    pub steps: Vec<TraceStep>,
+}
+
+impl SimTrace {
+    /// Jump of the shock landing at `step`, if any.
+    pub fn shock_at(&self, step: usize) -> Option<f64> {
+        self.shocks.iter().find(|s| s.step == step).map(|s| s.jump)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let file = std::fs::File::create(path)
+            .with_context(|| format!("Cannot create trace {}", path.display()))?;
+        let mut w = std::io::BufWriter::new(file);
+        self.write_to(&mut w)?;
+        w.flush()?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let file = std::fs::File::open(path)
+            .with_context(|| format!("Cannot open trace {}", path.display()))?;
+        Self::read_from(&mut std::io::BufReader::new(file))
+    }
+
+    pub fn write_to(&self, w: &mut impl Write) -> Result<()> {
+        w.write_all(TRACE_MAGIC)?;
+        put_u8(w, TRACE_VERSION)?;
+        put_u64(w, self.seed)?;
+        write_params(w, &self.params)?;
+
+        put_u32(w, self.shocks.len() as u32)?;
+        for s in &self.shocks {
+            put_u64(w, s.step as u64)?;
+            put_f64(w, s.jump)?;
+        }
+
+        put_u32(w, self.steps.len() as u32)?;
+        for step in &self.steps {
+            put_f64(w, step.fair_price)?;
+            put_u32(w, step.arb_position)?;
+            put_u32(w, step.orders.len() as u32)?;
+            for t in &step.orders {
+                put_u8(w, t.order.is_buy as u8)?;
+                put_u8(w, order_kind_tag(t.order.kind))?;
+                put_f64(w, t.order.size_y)?;
+                put_f64(w, t.order.limit_price.unwrap_or(f64::NAN))?;
+                put_u8(w, t.fills.len() as u8)?;
+                for f in &t.fills {
+                    put_u8(w, f.amm)?;
+                    put_u64(w, f.input)?;
+                    put_u64(w, f.output)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn read_from(r: &mut impl Read) -> Result<Self> {
+        let mut magic = [0u8; 4];
+        r.read_exact(&mut magic).context("Truncated trace header")?;
+        if &magic != TRACE_MAGIC {
+            bail!("Not a simulation trace (bad magic)");
+        }
+        let version = get_u8(r)?;
+        if version != TRACE_VERSION {
+            bail!("Unsupported trace version {} (expected {})", version, TRACE_VERSION);
+        }
+        let seed = get_u64(r)?;
+        let params = read_params(r)?;
+
+        let n_shocks = get_u32(r)? as usize;
+        let mut shocks = Vec::with_capacity(n_shocks);
+        for _ in 0..n_shocks {
+            let step = get_u64(r)? as usize;
+            let jump = get_f64(r)?;
+            shocks.push(ShockEvent { step, jump });
+        }
+
+        let n_steps = get_u32(r)? as usize;
+        let mut steps = Vec::with_capacity(n_steps);
+        for _ in 0..n_steps {
+            let fair_price = get_f64(r)?;
+            let arb_position = get_u32(r)?;
+            let n_orders = get_u32(r)? as usize;
+            let mut orders = Vec::with_capacity(n_orders);
+            for _ in 0..n_orders {
+                let is_buy = get_u8(r)? != 0;
+                let kind = order_kind_from_tag(get_u8(r)?)?;
+                let size_y = get_f64(r)?;
+                let limit = get_f64(r)?;
+                let n_fills = get_u8(r)? as usize;
+                let mut fills = Vec::with_capacity(n_fills);
+                for _ in 0..n_fills {
+                    fills.push(TraceFill { amm: get_u8(r)?, input: get_u64(r)?, output: get_u64(r)? });
+                }
+                orders.push(TraceOrder {
+                    order: RetailOrder {
+                        is_buy,
+                        size_y,
+                        kind,
+                        limit_price: if limit.is_nan() { None } else { Some(limit) },
+                    },
+                    fills,
+                });
+            }
+            steps.push(TraceStep { fair_price, arb_position, orders });
+        }
+
+        Ok(Self { seed, params, shocks, steps })
+    }
+}
+
+// ─── Encoding ─────────────────────────────────────────────────────────────────
+
+fn write_params(w: &mut impl Write, p: &MarketParams) -> Result<()> {
+    put_f64(w, p.sigma)?;
+    put_f64(w, p.lambda)?;
+    put_f64(w, p.order_size_mean)?;
+    put_u32(w, p.norm_fee_bps)?;
+    put_f64(w, p.norm_liquidity_mult)?;
+    let (tag, a) = match p.arrival_curve {
+        ArrivalCurve::Flat => (0, 0.0),
+        ArrivalCurve::Sinusoidal { amplitude } => (1, amplitude),
+        ArrivalCurve::UShaped { depth } => (2, depth),
+    };
+    put_u8(w, tag)?;
+    put_f64(w, a)?;
+    let (tag, a, b) = match p.clustering {
+        SizeClustering::Iid => (0, 0.0, 0.0),
+        SizeClustering::Ar1 { phi, regime_vol } => (1, phi, regime_vol),
+        SizeClustering::Hawkes { branching, decay } => (2, branching, decay),
+    };
+    put_u8(w, tag)?;
+    put_f64(w, a)?;
+    put_f64(w, b)?;
+    put_u8(w, p.extra_normalizers.len() as u8)?;
+    for n in &p.extra_normalizers {
+        put_u32(w, n.fee_bps)?;
+        put_f64(w, n.liquidity_mult)?;
+    }
+    Ok(())
+}
+
+fn read_params(r: &mut impl Read) -> Result<MarketParams> {
+    let sigma = get_f64(r)?;
+    let lambda = get_f64(r)?;
+    let order_size_mean = get_f64(r)?;
+    let norm_fee_bps = get_u32(r)?;
+    let norm_liquidity_mult = get_f64(r)?;
+    let (tag, a) = (get_u8(r)?, get_f64(r)?);
+    let arrival_curve = match tag {
+        0 => ArrivalCurve::Flat,
+        1 => ArrivalCurve::Sinusoidal { amplitude: a },
+        2 => ArrivalCurve::UShaped { depth: a },
+        t => bail!("Unknown arrival curve tag {}", t),
+    };
+    let (tag, a, b) = (get_u8(r)?, get_f64(r)?, get_f64(r)?);
+    let clustering = match tag {
+        0 => SizeClustering::Iid,
+        1 => SizeClustering::Ar1 { phi: a, regime_vol: b },
+        2 => SizeClustering::Hawkes { branching: a, decay: b },
+        t => bail!("Unknown clustering tag {}", t),
+    };
+    let n_extra = get_u8(r)? as usize;
+    let mut extra_normalizers = Vec::with_capacity(n_extra);
+    for _ in 0..n_extra {
+        extra_normalizers.push(NormalizerParams { fee_bps: get_u32(r)?, liquidity_mult: get_f64(r)? });
+    }
+    Ok(MarketParams {
+        sigma, lambda, order_size_mean, norm_fee_bps, norm_liquidity_mult, arrival_curve, clustering,
+        extra_normalizers,
+    })
+}
+
+fn order_kind_tag(kind: OrderKind) -> u8 {
+    match kind {
+        OrderKind::Market => 0,
+        OrderKind::TwapSlice => 1,
+        OrderKind::Rfq => 2,
+    }
+}
+
+fn order_kind_from_tag(tag: u8) -> Result<OrderKind> {
+    Ok(match tag {
+        0 => OrderKind::Market,
+        1 => OrderKind::TwapSlice,
+        2 => OrderKind::Rfq,
+        t => bail!("Unknown order kind tag {}", t),
+    })
+}
+
+fn put_u8(w: &mut impl Write, v: u8) -> Result<()> {
+    w.write_all(&[v])?;
+    Ok(())
+}
+fn put_u32(w: &mut impl Write, v: u32) -> Result<()> {
+    w.write_all(&v.to_le_bytes())?;
+    Ok(())
+}
+fn put_u64(w: &mut impl Write, v: u64) -> Result<()> {
+    w.write_all(&v.to_le_bytes())?;
+    Ok(())
+}
+fn put_f64(w: &mut impl Write, v: f64) -> Result<()> {
+    w.write_all(&v.to_le_bytes())?;
+    Ok(())
+}
+
+fn get_u8(r: &mut impl Read) -> Result<u8> {
+    let mut b = [0u8; 1];
+    r.read_exact(&mut b).context("Truncated trace")?;
+    Ok(b[0])
+}
+fn get_u32(r: &mut impl Read) -> Result<u32> {
+    let mut b = [0u8; 4];
+    r.read_exact(&mut b).context("Truncated trace")?;
+    Ok(u32::from_le_bytes(b))
+}
+fn get_u64(r: &mut impl Read) -> Result<u64> {
+    let mut b = [0u8; 8];
+    r.read_exact(&mut b).context("Truncated trace")?;
+    Ok(u64::from_le_bytes(b))
+}
+fn get_f64(r: &mut impl Read) -> Result<f64> {
+    let mut b = [0u8; 8];
+    r.read_exact(&mut b).context("Truncated trace")?;
+    Ok(f64::from_le_bytes(b))
+}
@@ -1,101 +1,43 @@
+use std::collections::VecDeque;
+
+use std::borrow::Cow;
+
+use serde::{Deserialize, Serialize};
+
+use crate::capital::{AllocatorKind, CapitalBound, CapitalFlows, InitialWeight, RebalanceMode, Schedule, ScoreComponents};
+use crate::runner::{CallBudget, QuoteGuard};
+use crate::market::{apply_cpamm_trade, cpamm_output, ArrivalCurve, DUST_RESERVE_FLOOR, SANITY_GRID_POINTS, ClusteringKind, ParamDynamics, Quantization};
+
 /// Scale factor: 1 unit = 1_000_000_000 (1e9)
 pub const SCALE: u64 = 1_000_000_000;
 pub const SCALE_F: f64 = 1_000_000_000.0;
 
+/// Julian year in seconds, the basis for annualizing (`SimConfig::step_dt_seconds`)
+pub const SECONDS_PER_YEAR: f64 = 365.25 * 24.0 * 3600.0;
+
 /// Maximum number of competing strategies (excluding the normalizer)
-pub const MAX_STRATEGIES: usize = 16;
-
-/// Per-strategy storage size in bytes (matches prop-amm-challenge)
-pub const STORAGE_SIZE: usize = 1024;
-
-// ─── Tag bytes sent to strategy programs ──────────────────────────────────────
-
-/// Compute swap quote (buy X = Y-in)
-pub const TAG_SWAP_BUY: u8 = 0;
-/// Compute swap quote (sell X = X-in)
-pub const TAG_SWAP_SELL: u8 = 1;
-/// After-swap hook (real trade executed)
-pub const TAG_AFTER_SWAP: u8 = 2;
-/// Metadata: return NAME bytes
-pub const TAG_GET_NAME: u8 = 3;
-/// Metadata: return MODEL_USED bytes
-pub const TAG_GET_MODEL: u8 = 4;
-/// Epoch boundary: called at the start of every new epoch with capital update
-pub const TAG_EPOCH_BOUNDARY: u8 = 5;
+pub const MAX_STRATEGIES: usize = crate::wire::MAX_COMPETITORS;
 
-// ─── Wire payloads ────────────────────────────────────────────────────────────
+/// Newest wire payload layout (`wire::ABI_VERSION`). Strategies export the layout
+/// they were built against as `__prop_amm_abi_version`, and loading fails unless it is
+/// within `MIN_ABI_VERSION..=ABI_VERSION`. Bump on any layout change; raise the minimum
+/// when the engine stops encoding an older layout.
+pub use crate::wire::ABI_VERSION;
+/// Oldest payload layout the engine still speaks
+pub const MIN_ABI_VERSION: u32 = 1;
 
-/// Payload sent for TAG_SWAP_BUY / TAG_SWAP_SELL  (matches original, extended by storage)
-#[repr(C, packed)]
-pub struct ComputeSwapPayload {
-    pub tag: u8,         // 0 or 1
-    pub input_amount: u64,
-    pub reserve_x: u64,
-    pub reserve_y: u64,
-    pub storage: [u8; STORAGE_SIZE],
-}
-
-/// Payload sent for TAG_AFTER_SWAP — enriched vs. original to expose competitive context.
-///
-/// Layout (byte offsets):
-///   0   tag             u8
-///   1   side            u8   (0=buy X, 1=sell X)
-///   2   input_amount    u64
-///  10   output_amount   u64
-///  18   reserve_x       u64  (post-trade)
-///  26   reserve_y       u64
-///  34   sim_step        u64  (global step within simulation)
-///  42   epoch_step      u32  (step within current epoch, 0-based)
-///  46   epoch_number    u32  (epoch index, 0-based)
-///  50   n_strategies    u8   (total number of competing strategies incl. normalizer)
-///  51   strategy_index  u8   (this strategy's index)
-///  52   flow_captured   f32  (fraction of this retail order routed here, 0.0-1.0)
-///  56   capital_weight  f32  (this strategy's fraction of total protocol capital)
-///  60   [f32; 8]        competing_spot_prices (spot price of each other AMM, NaN if unused)
-///  92   storage         [u8; STORAGE_SIZE]
-#[repr(C, packed)]
-pub struct AfterSwapPayload {
-    pub tag: u8,
-    pub side: u8,
-    pub input_amount: u64,
-    pub output_amount: u64,
-    pub reserve_x: u64,
-    pub reserve_y: u64,
-    pub sim_step: u64,
-    pub epoch_step: u32,
-    pub epoch_number: u32,
-    pub n_strategies: u8,
-    pub strategy_index: u8,
-    pub flow_captured: f32,
-    pub capital_weight: f32,
-    pub competing_spot_prices: [f32; 8],
-    pub storage: [u8; STORAGE_SIZE],
-}
+/// Storage slot (8 bytes, f64) a strategy writes its requested X value share to, when
+/// `SimConfig::strategy_rebalancing` is on; consumed and zeroed at the epoch boundary
+pub const REBALANCE_REQUEST_SLOT: usize = STORAGE_SIZE / 8 - 1;
 
-/// Payload sent for TAG_EPOCH_BOUNDARY — notifies strategy of new capital allocation.
-///
-/// Layout:
-///   0   tag                u8
-///   1   epoch_number       u32
-///   5   new_reserve_x      u64
-///  13   new_reserve_y      u64
-///  21   epoch_edge         f64   (edge earned in just-completed epoch)
-///  29   cumulative_edge    f64   (total edge across all epochs so far)
-///  37   capital_weight     f32   (new fraction of total protocol capital)
-///  41   storage            [u8; STORAGE_SIZE]  (read-write, persists)
-#[repr(C, packed)]
-pub struct EpochBoundaryPayload {
-    pub tag: u8,
-    pub epoch_number: u32,
-    pub new_reserve_x: u64,
-    pub new_reserve_y: u64,
-    pub epoch_edge: f64,
-    pub cumulative_edge: f64,
-    pub capital_weight: f32,
-    pub storage: [u8; STORAGE_SIZE],
-}
+// ─── Wire payloads ────────────────────────────────────────────────────────────
 
-// ─── Engine-side state ────────────────────────────────────────────────────────
+// Tags, layouts and encoders live in `wire`, shared with the SDK.
+pub use crate::wire::{
+    AfterSwapPayload, ComputeSwapPayload, EpochBoundaryPayload, ShockNewsPayload, StepDigestPayload, STORAGE_SIZE,
+    TAG_AFTER_SWAP, TAG_EPOCH_BOUNDARY, TAG_GET_ABI_VERSION, TAG_GET_MODEL, TAG_GET_NAME, TAG_SHOCK_NEWS,
+    TAG_STEP_DIGEST, TAG_SWAP_BUY, TAG_SWAP_SELL,
+};
 
 /// Live state of a single AMM instance in the engine.
 #[derive(Clone, Debug)]
@@ -108,6 +50,60 @@ pub struct AmmState {
     pub cumulative_edge: f64,
     pub epoch_edge: f64,
     pub epoch_trade_count: u64,
+    /// Retail fills sandwiched by the MEV attacker this epoch
+    pub epoch_sandwiches: u64,
+    /// RFQ orders won this epoch, and the edge earned on them
+    pub epoch_rfq_wins: u64,
+    pub epoch_rfq_edge: f64,
+    /// Edge split by counterparty this epoch: retail fills vs. informed flow
+    /// (arbitrage and sandwich legs). The two sum to `epoch_edge`.
+    pub epoch_retail_edge: f64,
+    pub epoch_arb_edge: f64,
+    /// Gross fee revenue this epoch (Y): implied fee × notional at fair, where the implied
+    /// fee is the output shortfall versus a zero-fee constant-product quote
+    pub epoch_fee_revenue: f64,
+    pub cumulative_fee_revenue: f64,
+    /// Sum of squared per-trade edge this epoch (with `epoch_edge` and
+    /// `epoch_trade_count`, gives the per-trade edge mean and variance)
+    pub epoch_trade_edge_sq: f64,
+    /// Notional (Y at fair) filled this epoch where the trader bought / sold X
+    pub epoch_buy_volume: f64,
+    pub epoch_sell_volume: f64,
+    /// Fills this epoch by counterparty: retail vs. informed flow
+    pub epoch_retail_trades: u64,
+    pub epoch_arb_trades: u64,
+    /// Sum over this epoch's retail fills of the share of the order routed here
+    pub epoch_flow_share_sum: f64,
+    /// Largest single fill absorbed this epoch (notional, Y at fair)
+    pub epoch_largest_trade: f64,
+    /// Fees held outside reserves under `SimConfig::fees_outside_reserves`, in the token
+    /// each was paid in; swept into the capital pool at the next rebalance
+    pub fee_account_x: u64,
+    pub fee_account_y: u64,
+    /// Mark-to-market PnL of held inventory from fair-price moves (Y), this epoch / run
+    pub epoch_inventory_pnl: f64,
+    pub cumulative_inventory_pnl: f64,
+    /// Retail notional filled over the run (Y-equivalent at fair)
+    pub cumulative_retail_volume: f64,
+    /// Edge valued in X at each accrual's fair price (`cumulative_edge` is the Y valuation)
+    pub cumulative_edge_x: f64,
+    /// Net X and Y received from trades over the run (signed token legs, unvalued)
+    pub cumulative_leg_x: f64,
+    pub cumulative_leg_y: f64,
+    /// X held at the start of the epoch, and the PnL of passively holding it
+    pub epoch_hold_x: f64,
+    pub epoch_hold_pnl: f64,
+    /// Step at which the kill switch froze this AMM (excluded from routing and arb
+    /// until the next rebalance)
+    pub frozen_at: Option<usize>,
+    /// Consecutive steps the spot has sat outside the circuit-breaker band, and whether
+    /// the breaker currently halts routing to this AMM
+    pub band_breach_steps: usize,
+    pub halted: bool,
+    /// Storage latency in trades (`SimConfig::storage_latency_trades`), and the storage
+    /// versions after each of the last k trades plus the one `compute_swap` sees, oldest first
+    pub storage_latency: usize,
+    pub storage_history: VecDeque<[u8; STORAGE_SIZE]>,
 
     // Capital tracking
     pub capital_weight: f64,   // fraction of total capital allocated here
@@ -126,6 +122,35 @@ impl AmmState {
             cumulative_edge: 0.0,
             epoch_edge: 0.0,
             epoch_trade_count: 0,
+            epoch_sandwiches: 0,
+            epoch_rfq_wins: 0,
+            epoch_rfq_edge: 0.0,
+            epoch_retail_edge: 0.0,
+            epoch_arb_edge: 0.0,
+            epoch_fee_revenue: 0.0,
+            cumulative_fee_revenue: 0.0,
+            epoch_trade_edge_sq: 0.0,
+            epoch_buy_volume: 0.0,
+            epoch_sell_volume: 0.0,
+            epoch_retail_trades: 0,
+            epoch_arb_trades: 0,
+            epoch_flow_share_sum: 0.0,
+            epoch_largest_trade: 0.0,
+            fee_account_x: 0,
+            fee_account_y: 0,
+            epoch_inventory_pnl: 0.0,
+            cumulative_inventory_pnl: 0.0,
+            cumulative_retail_volume: 0.0,
+            cumulative_edge_x: 0.0,
+            cumulative_leg_x: 0.0,
+            cumulative_leg_y: 0.0,
+            epoch_hold_x: reserve_x as f64 / SCALE_F,
+            epoch_hold_pnl: 0.0,
+            frozen_at: None,
+            band_breach_steps: 0,
+            halted: false,
+            storage_latency: 0,
+            storage_history: VecDeque::new(),
             capital_weight: 1.0, // will be normalized across N strategies after init
             strategy_index: idx,
             name: name.to_string(),
@@ -138,11 +163,49 @@ impl AmmState {
         self.reserve_y as f64 / self.reserve_x as f64
     }
 
+    /// Delay storage visibility to `compute_swap` by `trades` trades (0 = instant).
+    pub fn set_storage_latency(&mut self, trades: usize) {
+        self.storage_latency = trades;
+        self.storage_history.clear();
+        if trades > 0 {
+            self.storage_history.push_back(self.storage);
+        }
+    }
+
+    /// Storage as visible to `compute_swap`: the live storage, or under latency the
+    /// version left by the trade k trades ago.
+    #[inline]
+    pub fn quote_storage(&self) -> &[u8; STORAGE_SIZE] {
+        self.storage_history.front().unwrap_or(&self.storage)
+    }
+
+    /// Record the storage left by a trade's `after_swap`, retiring the oldest visible
+    /// version once k newer ones exist.
+    pub fn publish_storage(&mut self) {
+        if self.storage_latency == 0 {
+            return;
+        }
+        self.storage_history.push_back(self.storage);
+        while self.storage_history.len() > self.storage_latency + 1 {
+            self.storage_history.pop_front();
+        }
+    }
+
+    /// Pool value in Y at `fair_price`: reserves plus any fees held outside them.
+    #[inline]
+    pub fn value_at(&self, fair_price: f64) -> f64 {
+        ((self.reserve_x + self.fee_account_x) as f64 * fair_price
+            + (self.reserve_y + self.fee_account_y) as f64)
+            / SCALE_F
+    }
+
     /// Accrue edge from a trade, given the fair price at execution time.
     /// For AMM sells X (receives X, pays Y): edge = amountX * fair - amountY
     /// For AMM buys X  (receives Y, pays X): edge = amountY - amountX * fair
+    ///
+    /// Returns the edge accrued.
     #[inline]
-    pub fn accrue_edge(&mut self, amount_x: u64, amount_y: u64, is_buy: bool, fair_price: f64) {
+    pub fn accrue_edge(&mut self, amount_x: u64, amount_y: u64, is_buy: bool, fair_price: f64) -> f64 {
         let ax = amount_x as f64 / SCALE_F;
         let ay = amount_y as f64 / SCALE_F;
         let edge = if is_buy {
@@ -155,23 +218,220 @@ impl AmmState {
         self.cumulative_edge += edge;
         self.epoch_edge += edge;
         self.epoch_trade_count += 1;
+        self.epoch_trade_edge_sq += edge * edge;
+        self.cumulative_edge_x += edge / fair_price;
+        let (leg_x, leg_y) = if is_buy { (-ax, ay) } else { (ax, -ay) };
+        self.cumulative_leg_x += leg_x;
+        self.cumulative_leg_y += leg_y;
+        edge
+    }
+
+    /// Run edge in `numeraire`, each accrual valued at its own fair price.
+    #[inline]
+    pub fn edge_in(&self, numeraire: Numeraire) -> f64 {
+        match numeraire {
+            Numeraire::Y => self.cumulative_edge,
+            Numeraire::X => self.cumulative_edge_x,
+        }
+    }
+
+    /// Net trade legs valued in `numeraire` at a single marking price (e.g. the final fair).
+    #[inline]
+    pub fn marked_legs(&self, numeraire: Numeraire, fair_price: f64) -> f64 {
+        match numeraire {
+            Numeraire::Y => self.cumulative_leg_x * fair_price + self.cumulative_leg_y,
+            Numeraire::X => self.cumulative_leg_x + self.cumulative_leg_y / fair_price,
+        }
+    }
+
+    /// Account for one fill at the current (pre-trade) reserves: accrue edge, attribute
+    /// it to retail or informed flow, and accrue the implied fee revenue.
+    ///
+    /// `is_buy`: the trader buys X (Y in, X out). Returns the implied fee as a fraction
+    /// of output (0.0 when it cannot be inferred).
+    pub fn record_fill(&mut self, is_buy: bool, input: u64, output: u64, fair_price: f64, informed: bool) -> f64 {
+        let (reserve_in, reserve_out) = if is_buy {
+            (self.reserve_y, self.reserve_x)
+        } else {
+            (self.reserve_x, self.reserve_y)
+        };
+        let notional_y = input as f64 / SCALE_F * if is_buy { 1.0 } else { fair_price };
+        let no_fee_out = cpamm_output(input, reserve_in, reserve_out, 0);
+        let implied_fee = if no_fee_out > 0 { 1.0 - output as f64 / no_fee_out as f64 } else { 0.0 };
+        self.epoch_fee_revenue += implied_fee * notional_y;
+        self.cumulative_fee_revenue += implied_fee * notional_y;
+
+        let edge = self.accrue_edge(
+            if is_buy { output } else { input },
+            if is_buy { input } else { output },
+            is_buy,
+            fair_price,
+        );
+        if is_buy { self.epoch_buy_volume += notional_y; } else { self.epoch_sell_volume += notional_y; }
+        self.epoch_largest_trade = self.epoch_largest_trade.max(notional_y);
+        if informed {
+            self.epoch_arb_edge += edge;
+            self.epoch_arb_trades += 1;
+        } else {
+            self.epoch_retail_edge += edge;
+            self.epoch_retail_trades += 1;
+            self.cumulative_retail_volume += notional_y;
+        }
+        implied_fee
+    }
+
+    /// Apply a fill to reserves. With `fees_outside`, the implied fee share of the input
+    /// is credited to the fee account instead of compounding into reserves.
+    pub fn settle_fill(&mut self, is_buy: bool, input: u64, output: u64, implied_fee: f64, fees_outside: bool) {
+        let mut into_reserves = input;
+        if fees_outside && implied_fee > 0.0 {
+            let fee_in = (input as f64 * implied_fee.min(1.0)) as u64;
+            into_reserves -= fee_in;
+            if is_buy { self.fee_account_y += fee_in; } else { self.fee_account_x += fee_in; }
+        }
+        apply_cpamm_trade(&mut self.reserve_x, &mut self.reserve_y, is_buy, into_reserves, output);
+    }
+
+    /// Mark reserves to a fair-price move. Trades at a fixed fair price change pool value
+    /// by exactly their edge, so value change = edge + inventory PnL between rebalances.
+    #[inline]
+    pub fn mark_to_market(&mut self, old_fair: f64, new_fair: f64) {
+        let dp = new_fair - old_fair;
+        let pnl = self.reserve_x as f64 / SCALE_F * dp;
+        self.epoch_inventory_pnl += pnl;
+        self.cumulative_inventory_pnl += pnl;
+        self.epoch_hold_pnl += self.epoch_hold_x * dp;
+    }
+
+    /// Share of pool value held in X at the fair price.
+    #[inline]
+    pub fn inventory_share_x(&self, fair_price: f64) -> f64 {
+        let value_x = self.reserve_x as f64 * fair_price;
+        let total = value_x + self.reserve_y as f64;
+        if total > 0.0 { value_x / total } else { 0.0 }
+    }
+
+    /// Whether an inventory-skew limit bars this AMM from taking flow on one side:
+    /// trader buys (AMM pays out X) are barred once the X share is below `min_share`,
+    /// trader sells (AMM pays out Y) once the Y share is.
+    #[inline]
+    pub fn skew_blocks(&self, is_buy: bool, fair_price: f64, min_share: f64) -> bool {
+        let share_x = self.inventory_share_x(fair_price);
+        if is_buy { share_x < min_share } else { 1.0 - share_x < min_share }
     }
 }
 
 /// Per-epoch summary used for capital allocation decisions.
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, Serialize)]
 pub struct EpochSummary {
     pub epoch_number: u32,
     pub edge: f64,
     pub trade_count: u64,
+    /// Notional (Y at fair) filled where the trader bought / sold X
+    pub buy_volume: f64,
+    pub sell_volume: f64,
+    /// Fills by counterparty: retail orders vs. informed flow (arbitrage and sandwich
+    /// legs); the two sum to `trade_count`
+    pub retail_trade_count: u64,
+    pub arb_trade_count: u64,
+    /// Mean share of each retail order routed here, over the retail fills this AMM took
+    pub mean_flow_share: f64,
+    /// Largest single fill absorbed (notional, Y at fair)
+    pub largest_trade: f64,
+    /// Edge from informed flow (arbitrage and sandwich legs); typically negative
     pub arb_losses: f64,
+    /// Edge from retail fills; typically positive
     pub retail_gains: f64,
+    /// Gross fee revenue (Y) before adverse selection
+    pub fee_revenue: f64,
     /// Risk-adjusted score = edge - lambda * max(0, -edge)
     pub risk_adjusted_score: f64,
+    /// Retail fills on this AMM that were sandwiched by the MEV attacker
+    pub sandwich_count: u64,
+    /// RFQ orders issued to the pool this epoch (pool-level)
+    pub rfq_requests: u64,
+    /// RFQ orders this AMM won outright
+    pub rfq_wins: u64,
+    /// Edge earned on won RFQ orders (included in `edge`)
+    pub rfq_edge: f64,
+    /// Inventory PnL from fair-price moves (Y); `edge + inventory_pnl` is the pool's
+    /// mark-to-market value change over the epoch
+    pub inventory_pnl: f64,
+    /// Passive-holding PnL of the epoch-start inventory minus `inventory_pnl` (Y):
+    /// value lost to rebalancing against the price path, before edge
+    pub impermanent_loss: f64,
+    /// Step at which the kill switch froze this AMM this epoch, if it did
+    pub killed_at_step: Option<u64>,
+    /// Protocol take on this epoch's fee revenue (Y), deducted from capital at rebalance
+    pub protocol_fee: f64,
+    /// Cost (Y) of the inventory rebalance the strategy requested at this boundary
+    pub rebalance_cost: f64,
+    /// Mean and (population) variance of per-trade edge this epoch
+    pub trade_edge_mean: f64,
+    pub trade_edge_var: f64,
+    /// |Δ capital weight| at this rebalance, and the turnover cost it paid (Y)
+    pub turnover: f64,
+    pub turnover_cost: f64,
+    /// External deposit (> 0) or withdrawal (< 0) credited to this pool at the boundary (Y)
+    pub capital_flow: f64,
+    /// Value of X (Y at fair) the pool sold to re-center under `RebalanceMode::
+    /// InventoryNeutral` (negative = bought), and the cost paid on it
+    pub inventory_transfer: f64,
+    pub recenter_cost: f64,
+    /// What the allocator scored this strategy on at this rebalance
+    pub score_components: ScoreComponents,
+    /// Weight held this epoch, the allocator's target (after per-strategy bounds), and
+    /// the weight actually set (after the turnover limit)
+    pub prior_weight: f64,
+    pub target_weight: f64,
+    pub new_weight: f64,
+}
+
+/// How a noise-routed retail order is split when it bypasses the optimal router.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NaiveRouting {
+    /// Entire order goes to a single AMM chosen uniformly at random
+    RandomSingle,
+    /// Order is split across AMMs proportional to TVL (2 · reserve_y)
+    ProportionalToTvl,
+}
+
+/// Token in which edge is reported.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Numeraire {
+    /// Y per unit (original behavior)
+    #[default]
+    Y,
+    /// X per unit: each accrual divided by its fair price
+    X,
+}
+
+/// Order of arbitrage vs. retail flow within a single step.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StepOrdering {
+    /// Arbitrageurs trade on the new fair price before any retail order (original behavior)
+    ArbFirst,
+    /// Retail orders hit stale quotes before arbitrageurs correct them
+    RetailFirst,
+    /// Arb pass and retail orders are shuffled with the simulation's seeded RNG
+    RandomInterleave,
+}
+
+/// A discrete, instantaneous fair-price jump.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ShockEvent {
+    /// Step at which the jump is applied (after that step's GBM move)
+    pub step: usize,
+    /// Signed fractional jump: fair *= 1 + jump (e.g. -0.05 = 5% crash)
+    pub jump: f64,
 }
 
 /// Configuration for a multi-epoch simulation run.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
 pub struct SimConfig {
     /// Total simulation steps
     pub total_steps: usize,
@@ -183,14 +443,196 @@ pub struct SimConfig {
     pub base_reserve_x: u64,
     /// Initial Y reserves per AMM
     pub base_reserve_y: u64,
+    /// Initial fair price (Y per X); strategy and normalizer pools start priced at it.
+    /// Defaults to `base_reserve_y / base_reserve_x`
+    pub initial_price: Option<f64>,
+    /// Share of each strategy pool's initial value (at the initial price) held in X;
+    /// away from 0.5 the pool's spot starts off fair and is arbed on the first step
+    pub initial_x_share: f64,
+    /// Starting capital weights for named strategies, for seeded tournaments where
+    /// incumbents start larger; pools are sized by weight (equal when empty)
+    pub initial_weights: Vec<InitialWeight>,
     /// Risk-aversion coefficient for capital allocation (CVaR penalty weight)
     pub lambda: f64,
     /// Minimum capital weight any strategy can hold (prevents starvation)
     pub min_capital_weight: f64,
+    /// Per-strategy weight floors/ceilings applied on top of the allocator's weights
+    pub capital_bounds: Vec<CapitalBound>,
     /// Temperature for softmax capital allocation (higher = more uniform)
     pub softmax_temperature: f64,
+    /// Rule that turns epoch summaries into capital weights (see `capital`)
+    pub allocator: AllocatorKind,
+    /// How `softmax_temperature` and `lambda` evolve across epochs (e.g. start exploratory,
+    /// converge to winner-take-most)
+    pub temperature_schedule: Schedule,
+    pub lambda_schedule: Schedule,
+    /// Debug mode: audit every rebalance at the fair price and record those whose capital
+    /// drifts beyond `conservation_tolerance` (relative) in `SimResult::conservation_violations`
+    pub assert_invariants: bool,
+    pub conservation_tolerance: f64,
+    /// Whether pools keep their own spot or are re-centered at fair when capital moves
+    pub rebalance_mode: RebalanceMode,
+    /// Largest capital-weight change any strategy may see at one rebalance (1.0 = unlimited)
+    pub max_rebalance_delta: f64,
+    /// Cost of reallocating capital, in bps of each strategy's moved capital
+    pub turnover_cost_bps: u32,
+    /// External deposits/withdrawals applied at epoch boundaries, split by capital weight
+    pub capital_flows: CapitalFlows,
     /// Minimum arb profit floor (in Y, unscaled) to trigger an arb trade
     pub arb_profit_floor: f64,
+    /// Fraction of retail orders routed naively instead of optimally (0.0 = all optimal)
+    pub router_noise: f64,
+    /// Naive routing rule applied to the `router_noise` fraction of orders
+    pub naive_routing: NaiveRouting,
+    /// Route optimally on output curves batch-quoted at this many sizes per AMM per
+    /// order (`route_order_on_curves`) instead of hundreds of single quotes (0 = exact)
+    pub batch_quote_points: usize,
+    /// Reserve (scaled, either side) below which the optimal router neither quotes nor
+    /// sends flow to a pool
+    pub router_reserve_floor: u64,
+    /// Quote every AMM on a coarse grid (`market::SANITY_GRID_POINTS` extra calls) before
+    /// each routed order and arb search, and keep AMMs whose quotes are non-monotone or
+    /// wildly non-concave out of that order; batch-quoted routing always checks its curves
+    pub degenerate_quote_guard: bool,
+    /// Price tick and lot size imposed on every quote and fill, strategies and
+    /// normalizers alike (off by default)
+    pub quantization: Quantization,
+    /// Aggregator take-rate skimmed off each retail order before it reaches any AMM (bps)
+    pub routing_fee_bps: u32,
+    /// Probability that a fresh retail order becomes a TWAP/iceberg parent (0.0 = disabled)
+    pub twap_prob: f64,
+    /// Number of consecutive steps a TWAP parent is sliced over
+    pub twap_slices: usize,
+    /// Parent order size as a multiple of the sampled retail order size
+    pub twap_size_mult: f64,
+    /// Intra-step sequencing of the arb pass and retail orders
+    pub step_ordering: StepOrdering,
+    /// Fraction of retail orders carrying a limit price (0.0 = all market orders)
+    pub limit_order_prob: f64,
+    /// Mean of the exponential limit tolerance around fair (0.005 = 50 bps)
+    pub limit_tolerance_mean: f64,
+    /// Enable the MEV sandwich attacker on large retail orders
+    pub sandwich_enabled: bool,
+    /// Minimum retail order size (Y, unscaled) the attacker will sandwich
+    pub sandwich_min_order_y: f64,
+    /// Slippage the victim tolerates vs. its routed quote; bounds the attacker's front-run
+    pub sandwich_victim_tolerance: f64,
+    /// Shocks applied at fixed steps, in addition to random ones
+    pub shock_schedule: Vec<ShockEvent>,
+    /// Per-step probability of a random shock (0.0 = none)
+    pub shock_prob: f64,
+    /// Absolute size of random shocks; direction is a fair coin flip
+    pub shock_size: f64,
+    /// Announce each shock to strategies one step early via TAG_SHOCK_NEWS
+    pub shock_news: bool,
+    /// Intraday shape of the retail arrival rate over each epoch
+    pub arrival_curve: ArrivalCurve,
+    /// Retail volume elasticity to pool price improvement vs. the normalizer (0.0 = fixed flow)
+    pub demand_elasticity: f64,
+    /// Cap on the elastic volume multiplier
+    pub demand_max_mult: f64,
+    /// Order size/arrival clustering model, parameters sampled per simulation
+    pub size_clustering: ClusteringKind,
+    /// Fraction of one-shot retail orders sent as winner-take-all RFQs
+    pub rfq_prob: f64,
+    /// Capture every trade into `SimResult::trades` (see `events`)
+    pub record_trades: bool,
+    /// Record every AMM's state every K steps into `SimResult::snapshots` (0 = off)
+    pub snapshot_stride: usize,
+    /// Include each strategy's storage in every snapshot (see `events::write_step_log`)
+    pub snapshot_storage: bool,
+    /// Storage slots (u64 words) read into `TradeEvent::slots` after every strategy fill
+    /// and its after_swap, when `record_trades` is set (see `events::parse_slots`)
+    pub trade_slots: Vec<usize>,
+    /// Record a replayable trace into `SimResult::trace` (see `sim::replay_simulation`)
+    pub record_trace: bool,
+    /// Kill switch: a strategy AMM whose X or Y reserve falls below this fraction of
+    /// `base_reserve_x`/`base_reserve_y` is frozen until the next rebalance (0.0 = off)
+    pub kill_reserve_floor: f64,
+    /// Inventory-skew risk limit: once X (or Y) falls below this share of a strategy AMM's
+    /// value at fair, retail flow that would deplete it further is not routed there (0.0 = off)
+    pub min_inventory_share: f64,
+    /// Token in which `StrategyResult::final_edge` and normalizer edges are reported;
+    /// capital allocation always scores Y edge
+    pub numeraire: Numeraire,
+    /// Credit the implied fee share of each strategy fill to a separate fee account
+    /// (swept into capital at rebalance) instead of compounding it into reserves
+    pub fees_outside_reserves: bool,
+    /// Protocol take-rate: fraction of each strategy's epoch fee revenue removed from its
+    /// capital before rebalancing (0.0 = off)
+    pub protocol_fee_share: f64,
+    /// Apply the target X value share a strategy writes to `REBALANCE_REQUEST_SLOT` during
+    /// its epoch hook, swapping at fair price
+    pub strategy_rebalancing: bool,
+    /// Cost of a strategy-requested or inventory-neutral rebalance, in bps of the notional
+    /// swapped
+    pub rebalance_cost_bps: u32,
+    /// Circuit breaker: relative spot-vs-fair deviation beyond which a strategy AMM counts
+    /// as out of band (0.0 = off)
+    pub circuit_breaker_band: f64,
+    /// Consecutive out-of-band steps before routing to the AMM halts; it resumes on the
+    /// first step back inside the band
+    pub circuit_breaker_steps: usize,
+    /// Storage written by `after_swap` becomes visible to `compute_swap` only after this
+    /// many further trades on the same AMM, modelling settlement lag (0 = instant)
+    pub storage_latency_trades: usize,
+    /// Show every arbitrage after_swap the competitor reserves from before the arb pass,
+    /// instead of the reserves left by the arbs earlier in index order
+    pub simultaneous_arb: bool,
+    /// Number of passive CPAMM normalizer venues, each with independently sampled fee
+    /// and liquidity (≥ 1; extras are sampled only when > 1)
+    pub n_normalizers: usize,
+    /// Whether σ, λ and the order-size mean change regime at each epoch boundary
+    pub param_dynamics: ParamDynamics,
+    /// Wall-clock length of one step in seconds: the time scale for `annual_volatility`,
+    /// `orders_per_minute` and annualized results. Unset, the market is in per-step units
+    /// only and nothing is annualized
+    pub step_dt_seconds: Option<f64>,
+    /// Pin σ to this annualized volatility (e.g. 0.8 = 80%) instead of sampling it
+    pub annual_volatility: Option<f64>,
+    /// Pin λ to this retail arrival rate instead of sampling it
+    pub orders_per_minute: Option<f64>,
+    /// Pin the per-step σ instead of sampling it (overrides `annual_volatility`)
+    pub sigma_per_step: Option<f64>,
+    /// Pin the retail arrival rate λ in orders per step (overrides `orders_per_minute`)
+    pub orders_per_step: Option<f64>,
+    /// Pin the primary normalizer's fee (bps)
+    pub norm_fee_bps: Option<u32>,
+    /// Pin the primary normalizer's liquidity multiplier
+    pub norm_liquidity_mult: Option<f64>,
+    /// Self-play: each strategy artifact is instantiated this many times as independent
+    /// competitors, named `"<name> #k"` (1 = off)
+    pub self_play_copies: usize,
+    /// Per-call compute budget applied to every strategy the engine loads, and the
+    /// penalty for exceeding it (unlimited by default)
+    pub call_budget: CallBudget,
+    /// How strategy quotes that would pay out the whole output reserve are handled
+    /// (rejected, without disqualification, by default)
+    pub quote_guard: QuoteGuard,
+    /// Run each native strategy in its own worker process (`Runtime::Subprocess`), so a
+    /// segfault or abort disables that strategy instead of killing the run
+    pub isolate_strategies: bool,
+    /// Hardened mode: fence the payload and storage handed to each native strategy with
+    /// canaries and disqualify a strategy that writes outside its 1024-byte storage
+    /// (see `SimResult::tamper_events`)
+    pub harden_storage: bool,
+    /// Collect what native strategies write with the SDK's `log!` (the optional
+    /// `__prop_amm_log` export) into `SimResult::strategy_logs`
+    pub strategy_logs: bool,
+    /// Time strategy calls and the engine's routing, arbitrage and rebalancing phases
+    /// into `SimResult::profile` (batch runners are switched on to match; others need
+    /// `StrategyRunner::set_profiling`)
+    pub profile: bool,
+    /// Built-in reference opponents (`reference::names`) appended after the loaded
+    /// strategies in every batch simulation (`run_batch`, round-robin, leave-one-out)
+    pub opponents: Vec<String>,
+    /// Tournament mode: refuse to load a native strategy that imports network,
+    /// filesystem, thread or clock functions or carries oversized statics
+    /// (`runner::audit_artifact`)
+    pub audit_artifacts: bool,
+    /// Fairness flag: send every strategy the same pool-wide TAG_STEP_DIGEST each step,
+    /// so venues that win no flow are not blind to the market
+    pub step_digest: bool,
 }
 
 impl Default for SimConfig {
@@ -201,10 +643,276 @@ impl Default for SimConfig {
             seed: 0,
             base_reserve_x: 100 * SCALE,  // 100 X
             base_reserve_y: 10_000 * SCALE, // 10,000 Y  → spot = 100
+            initial_price: None,
+            initial_x_share: 0.5,
+            initial_weights: Vec::new(),
             lambda: 2.0,
             min_capital_weight: 0.02,  // 2% minimum allocation
+            capital_bounds: Vec::new(),
             softmax_temperature: 1.0,
+            allocator: AllocatorKind::Softmax,
+            temperature_schedule: Schedule::Constant,
+            lambda_schedule: Schedule::Constant,
+            assert_invariants: false,
+            conservation_tolerance: 1e-6,
+            rebalance_mode: RebalanceMode::PreserveSpot,
+            max_rebalance_delta: 1.0,
+            turnover_cost_bps: 0,
+            capital_flows: CapitalFlows::None,
             arb_profit_floor: 0.01,
+            router_noise: 0.0,
+            naive_routing: NaiveRouting::RandomSingle,
+            batch_quote_points: 0,
+            router_reserve_floor: DUST_RESERVE_FLOOR,
+            degenerate_quote_guard: false,
+            quantization: Quantization::default(),
+            routing_fee_bps: 0,
+            twap_prob: 0.0,
+            twap_slices: 10,
+            twap_size_mult: 10.0,
+            step_ordering: StepOrdering::ArbFirst,
+            limit_order_prob: 0.0,
+            limit_tolerance_mean: 0.005,
+            sandwich_enabled: false,
+            sandwich_min_order_y: 50.0,
+            sandwich_victim_tolerance: 0.01,
+            shock_schedule: Vec::new(),
+            shock_prob: 0.0,
+            shock_size: 0.05,
+            shock_news: false,
+            arrival_curve: ArrivalCurve::Flat,
+            demand_elasticity: 0.0,
+            demand_max_mult: 3.0,
+            size_clustering: ClusteringKind::Off,
+            rfq_prob: 0.0,
+            record_trades: false,
+            snapshot_stride: 0,
+            snapshot_storage: false,
+            trade_slots: Vec::new(),
+            record_trace: false,
+            kill_reserve_floor: 0.0,
+            min_inventory_share: 0.0,
+            numeraire: Numeraire::Y,
+            fees_outside_reserves: false,
+            protocol_fee_share: 0.0,
+            strategy_rebalancing: false,
+            rebalance_cost_bps: 10,
+            circuit_breaker_band: 0.0,
+            circuit_breaker_steps: 10,
+            storage_latency_trades: 0,
+            simultaneous_arb: false,
+            n_normalizers: 1,
+            param_dynamics: ParamDynamics::Fixed,
+            step_dt_seconds: None,
+            annual_volatility: None,
+            orders_per_minute: None,
+            sigma_per_step: None,
+            orders_per_step: None,
+            norm_fee_bps: None,
+            norm_liquidity_mult: None,
+            self_play_copies: 1,
+            call_budget: CallBudget::default(),
+            quote_guard: QuoteGuard::default(),
+            isolate_strategies: false,
+            harden_storage: false,
+            strategy_logs: false,
+            profile: false,
+            opponents: Vec::new(),
+            audit_artifacts: false,
+            step_digest: false,
+        }
+    }
+}
+
+impl SimConfig {
+    /// This config with `softmax_temperature` and `lambda` taken from their schedules at
+    /// the rebalance closing `epoch`; borrowed unchanged when both are constant.
+    pub fn scheduled_for(&self, epoch: u32) -> Cow<'_, SimConfig> {
+        if self.temperature_schedule == Schedule::Constant && self.lambda_schedule == Schedule::Constant {
+            return Cow::Borrowed(self);
+        }
+        Cow::Owned(SimConfig {
+            softmax_temperature: self.temperature_schedule.value(self.softmax_temperature, epoch),
+            lambda: self.lambda_schedule.value(self.lambda, epoch),
+            ..self.clone()
+        })
+    }
+
+    /// Fair price at step 0.
+    pub fn initial_fair_price(&self) -> f64 {
+        self.initial_price.unwrap_or(self.base_reserve_y as f64 / self.base_reserve_x as f64)
+    }
+
+    /// Reserves of a strategy pool holding `scale` × the base capital, split per
+    /// `initial_x_share` at `initial_fair_price`.
+    pub fn initial_reserves(&self, scale: f64) -> (u64, u64) {
+        let x_value = 2.0 * self.initial_x_share;
+        let rx = match self.initial_price {
+            None => self.base_reserve_x as f64 * x_value,
+            Some(price) => self.base_reserve_y as f64 * x_value / price,
+        };
+        let ry = self.base_reserve_y as f64 * (2.0 - x_value);
+        ((rx * scale) as u64, (ry * scale) as u64)
+    }
+
+    /// Grid size for the router's and arb search's quote sanity check (0 = off).
+    pub fn sanity_points(&self) -> usize {
+        if self.degenerate_quote_guard { SANITY_GRID_POINTS } else { 0 }
+    }
+
+    /// Steps in a year of `step_dt_seconds` steps. The conversions below are all `None`
+    /// without a step length.
+    pub fn steps_per_year(&self) -> Option<f64> {
+        self.step_dt_seconds.map(|dt| SECONDS_PER_YEAR / dt)
+    }
+
+    /// Per-step σ for an annualized volatility (√time scaling).
+    pub fn sigma_from_annual(&self, annual_volatility: f64) -> Option<f64> {
+        Some(annual_volatility / self.steps_per_year()?.sqrt())
+    }
+
+    /// Annualized volatility of a per-step σ.
+    pub fn annualized_sigma(&self, sigma: f64) -> Option<f64> {
+        Some(sigma * self.steps_per_year()?.sqrt())
+    }
+
+    /// Per-step λ for an arrival rate in orders per minute.
+    pub fn lambda_from_per_minute(&self, orders_per_minute: f64) -> Option<f64> {
+        Some(orders_per_minute * self.step_dt_seconds? / 60.0)
+    }
+
+    /// Arrival rate in orders per minute of a per-step λ.
+    pub fn orders_per_minute_of(&self, lambda: f64) -> Option<f64> {
+        Some(lambda * 60.0 / self.step_dt_seconds?)
+    }
+
+    /// Simple (non-compounded) annualization of a return earned over `steps` steps.
+    pub fn annualize_return(&self, ret: f64, steps: usize) -> Option<f64> {
+        Some(ret * self.steps_per_year()? / steps.max(1) as f64)
+    }
+
+    /// Parse and `validate` a TOML config. Keys left out keep their `Default` values, so
+    /// a file only needs what it changes.
+    pub fn from_toml(text: &str) -> Result<Self, String> {
+        let config: SimConfig = toml::from_str(text).map_err(|e| e.to_string())?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// The full config as TOML, loadable with `from_toml`.
+    pub fn to_toml(&self) -> Result<String, String> {
+        toml::to_string(self).map_err(|e| e.to_string())
+    }
+
+    /// Check the settings are consistent: epochs tile the run, weights and probabilities
+    /// are in range, allocator parameters are usable and opponents exist. Every problem
+    /// found is listed.
+    pub fn validate(&self) -> Result<(), String> {
+        let mut errors = Vec::new();
+        let unit = |x: f64| (0.0..=1.0).contains(&x);
+        let positive = |x: f64| x > 0.0 && x.is_finite();
+        if self.total_steps == 0 || self.epoch_len == 0 {
+            errors.push("total_steps and epoch_len must be positive".to_string());
+        } else if !self.total_steps.is_multiple_of(self.epoch_len) {
+            errors.push(format!("epoch_len {} does not divide total_steps {}", self.epoch_len, self.total_steps));
+        }
+        if !(0.0..1.0).contains(&self.min_capital_weight) {
+            errors.push(format!("min_capital_weight {} is outside [0, 1)", self.min_capital_weight));
+        }
+        if self.initial_price.is_some_and(|p| !positive(p)) {
+            errors.push(format!("initial_price {} must be positive", self.initial_price.unwrap_or_default()));
+        }
+        if !(self.initial_x_share > 0.0 && self.initial_x_share < 1.0) {
+            errors.push(format!("initial_x_share {} is outside (0, 1)", self.initial_x_share));
+        }
+        if self.initial_weights.iter().any(|w| !unit(w.weight)) || self.initial_weights.iter().map(|w| w.weight).sum::<f64>() > 1.0 + 1e-9 {
+            errors.push("initial weights must be in [0, 1] and sum to at most 1".to_string());
+        }
+        for b in &self.capital_bounds {
+            let (floor, ceiling) = (b.floor.unwrap_or(0.0), b.ceiling.unwrap_or(1.0));
+            if !unit(floor) || !unit(ceiling) || floor > ceiling {
+                errors.push(format!("capital bound for `{}` needs 0 <= floor <= ceiling <= 1", b.strategy));
+            }
+        }
+        if !positive(self.softmax_temperature) {
+            errors.push(format!("softmax_temperature {} must be positive", self.softmax_temperature));
+        }
+        match &self.allocator {
+            AllocatorKind::Kelly { fraction } if !positive(*fraction) => {
+                errors.push(format!("kelly fraction {fraction} must be positive"));
+            }
+            AllocatorKind::Cvar { window, alpha } if *window == 0 || !(*alpha > 0.0 && *alpha <= 1.0) => {
+                errors.push(format!("cvar needs window >= 1 and alpha in (0, 1], got {window} and {alpha}"));
+            }
+            AllocatorKind::Exp3 { gamma } if !(*gamma > 0.0 && *gamma <= 1.0) => {
+                errors.push(format!("exp3 gamma {gamma} is outside (0, 1]"));
+            }
+            AllocatorKind::Rank { ladder } if ladder.iter().any(|&w| !unit(w)) || ladder.iter().sum::<f64>() > 1.0 + 1e-9 => {
+                errors.push("rank ladder shares must be in [0, 1] and sum to at most 1".to_string());
+            }
+            // Loads the library once here; every simulation then reuses it
+            AllocatorKind::Plugin { .. } => {
+                if let Err(e) = self.allocator.build() {
+                    errors.push(e);
+                }
+            }
+            _ => {}
+        }
+        if !positive(self.max_rebalance_delta) {
+            errors.push(format!("max_rebalance_delta {} must be positive", self.max_rebalance_delta));
+        }
+        for (name, p) in [
+            ("router_noise", self.router_noise),
+            ("twap_prob", self.twap_prob),
+            ("limit_order_prob", self.limit_order_prob),
+            ("shock_prob", self.shock_prob),
+            ("rfq_prob", self.rfq_prob),
+            ("protocol_fee_share", self.protocol_fee_share),
+            ("kill_reserve_floor", self.kill_reserve_floor),
+            ("min_inventory_share", self.min_inventory_share),
+        ] {
+            if !unit(p) {
+                errors.push(format!("{name} {p} is outside [0, 1]"));
+            }
+        }
+        if !(self.quantization.tick_size >= 0.0 && self.quantization.tick_size.is_finite()) {
+            errors.push(format!("tick_size {} must be zero or positive", self.quantization.tick_size));
+        }
+        match self.step_dt_seconds {
+            Some(dt) if !positive(dt) => errors.push(format!("step_dt_seconds {dt} must be positive")),
+            Some(_) => {}
+            None => {
+                for (name, v) in [("annual_volatility", self.annual_volatility), ("orders_per_minute", self.orders_per_minute)] {
+                    if v.is_some() {
+                        errors.push(format!("{name} needs step_dt_seconds to convert to per-step units"));
+                    }
+                }
+            }
+        }
+        for (name, v) in [
+            ("annual_volatility", self.annual_volatility),
+            ("orders_per_minute", self.orders_per_minute),
+            ("sigma_per_step", self.sigma_per_step),
+            ("orders_per_step", self.orders_per_step),
+            ("norm_liquidity_mult", self.norm_liquidity_mult),
+        ] {
+            if let Some(v) = v.filter(|&v| !positive(v)) {
+                errors.push(format!("{name} {v} must be positive"));
+            }
+        }
+        if let Some(fee) = self.norm_fee_bps.filter(|&fee| fee >= 10_000) {
+            errors.push(format!("norm_fee_bps {fee} must be below 10000"));
+        }
+        if self.n_normalizers == 0 {
+            errors.push("n_normalizers must be at least 1".to_string());
+        }
+        if self.self_play_copies == 0 {
+            errors.push("self_play_copies must be at least 1".to_string());
+        }
+        let available = crate::reference::names();
+        for name in self.opponents.iter().filter(|n| !available.contains(n)) {
+            errors.push(format!("unknown opponent `{name}`"));
         }
+        if errors.is_empty() { Ok(()) } else { Err(errors.join("; ")) }
     }
 }
@@ -0,0 +1,160 @@
+//! Quote-surface checks behind `validate`.
+//!
+//! `check_quotes` probes `compute_swap` on both sides over a grid of sizes at several
+//! reserve levels and skews, and `check_payloads` feeds it truncated and random
+//! payloads. Each check is an error or a warning: errors fail validation, warnings flag
+//! quotes the router copes with but that cost the strategy flow (the equimarginal
+//! router leaves non-concave quotes out of an order, see `market::degenerate_quotes`).
+
+use serde::Serialize;
+
+use crate::market::degenerate_quotes;
+use crate::runner::StrategyRunner;
+use crate::types::{SCALE, STORAGE_SIZE};
+
+/// Pool states probed, as unscaled (reserve_x, reserve_y): three depths at the default
+/// price, then X scarce and X abundant at 4× either way of it
+pub const RESERVES: [(u64, u64); 5] = [(1, 100), (100, 10_000), (100_000, 10_000_000), (25, 10_000), (400, 10_000)];
+
+/// Sizes per side and pool state, evenly spaced up to `MAX_SIZE_FRACTION` of the input reserve
+pub const GRID_POINTS: usize = 16;
+pub const MAX_SIZE_FRACTION: f64 = 0.5;
+
+/// Random payloads sent by `check_payloads`, after every truncation of a valid one
+pub const FUZZ_ROUNDS: usize = 256;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// Outcome of one check.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Check {
+    pub name: &'static str,
+    pub severity: Severity,
+    pub passed: bool,
+    /// Calls made; 0 where not counted
+    pub probes: usize,
+    /// First failure; empty when the check passed
+    pub detail: String,
+}
+
+impl Check {
+    fn new(name: &'static str, severity: Severity) -> Self {
+        Self { name, severity, passed: true, probes: 0, detail: String::new() }
+    }
+
+    fn fail(&mut self, detail: String) {
+        if self.passed {
+            self.passed = false;
+            self.detail = detail;
+        }
+    }
+}
+
+/// Every check of one strategy.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ValidationReport {
+    pub strategy: String,
+    pub checks: Vec<Check>,
+}
+
+impl ValidationReport {
+    /// No error-severity check failed.
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|c| c.passed || c.severity == Severity::Warning)
+    }
+
+    /// Record a check run elsewhere (the determinism check, say), with no call count.
+    pub fn push(&mut self, name: &'static str, severity: Severity, failure: Option<String>) {
+        let mut check = Check::new(name, severity);
+        if let Some(detail) = failure {
+            check.fail(detail);
+        }
+        self.checks.push(check);
+    }
+}
+
+fn side(is_buy: bool) -> &'static str {
+    if is_buy { "buy" } else { "sell" }
+}
+
+/// Probe `runner`'s quotes with empty storage:
+///
+/// - `zero_input`: a zero-size trade gets nothing
+/// - `positive`: a 1% trade at the default pool gets a positive quote
+/// - `within_reserve`: no quote reaches the output reserve
+/// - `monotone`: larger trades never get less
+/// - `concave` (warning): no marginal rate beats an earlier one (`degenerate_quotes`)
+pub fn check_quotes(runner: &StrategyRunner) -> Vec<Check> {
+    let storage = [0u8; STORAGE_SIZE];
+    let mut zero = Check::new("zero_input", Severity::Error);
+    let mut positive = Check::new("positive", Severity::Error);
+    let mut within = Check::new("within_reserve", Severity::Error);
+    let mut monotone = Check::new("monotone", Severity::Error);
+    let mut concave = Check::new("concave", Severity::Warning);
+    runner.take_over_quotes();
+
+    for (x, y) in RESERVES {
+        let (rx, ry) = (x * SCALE, y * SCALE);
+        for is_buy in [true, false] {
+            let (reserve_in, reserve_out) = if is_buy { (ry, rx) } else { (rx, ry) };
+            let at = format!("{} at rx={x} ry={y}", side(is_buy));
+
+            let out = runner.compute_swap(is_buy, 0, rx, ry, &storage);
+            zero.probes += 1;
+            if out != 0 {
+                zero.fail(format!("{at}: zero input quoted {out}"));
+            }
+
+            let sizes: Vec<u64> = (1..=GRID_POINTS)
+                .map(|k| (reserve_in as f64 * MAX_SIZE_FRACTION * k as f64 / GRID_POINTS as f64) as u64)
+                .collect();
+            let outputs: Vec<u64> = sizes.iter().map(|&size| runner.compute_swap(is_buy, size, rx, ry, &storage)).collect();
+            let over_quotes = runner.take_over_quotes().len();
+            within.probes += sizes.len();
+            monotone.probes += sizes.len();
+            concave.probes += sizes.len();
+            if over_quotes > 0 {
+                within.fail(format!("{at}: {over_quotes} of {} quotes reached the output reserve {reserve_out}", sizes.len()));
+            }
+            if let Some(k) = (1..outputs.len()).find(|&k| outputs[k].saturating_add(2) < outputs[k - 1]) {
+                monotone.fail(format!(
+                    "{at}: input {} quoted {} but the larger {} quoted {}",
+                    sizes[k - 1], outputs[k - 1], sizes[k], outputs[k]
+                ));
+            } else if degenerate_quotes(&sizes, &outputs) {
+                concave.fail(format!("{at}: marginal output rises with size over {:?}", outputs));
+            }
+        }
+    }
+
+    let (rx, ry) = (100 * SCALE, 10_000 * SCALE);
+    for is_buy in [true, false] {
+        let input = if is_buy { ry } else { rx } / 100;
+        positive.probes += 1;
+        if runner.compute_swap(is_buy, input, rx, ry, &storage) == 0 {
+            positive.fail(format!("{} of 1% of the input reserve quoted 0", side(is_buy)));
+        }
+    }
+    vec![zero, positive, within, monotone, concave]
+}
+
+/// `fuzz`: truncated and random payloads neither trap nor crash the strategy. Run it on
+/// an instance that can afford to crash (a native library in `Runtime::Subprocess`).
+/// Passes trivially for in-process strategies, which take no payload.
+pub fn check_payloads(runner: &StrategyRunner, seed: u64) -> Check {
+    let mut check = Check::new("fuzz", Severity::Error);
+    runner.take_faults();
+    check.probes = runner.fuzz_compute_swap(FUZZ_ROUNDS, seed).unwrap_or(0);
+    let faults = runner.take_faults().len();
+    if runner.is_disabled() {
+        check.fail("the strategy crashed on a malformed payload".to_string());
+    } else if faults > 0 {
+        check.fail(format!("{faults} of {} malformed payloads trapped", check.probes));
+    }
+    check
+}
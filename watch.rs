@@ -0,0 +1,95 @@
+//! Save detection and run-to-run scoring for the `watch` loop.
+//!
+//! `SaveDetector` turns periodic file stamps into "the file was saved" events, waiting
+//! for a stamp to hold still across two polls so a half-written file is not compiled.
+//! `WatchScore` condenses the watched strategy's aggregate into the handful of metrics the
+//! loop prints, and renders them next to the previous run's.
+
+use std::path::Path;
+use std::time::SystemTime;
+
+use crate::sim::AggregatedResult;
+
+/// A file's modification time and size.
+pub type FileStamp = (SystemTime, u64);
+
+/// `path`'s current stamp, `None` if it cannot be read (for example, mid-save).
+pub fn file_stamp(path: &Path) -> Option<FileStamp> {
+    let meta = std::fs::metadata(path).ok()?;
+    Some((meta.modified().ok()?, meta.len()))
+}
+
+/// Reports a save once the file's stamp differs from the last reported one and has
+/// stayed the same for two consecutive polls. The first stamp seen counts as a save.
+#[derive(Clone, Debug, Default)]
+pub struct SaveDetector {
+    seen: Option<FileStamp>,
+    pending: Option<FileStamp>,
+}
+
+impl SaveDetector {
+    /// Feed the stamp from one poll; `true` when it completes a save.
+    pub fn observe(&mut self, stamp: Option<FileStamp>) -> bool {
+        if stamp.is_none() || stamp == self.seen {
+            self.pending = None;
+            return false;
+        }
+        if stamp == self.pending {
+            self.seen = self.pending.take();
+            return true;
+        }
+        self.pending = stamp;
+        false
+    }
+}
+
+/// The watched strategy's key metrics in one run.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WatchScore {
+    pub values: [f64; 6],
+    /// 1-based rank by mean edge among `field` strategies
+    pub rank: usize,
+    pub field: usize,
+}
+
+impl WatchScore {
+    pub const LABELS: [&'static str; 6] = ["Mean edge", "Edge vs norm", "Flow %", "Sharpe", "Max drawdown", "Final cap %"];
+
+    /// Score the first strategy in `results`; `None` if there is none.
+    pub fn of(results: &[AggregatedResult]) -> Option<Self> {
+        let r = results.first()?;
+        Some(Self {
+            values: [
+                r.mean_edge,
+                r.edge_vs_normalizer,
+                r.mean_flow_share * 100.0,
+                r.sharpe,
+                r.mean_max_drawdown,
+                r.mean_final_capital_weight * 100.0,
+            ],
+            rank: 1 + results.iter().filter(|o| o.mean_edge > r.mean_edge).count(),
+            field: results.len(),
+        })
+    }
+
+    /// The score as a table, with `previous` and the change from it when given. A
+    /// positive rank change means the strategy moved up.
+    pub fn render(&self, previous: Option<&WatchScore>) -> String {
+        let mut out = match previous {
+            Some(_) => format!("{:<14} {:>10} {:>10} {:>10}\n", "", "Now", "Before", "Change"),
+            None => format!("{:<14} {:>10}\n", "", "Now"),
+        };
+        for (i, label) in Self::LABELS.iter().enumerate() {
+            out += &match previous {
+                Some(p) => format!("{label:<14} {:>10.3} {:>10.3} {:>+10.3}\n", self.values[i], p.values[i], self.values[i] - p.values[i]),
+                None => format!("{label:<14} {:>10.3}\n", self.values[i]),
+            };
+        }
+        let rank = |s: &WatchScore| format!("{}/{}", s.rank, s.field);
+        out += &match previous {
+            Some(p) => format!("{:<14} {:>10} {:>10} {:>+10}\n", "Rank", rank(self), rank(p), p.rank as i64 - self.rank as i64),
+            None => format!("{:<14} {:>10}\n", "Rank", rank(self)),
+        };
+        out
+    }
+}
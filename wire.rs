@@ -0,0 +1,520 @@
+//! Byte layout of the payloads the engine sends to strategy programs, shared by the
+//! engine's encoders and the SDK's decoders so the two cannot drift apart.
+//!
+//! Only `core` is used: the engine mounts this file as `crate::wire`, and the SDK, which
+//! is `no_std` when built for Solana, includes the same file with
+//! `#[path = "wire.rs"] mod wire`.
+//!
+//! Every payload is a fixed header, then the strategy's `STORAGE_SIZE` bytes of storage.
+//! From layout v4 a version byte follows the storage. It goes at the end so no earlier
+//! offset moves, and a decoder can tell which layout it was handed. Multi-byte fields
+//! are little-endian.
+
+/// Per-strategy storage size in bytes (matches prop-amm-challenge)
+pub const STORAGE_SIZE: usize = 1024;
+
+/// Competitor slots in the after-swap payload from v2 (the engine's `MAX_STRATEGIES`)
+pub const MAX_COMPETITORS: usize = 16;
+
+/// Newest payload layout. Strategies export the layout they were built against as
+/// `__prop_amm_abi_version` and the engine encodes that one.
+///
+/// v2 widened the after-swap competitor prices from 8 to `MAX_COMPETITORS` slots and
+/// added an explicit count; v3 added competitor reserves; v4 appends the version byte.
+/// Storage is the strategy's own in every layout but for one reserved slot: slot 127
+/// (`REBALANCE_REQUEST_SLOT`), which the engine reads and zeroes at each epoch boundary
+/// when run with `SimConfig::strategy_rebalancing`.
+pub const ABI_VERSION: u32 = 4;
+
+/// First layout that ends in a version byte
+pub const VERSION_BYTE_SINCE: u32 = 4;
+
+// ─── Tags (byte 0 of every payload) ───────────────────────────────────────────
+
+/// Compute swap quote (buy X = Y-in)
+pub const TAG_SWAP_BUY: u8 = 0;
+/// Compute swap quote (sell X = X-in)
+pub const TAG_SWAP_SELL: u8 = 1;
+/// After-swap hook (real trade executed)
+pub const TAG_AFTER_SWAP: u8 = 2;
+/// Metadata: return NAME bytes
+pub const TAG_GET_NAME: u8 = 3;
+/// Metadata: return MODEL_USED bytes
+pub const TAG_GET_MODEL: u8 = 4;
+/// Epoch boundary: called at the start of every new epoch with capital update
+pub const TAG_EPOCH_BOUNDARY: u8 = 5;
+/// Shock news: a discrete fair-price jump will hit on the next step
+pub const TAG_SHOCK_NEWS: u8 = 6;
+/// Step digest: pool-wide trade summary at the end of each step, sent only when the
+/// engine runs with `SimConfig::step_digest`
+pub const TAG_STEP_DIGEST: u8 = 7;
+/// Metadata: return the ABI version as u32 (SBF programs' `__prop_amm_abi_version`)
+pub const TAG_GET_ABI_VERSION: u8 = 8;
+
+// ─── Offsets ──────────────────────────────────────────────────────────────────
+
+/// Total length of a payload with a `header`-byte header in layout `version`.
+pub const fn payload_len(header: usize, version: u32) -> usize {
+    header + STORAGE_SIZE + if version >= VERSION_BYTE_SINCE { 1 } else { 0 }
+}
+
+/// The version byte at the end of `data`, if it is a versioned payload with a
+/// `header`-byte header.
+pub fn version_byte(data: &[u8], header: usize) -> Option<u32> {
+    if data.len() == header + STORAGE_SIZE + 1 { Some(data[header + STORAGE_SIZE] as u32) } else { None }
+}
+
+/// TAG_SWAP_BUY / TAG_SWAP_SELL
+pub mod swap {
+    pub const TAG: usize = 0;
+    pub const INPUT_AMOUNT: usize = 1;
+    pub const RESERVE_X: usize = 9;
+    pub const RESERVE_Y: usize = 17;
+    pub const STORAGE: usize = 25;
+    pub const HEADER: usize = 25;
+
+    pub const fn len(version: u32) -> usize {
+        super::payload_len(HEADER, version)
+    }
+}
+
+/// TAG_AFTER_SWAP
+pub mod after_swap {
+    pub const TAG: usize = 0;
+    pub const SIDE: usize = 1;
+    pub const INPUT_AMOUNT: usize = 2;
+    pub const OUTPUT_AMOUNT: usize = 10;
+    pub const RESERVE_X: usize = 18;
+    pub const RESERVE_Y: usize = 26;
+    pub const SIM_STEP: usize = 34;
+    pub const EPOCH_STEP: usize = 42;
+    pub const EPOCH_NUMBER: usize = 46;
+    pub const N_STRATEGIES: usize = 50;
+    pub const STRATEGY_INDEX: usize = 51;
+    pub const FLOW_CAPTURED: usize = 52;
+    pub const CAPITAL_WEIGHT: usize = 56;
+    /// v1 only: 8 price slots and no count
+    pub const V1_SPOT_PRICES: usize = 60;
+    pub const V1_SLOTS: usize = 8;
+    /// v2+
+    pub const N_COMPETING: usize = 60;
+    pub const SPOT_PRICES: usize = 61;
+    /// v3+
+    pub const COMPETING_RESERVE_Y: usize = 125;
+
+    /// Header length (where storage starts) in layout `version`.
+    pub const fn header(version: u32) -> usize {
+        match version {
+            0..=1 => 92,
+            2 => 125,
+            _ => 189,
+        }
+    }
+
+    /// Competitor slots in layout `version`.
+    pub const fn slots(version: u32) -> usize {
+        if version <= 1 { V1_SLOTS } else { super::MAX_COMPETITORS }
+    }
+
+    pub const fn len(version: u32) -> usize {
+        super::payload_len(header(version), version)
+    }
+
+    /// Layout of an after-swap payload, from its version byte or else its length.
+    pub fn version_of(data: &[u8]) -> Option<u32> {
+        if let Some(v) = super::version_byte(data, header(super::ABI_VERSION)) {
+            return Some(v);
+        }
+        (1..super::VERSION_BYTE_SINCE).rev().find(|&v| data.len() == len(v))
+    }
+}
+
+/// TAG_EPOCH_BOUNDARY
+pub mod epoch_boundary {
+    pub const TAG: usize = 0;
+    pub const EPOCH_NUMBER: usize = 1;
+    pub const NEW_RESERVE_X: usize = 5;
+    pub const NEW_RESERVE_Y: usize = 13;
+    pub const EPOCH_EDGE: usize = 21;
+    pub const CUMULATIVE_EDGE: usize = 29;
+    pub const CAPITAL_WEIGHT: usize = 37;
+    pub const HEADER: usize = 41;
+
+    pub const fn len(version: u32) -> usize {
+        super::payload_len(HEADER, version)
+    }
+}
+
+/// TAG_SHOCK_NEWS
+pub mod shock_news {
+    pub const TAG: usize = 0;
+    pub const SHOCK_STEP: usize = 1;
+    pub const MAGNITUDE: usize = 9;
+    pub const HEADER: usize = 17;
+
+    pub const fn len(version: u32) -> usize {
+        super::payload_len(HEADER, version)
+    }
+}
+
+/// TAG_STEP_DIGEST
+pub mod step_digest {
+    pub const TAG: usize = 0;
+    pub const SIM_STEP: usize = 1;
+    pub const RETAIL_NOTIONAL: usize = 9;
+    pub const NET_RETAIL_FLOW: usize = 17;
+    pub const ARB_COUNT: usize = 25;
+    pub const HEADER: usize = 29;
+
+    pub const fn len(version: u32) -> usize {
+        super::payload_len(HEADER, version)
+    }
+}
+
+// ─── Payloads ─────────────────────────────────────────────────────────────────
+
+/// Payload sent for TAG_SWAP_BUY / TAG_SWAP_SELL  (matches original, extended by storage)
+#[repr(C, packed)]
+pub struct ComputeSwapPayload {
+    pub tag: u8,         // 0 or 1
+    pub input_amount: u64,
+    pub reserve_x: u64,
+    pub reserve_y: u64,
+    pub storage: [u8; STORAGE_SIZE],
+}
+
+/// Payload sent for TAG_AFTER_SWAP — enriched vs. original to expose competitive context.
+///
+/// Layout (byte offsets, `after_swap`):
+///   0   tag             u8
+///   1   side            u8   (0=buy X, 1=sell X)
+///   2   input_amount    u64
+///  10   output_amount   u64
+///  18   reserve_x       u64  (post-trade)
+///  26   reserve_y       u64
+///  34   sim_step        u64  (global step within simulation)
+///  42   epoch_step      u32  (step within current epoch, 0-based)
+///  46   epoch_number    u32  (epoch index, 0-based)
+///  50   n_strategies    u8   (total number of competing AMMs incl. normalizers)
+///  51   strategy_index  u8   (this strategy's index)
+///  52   flow_captured   f32  (fraction of this retail order routed here, 0.0-1.0)
+///  56   capital_weight  f32  (this strategy's fraction of total protocol capital)
+///  60   n_competing     u8   (filled competitor slots, at most MAX_COMPETITORS)
+///  61   [f32; 16]       competing_spot_prices (spot price of each other AMM, NaN if unused)
+/// 125   [f32; 16]       competing_reserve_y (Y reserve of each other AMM, same slots and
+///                       1e9 scale as reserve_y; NaN if unused)
+/// 189   storage         [u8; STORAGE_SIZE]
+/// 1213  version         u8   (v4+)
+///
+/// Older strategies get older layouts: v3 has no version byte, v2 has no reserves
+/// either (storage at 125), and v1 has no count, with 8 price slots at 60 and storage
+/// at 92. Competitors beyond the available slots are left out.
+#[repr(C, packed)]
+pub struct AfterSwapPayload {
+    pub tag: u8,
+    pub side: u8,
+    pub input_amount: u64,
+    pub output_amount: u64,
+    pub reserve_x: u64,
+    pub reserve_y: u64,
+    pub sim_step: u64,
+    pub epoch_step: u32,
+    pub epoch_number: u32,
+    pub n_strategies: u8,
+    pub strategy_index: u8,
+    pub flow_captured: f32,
+    pub capital_weight: f32,
+    pub n_competing: u8,
+    pub competing_spot_prices: [f32; MAX_COMPETITORS],
+    pub competing_reserve_y: [f32; MAX_COMPETITORS],
+    pub storage: [u8; STORAGE_SIZE],
+}
+
+/// Payload sent for TAG_EPOCH_BOUNDARY — notifies strategy of new capital allocation.
+///
+/// Layout (`epoch_boundary`):
+///   0   tag                u8
+///   1   epoch_number       u32
+///   5   new_reserve_x      u64
+///  13   new_reserve_y      u64
+///  21   epoch_edge         f64   (edge earned in just-completed epoch)
+///  29   cumulative_edge    f64   (total edge across all epochs so far)
+///  37   capital_weight     f32   (new fraction of total protocol capital)
+///  41   storage            [u8; STORAGE_SIZE]  (read-write, persists)
+#[repr(C, packed)]
+pub struct EpochBoundaryPayload {
+    pub tag: u8,
+    pub epoch_number: u32,
+    pub new_reserve_x: u64,
+    pub new_reserve_y: u64,
+    pub epoch_edge: f64,
+    pub cumulative_edge: f64,
+    pub capital_weight: f32,
+    pub storage: [u8; STORAGE_SIZE],
+}
+
+/// Payload sent for TAG_SHOCK_NEWS — announced one step before a price shock lands.
+///
+/// Layout (`shock_news`):
+///   0   tag                u8
+///   1   shock_step         u64   (step at which the jump is applied)
+///   9   magnitude          f64   (absolute fractional jump, e.g. 0.05; direction not revealed)
+///  17   storage            [u8; STORAGE_SIZE]  (read-write, persists)
+#[repr(C, packed)]
+pub struct ShockNewsPayload {
+    pub tag: u8,
+    pub shock_step: u64,
+    pub magnitude: f64,
+    pub storage: [u8; STORAGE_SIZE],
+}
+
+/// Payload sent for TAG_STEP_DIGEST — pool-wide trade summary at the end of each step.
+///
+/// Layout (`step_digest`):
+///   0   tag                u8
+///   1   sim_step           u64
+///   9   retail_notional    f64   (retail volume filled across all AMMs, Y at fair)
+///  17   net_retail_flow    f64   (buy-X minus sell-X retail notional, Y at fair)
+///  25   arb_count          u32   (arbitrage trades across all AMMs)
+///  29   storage            [u8; STORAGE_SIZE]  (read-write, persists)
+#[repr(C, packed)]
+pub struct StepDigestPayload {
+    pub tag: u8,
+    pub sim_step: u64,
+    pub retail_notional: f64,
+    pub net_retail_flow: f64,
+    pub arb_count: u32,
+    pub storage: [u8; STORAGE_SIZE],
+}
+
+// ─── Field access ─────────────────────────────────────────────────────────────
+
+macro_rules! le_fields {
+    ($($put:ident, $get:ident: $t:ty;)*) => {$(
+        #[inline]
+        pub fn $put(buf: &mut [u8], off: usize, v: $t) {
+            buf[off..off + core::mem::size_of::<$t>()].copy_from_slice(&v.to_le_bytes());
+        }
+
+        #[inline]
+        pub fn $get(data: &[u8], off: usize) -> Option<$t> {
+            Some(<$t>::from_le_bytes(data.get(off..off + core::mem::size_of::<$t>())?.try_into().ok()?))
+        }
+    )*};
+}
+
+le_fields! {
+    put_u32, get_u32: u32;
+    put_u64, get_u64: u64;
+    put_f32, get_f32: f32;
+    put_f64, get_f64: f64;
+}
+
+fn storage_at(data: &[u8], off: usize) -> Option<[u8; STORAGE_SIZE]> {
+    data.get(off..off + STORAGE_SIZE)?.try_into().ok()
+}
+
+/// Storage and, in versioned layouts, the trailing version byte.
+fn put_tail(buf: &mut [u8], header: usize, storage: &[u8; STORAGE_SIZE], version: u32) {
+    buf[header..header + STORAGE_SIZE].copy_from_slice(storage);
+    if version >= VERSION_BYTE_SINCE {
+        buf[header + STORAGE_SIZE] = version as u8;
+    }
+}
+
+/// Layout of a fixed-header payload: its version byte, or the newest unversioned layout.
+fn fixed_version(data: &[u8], header: usize) -> Option<u32> {
+    match version_byte(data, header) {
+        Some(v) => Some(v),
+        None if data.len() == header + STORAGE_SIZE => Some(VERSION_BYTE_SINCE - 1),
+        None => None,
+    }
+}
+
+// ─── Encode / decode ──────────────────────────────────────────────────────────
+// `buf` must hold at least `<payload>::len(version)` bytes; each encoder returns the
+// length it wrote. Decoders return the payload and the layout it was read in, and
+// reject data whose length matches no layout.
+
+pub fn encode_compute_swap(
+    is_buy: bool,
+    input_amount: u64,
+    reserve_x: u64,
+    reserve_y: u64,
+    storage: &[u8; STORAGE_SIZE],
+    version: u32,
+    buf: &mut [u8],
+) -> usize {
+    buf[swap::TAG] = if is_buy { TAG_SWAP_BUY } else { TAG_SWAP_SELL };
+    put_u64(buf, swap::INPUT_AMOUNT, input_amount);
+    put_u64(buf, swap::RESERVE_X, reserve_x);
+    put_u64(buf, swap::RESERVE_Y, reserve_y);
+    put_tail(buf, swap::STORAGE, storage, version);
+    swap::len(version)
+}
+
+pub fn decode_compute_swap(data: &[u8]) -> Option<(ComputeSwapPayload, u32)> {
+    let version = fixed_version(data, swap::HEADER)?;
+    let payload = ComputeSwapPayload {
+        tag: data[swap::TAG],
+        input_amount: get_u64(data, swap::INPUT_AMOUNT)?,
+        reserve_x: get_u64(data, swap::RESERVE_X)?,
+        reserve_y: get_u64(data, swap::RESERVE_Y)?,
+        storage: storage_at(data, swap::STORAGE)?,
+    };
+    Some((payload, version))
+}
+
+/// Encode in layout `version`: v1 has 8 competitor slots and no count, v2 no competitor
+/// reserves, v3 no version byte.
+pub fn encode_after_swap(p: &AfterSwapPayload, storage: &[u8; STORAGE_SIZE], version: u32, buf: &mut [u8]) -> usize {
+    use after_swap::*;
+    buf[TAG] = p.tag;
+    buf[SIDE] = p.side;
+    put_u64(buf, INPUT_AMOUNT, p.input_amount);
+    put_u64(buf, OUTPUT_AMOUNT, p.output_amount);
+    put_u64(buf, RESERVE_X, p.reserve_x);
+    put_u64(buf, RESERVE_Y, p.reserve_y);
+    put_u64(buf, SIM_STEP, p.sim_step);
+    put_u32(buf, EPOCH_STEP, p.epoch_step);
+    put_u32(buf, EPOCH_NUMBER, p.epoch_number);
+    buf[N_STRATEGIES] = p.n_strategies;
+    buf[STRATEGY_INDEX] = p.strategy_index;
+    put_f32(buf, FLOW_CAPTURED, p.flow_captured);
+    put_f32(buf, CAPITAL_WEIGHT, p.capital_weight);
+    let prices_at = if version >= 2 {
+        buf[N_COMPETING] = p.n_competing.min(MAX_COMPETITORS as u8);
+        SPOT_PRICES
+    } else {
+        V1_SPOT_PRICES
+    };
+    let competing_spot_prices = p.competing_spot_prices;
+    for (i, &sp) in competing_spot_prices[..slots(version)].iter().enumerate() {
+        put_f32(buf, prices_at + i * 4, sp);
+    }
+    if version >= 3 {
+        let competing_reserve_y = p.competing_reserve_y;
+        for (i, &ry) in competing_reserve_y.iter().enumerate() {
+            put_f32(buf, COMPETING_RESERVE_Y + i * 4, ry);
+        }
+    }
+    put_tail(buf, header(version), storage, version);
+    len(version)
+}
+
+/// Slots a layout does not carry decode as NaN; v1's count is its leading non-NaN prices.
+pub fn decode_after_swap(data: &[u8]) -> Option<(AfterSwapPayload, u32)> {
+    use after_swap::*;
+    let version = version_of(data)?;
+    let prices_at = if version >= 2 { SPOT_PRICES } else { V1_SPOT_PRICES };
+    let mut competing_spot_prices = [f32::NAN; MAX_COMPETITORS];
+    for (i, sp) in competing_spot_prices[..slots(version)].iter_mut().enumerate() {
+        *sp = get_f32(data, prices_at + i * 4)?;
+    }
+    let mut competing_reserve_y = [f32::NAN; MAX_COMPETITORS];
+    if version >= 3 {
+        for (i, ry) in competing_reserve_y.iter_mut().enumerate() {
+            *ry = get_f32(data, COMPETING_RESERVE_Y + i * 4)?;
+        }
+    }
+    let n_competing = if version >= 2 {
+        data[N_COMPETING].min(MAX_COMPETITORS as u8)
+    } else {
+        competing_spot_prices.iter().take_while(|p| !p.is_nan()).count() as u8
+    };
+    let payload = AfterSwapPayload {
+        tag: data[TAG],
+        side: data[SIDE],
+        input_amount: get_u64(data, INPUT_AMOUNT)?,
+        output_amount: get_u64(data, OUTPUT_AMOUNT)?,
+        reserve_x: get_u64(data, RESERVE_X)?,
+        reserve_y: get_u64(data, RESERVE_Y)?,
+        sim_step: get_u64(data, SIM_STEP)?,
+        epoch_step: get_u32(data, EPOCH_STEP)?,
+        epoch_number: get_u32(data, EPOCH_NUMBER)?,
+        n_strategies: data[N_STRATEGIES],
+        strategy_index: data[STRATEGY_INDEX],
+        flow_captured: get_f32(data, FLOW_CAPTURED)?,
+        capital_weight: get_f32(data, CAPITAL_WEIGHT)?,
+        n_competing,
+        competing_spot_prices,
+        competing_reserve_y,
+        storage: storage_at(data, header(version))?,
+    };
+    Some((payload, version))
+}
+
+pub fn encode_epoch_boundary(p: &EpochBoundaryPayload, storage: &[u8; STORAGE_SIZE], version: u32, buf: &mut [u8]) -> usize {
+    use epoch_boundary::*;
+    buf[TAG] = TAG_EPOCH_BOUNDARY;
+    put_u32(buf, EPOCH_NUMBER, p.epoch_number);
+    put_u64(buf, NEW_RESERVE_X, p.new_reserve_x);
+    put_u64(buf, NEW_RESERVE_Y, p.new_reserve_y);
+    put_f64(buf, EPOCH_EDGE, p.epoch_edge);
+    put_f64(buf, CUMULATIVE_EDGE, p.cumulative_edge);
+    put_f32(buf, CAPITAL_WEIGHT, p.capital_weight);
+    put_tail(buf, HEADER, storage, version);
+    len(version)
+}
+
+pub fn decode_epoch_boundary(data: &[u8]) -> Option<(EpochBoundaryPayload, u32)> {
+    use epoch_boundary::*;
+    let version = fixed_version(data, HEADER)?;
+    let payload = EpochBoundaryPayload {
+        tag: data[TAG],
+        epoch_number: get_u32(data, EPOCH_NUMBER)?,
+        new_reserve_x: get_u64(data, NEW_RESERVE_X)?,
+        new_reserve_y: get_u64(data, NEW_RESERVE_Y)?,
+        epoch_edge: get_f64(data, EPOCH_EDGE)?,
+        cumulative_edge: get_f64(data, CUMULATIVE_EDGE)?,
+        capital_weight: get_f32(data, CAPITAL_WEIGHT)?,
+        storage: storage_at(data, HEADER)?,
+    };
+    Some((payload, version))
+}
+
+pub fn encode_shock_news(p: &ShockNewsPayload, storage: &[u8; STORAGE_SIZE], version: u32, buf: &mut [u8]) -> usize {
+    use shock_news::*;
+    buf[TAG] = TAG_SHOCK_NEWS;
+    put_u64(buf, SHOCK_STEP, p.shock_step);
+    put_f64(buf, MAGNITUDE, p.magnitude);
+    put_tail(buf, HEADER, storage, version);
+    len(version)
+}
+
+pub fn decode_shock_news(data: &[u8]) -> Option<(ShockNewsPayload, u32)> {
+    use shock_news::*;
+    let version = fixed_version(data, HEADER)?;
+    let payload = ShockNewsPayload {
+        tag: data[TAG],
+        shock_step: get_u64(data, SHOCK_STEP)?,
+        magnitude: get_f64(data, MAGNITUDE)?,
+        storage: storage_at(data, HEADER)?,
+    };
+    Some((payload, version))
+}
+
+pub fn encode_step_digest(p: &StepDigestPayload, storage: &[u8; STORAGE_SIZE], version: u32, buf: &mut [u8]) -> usize {
+    use step_digest::*;
+    buf[TAG] = TAG_STEP_DIGEST;
+    put_u64(buf, SIM_STEP, p.sim_step);
+    put_f64(buf, RETAIL_NOTIONAL, p.retail_notional);
+    put_f64(buf, NET_RETAIL_FLOW, p.net_retail_flow);
+    put_u32(buf, ARB_COUNT, p.arb_count);
+    put_tail(buf, HEADER, storage, version);
+    len(version)
+}
+
+pub fn decode_step_digest(data: &[u8]) -> Option<(StepDigestPayload, u32)> {
+    use step_digest::*;
+    let version = fixed_version(data, HEADER)?;
+    let payload = StepDigestPayload {
+        tag: data[TAG],
+        sim_step: get_u64(data, SIM_STEP)?,
+        retail_notional: get_f64(data, RETAIL_NOTIONAL)?,
+        net_retail_flow: get_f64(data, NET_RETAIL_FLOW)?,
+        arb_count: get_u32(data, ARB_COUNT)?,
+        storage: storage_at(data, HEADER)?,
+    };
+    Some((payload, version))
+}